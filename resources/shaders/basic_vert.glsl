@@ -2,10 +2,7 @@
 
 layout(location = 0) in vec3 VertPos;
 
-layout(std140) uniform VertData
-{
-    mat4 MVP;
-};
+#include "vert_data.glsl"
 
 void main()
 {
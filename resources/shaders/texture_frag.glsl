@@ -6,7 +6,20 @@ out vec4 FragColor;
 in vec2 TexCoord;
 uniform sampler2D tex;
 
+layout(std140) uniform Material
+{
+    vec3 Diffuse;
+    float Shininess;
+    vec3 Specular;
+    bool HasTexture;
+};
+
 void main()
 {
-    FragColor = texture(tex, TexCoord);
+    vec4 color = vec4(Diffuse, 1.0f);
+    if (HasTexture)
+    {
+        color *= texture(tex, TexCoord);
+    }
+    FragColor = color;
 }
\ No newline at end of file
@@ -7,10 +7,7 @@ layout(location = 2) in vec2 UV;
 
 out vec2 TexCoord;
 
-layout(std140) uniform VertData
-{
-    mat4 MVP;
-};
+#include "vert_data.glsl"
 
 void main()
 {
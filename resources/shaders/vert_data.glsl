@@ -0,0 +1,4 @@
+layout(std140) uniform VertData
+{
+    mat4 MVP;
+};
\ No newline at end of file
@@ -0,0 +1,317 @@
+//! Backends that carry out GL calls
+//!
+//! Everything in `gfx` talks to the GPU through a `Context`, which forwards each call to a `GlBackend`.
+//! In the browser that is `WebGlBackend`, and native tests use `RecordingBackend`, which logs the calls
+//! that change state and simulates GL objects and errors
+
+use std::{any::Any, ops::Deref, rc::Rc};
+use web_sys::WebGl2RenderingContext;
+use crate::gfx::gl_object::uniform::UniformValue;
+
+pub mod web;
+pub mod recording;
+#[cfg(any(feature = "debug", test))]
+pub mod validation;
+
+macro_rules! define_gl_ids
+{
+    ($($(#[$attr:meta])* $name:ident),* $(,)?) =>
+    {
+        $(
+        $(#[$attr])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(pub u32);
+        )*
+    };
+}
+define_gl_ids!
+{
+    /// Id of a buffer object created by a backend
+    BufferId,
+    /// Id of a texture object created by a backend
+    TextureId,
+    /// Id of a shader program created by a backend
+    ProgramId,
+    /// Id of a shader created by a backend
+    ShaderId,
+    /// Id of a vertex array object created by a backend
+    VertexArrayId,
+    /// Id of a uniform location within a linked program, which is invalidated when the program is deleted
+    UniformLocationId,
+}
+
+/// Id of any object that can be named with `GlBackend::label_object`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ObjectId
+{
+    Buffer(BufferId),
+    Texture(TextureId),
+    Program(ProgramId),
+    VertexArray(VertexArrayId),
+}
+
+macro_rules! impl_object_id_from
+{
+    ($($variant:ident($id:ident)),* $(,)?) =>
+    {
+        $(
+        impl From<$id> for ObjectId
+        {
+            fn from(id: $id) -> ObjectId
+            {
+                ObjectId::$variant(id)
+            }
+        }
+        )*
+    };
+}
+impl_object_id_from!(Buffer(BufferId), Texture(TextureId), Program(ProgramId), VertexArray(VertexArrayId));
+
+/// Name, GLSL type and array size of an active uniform or attribute
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveInfo
+{
+    pub name: String,
+    pub data_type: u32,
+    pub size: i32,
+}
+
+/// The GL calls that `gfx` makes
+///
+/// Objects are referred to by ids, which are only valid within the backend that created them.
+/// Queries return typed values instead of the JS values that WebGL returns, so that they can
+/// be answered without a browser
+///
+/// Calls are `#[track_caller]`, so that a backend can tell which line of `gfx` made them
+pub trait GlBackend
+{
+    // Buffers
+    #[track_caller]
+    fn create_buffer(&self) -> Option<BufferId>;
+    #[track_caller]
+    fn delete_buffer(&self, buffer: BufferId);
+    #[track_caller]
+    fn bind_buffer(&self, target: u32, buffer: Option<BufferId>);
+    #[track_caller]
+    fn buffer_data(&self, target: u32, data: &[u8], usage: u32);
+    /// Allocate `size` bytes of zeroed storage
+    #[track_caller]
+    fn buffer_data_size(&self, target: u32, size: i32, usage: u32);
+    #[track_caller]
+    fn buffer_sub_data(&self, target: u32, offset: i32, data: &[u8]);
+    #[track_caller]
+    fn bind_buffer_range(&self, target: u32, index: u32, buffer: Option<BufferId>, offset: i32, size: i32);
+
+    // Vertex arrays
+    #[track_caller]
+    fn create_vertex_array(&self) -> Option<VertexArrayId>;
+    #[track_caller]
+    fn delete_vertex_array(&self, vertex_array: VertexArrayId);
+    #[track_caller]
+    fn bind_vertex_array(&self, vertex_array: Option<VertexArrayId>);
+    #[track_caller]
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, data_type: u32, normalized: bool, stride: i32, offset: i32);
+    #[track_caller]
+    fn enable_vertex_attrib_array(&self, index: u32);
+
+    // Textures
+    #[track_caller]
+    fn create_texture(&self) -> Option<TextureId>;
+    #[track_caller]
+    fn delete_texture(&self, texture: TextureId);
+    #[track_caller]
+    fn bind_texture(&self, target: u32, texture: Option<TextureId>);
+    #[track_caller]
+    fn active_texture(&self, texture: u32);
+    #[track_caller]
+    fn tex_parameteri(&self, target: u32, pname: u32, param: i32);
+    /// Specify the level `level` image of the bound texture, with `data` as `UNSIGNED_BYTE`s
+    /// Without `data`, the storage is allocated and left zeroed
+    #[allow(clippy::too_many_arguments)]
+    #[track_caller]
+    fn tex_image_2d(&self, target: u32, level: i32, internal_format: i32, width: i32, height: i32, format: u32, data: Option<&[u8]>) -> Result<(), String>;
+
+    // Shaders and programs
+    #[track_caller]
+    fn create_shader(&self, shader_type: u32) -> Option<ShaderId>;
+    #[track_caller]
+    fn delete_shader(&self, shader: ShaderId);
+    #[track_caller]
+    fn shader_source(&self, shader: ShaderId, source: &str);
+    #[track_caller]
+    fn compile_shader(&self, shader: ShaderId);
+    #[track_caller]
+    fn shader_compile_status(&self, shader: ShaderId) -> bool;
+    #[track_caller]
+    fn shader_info_log(&self, shader: ShaderId) -> Option<String>;
+    #[track_caller]
+    fn create_program(&self) -> Option<ProgramId>;
+    #[track_caller]
+    fn delete_program(&self, program: ProgramId);
+    #[track_caller]
+    fn attach_shader(&self, program: ProgramId, shader: ShaderId);
+    #[track_caller]
+    fn link_program(&self, program: ProgramId);
+    #[track_caller]
+    fn program_link_status(&self, program: ProgramId) -> bool;
+    #[track_caller]
+    fn program_info_log(&self, program: ProgramId) -> Option<String>;
+    #[track_caller]
+    fn use_program(&self, program: Option<ProgramId>);
+
+    // Program introspection
+    /// Integer program parameter `pname`, i.e. `ACTIVE_UNIFORMS`
+    #[track_caller]
+    fn program_parameter(&self, program: ProgramId, pname: u32) -> i32;
+    #[track_caller]
+    fn active_uniform(&self, program: ProgramId, index: u32) -> Option<ActiveInfo>;
+    #[track_caller]
+    fn active_attrib(&self, program: ProgramId, index: u32) -> Option<ActiveInfo>;
+    #[track_caller]
+    fn attrib_location(&self, program: ProgramId, name: &str) -> i32;
+    /// Query integer parameter `pname`, i.e. `UNIFORM_OFFSET`, of each of the uniforms at `indices`
+    #[track_caller]
+    fn active_uniforms_parameter(&self, program: ProgramId, indices: &[u32], pname: u32) -> Vec<Option<i32>>;
+    #[track_caller]
+    fn active_uniform_block_name(&self, program: ProgramId, index: u32) -> Option<String>;
+    /// Integer parameter `pname`, i.e. `UNIFORM_BLOCK_DATA_SIZE`, of the uniform block at `index`
+    #[track_caller]
+    fn active_uniform_block_parameter(&self, program: ProgramId, index: u32, pname: u32) -> Option<i32>;
+
+    // Uniforms
+    #[track_caller]
+    fn uniform_location(&self, program: ProgramId, name: &str) -> Option<UniformLocationId>;
+    /// Set `value` on the uniform at `location` of the bound program
+    #[track_caller]
+    fn uniform(&self, location: UniformLocationId, value: &UniformValue);
+    #[track_caller]
+    fn uniform_block_index(&self, program: ProgramId, name: &str) -> u32;
+    #[track_caller]
+    fn uniform_block_binding(&self, program: ProgramId, index: u32, binding: u32);
+
+    // Drawing and global state
+    #[track_caller]
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32);
+    #[track_caller]
+    fn pixel_storei(&self, pname: u32, param: i32);
+    #[track_caller]
+    fn enable(&self, capability: u32);
+    #[track_caller]
+    fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32);
+    #[track_caller]
+    fn clear(&self, mask: u32);
+    #[track_caller]
+    fn draw_elements(&self, mode: u32, count: i32, data_type: u32, offset: i32);
+    /// Read `UNSIGNED_BYTE` pixels of the drawing buffer in `format` into `pixels`
+    #[allow(dead_code, clippy::too_many_arguments)]
+    #[track_caller]
+    fn read_pixels(&self, x: i32, y: i32, width: i32, height: i32, format: u32, pixels: &mut [u8]) -> Result<(), String>;
+    fn get_error(&self) -> u32;
+    /// Integer context parameter `pname`, i.e. `UNIFORM_BUFFER_OFFSET_ALIGNMENT`
+    #[track_caller]
+    fn parameter(&self, pname: u32) -> Option<i32>;
+    #[allow(dead_code)]
+    fn is_context_lost(&self) -> bool;
+
+    /// Name `object` for debugging, i.e. after the manager handle and label of the GL object that owns it
+    /// Backends that don't report on objects ignore the name
+    fn label_object(&self, _object: ObjectId, _label: &str) {}
+
+    /// Used to get at the concrete backend, i.e. the `WebGl2RenderingContext` of a `WebGlBackend`
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Shared handle to a GL backend, which every GL object keeps a clone of
+///
+/// Calls are made through `Deref`, i.e. `context.bind_buffer(Context::ARRAY_BUFFER, None)`
+#[derive(Clone)]
+pub struct Context(Rc<dyn GlBackend>);
+
+impl Context
+{
+    pub fn new<B: GlBackend + 'static>(backend: B) -> Context
+    {
+        Context(Rc::new(backend))
+    }
+
+    /// Get the backend if it is a `B`
+    #[allow(dead_code)]
+    pub fn backend<B: GlBackend + 'static>(&self) -> Option<&B>
+    {
+        self.0.as_any().downcast_ref::<B>()
+    }
+}
+
+impl Deref for Context
+{
+    type Target = dyn GlBackend;
+
+    fn deref(&self) -> &Self::Target
+    {
+        &*self.0
+    }
+}
+
+/// Re-export WebGL2's enum values on `Context`, so that they stay available to every backend
+macro_rules! gl_constants
+{
+    ($($name:ident),* $(,)?) =>
+    {
+        #[allow(dead_code)]
+        impl Context
+        {
+            $(pub const $name: u32 = WebGl2RenderingContext::$name;)*
+
+            /// Names and values of the constants, for describing calls
+            pub const CONSTANTS: &'static [(&'static str, u32)] = &[$((stringify!($name), WebGl2RenderingContext::$name)),*];
+
+            /// Name of the constant `value`, or its hex value if `Context` doesn't have it
+            pub fn constant_name(value: u32) -> String
+            {
+                Context::CONSTANTS.iter().find(|(_, constant)| *constant == value)
+                    .map_or_else(|| format!("{:#x}", value), |(name, _)| name.to_string())
+            }
+        }
+    };
+}
+gl_constants!
+{
+    // Errors
+    NO_ERROR, INVALID_ENUM, INVALID_VALUE, INVALID_OPERATION, INVALID_FRAMEBUFFER_OPERATION, OUT_OF_MEMORY, CONTEXT_LOST_WEBGL,
+    INVALID_INDEX,
+
+    // Data types
+    BYTE, UNSIGNED_BYTE, SHORT, UNSIGNED_SHORT, INT, UNSIGNED_INT, FLOAT, BOOL,
+    FLOAT_VEC2, FLOAT_VEC3, FLOAT_VEC4, INT_VEC2, INT_VEC3, INT_VEC4, UNSIGNED_INT_VEC2, UNSIGNED_INT_VEC3, UNSIGNED_INT_VEC4,
+    BOOL_VEC2, BOOL_VEC3, BOOL_VEC4,
+    FLOAT_MAT2, FLOAT_MAT3, FLOAT_MAT4, FLOAT_MAT2X3, FLOAT_MAT2X4, FLOAT_MAT3X2, FLOAT_MAT3X4, FLOAT_MAT4X2, FLOAT_MAT4X3,
+    SAMPLER_2D, SAMPLER_3D, SAMPLER_CUBE, SAMPLER_2D_SHADOW, SAMPLER_2D_ARRAY, SAMPLER_2D_ARRAY_SHADOW, SAMPLER_CUBE_SHADOW,
+    INT_SAMPLER_2D, INT_SAMPLER_3D, INT_SAMPLER_CUBE, INT_SAMPLER_2D_ARRAY,
+    UNSIGNED_INT_SAMPLER_2D, UNSIGNED_INT_SAMPLER_3D, UNSIGNED_INT_SAMPLER_CUBE, UNSIGNED_INT_SAMPLER_2D_ARRAY,
+
+    // Buffers
+    ARRAY_BUFFER, ELEMENT_ARRAY_BUFFER, UNIFORM_BUFFER, STATIC_DRAW, DYNAMIC_DRAW, STREAM_DRAW, BUFFER_SIZE,
+    ARRAY_BUFFER_BINDING, ELEMENT_ARRAY_BUFFER_BINDING, UNIFORM_BUFFER_BINDING, UNIFORM_BUFFER_START, UNIFORM_BUFFER_SIZE,
+    UNIFORM_BUFFER_OFFSET_ALIGNMENT,
+
+    // Vertex arrays
+    VERTEX_ARRAY_BINDING, VERTEX_ATTRIB_ARRAY_ENABLED, VERTEX_ATTRIB_ARRAY_SIZE, VERTEX_ATTRIB_ARRAY_BUFFER_BINDING,
+
+    // Textures
+    TEXTURE_2D, TEXTURE0, TEXTURE1, TEXTURE2, TEXTURE3, TEXTURE_BINDING_2D,
+    TEXTURE_WRAP_S, TEXTURE_WRAP_T, TEXTURE_MIN_FILTER, TEXTURE_MAG_FILTER,
+    REPEAT, CLAMP_TO_EDGE, MIRRORED_REPEAT, NEAREST, LINEAR, LINEAR_MIPMAP_LINEAR,
+    ALPHA, LUMINANCE, LUMINANCE_ALPHA, RED, RG, RGB, RGBA,
+    R8, RG8, RGB8, RGBA8, SRGB8, SRGB8_ALPHA8, R16F, RGBA16F, RGB32F, RGBA32F, RGB565, RGBA4, RGB5_A1,
+    UNPACK_ALIGNMENT,
+
+    // Shaders
+    VERTEX_SHADER, FRAGMENT_SHADER, COMPILE_STATUS, LINK_STATUS, CURRENT_PROGRAM,
+    ACTIVE_UNIFORMS, ACTIVE_ATTRIBUTES, ACTIVE_UNIFORM_BLOCKS,
+    UNIFORM_BLOCK_INDEX, UNIFORM_OFFSET, UNIFORM_ARRAY_STRIDE, UNIFORM_MATRIX_STRIDE,
+    UNIFORM_BLOCK_BINDING, UNIFORM_BLOCK_DATA_SIZE,
+
+    // Drawing
+    POINTS, LINES, TRIANGLES, TRIANGLE_STRIP, CULL_FACE, DEPTH_TEST, BLEND,
+    COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT,
+}
@@ -0,0 +1,901 @@
+//! Backend that records GL calls instead of making them
+//!
+//! Object ids, bindings and program introspection are simulated well enough for `gfx` to run
+//! without a browser, so that the calls it makes can be checked in native tests
+
+#![allow(dead_code)]
+
+use std::
+{
+    any::Any,
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+};
+use crate::gfx::
+{
+    Context,
+    backend::*,
+    gl_object::uniform::UniformValue,
+};
+
+/// A call that changed GL state, as recorded by a `RecordingBackend`
+#[derive(Debug, Clone, PartialEq)]
+pub enum GlCall
+{
+    CreateBuffer(BufferId),
+    DeleteBuffer(BufferId),
+    BindBuffer(u32, Option<BufferId>),
+    BufferData(u32, Vec<u8>, u32),
+    BufferDataSize(u32, i32, u32),
+    BufferSubData(u32, i32, Vec<u8>),
+    BindBufferRange(u32, u32, Option<BufferId>, i32, i32),
+
+    CreateVertexArray(VertexArrayId),
+    DeleteVertexArray(VertexArrayId),
+    BindVertexArray(Option<VertexArrayId>),
+    VertexAttribPointer { index: u32, size: i32, data_type: u32, normalized: bool, stride: i32, offset: i32 },
+    EnableVertexAttribArray(u32),
+
+    CreateTexture(TextureId),
+    DeleteTexture(TextureId),
+    BindTexture(u32, Option<TextureId>),
+    ActiveTexture(u32),
+    TexParameteri(u32, u32, i32),
+    TexImage2d { target: u32, level: i32, internal_format: i32, width: i32, height: i32, format: u32, data: Option<Vec<u8>> },
+
+    CreateShader(ShaderId, u32),
+    DeleteShader(ShaderId),
+    ShaderSource(ShaderId, String),
+    CompileShader(ShaderId),
+    CreateProgram(ProgramId),
+    DeleteProgram(ProgramId),
+    AttachShader(ProgramId, ShaderId),
+    LinkProgram(ProgramId),
+    UseProgram(Option<ProgramId>),
+    Uniform(UniformLocationId, UniformValue),
+    UniformBlockBinding(ProgramId, u32, u32),
+
+    Viewport(i32, i32, i32, i32),
+    PixelStorei(u32, i32),
+    Enable(u32),
+    ClearColor(f32, f32, f32, f32),
+    Clear(u32),
+    DrawElements { mode: u32, count: i32, data_type: u32, offset: i32 },
+}
+
+/// A uniform of a simulated program
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockUniform
+{
+    pub info: ActiveInfo,
+    /// Index of the uniform block that the uniform is in and its byte offset within it
+    pub block: Option<(u32, i32)>,
+}
+
+/// What a `RecordingBackend` reports as active in the programs it links
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProgramInfo
+{
+    pub uniforms: Vec<MockUniform>,
+    /// Attributes along with their locations
+    pub attribs: Vec<(ActiveInfo, u32)>,
+    /// Names and data sizes of uniform blocks
+    pub blocks: Vec<(String, i32)>,
+}
+
+impl ProgramInfo
+{
+    /// Add a uniform of GLSL type `data_type` to the default block
+    pub fn uniform(mut self, name: &str, data_type: u32) -> ProgramInfo
+    {
+        self.uniforms.push(MockUniform { info: ActiveInfo { name: name.to_string(), data_type, size: 1 }, block: None });
+        self
+    }
+
+    /// Add a uniform of GLSL type `data_type` at `offset` within the block `block_name`, which must already be added
+    pub fn block_uniform(mut self, name: &str, data_type: u32, block_name: &str, offset: i32) -> ProgramInfo
+    {
+        let block = self.blocks.iter().position(|(name, _)| name == block_name).expect("block added before its uniforms") as u32;
+        self.uniforms.push(MockUniform { info: ActiveInfo { name: name.to_string(), data_type, size: 1 }, block: Some((block, offset)) });
+        self
+    }
+
+    /// Add an attribute of GLSL type `data_type` at `location`
+    pub fn attrib(mut self, name: &str, data_type: u32, location: u32) -> ProgramInfo
+    {
+        self.attribs.push((ActiveInfo { name: name.to_string(), data_type, size: 1 }, location));
+        self
+    }
+
+    /// Add a uniform block that needs `data_size` bytes
+    pub fn block(mut self, name: &str, data_size: i32) -> ProgramInfo
+    {
+        self.blocks.push((name.to_string(), data_size));
+        self
+    }
+
+    /// Find the default block uniform `name`, where arrays can be named with or without `[0]`
+    fn default_block_uniform(&self, name: &str) -> Option<&MockUniform>
+    {
+        let name = name.trim_end_matches("[0]");
+        self.uniforms.iter().find(|uniform| uniform.block.is_none() && uniform.info.name.trim_end_matches("[0]") == name)
+    }
+}
+
+struct ShaderState
+{
+    compiled: bool,
+    info_log: Option<String>,
+}
+
+#[derive(Default)]
+struct ProgramState
+{
+    shaders: Vec<ShaderId>,
+    // Set once the program is successfully linked
+    info: Option<ProgramInfo>,
+    info_log: Option<String>,
+    block_bindings: HashMap<u32, u32>,
+}
+
+#[derive(Default)]
+struct State
+{
+    calls: Vec<GlCall>,
+    errors: VecDeque<u32>,
+    next_id: u32,
+
+    buffers: HashSet<BufferId>,
+    vertex_arrays: HashSet<VertexArrayId>,
+    textures: HashSet<TextureId>,
+    shaders: HashMap<ShaderId, ShaderState>,
+    programs: HashMap<ProgramId, ProgramState>,
+    uniform_locations: HashMap<UniformLocationId, (ProgramId, String)>,
+
+    // Bound buffers by target, except ELEMENT_ARRAY_BUFFER which belongs to the bound vertex array
+    buffer_bindings: HashMap<u32, BufferId>,
+    element_bindings: HashMap<Option<VertexArrayId>, BufferId>,
+    vertex_array_binding: Option<VertexArrayId>,
+    texture_bindings: HashMap<u32, TextureId>,
+    program_binding: Option<ProgramId>,
+
+    program_info: ProgramInfo,
+    compile_error: Option<String>,
+}
+
+impl State
+{
+    fn next_id(&mut self) -> u32
+    {
+        // Ids start at 1, like GL object names
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn error(&mut self, error: u32)
+    {
+        self.errors.push_back(error);
+    }
+
+    fn bound_buffer(&self, target: u32) -> Option<BufferId>
+    {
+        match target
+        {
+            Context::ELEMENT_ARRAY_BUFFER => self.element_bindings.get(&self.vertex_array_binding).copied(),
+            _ => self.buffer_bindings.get(&target).copied(),
+        }
+    }
+
+    fn linked_program(&self, program: ProgramId) -> Option<&ProgramInfo>
+    {
+        self.programs.get(&program)?.info.as_ref()
+    }
+}
+
+/// Backend that records every call that changes GL state, for native tests
+///
+/// Objects get ids as they would in GL, and using an id that doesn't belong to a live object,
+/// or calls that need a binding that isn't there, push `INVALID_OPERATION` to the error queue
+/// Deleting an unknown id is an error too, unlike in GL, so that double deletes show up
+///
+/// Shaders compile unless `set_compile_error` is used, and linked programs report whatever
+/// was given to `set_program_info` as active
+pub struct RecordingBackend
+{
+    state: RefCell<State>,
+}
+
+impl RecordingBackend
+{
+    pub fn new() -> RecordingBackend
+    {
+        RecordingBackend { state: RefCell::new(State::default()) }
+    }
+
+    /// Every recorded call so far
+    pub fn calls(&self) -> Vec<GlCall>
+    {
+        self.state.borrow().calls.clone()
+    }
+
+    /// Every recorded call since the last time the calls were taken
+    pub fn take_calls(&self) -> Vec<GlCall>
+    {
+        std::mem::take(&mut self.state.borrow_mut().calls)
+    }
+
+    /// Queue `error` to be returned by `get_error`
+    pub fn push_error(&self, error: u32)
+    {
+        self.state.borrow_mut().error(error);
+    }
+
+    /// Set what programs linked from now on report as active
+    pub fn set_program_info(&self, info: ProgramInfo)
+    {
+        self.state.borrow_mut().program_info = info;
+    }
+
+    /// Make shaders compiled from now on fail with `info_log`, or succeed again with `None`
+    pub fn set_compile_error(&self, info_log: Option<String>)
+    {
+        self.state.borrow_mut().compile_error = info_log;
+    }
+
+    /// Number of objects that have been created but not deleted
+    pub fn live_objects(&self) -> usize
+    {
+        let state = self.state.borrow();
+        state.buffers.len() + state.vertex_arrays.len() + state.textures.len() + state.shaders.len() + state.programs.len()
+    }
+
+    /// Binding point of the uniform block at `index` of `program`, as set by `uniform_block_binding`
+    pub fn block_binding(&self, program: ProgramId, index: u32) -> Option<u32>
+    {
+        self.state.borrow().programs.get(&program)?.block_bindings.get(&index).copied()
+    }
+
+    /// Program and name of the uniform that `location` was looked up for, while the program is alive
+    pub fn uniform_name(&self, location: UniformLocationId) -> Option<(ProgramId, String)>
+    {
+        self.state.borrow().uniform_locations.get(&location).cloned()
+    }
+
+    fn record(&self, call: GlCall)
+    {
+        self.state.borrow_mut().calls.push(call);
+    }
+
+    /// Push `INVALID_OPERATION` unless the state is `valid` for the call
+    fn check(&self, valid: impl FnOnce(&State) -> bool)
+    {
+        let valid = valid(&self.state.borrow());
+        if !valid
+        {
+            self.push_error(Context::INVALID_OPERATION);
+        }
+    }
+}
+
+impl Default for RecordingBackend
+{
+    fn default() -> RecordingBackend
+    {
+        RecordingBackend::new()
+    }
+}
+
+impl GlBackend for RecordingBackend
+{
+    fn create_buffer(&self) -> Option<BufferId>
+    {
+        let mut state = self.state.borrow_mut();
+        let buffer = BufferId(state.next_id());
+        state.buffers.insert(buffer);
+        state.calls.push(GlCall::CreateBuffer(buffer));
+        Some(buffer)
+    }
+
+    fn delete_buffer(&self, buffer: BufferId)
+    {
+        self.record(GlCall::DeleteBuffer(buffer));
+        let mut state = self.state.borrow_mut();
+        if state.buffers.remove(&buffer)
+        {
+            state.buffer_bindings.retain(|_, bound| *bound != buffer);
+            state.element_bindings.retain(|_, bound| *bound != buffer);
+        }
+        else
+        {
+            state.error(Context::INVALID_OPERATION);
+        }
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: Option<BufferId>)
+    {
+        self.record(GlCall::BindBuffer(target, buffer));
+        let mut state = self.state.borrow_mut();
+        if buffer.is_some_and(|buffer| !state.buffers.contains(&buffer))
+        {
+            return state.error(Context::INVALID_OPERATION);
+        }
+        match (target, buffer)
+        {
+            (Context::ELEMENT_ARRAY_BUFFER, Some(buffer)) => { let vertex_array = state.vertex_array_binding; state.element_bindings.insert(vertex_array, buffer); },
+            (Context::ELEMENT_ARRAY_BUFFER, None) => { let vertex_array = state.vertex_array_binding; state.element_bindings.remove(&vertex_array); },
+            (target, Some(buffer)) => { state.buffer_bindings.insert(target, buffer); },
+            (target, None) => { state.buffer_bindings.remove(&target); },
+        }
+    }
+
+    fn buffer_data(&self, target: u32, data: &[u8], usage: u32)
+    {
+        self.record(GlCall::BufferData(target, data.to_vec(), usage));
+        self.check(|state| state.bound_buffer(target).is_some());
+    }
+
+    fn buffer_data_size(&self, target: u32, size: i32, usage: u32)
+    {
+        self.record(GlCall::BufferDataSize(target, size, usage));
+        self.check(|state| state.bound_buffer(target).is_some());
+    }
+
+    fn buffer_sub_data(&self, target: u32, offset: i32, data: &[u8])
+    {
+        self.record(GlCall::BufferSubData(target, offset, data.to_vec()));
+        self.check(|state| state.bound_buffer(target).is_some());
+    }
+
+    fn bind_buffer_range(&self, target: u32, index: u32, buffer: Option<BufferId>, offset: i32, size: i32)
+    {
+        self.record(GlCall::BindBufferRange(target, index, buffer, offset, size));
+        self.check(|state| buffer.is_none_or(|buffer| state.buffers.contains(&buffer)));
+    }
+
+    fn create_vertex_array(&self) -> Option<VertexArrayId>
+    {
+        let mut state = self.state.borrow_mut();
+        let vertex_array = VertexArrayId(state.next_id());
+        state.vertex_arrays.insert(vertex_array);
+        state.calls.push(GlCall::CreateVertexArray(vertex_array));
+        Some(vertex_array)
+    }
+
+    fn delete_vertex_array(&self, vertex_array: VertexArrayId)
+    {
+        self.record(GlCall::DeleteVertexArray(vertex_array));
+        let mut state = self.state.borrow_mut();
+        if state.vertex_arrays.remove(&vertex_array)
+        {
+            state.element_bindings.remove(&Some(vertex_array));
+            if state.vertex_array_binding == Some(vertex_array)
+            {
+                state.vertex_array_binding = None;
+            }
+        }
+        else
+        {
+            state.error(Context::INVALID_OPERATION);
+        }
+    }
+
+    fn bind_vertex_array(&self, vertex_array: Option<VertexArrayId>)
+    {
+        self.record(GlCall::BindVertexArray(vertex_array));
+        let mut state = self.state.borrow_mut();
+        if vertex_array.is_some_and(|vertex_array| !state.vertex_arrays.contains(&vertex_array))
+        {
+            return state.error(Context::INVALID_OPERATION);
+        }
+        state.vertex_array_binding = vertex_array;
+    }
+
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, data_type: u32, normalized: bool, stride: i32, offset: i32)
+    {
+        self.record(GlCall::VertexAttribPointer { index, size, data_type, normalized, stride, offset });
+        // Attributes are sourced from the bound array buffer
+        self.check(|state| state.bound_buffer(Context::ARRAY_BUFFER).is_some());
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32)
+    {
+        self.record(GlCall::EnableVertexAttribArray(index));
+    }
+
+    fn create_texture(&self) -> Option<TextureId>
+    {
+        let mut state = self.state.borrow_mut();
+        let texture = TextureId(state.next_id());
+        state.textures.insert(texture);
+        state.calls.push(GlCall::CreateTexture(texture));
+        Some(texture)
+    }
+
+    fn delete_texture(&self, texture: TextureId)
+    {
+        self.record(GlCall::DeleteTexture(texture));
+        let mut state = self.state.borrow_mut();
+        if state.textures.remove(&texture)
+        {
+            state.texture_bindings.retain(|_, bound| *bound != texture);
+        }
+        else
+        {
+            state.error(Context::INVALID_OPERATION);
+        }
+    }
+
+    fn bind_texture(&self, target: u32, texture: Option<TextureId>)
+    {
+        self.record(GlCall::BindTexture(target, texture));
+        let mut state = self.state.borrow_mut();
+        match texture
+        {
+            Some(texture) if !state.textures.contains(&texture) => state.error(Context::INVALID_OPERATION),
+            Some(texture) => { state.texture_bindings.insert(target, texture); },
+            None => { state.texture_bindings.remove(&target); },
+        }
+    }
+
+    fn active_texture(&self, texture: u32)
+    {
+        self.record(GlCall::ActiveTexture(texture));
+    }
+
+    fn tex_parameteri(&self, target: u32, pname: u32, param: i32)
+    {
+        self.record(GlCall::TexParameteri(target, pname, param));
+        self.check(|state| state.texture_bindings.contains_key(&target));
+    }
+
+    fn tex_image_2d(&self, target: u32, level: i32, internal_format: i32, width: i32, height: i32, format: u32, data: Option<&[u8]>) -> Result<(), String>
+    {
+        self.record(GlCall::TexImage2d { target, level, internal_format, width, height, format, data: data.map(|data| data.to_vec()) });
+        self.check(|state| state.texture_bindings.contains_key(&target));
+        Ok(())
+    }
+
+    fn create_shader(&self, shader_type: u32) -> Option<ShaderId>
+    {
+        let mut state = self.state.borrow_mut();
+        let shader = ShaderId(state.next_id());
+        state.shaders.insert(shader, ShaderState { compiled: false, info_log: None });
+        state.calls.push(GlCall::CreateShader(shader, shader_type));
+        Some(shader)
+    }
+
+    fn delete_shader(&self, shader: ShaderId)
+    {
+        self.record(GlCall::DeleteShader(shader));
+        let mut state = self.state.borrow_mut();
+        if state.shaders.remove(&shader).is_none()
+        {
+            state.error(Context::INVALID_OPERATION);
+        }
+    }
+
+    fn shader_source(&self, shader: ShaderId, source: &str)
+    {
+        self.record(GlCall::ShaderSource(shader, source.to_string()));
+        self.check(|state| state.shaders.contains_key(&shader));
+    }
+
+    fn compile_shader(&self, shader: ShaderId)
+    {
+        self.record(GlCall::CompileShader(shader));
+        let mut state = self.state.borrow_mut();
+        let compile_error = state.compile_error.clone();
+        match state.shaders.get_mut(&shader)
+        {
+            Some(shader) =>
+            {
+                shader.compiled = compile_error.is_none();
+                shader.info_log = compile_error;
+            },
+            None => state.error(Context::INVALID_OPERATION),
+        }
+    }
+
+    fn shader_compile_status(&self, shader: ShaderId) -> bool
+    {
+        self.state.borrow().shaders.get(&shader).is_some_and(|shader| shader.compiled)
+    }
+
+    fn shader_info_log(&self, shader: ShaderId) -> Option<String>
+    {
+        let state = self.state.borrow();
+        let shader = state.shaders.get(&shader)?;
+        Some(shader.info_log.clone().unwrap_or_default())
+    }
+
+    fn create_program(&self) -> Option<ProgramId>
+    {
+        let mut state = self.state.borrow_mut();
+        let program = ProgramId(state.next_id());
+        state.programs.insert(program, ProgramState::default());
+        state.calls.push(GlCall::CreateProgram(program));
+        Some(program)
+    }
+
+    fn delete_program(&self, program: ProgramId)
+    {
+        self.record(GlCall::DeleteProgram(program));
+        let mut state = self.state.borrow_mut();
+        if state.programs.remove(&program).is_some()
+        {
+            state.uniform_locations.retain(|_, (location_program, _)| *location_program != program);
+            if state.program_binding == Some(program)
+            {
+                state.program_binding = None;
+            }
+        }
+        else
+        {
+            state.error(Context::INVALID_OPERATION);
+        }
+    }
+
+    fn attach_shader(&self, program: ProgramId, shader: ShaderId)
+    {
+        self.record(GlCall::AttachShader(program, shader));
+        let mut state = self.state.borrow_mut();
+        if !state.shaders.contains_key(&shader)
+        {
+            return state.error(Context::INVALID_OPERATION);
+        }
+        match state.programs.get_mut(&program)
+        {
+            Some(program) => program.shaders.push(shader),
+            None => state.error(Context::INVALID_OPERATION),
+        }
+    }
+
+    fn link_program(&self, program: ProgramId)
+    {
+        self.record(GlCall::LinkProgram(program));
+        let mut state = self.state.borrow_mut();
+        let State { programs, shaders, program_info, errors, .. } = &mut *state;
+        match programs.get_mut(&program)
+        {
+            Some(program) =>
+            {
+                let compiled = !program.shaders.is_empty() && program.shaders.iter()
+                    .all(|shader| shaders.get(shader).is_some_and(|shader| shader.compiled));
+                program.block_bindings.clear();
+                if compiled
+                {
+                    program.info = Some(program_info.clone());
+                    program.info_log = None;
+                }
+                else
+                {
+                    program.info = None;
+                    program.info_log = Some("Attached shaders aren't compiled".to_string());
+                }
+            },
+            None => errors.push_back(Context::INVALID_OPERATION),
+        }
+    }
+
+    fn program_link_status(&self, program: ProgramId) -> bool
+    {
+        self.state.borrow().linked_program(program).is_some()
+    }
+
+    fn program_info_log(&self, program: ProgramId) -> Option<String>
+    {
+        let state = self.state.borrow();
+        let program = state.programs.get(&program)?;
+        Some(program.info_log.clone().unwrap_or_default())
+    }
+
+    fn use_program(&self, program: Option<ProgramId>)
+    {
+        self.record(GlCall::UseProgram(program));
+        let mut state = self.state.borrow_mut();
+        if program.is_some_and(|program| state.linked_program(program).is_none())
+        {
+            return state.error(Context::INVALID_OPERATION);
+        }
+        state.program_binding = program;
+    }
+
+    fn program_parameter(&self, program: ProgramId, pname: u32) -> i32
+    {
+        let state = self.state.borrow();
+        let info = match state.linked_program(program)
+        {
+            Some(info) => info,
+            None => return 0,
+        };
+        match pname
+        {
+            Context::ACTIVE_UNIFORMS => info.uniforms.len() as i32,
+            Context::ACTIVE_ATTRIBUTES => info.attribs.len() as i32,
+            Context::ACTIVE_UNIFORM_BLOCKS => info.blocks.len() as i32,
+            Context::LINK_STATUS => 1,
+            _ => 0,
+        }
+    }
+
+    fn active_uniform(&self, program: ProgramId, index: u32) -> Option<ActiveInfo>
+    {
+        Some(self.state.borrow().linked_program(program)?.uniforms.get(index as usize)?.info.clone())
+    }
+
+    fn active_attrib(&self, program: ProgramId, index: u32) -> Option<ActiveInfo>
+    {
+        Some(self.state.borrow().linked_program(program)?.attribs.get(index as usize)?.0.clone())
+    }
+
+    fn attrib_location(&self, program: ProgramId, name: &str) -> i32
+    {
+        let state = self.state.borrow();
+        state.linked_program(program)
+            .and_then(|info| info.attribs.iter().find(|(attrib, _)| attrib.name == name))
+            .map_or(-1, |(_, location)| *location as i32)
+    }
+
+    fn active_uniforms_parameter(&self, program: ProgramId, indices: &[u32], pname: u32) -> Vec<Option<i32>>
+    {
+        let state = self.state.borrow();
+        let info = state.linked_program(program);
+        indices.iter().map(|&index|
+            {
+                let uniform = info?.uniforms.get(index as usize)?;
+                match (pname, uniform.block)
+                {
+                    (Context::UNIFORM_BLOCK_INDEX, Some((block, _))) => Some(block as i32),
+                    (Context::UNIFORM_OFFSET, Some((_, offset))) => Some(offset),
+                    // Only non-array, non-matrix uniforms are simulated within blocks
+                    (Context::UNIFORM_ARRAY_STRIDE, Some(_)) | (Context::UNIFORM_MATRIX_STRIDE, Some(_)) => Some(0),
+                    // Uniforms in the default block report -1 for everything
+                    (Context::UNIFORM_BLOCK_INDEX, None) | (Context::UNIFORM_OFFSET, None) |
+                    (Context::UNIFORM_ARRAY_STRIDE, None) | (Context::UNIFORM_MATRIX_STRIDE, None) => Some(-1),
+                    _ => None,
+                }
+            }).collect()
+    }
+
+    fn active_uniform_block_name(&self, program: ProgramId, index: u32) -> Option<String>
+    {
+        Some(self.state.borrow().linked_program(program)?.blocks.get(index as usize)?.0.clone())
+    }
+
+    fn active_uniform_block_parameter(&self, program: ProgramId, index: u32, pname: u32) -> Option<i32>
+    {
+        let state = self.state.borrow();
+        let data_size = state.linked_program(program)?.blocks.get(index as usize)?.1;
+        match pname
+        {
+            Context::UNIFORM_BLOCK_DATA_SIZE => Some(data_size),
+            Context::UNIFORM_BLOCK_BINDING => Some(state.programs.get(&program)?.block_bindings.get(&index).copied().unwrap_or(0) as i32),
+            _ => None,
+        }
+    }
+
+    fn uniform_location(&self, program: ProgramId, name: &str) -> Option<UniformLocationId>
+    {
+        let mut state = self.state.borrow_mut();
+        state.linked_program(program)?.default_block_uniform(name)?;
+        let location = UniformLocationId(state.next_id());
+        state.uniform_locations.insert(location, (program, name.to_string()));
+        Some(location)
+    }
+
+    fn uniform(&self, location: UniformLocationId, value: &UniformValue)
+    {
+        self.record(GlCall::Uniform(location, value.clone()));
+        // Locations can only be set on the program they came from, and only while it is bound
+        self.check(|state| state.program_binding.is_some() && state.uniform_locations.get(&location).map(|(program, _)| *program) == state.program_binding);
+    }
+
+    fn uniform_block_index(&self, program: ProgramId, name: &str) -> u32
+    {
+        self.state.borrow().linked_program(program)
+            .and_then(|info| info.blocks.iter().position(|(block, _)| block == name))
+            .map_or(Context::INVALID_INDEX, |index| index as u32)
+    }
+
+    fn uniform_block_binding(&self, program: ProgramId, index: u32, binding: u32)
+    {
+        self.record(GlCall::UniformBlockBinding(program, index, binding));
+        let mut state = self.state.borrow_mut();
+        let num_blocks = state.linked_program(program).map(|info| info.blocks.len());
+        match (num_blocks, state.programs.get_mut(&program))
+        {
+            (Some(num_blocks), Some(program)) if (index as usize) < num_blocks => { program.block_bindings.insert(index, binding); },
+            (Some(_), Some(_)) => state.error(Context::INVALID_VALUE),
+            _ => state.error(Context::INVALID_OPERATION),
+        }
+    }
+
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32)
+    {
+        self.record(GlCall::Viewport(x, y, width, height));
+    }
+
+    fn pixel_storei(&self, pname: u32, param: i32)
+    {
+        self.record(GlCall::PixelStorei(pname, param));
+    }
+
+    fn enable(&self, capability: u32)
+    {
+        self.record(GlCall::Enable(capability));
+    }
+
+    fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32)
+    {
+        self.record(GlCall::ClearColor(red, green, blue, alpha));
+    }
+
+    fn clear(&self, mask: u32)
+    {
+        self.record(GlCall::Clear(mask));
+    }
+
+    fn draw_elements(&self, mode: u32, count: i32, data_type: u32, offset: i32)
+    {
+        self.record(GlCall::DrawElements { mode, count, data_type, offset });
+        self.check(|state| state.program_binding.is_some() && state.bound_buffer(Context::ELEMENT_ARRAY_BUFFER).is_some());
+    }
+
+    /// Nothing is drawn, so the pixels are all zeros
+    fn read_pixels(&self, _x: i32, _y: i32, _width: i32, _height: i32, _format: u32, pixels: &mut [u8]) -> Result<(), String>
+    {
+        pixels.fill(0);
+        Ok(())
+    }
+
+    fn get_error(&self) -> u32
+    {
+        self.state.borrow_mut().errors.pop_front().unwrap_or(Context::NO_ERROR)
+    }
+
+    fn parameter(&self, pname: u32) -> Option<i32>
+    {
+        let state = self.state.borrow();
+        // Bindings are reported as ids, with 0 for nothing bound
+        match pname
+        {
+            Context::UNIFORM_BUFFER_OFFSET_ALIGNMENT => Some(256),
+            Context::ARRAY_BUFFER_BINDING => Some(state.bound_buffer(Context::ARRAY_BUFFER).map_or(0, |buffer| buffer.0 as i32)),
+            Context::ELEMENT_ARRAY_BUFFER_BINDING => Some(state.bound_buffer(Context::ELEMENT_ARRAY_BUFFER).map_or(0, |buffer| buffer.0 as i32)),
+            Context::UNIFORM_BUFFER_BINDING => Some(state.bound_buffer(Context::UNIFORM_BUFFER).map_or(0, |buffer| buffer.0 as i32)),
+            Context::VERTEX_ARRAY_BINDING => Some(state.vertex_array_binding.map_or(0, |vertex_array| vertex_array.0 as i32)),
+            Context::TEXTURE_BINDING_2D => Some(state.texture_bindings.get(&Context::TEXTURE_2D).map_or(0, |texture| texture.0 as i32)),
+            Context::CURRENT_PROGRAM => Some(state.program_binding.map_or(0, |program| program.0 as i32)),
+            _ => None,
+        }
+    }
+
+    fn is_context_lost(&self) -> bool
+    {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::gfx::
+    {
+        Context,
+        GfxError,
+        GlError,
+        gl_get_errors,
+        backend::{*, recording::*},
+    };
+
+    fn new_context() -> Context
+    {
+        Context::new(RecordingBackend::new())
+    }
+
+    fn recording(context: &Context) -> &RecordingBackend
+    {
+        context.backend::<RecordingBackend>().expect("recording backend")
+    }
+
+    #[test]
+    fn test_object_ids()
+    {
+        let context = new_context();
+        let buffer = context.create_buffer().expect("buffer");
+        let texture = context.create_texture().expect("texture");
+        assert_eq!(BufferId(1), buffer);
+        assert_eq!(TextureId(2), texture);
+        assert_eq!(2, recording(&context).live_objects());
+
+        context.bind_buffer(Context::ARRAY_BUFFER, Some(buffer));
+        assert_eq!(Some(1), context.parameter(Context::ARRAY_BUFFER_BINDING));
+        context.delete_buffer(buffer);
+        // Deleting a bound object unbinds it
+        assert_eq!(Some(0), context.parameter(Context::ARRAY_BUFFER_BINDING));
+        assert_eq!(GfxError::GlErrors(vec![GlError::NoError]), gl_get_errors(&context));
+
+        context.bind_buffer(Context::ARRAY_BUFFER, Some(buffer));
+        context.delete_buffer(buffer);
+        context.buffer_data(Context::ARRAY_BUFFER, &[0], Context::STATIC_DRAW);
+        assert_eq!(GfxError::GlErrors(vec![GlError::InvalidOperation; 3]), gl_get_errors(&context));
+
+        assert_eq!(vec![
+            GlCall::CreateBuffer(buffer),
+            GlCall::CreateTexture(texture),
+            GlCall::BindBuffer(Context::ARRAY_BUFFER, Some(buffer)),
+            GlCall::DeleteBuffer(buffer),
+            GlCall::BindBuffer(Context::ARRAY_BUFFER, Some(buffer)),
+            GlCall::DeleteBuffer(buffer),
+            GlCall::BufferData(Context::ARRAY_BUFFER, vec![0], Context::STATIC_DRAW),
+        ], recording(&context).take_calls());
+        assert!(recording(&context).calls().is_empty());
+    }
+
+    #[test]
+    fn test_element_binding_belongs_to_vertex_array()
+    {
+        let context = new_context();
+        let vertex_array = context.create_vertex_array().expect("vertex array");
+        let buffer = context.create_buffer().expect("buffer");
+        context.bind_vertex_array(Some(vertex_array));
+        context.bind_buffer(Context::ELEMENT_ARRAY_BUFFER, Some(buffer));
+        context.bind_vertex_array(None);
+        assert_eq!(Some(0), context.parameter(Context::ELEMENT_ARRAY_BUFFER_BINDING));
+        context.bind_vertex_array(Some(vertex_array));
+        assert_eq!(Some(buffer.0 as i32), context.parameter(Context::ELEMENT_ARRAY_BUFFER_BINDING));
+    }
+
+    #[test]
+    fn test_programs()
+    {
+        let context = new_context();
+        recording(&context).set_program_info(ProgramInfo::default()
+            .uniform("tex", Context::SAMPLER_2D)
+            .block("Material", 32)
+            .block_uniform("Diffuse", Context::FLOAT_VEC3, "Material", 0)
+            .attrib("Vertex", Context::FLOAT_VEC3, 0));
+
+        let program = context.create_program().expect("program");
+        let shader = context.create_shader(Context::VERTEX_SHADER).expect("shader");
+        context.compile_shader(shader);
+        context.attach_shader(program, shader);
+        context.link_program(program);
+        assert!(context.program_link_status(program));
+        assert_eq!(2, context.program_parameter(program, Context::ACTIVE_UNIFORMS));
+        assert_eq!(0, context.attrib_location(program, "Vertex"));
+        assert_eq!(vec![Some(-1), Some(0)], context.active_uniforms_parameter(program, &[0, 1], Context::UNIFORM_BLOCK_INDEX));
+        assert_eq!(Some(32), context.active_uniform_block_parameter(program, 0, Context::UNIFORM_BLOCK_DATA_SIZE));
+        assert_eq!(Context::INVALID_INDEX, context.uniform_block_index(program, "VertData"));
+        // Only default block uniforms have locations
+        assert!(context.uniform_location(program, "Diffuse").is_none());
+
+        let location = context.uniform_location(program, "tex").expect("tex location");
+        context.uniform(location, &UniformValue::Int(1, vec![0]));
+        context.use_program(Some(program));
+        context.uniform(location, &UniformValue::Int(1, vec![0]));
+        context.uniform_block_binding(program, 0, 3);
+        assert_eq!(Some(3), recording(&context).block_binding(program, 0));
+        // Uniform set without the program bound
+        assert_eq!(GfxError::GlErrors(vec![GlError::InvalidOperation]), gl_get_errors(&context));
+
+        recording(&context).set_compile_error(Some("ERROR: 0:1: syntax error".to_string()));
+        let other = context.create_program().expect("program");
+        let shader = context.create_shader(Context::VERTEX_SHADER).expect("shader");
+        context.compile_shader(shader);
+        assert!(!context.shader_compile_status(shader));
+        assert_eq!(Some("ERROR: 0:1: syntax error".to_string()), context.shader_info_log(shader));
+        context.attach_shader(other, shader);
+        context.link_program(other);
+        assert!(!context.program_link_status(other));
+
+        context.delete_program(program);
+        context.uniform(location, &UniformValue::Int(1, vec![0]));
+        assert_eq!(GfxError::GlErrors(vec![GlError::InvalidOperation]), gl_get_errors(&context));
+    }
+}
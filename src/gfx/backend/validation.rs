@@ -0,0 +1,681 @@
+//! Debug layer that checks for GL errors after every call
+//!
+//! Errors are reported with the call that caused them, the line that made it and the objects that
+//! were bound at the time, instead of showing up in `gl_get_errors` frames later.
+//! Objects are named after their manager handle and label, once the manager has named them with `label_object`
+
+use std::
+{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    panic::Location,
+};
+use crate::gfx::
+{
+    Context,
+    GlError,
+    backend::*,
+    gl_object::uniform::UniformValue,
+};
+
+/// What a `ValidatingBackend` does with the errors it finds
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OnGlError
+{
+    /// Log the error to the console and carry on
+    #[cfg_attr(not(feature = "debug"), allow(dead_code))]
+    Log,
+    /// Panic, stopping at the first error
+    #[allow(dead_code)]
+    Panic,
+}
+
+/// GL errors raised by a single call
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlCallError
+{
+    pub errors: Vec<GlError>,
+    /// The call and its arguments, i.e. `bind_buffer(ARRAY_BUFFER, Some(BufferId(3) Handle<ArrayBuffer>(..) "robot vertices"))`
+    pub call: String,
+    /// Where in the Rust source the call was made, which is outside of `gfx` for calls made through GL objects
+    pub location: &'static Location<'static>,
+    /// Objects that were bound when the call was made
+    pub bindings: String,
+}
+
+impl fmt::Display for GlCallError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let errors: Vec<String> = self.errors.iter().map(|error| error.to_string()).collect();
+        write!(f, "GL error {} from {} at {}\n  bound: {}", errors.join(", "), self.call, self.location, self.bindings)
+    }
+}
+
+/// Shorthand for `Context::constant_name`, which is used in every call description
+fn name(value: u32) -> String
+{
+    Context::constant_name(value)
+}
+
+/// `object` along with the label it was given, if any
+fn labeled<T: Into<ObjectId> + fmt::Debug + Copy>(labels: &HashMap<ObjectId, String>, object: T) -> String
+{
+    match labels.get(&object.into())
+    {
+        Some(label) => format!("{:?} {}", object, label),
+        None => format!("{:?}", object),
+    }
+}
+
+/// Objects bound by the calls that went through the layer
+#[derive(Default)]
+struct Bindings
+{
+    buffers: HashMap<u32, BufferId>,
+    // The element array buffer binding belongs to the bound vertex array
+    element_buffers: HashMap<Option<VertexArrayId>, BufferId>,
+    vertex_array: Option<VertexArrayId>,
+    // Textures by texture unit and target
+    textures: HashMap<(u32, u32), TextureId>,
+    active_texture: u32,
+    program: Option<ProgramId>,
+}
+
+impl Bindings
+{
+    /// List the bound objects, naming them with `labels`
+    fn describe(&self, labels: &HashMap<ObjectId, String>) -> String
+    {
+        let mut bound = vec![];
+        let mut buffers: Vec<_> = self.buffers.iter().collect();
+        buffers.sort();
+        bound.extend(buffers.into_iter().map(|(target, buffer)| format!("{} {}", name(*target), labeled(labels, *buffer))));
+        if let Some(vertex_array) = self.vertex_array
+        {
+            bound.push(labeled(labels, vertex_array));
+        }
+        if let Some(buffer) = self.element_buffers.get(&self.vertex_array)
+        {
+            bound.push(format!("ELEMENT_ARRAY_BUFFER {}", labeled(labels, *buffer)));
+        }
+        let mut textures: Vec<_> = self.textures.iter().collect();
+        textures.sort();
+        bound.extend(textures.into_iter().map(|((unit, target), texture)| format!("{} {} on {}", name(*target), labeled(labels, *texture), name(*unit))));
+        if let Some(program) = self.program
+        {
+            bound.push(labeled(labels, program));
+        }
+        if bound.is_empty()
+        {
+            "nothing".to_string()
+        }
+        else
+        {
+            bound.join(", ")
+        }
+    }
+}
+
+/// Backend that forwards calls to another backend, checking `get_error` after each one
+///
+/// `as_any` is forwarded too, so `Context::backend` still finds the wrapped backend.
+/// Calls made while the context is lost aren't checked, since they all fail
+pub struct ValidatingBackend<B: GlBackend>
+{
+    inner: B,
+    on_error: OnGlError,
+    bindings: RefCell<Bindings>,
+    // Names given to objects with `label_object`
+    labels: RefCell<HashMap<ObjectId, String>>,
+}
+
+impl<B: GlBackend> ValidatingBackend<B>
+{
+    pub fn new(inner: B, on_error: OnGlError) -> ValidatingBackend<B>
+    {
+        ValidatingBackend
+        {
+            inner,
+            on_error,
+            bindings: RefCell::new(Bindings { active_texture: Context::TEXTURE0, ..Bindings::default() }),
+            labels: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// `object` along with the label it was given, if any, i.e. `BufferId(3) Handle<ArrayBuffer>(..) "robot vertices"`
+    fn object<T: Into<ObjectId> + fmt::Debug + Copy>(&self, object: T) -> String
+    {
+        labeled(&self.labels.borrow(), object)
+    }
+
+    /// Like `object`, for the optional objects that binding calls take
+    fn optional_object<T: Into<ObjectId> + fmt::Debug + Copy>(&self, object: Option<T>) -> String
+    {
+        object.map_or("None".to_string(), |object| format!("Some({})", self.object(object)))
+    }
+
+    /// Report any errors raised by the call that `describe` describes
+    /// Returns whether the call succeeded, in which case the bindings it made are kept track of
+    #[track_caller]
+    fn check(&self, describe: impl FnOnce() -> String) -> bool
+    {
+        let location = Location::caller();
+        let mut error = self.inner.get_error();
+        if error == Context::NO_ERROR || self.inner.is_context_lost()
+        {
+            return error == Context::NO_ERROR;
+        }
+        let mut errors = vec![];
+        while error != Context::NO_ERROR
+        {
+            errors.push(GlError::from(error));
+            error = self.inner.get_error();
+        }
+        let error = GlCallError { errors, call: describe(), location, bindings: self.bindings.borrow().describe(&self.labels.borrow()) };
+        match self.on_error
+        {
+            OnGlError::Log => crate::log_s(error.to_string()),
+            OnGlError::Panic => panic!("{}", error),
+        }
+        false
+    }
+}
+
+impl<B: GlBackend> GlBackend for ValidatingBackend<B>
+{
+    fn create_buffer(&self) -> Option<BufferId>
+    {
+        let buffer = self.inner.create_buffer();
+        self.check(|| "create_buffer()".to_string());
+        buffer
+    }
+
+    fn delete_buffer(&self, buffer: BufferId)
+    {
+        self.inner.delete_buffer(buffer);
+        if !self.check(|| format!("delete_buffer({})", self.object(buffer)))
+        {
+            return;
+        }
+        self.labels.borrow_mut().remove(&ObjectId::Buffer(buffer));
+        let mut bindings = self.bindings.borrow_mut();
+        bindings.buffers.retain(|_, bound| *bound != buffer);
+        bindings.element_buffers.retain(|_, bound| *bound != buffer);
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: Option<BufferId>)
+    {
+        self.inner.bind_buffer(target, buffer);
+        if !self.check(|| format!("bind_buffer({}, {})", name(target), self.optional_object(buffer)))
+        {
+            return;
+        }
+        let mut bindings = self.bindings.borrow_mut();
+        let vertex_array = bindings.vertex_array;
+        match (target, buffer)
+        {
+            (Context::ELEMENT_ARRAY_BUFFER, Some(buffer)) => { bindings.element_buffers.insert(vertex_array, buffer); },
+            (Context::ELEMENT_ARRAY_BUFFER, None) => { bindings.element_buffers.remove(&vertex_array); },
+            (target, Some(buffer)) => { bindings.buffers.insert(target, buffer); },
+            (target, None) => { bindings.buffers.remove(&target); },
+        }
+    }
+
+    fn buffer_data(&self, target: u32, data: &[u8], usage: u32)
+    {
+        self.inner.buffer_data(target, data, usage);
+        self.check(|| format!("buffer_data({}, {} bytes, {})", name(target), data.len(), name(usage)));
+    }
+
+    fn buffer_data_size(&self, target: u32, size: i32, usage: u32)
+    {
+        self.inner.buffer_data_size(target, size, usage);
+        self.check(|| format!("buffer_data_size({}, {}, {})", name(target), size, name(usage)));
+    }
+
+    fn buffer_sub_data(&self, target: u32, offset: i32, data: &[u8])
+    {
+        self.inner.buffer_sub_data(target, offset, data);
+        self.check(|| format!("buffer_sub_data({}, {}, {} bytes)", name(target), offset, data.len()));
+    }
+
+    fn bind_buffer_range(&self, target: u32, index: u32, buffer: Option<BufferId>, offset: i32, size: i32)
+    {
+        self.inner.bind_buffer_range(target, index, buffer, offset, size);
+        if !self.check(|| format!("bind_buffer_range({}, {}, {}, {}, {})", name(target), index, self.optional_object(buffer), offset, size))
+        {
+            return;
+        }
+        // Binding a range also binds the buffer to the generic binding point
+        if let Some(buffer) = buffer
+        {
+            self.bindings.borrow_mut().buffers.insert(target, buffer);
+        }
+    }
+
+    fn create_vertex_array(&self) -> Option<VertexArrayId>
+    {
+        let vertex_array = self.inner.create_vertex_array();
+        self.check(|| "create_vertex_array()".to_string());
+        vertex_array
+    }
+
+    fn delete_vertex_array(&self, vertex_array: VertexArrayId)
+    {
+        self.inner.delete_vertex_array(vertex_array);
+        if !self.check(|| format!("delete_vertex_array({})", self.object(vertex_array)))
+        {
+            return;
+        }
+        self.labels.borrow_mut().remove(&ObjectId::VertexArray(vertex_array));
+        let mut bindings = self.bindings.borrow_mut();
+        bindings.element_buffers.remove(&Some(vertex_array));
+        if bindings.vertex_array == Some(vertex_array)
+        {
+            bindings.vertex_array = None;
+        }
+    }
+
+    fn bind_vertex_array(&self, vertex_array: Option<VertexArrayId>)
+    {
+        self.inner.bind_vertex_array(vertex_array);
+        if !self.check(|| format!("bind_vertex_array({})", self.optional_object(vertex_array)))
+        {
+            return;
+        }
+        self.bindings.borrow_mut().vertex_array = vertex_array;
+    }
+
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, data_type: u32, normalized: bool, stride: i32, offset: i32)
+    {
+        self.inner.vertex_attrib_pointer(index, size, data_type, normalized, stride, offset);
+        self.check(|| format!("vertex_attrib_pointer({}, {}, {}, {}, {}, {})", index, size, name(data_type), normalized, stride, offset));
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32)
+    {
+        self.inner.enable_vertex_attrib_array(index);
+        self.check(|| format!("enable_vertex_attrib_array({})", index));
+    }
+
+    fn create_texture(&self) -> Option<TextureId>
+    {
+        let texture = self.inner.create_texture();
+        self.check(|| "create_texture()".to_string());
+        texture
+    }
+
+    fn delete_texture(&self, texture: TextureId)
+    {
+        self.inner.delete_texture(texture);
+        if !self.check(|| format!("delete_texture({})", self.object(texture)))
+        {
+            return;
+        }
+        self.labels.borrow_mut().remove(&ObjectId::Texture(texture));
+        self.bindings.borrow_mut().textures.retain(|_, bound| *bound != texture);
+    }
+
+    fn bind_texture(&self, target: u32, texture: Option<TextureId>)
+    {
+        self.inner.bind_texture(target, texture);
+        if !self.check(|| format!("bind_texture({}, {})", name(target), self.optional_object(texture)))
+        {
+            return;
+        }
+        let mut bindings = self.bindings.borrow_mut();
+        let unit = bindings.active_texture;
+        match texture
+        {
+            Some(texture) => { bindings.textures.insert((unit, target), texture); },
+            None => { bindings.textures.remove(&(unit, target)); },
+        }
+    }
+
+    fn active_texture(&self, texture: u32)
+    {
+        self.inner.active_texture(texture);
+        if !self.check(|| format!("active_texture({})", name(texture)))
+        {
+            return;
+        }
+        self.bindings.borrow_mut().active_texture = texture;
+    }
+
+    fn tex_parameteri(&self, target: u32, pname: u32, param: i32)
+    {
+        self.inner.tex_parameteri(target, pname, param);
+        self.check(|| format!("tex_parameteri({}, {}, {})", name(target), name(pname), name(param as u32)));
+    }
+
+    fn tex_image_2d(&self, target: u32, level: i32, internal_format: i32, width: i32, height: i32, format: u32, data: Option<&[u8]>) -> Result<(), String>
+    {
+        let result = self.inner.tex_image_2d(target, level, internal_format, width, height, format, data);
+        self.check(|| format!("tex_image_2d({}, {}, {}, {}, {}, {}, {})", name(target), level, name(internal_format as u32), width, height, name(format),
+                              data.map_or("no data".to_string(), |data| format!("{} bytes", data.len()))));
+        result
+    }
+
+    fn create_shader(&self, shader_type: u32) -> Option<ShaderId>
+    {
+        let shader = self.inner.create_shader(shader_type);
+        self.check(|| format!("create_shader({})", name(shader_type)));
+        shader
+    }
+
+    fn delete_shader(&self, shader: ShaderId)
+    {
+        self.inner.delete_shader(shader);
+        self.check(|| format!("delete_shader({:?})", shader));
+    }
+
+    fn shader_source(&self, shader: ShaderId, source: &str)
+    {
+        self.inner.shader_source(shader, source);
+        self.check(|| format!("shader_source({:?}, {} lines)", shader, source.lines().count()));
+    }
+
+    fn compile_shader(&self, shader: ShaderId)
+    {
+        self.inner.compile_shader(shader);
+        self.check(|| format!("compile_shader({:?})", shader));
+    }
+
+    fn shader_compile_status(&self, shader: ShaderId) -> bool
+    {
+        let status = self.inner.shader_compile_status(shader);
+        self.check(|| format!("shader_compile_status({:?})", shader));
+        status
+    }
+
+    fn shader_info_log(&self, shader: ShaderId) -> Option<String>
+    {
+        let info_log = self.inner.shader_info_log(shader);
+        self.check(|| format!("shader_info_log({:?})", shader));
+        info_log
+    }
+
+    fn create_program(&self) -> Option<ProgramId>
+    {
+        let program = self.inner.create_program();
+        self.check(|| "create_program()".to_string());
+        program
+    }
+
+    fn delete_program(&self, program: ProgramId)
+    {
+        self.inner.delete_program(program);
+        if !self.check(|| format!("delete_program({})", self.object(program)))
+        {
+            return;
+        }
+        self.labels.borrow_mut().remove(&ObjectId::Program(program));
+        let mut bindings = self.bindings.borrow_mut();
+        if bindings.program == Some(program)
+        {
+            bindings.program = None;
+        }
+    }
+
+    fn attach_shader(&self, program: ProgramId, shader: ShaderId)
+    {
+        self.inner.attach_shader(program, shader);
+        self.check(|| format!("attach_shader({}, {:?})", self.object(program), shader));
+    }
+
+    fn link_program(&self, program: ProgramId)
+    {
+        self.inner.link_program(program);
+        self.check(|| format!("link_program({})", self.object(program)));
+    }
+
+    fn program_link_status(&self, program: ProgramId) -> bool
+    {
+        let status = self.inner.program_link_status(program);
+        self.check(|| format!("program_link_status({})", self.object(program)));
+        status
+    }
+
+    fn program_info_log(&self, program: ProgramId) -> Option<String>
+    {
+        let info_log = self.inner.program_info_log(program);
+        self.check(|| format!("program_info_log({})", self.object(program)));
+        info_log
+    }
+
+    fn use_program(&self, program: Option<ProgramId>)
+    {
+        self.inner.use_program(program);
+        if !self.check(|| format!("use_program({})", self.optional_object(program)))
+        {
+            return;
+        }
+        self.bindings.borrow_mut().program = program;
+    }
+
+    fn program_parameter(&self, program: ProgramId, pname: u32) -> i32
+    {
+        let value = self.inner.program_parameter(program, pname);
+        self.check(|| format!("program_parameter({}, {})", self.object(program), name(pname)));
+        value
+    }
+
+    fn active_uniform(&self, program: ProgramId, index: u32) -> Option<ActiveInfo>
+    {
+        let info = self.inner.active_uniform(program, index);
+        self.check(|| format!("active_uniform({}, {})", self.object(program), index));
+        info
+    }
+
+    fn active_attrib(&self, program: ProgramId, index: u32) -> Option<ActiveInfo>
+    {
+        let info = self.inner.active_attrib(program, index);
+        self.check(|| format!("active_attrib({}, {})", self.object(program), index));
+        info
+    }
+
+    fn attrib_location(&self, program: ProgramId, name: &str) -> i32
+    {
+        let location = self.inner.attrib_location(program, name);
+        self.check(|| format!("attrib_location({}, {:?})", self.object(program), name));
+        location
+    }
+
+    fn active_uniforms_parameter(&self, program: ProgramId, indices: &[u32], pname: u32) -> Vec<Option<i32>>
+    {
+        let values = self.inner.active_uniforms_parameter(program, indices, pname);
+        self.check(|| format!("active_uniforms_parameter({}, {:?}, {})", self.object(program), indices, name(pname)));
+        values
+    }
+
+    fn active_uniform_block_name(&self, program: ProgramId, index: u32) -> Option<String>
+    {
+        let block_name = self.inner.active_uniform_block_name(program, index);
+        self.check(|| format!("active_uniform_block_name({}, {})", self.object(program), index));
+        block_name
+    }
+
+    fn active_uniform_block_parameter(&self, program: ProgramId, index: u32, pname: u32) -> Option<i32>
+    {
+        let value = self.inner.active_uniform_block_parameter(program, index, pname);
+        self.check(|| format!("active_uniform_block_parameter({}, {}, {})", self.object(program), index, name(pname)));
+        value
+    }
+
+    fn uniform_location(&self, program: ProgramId, name: &str) -> Option<UniformLocationId>
+    {
+        let location = self.inner.uniform_location(program, name);
+        self.check(|| format!("uniform_location({}, {:?})", self.object(program), name));
+        location
+    }
+
+    fn uniform(&self, location: UniformLocationId, value: &UniformValue)
+    {
+        self.inner.uniform(location, value);
+        self.check(|| format!("uniform({:?}, {:?})", location, value));
+    }
+
+    fn uniform_block_index(&self, program: ProgramId, name: &str) -> u32
+    {
+        let index = self.inner.uniform_block_index(program, name);
+        self.check(|| format!("uniform_block_index({}, {:?})", self.object(program), name));
+        index
+    }
+
+    fn uniform_block_binding(&self, program: ProgramId, index: u32, binding: u32)
+    {
+        self.inner.uniform_block_binding(program, index, binding);
+        self.check(|| format!("uniform_block_binding({}, {}, {})", self.object(program), index, binding));
+    }
+
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32)
+    {
+        self.inner.viewport(x, y, width, height);
+        self.check(|| format!("viewport({}, {}, {}, {})", x, y, width, height));
+    }
+
+    fn pixel_storei(&self, pname: u32, param: i32)
+    {
+        self.inner.pixel_storei(pname, param);
+        self.check(|| format!("pixel_storei({}, {})", name(pname), param));
+    }
+
+    fn enable(&self, capability: u32)
+    {
+        self.inner.enable(capability);
+        self.check(|| format!("enable({})", name(capability)));
+    }
+
+    fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32)
+    {
+        self.inner.clear_color(red, green, blue, alpha);
+        self.check(|| format!("clear_color({}, {}, {}, {})", red, green, blue, alpha));
+    }
+
+    fn clear(&self, mask: u32)
+    {
+        self.inner.clear(mask);
+        self.check(|| format!("clear({:#x})", mask));
+    }
+
+    fn draw_elements(&self, mode: u32, count: i32, data_type: u32, offset: i32)
+    {
+        self.inner.draw_elements(mode, count, data_type, offset);
+        self.check(|| format!("draw_elements({}, {}, {}, {})", name(mode), count, name(data_type), offset));
+    }
+
+    fn read_pixels(&self, x: i32, y: i32, width: i32, height: i32, format: u32, pixels: &mut [u8]) -> Result<(), String>
+    {
+        let result = self.inner.read_pixels(x, y, width, height, format, pixels);
+        self.check(|| format!("read_pixels({}, {}, {}, {}, {})", x, y, width, height, name(format)));
+        result
+    }
+
+    fn get_error(&self) -> u32
+    {
+        self.inner.get_error()
+    }
+
+    fn parameter(&self, pname: u32) -> Option<i32>
+    {
+        let value = self.inner.parameter(pname);
+        self.check(|| format!("parameter({})", name(pname)));
+        value
+    }
+
+    fn is_context_lost(&self) -> bool
+    {
+        self.inner.is_context_lost()
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        self.inner.as_any()
+    }
+
+    fn label_object(&self, object: ObjectId, label: &str)
+    {
+        self.inner.label_object(object, label);
+        self.labels.borrow_mut().insert(object, label.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::panic::{self, AssertUnwindSafe};
+    use crate::gfx::
+    {
+        Context,
+        backend::{recording::{GlCall, RecordingBackend}, validation::*},
+        gl_object::{ArrayBuffer, manager::GlObjectManager, traits::GlObject},
+    };
+
+    fn new_context() -> Context
+    {
+        Context::new(ValidatingBackend::new(RecordingBackend::new(), OnGlError::Panic))
+    }
+
+    /// Message of the panic raised by `func`, if any
+    fn panic_message(func: impl FnOnce()) -> Option<String>
+    {
+        let result = panic::catch_unwind(AssertUnwindSafe(func));
+        result.err().map(|payload| payload.downcast_ref::<String>().cloned().unwrap_or_default())
+    }
+
+    #[test]
+    fn test_forwards_calls()
+    {
+        let context = new_context();
+        let buffer = context.create_buffer().expect("buffer");
+        context.bind_buffer(Context::ARRAY_BUFFER, Some(buffer));
+        // The wrapped backend is still reachable
+        let recording = context.backend::<RecordingBackend>().expect("recording backend");
+        assert_eq!(vec![GlCall::CreateBuffer(buffer), GlCall::BindBuffer(Context::ARRAY_BUFFER, Some(buffer))], recording.take_calls());
+    }
+
+    #[test]
+    fn test_error_call_site()
+    {
+        let context = new_context();
+        let vertex_array = context.create_vertex_array().expect("vertex array");
+        let buffer = context.create_buffer().expect("buffer");
+        context.bind_vertex_array(Some(vertex_array));
+        context.bind_buffer(Context::ELEMENT_ARRAY_BUFFER, Some(buffer));
+        context.delete_buffer(buffer);
+
+        let line = line!() + 1;
+        let message = panic_message(|| context.bind_buffer(Context::ARRAY_BUFFER, Some(buffer))).expect("invalid bind panics");
+        assert!(message.starts_with(&format!("GL error InvalidOperation from bind_buffer(ARRAY_BUFFER, Some(BufferId(2))) at {}:{}:", file!(), line)), "{}", message);
+        // The failed bind isn't counted as bound
+        assert!(message.ends_with("\n  bound: VertexArrayId(1)"), "{}", message);
+
+        // Stands in for an error raised by `enable` itself
+        context.backend::<RecordingBackend>().expect("recording backend").push_error(Context::INVALID_VALUE);
+        let message = panic_message(|| context.enable(Context::DEPTH_TEST)).expect("queued error panics");
+        assert!(message.starts_with("GL error InvalidValue from enable(DEPTH_TEST)"), "{}", message);
+        assert_eq!(None, panic_message(|| context.enable(Context::DEPTH_TEST)));
+    }
+
+    #[test]
+    fn test_error_names_objects()
+    {
+        let context = new_context();
+        let mut manager = GlObjectManager::new();
+        let arr_buff = manager.insert_labeled_array_buffer("robot vertices", ArrayBuffer::new(&context).expect("array buffer"));
+        let other_buff = manager.insert_array_buffer(ArrayBuffer::new(&context).expect("array buffer"));
+        ArrayBuffer::bind(&manager, other_buff);
+
+        // Stands in for an error raised by binding the buffer
+        context.backend::<RecordingBackend>().expect("recording backend").push_error(Context::INVALID_OPERATION);
+        let line = line!() + 1;
+        let message = panic_message(|| ArrayBuffer::bind(&manager, arr_buff)).expect("invalid bind panics");
+        // Objects are named by their handle and label, and the location is the line that bound the buffer instead of one within `gfx`
+        let call = format!("bind_buffer(ARRAY_BUFFER, Some(BufferId(1) {:?} \"robot vertices\"))", arr_buff);
+        assert!(message.starts_with(&format!("GL error InvalidOperation from {} at {}:{}:", call, file!(), line)), "{}", message);
+        assert!(message.ends_with(&format!("\n  bound: ARRAY_BUFFER BufferId(2) {:?}", other_buff)), "{}", message);
+    }
+}
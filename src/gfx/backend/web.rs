@@ -0,0 +1,443 @@
+//! Backend for a browser's WebGL2 context
+
+use std::{any::Any, cell::RefCell, collections::HashMap};
+use wasm_bindgen::JsValue;
+use web_sys::
+{
+    WebGl2RenderingContext,
+    WebGlBuffer,
+    WebGlProgram,
+    WebGlShader,
+    WebGlTexture,
+    WebGlUniformLocation,
+    WebGlVertexArrayObject,
+};
+use crate::gfx::
+{
+    backend::*,
+    gl_object::uniform::UniformValue,
+};
+
+/// WebGL objects by the ids that were given out for them
+struct IdTable<T>
+{
+    next: u32,
+    objects: HashMap<u32, T>,
+}
+
+impl<T: Clone> IdTable<T>
+{
+    fn new() -> IdTable<T>
+    {
+        // Ids start at 1, like GL object names
+        IdTable { next: 1, objects: HashMap::new() }
+    }
+
+    fn insert(&mut self, object: T) -> u32
+    {
+        let id = self.next;
+        self.next += 1;
+        self.objects.insert(id, object);
+        id
+    }
+
+    fn get(&self, id: u32) -> Option<T>
+    {
+        self.objects.get(&id).cloned()
+    }
+
+    fn remove(&mut self, id: u32) -> Option<T>
+    {
+        self.objects.remove(&id)
+    }
+}
+
+/// Read an integer from a JS value returned by a WebGL parameter query
+fn js_i32(value: &JsValue) -> Option<i32>
+{
+    value.as_f64().map(|value| value as i32)
+}
+
+pub struct WebGlBackend
+{
+    context: WebGl2RenderingContext,
+    buffers: RefCell<IdTable<WebGlBuffer>>,
+    vertex_arrays: RefCell<IdTable<WebGlVertexArrayObject>>,
+    textures: RefCell<IdTable<WebGlTexture>>,
+    shaders: RefCell<IdTable<WebGlShader>>,
+    programs: RefCell<IdTable<WebGlProgram>>,
+    // Locations are tied to the program they were queried from, and are freed along with it
+    uniform_locations: RefCell<IdTable<(ProgramId, WebGlUniformLocation)>>,
+}
+
+impl WebGlBackend
+{
+    pub fn new(context: WebGl2RenderingContext) -> WebGlBackend
+    {
+        WebGlBackend
+        {
+            context,
+            buffers: RefCell::new(IdTable::new()),
+            vertex_arrays: RefCell::new(IdTable::new()),
+            textures: RefCell::new(IdTable::new()),
+            shaders: RefCell::new(IdTable::new()),
+            programs: RefCell::new(IdTable::new()),
+            uniform_locations: RefCell::new(IdTable::new()),
+        }
+    }
+
+    /// The underlying WebGL context, for calls that aren't part of `GlBackend`
+    #[allow(dead_code)]
+    pub fn raw(&self) -> &WebGl2RenderingContext
+    {
+        &self.context
+    }
+
+    fn buffer(&self, buffer: Option<BufferId>) -> Option<WebGlBuffer>
+    {
+        buffer.and_then(|buffer| self.buffers.borrow().get(buffer.0))
+    }
+
+    fn program(&self, program: ProgramId) -> Option<WebGlProgram>
+    {
+        self.programs.borrow().get(program.0)
+    }
+
+    fn shader(&self, shader: ShaderId) -> Option<WebGlShader>
+    {
+        self.shaders.borrow().get(shader.0)
+    }
+}
+
+impl GlBackend for WebGlBackend
+{
+    fn create_buffer(&self) -> Option<BufferId>
+    {
+        let buffer = self.context.create_buffer()?;
+        Some(BufferId(self.buffers.borrow_mut().insert(buffer)))
+    }
+
+    fn delete_buffer(&self, buffer: BufferId)
+    {
+        self.context.delete_buffer(self.buffers.borrow_mut().remove(buffer.0).as_ref());
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: Option<BufferId>)
+    {
+        self.context.bind_buffer(target, self.buffer(buffer).as_ref());
+    }
+
+    fn buffer_data(&self, target: u32, data: &[u8], usage: u32)
+    {
+        self.context.buffer_data_with_u8_array(target, data, usage);
+    }
+
+    fn buffer_data_size(&self, target: u32, size: i32, usage: u32)
+    {
+        self.context.buffer_data_with_i32(target, size, usage);
+    }
+
+    fn buffer_sub_data(&self, target: u32, offset: i32, data: &[u8])
+    {
+        self.context.buffer_sub_data_with_i32_and_u8_array(target, offset, data);
+    }
+
+    fn bind_buffer_range(&self, target: u32, index: u32, buffer: Option<BufferId>, offset: i32, size: i32)
+    {
+        self.context.bind_buffer_range_with_i32_and_i32(target, index, self.buffer(buffer).as_ref(), offset, size);
+    }
+
+    fn create_vertex_array(&self) -> Option<VertexArrayId>
+    {
+        let vertex_array = self.context.create_vertex_array()?;
+        Some(VertexArrayId(self.vertex_arrays.borrow_mut().insert(vertex_array)))
+    }
+
+    fn delete_vertex_array(&self, vertex_array: VertexArrayId)
+    {
+        self.context.delete_vertex_array(self.vertex_arrays.borrow_mut().remove(vertex_array.0).as_ref());
+    }
+
+    fn bind_vertex_array(&self, vertex_array: Option<VertexArrayId>)
+    {
+        let vertex_array = vertex_array.and_then(|vertex_array| self.vertex_arrays.borrow().get(vertex_array.0));
+        self.context.bind_vertex_array(vertex_array.as_ref());
+    }
+
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, data_type: u32, normalized: bool, stride: i32, offset: i32)
+    {
+        self.context.vertex_attrib_pointer_with_i32(index, size, data_type, normalized, stride, offset);
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32)
+    {
+        self.context.enable_vertex_attrib_array(index);
+    }
+
+    fn create_texture(&self) -> Option<TextureId>
+    {
+        let texture = self.context.create_texture()?;
+        Some(TextureId(self.textures.borrow_mut().insert(texture)))
+    }
+
+    fn delete_texture(&self, texture: TextureId)
+    {
+        self.context.delete_texture(self.textures.borrow_mut().remove(texture.0).as_ref());
+    }
+
+    fn bind_texture(&self, target: u32, texture: Option<TextureId>)
+    {
+        let texture = texture.and_then(|texture| self.textures.borrow().get(texture.0));
+        self.context.bind_texture(target, texture.as_ref());
+    }
+
+    fn active_texture(&self, texture: u32)
+    {
+        self.context.active_texture(texture);
+    }
+
+    fn tex_parameteri(&self, target: u32, pname: u32, param: i32)
+    {
+        self.context.tex_parameteri(target, pname, param);
+    }
+
+    fn tex_image_2d(&self, target: u32, level: i32, internal_format: i32, width: i32, height: i32, format: u32, data: Option<&[u8]>) -> Result<(), String>
+    {
+        self.context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            target, level, internal_format, width, height, 0, format, WebGl2RenderingContext::UNSIGNED_BYTE, data
+        ).map_err(|err| format!("{:?}", err))
+    }
+
+    fn create_shader(&self, shader_type: u32) -> Option<ShaderId>
+    {
+        let shader = self.context.create_shader(shader_type)?;
+        Some(ShaderId(self.shaders.borrow_mut().insert(shader)))
+    }
+
+    fn delete_shader(&self, shader: ShaderId)
+    {
+        self.context.delete_shader(self.shaders.borrow_mut().remove(shader.0).as_ref());
+    }
+
+    fn shader_source(&self, shader: ShaderId, source: &str)
+    {
+        if let Some(shader) = self.shader(shader)
+        {
+            self.context.shader_source(&shader, source);
+        }
+    }
+
+    fn compile_shader(&self, shader: ShaderId)
+    {
+        if let Some(shader) = self.shader(shader)
+        {
+            self.context.compile_shader(&shader);
+        }
+    }
+
+    fn shader_compile_status(&self, shader: ShaderId) -> bool
+    {
+        self.shader(shader).is_some_and(|shader| self.context.get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS).as_bool().unwrap_or(false))
+    }
+
+    fn shader_info_log(&self, shader: ShaderId) -> Option<String>
+    {
+        self.context.get_shader_info_log(&self.shader(shader)?)
+    }
+
+    fn create_program(&self) -> Option<ProgramId>
+    {
+        let program = self.context.create_program()?;
+        Some(ProgramId(self.programs.borrow_mut().insert(program)))
+    }
+
+    fn delete_program(&self, program: ProgramId)
+    {
+        self.uniform_locations.borrow_mut().objects.retain(|_, (location_program, _)| *location_program != program);
+        self.context.delete_program(self.programs.borrow_mut().remove(program.0).as_ref());
+    }
+
+    fn attach_shader(&self, program: ProgramId, shader: ShaderId)
+    {
+        if let (Some(program), Some(shader)) = (self.program(program), self.shader(shader))
+        {
+            self.context.attach_shader(&program, &shader);
+        }
+    }
+
+    fn link_program(&self, program: ProgramId)
+    {
+        if let Some(program) = self.program(program)
+        {
+            self.context.link_program(&program);
+        }
+    }
+
+    fn program_link_status(&self, program: ProgramId) -> bool
+    {
+        self.program_parameter_js(program, WebGl2RenderingContext::LINK_STATUS).and_then(|status| status.as_bool()).unwrap_or(false)
+    }
+
+    fn program_info_log(&self, program: ProgramId) -> Option<String>
+    {
+        self.context.get_program_info_log(&self.program(program)?)
+    }
+
+    fn use_program(&self, program: Option<ProgramId>)
+    {
+        let program = program.and_then(|program| self.program(program));
+        self.context.use_program(program.as_ref());
+    }
+
+    fn program_parameter(&self, program: ProgramId, pname: u32) -> i32
+    {
+        self.program_parameter_js(program, pname).as_ref().and_then(js_i32).unwrap_or(0)
+    }
+
+    fn active_uniform(&self, program: ProgramId, index: u32) -> Option<ActiveInfo>
+    {
+        let info = self.context.get_active_uniform(&self.program(program)?, index)?;
+        Some(ActiveInfo { name: info.name(), data_type: info.type_(), size: info.size() })
+    }
+
+    fn active_attrib(&self, program: ProgramId, index: u32) -> Option<ActiveInfo>
+    {
+        let info = self.context.get_active_attrib(&self.program(program)?, index)?;
+        Some(ActiveInfo { name: info.name(), data_type: info.type_(), size: info.size() })
+    }
+
+    fn attrib_location(&self, program: ProgramId, name: &str) -> i32
+    {
+        self.program(program).map_or(-1, |program| self.context.get_attrib_location(&program, name))
+    }
+
+    fn active_uniforms_parameter(&self, program: ProgramId, indices: &[u32], pname: u32) -> Vec<Option<i32>>
+    {
+        let program = match self.program(program)
+        {
+            Some(program) => program,
+            None => return vec![None; indices.len()],
+        };
+        let indices: js_sys::Array = indices.iter().map(|&index| JsValue::from(index)).collect();
+        let values = js_sys::Array::from(&self.context.get_active_uniforms(&program, &indices, pname));
+        values.iter().map(|value| js_i32(&value)).collect()
+    }
+
+    fn active_uniform_block_name(&self, program: ProgramId, index: u32) -> Option<String>
+    {
+        self.context.get_active_uniform_block_name(&self.program(program)?, index)
+    }
+
+    fn active_uniform_block_parameter(&self, program: ProgramId, index: u32, pname: u32) -> Option<i32>
+    {
+        self.context.get_active_uniform_block_parameter(&self.program(program)?, index, pname).ok().as_ref().and_then(js_i32)
+    }
+
+    fn uniform_location(&self, program: ProgramId, name: &str) -> Option<UniformLocationId>
+    {
+        let location = self.context.get_uniform_location(&self.program(program)?, name)?;
+        Some(UniformLocationId(self.uniform_locations.borrow_mut().insert((program, location))))
+    }
+
+    fn uniform(&self, location: UniformLocationId, value: &UniformValue)
+    {
+        let location = self.uniform_locations.borrow().get(location.0).map(|(_, location)| location);
+        let location = location.as_ref();
+        match value
+        {
+            UniformValue::Float(1, data) => self.context.uniform1fv_with_f32_array(location, data),
+            UniformValue::Float(2, data) => self.context.uniform2fv_with_f32_array(location, data),
+            UniformValue::Float(3, data) => self.context.uniform3fv_with_f32_array(location, data),
+            UniformValue::Float(4, data) => self.context.uniform4fv_with_f32_array(location, data),
+            UniformValue::Int(1, data) => self.context.uniform1iv_with_i32_array(location, data),
+            UniformValue::Int(2, data) => self.context.uniform2iv_with_i32_array(location, data),
+            UniformValue::Int(3, data) => self.context.uniform3iv_with_i32_array(location, data),
+            UniformValue::Int(4, data) => self.context.uniform4iv_with_i32_array(location, data),
+            UniformValue::UInt(1, data) => self.context.uniform1uiv_with_u32_array(location, data),
+            UniformValue::UInt(2, data) => self.context.uniform2uiv_with_u32_array(location, data),
+            UniformValue::UInt(3, data) => self.context.uniform3uiv_with_u32_array(location, data),
+            UniformValue::UInt(4, data) => self.context.uniform4uiv_with_u32_array(location, data),
+            UniformValue::Matrix(2, data) => self.context.uniform_matrix2fv_with_f32_array(location, false, data),
+            UniformValue::Matrix(3, data) => self.context.uniform_matrix3fv_with_f32_array(location, false, data),
+            UniformValue::Matrix(4, data) => self.context.uniform_matrix4fv_with_f32_array(location, false, data),
+            _ => panic!("Invalid uniform value {:?}", value),
+        }
+    }
+
+    fn uniform_block_index(&self, program: ProgramId, name: &str) -> u32
+    {
+        self.program(program).map_or(WebGl2RenderingContext::INVALID_INDEX, |program| self.context.get_uniform_block_index(&program, name))
+    }
+
+    fn uniform_block_binding(&self, program: ProgramId, index: u32, binding: u32)
+    {
+        if let Some(program) = self.program(program)
+        {
+            self.context.uniform_block_binding(&program, index, binding);
+        }
+    }
+
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32)
+    {
+        self.context.viewport(x, y, width, height);
+    }
+
+    fn pixel_storei(&self, pname: u32, param: i32)
+    {
+        self.context.pixel_storei(pname, param);
+    }
+
+    fn enable(&self, capability: u32)
+    {
+        self.context.enable(capability);
+    }
+
+    fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32)
+    {
+        self.context.clear_color(red, green, blue, alpha);
+    }
+
+    fn clear(&self, mask: u32)
+    {
+        self.context.clear(mask);
+    }
+
+    fn draw_elements(&self, mode: u32, count: i32, data_type: u32, offset: i32)
+    {
+        self.context.draw_elements_with_i32(mode, count, data_type, offset);
+    }
+
+    fn read_pixels(&self, x: i32, y: i32, width: i32, height: i32, format: u32, pixels: &mut [u8]) -> Result<(), String>
+    {
+        self.context.read_pixels_with_opt_u8_array(x, y, width, height, format, WebGl2RenderingContext::UNSIGNED_BYTE, Some(pixels))
+            .map_err(|err| format!("{:?}", err))
+    }
+
+    fn get_error(&self) -> u32
+    {
+        self.context.get_error()
+    }
+
+    fn parameter(&self, pname: u32) -> Option<i32>
+    {
+        self.context.get_parameter(pname).ok().as_ref().and_then(js_i32)
+    }
+
+    fn is_context_lost(&self) -> bool
+    {
+        self.context.is_context_lost()
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        self
+    }
+}
+
+impl WebGlBackend
+{
+    fn program_parameter_js(&self, program: ProgramId, pname: u32) -> Option<JsValue>
+    {
+        Some(self.context.get_program_parameter(&self.program(program)?, pname))
+    }
+}
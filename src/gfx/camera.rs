@@ -231,6 +231,32 @@ impl Camera
         self.rotate_cam(Quaternion::from_axis_angle(self.orientation.invert() * self.world_forward, Deg(theta)));
     }
 
+    // Orbit Camera
+
+    /// Rotate the camera around `center` by `rotation`, keeping `center` at the same
+    /// place within the camera's view
+    /// `rotation` is applied the same way as in `rotate_cam()`
+    pub fn orbit(&mut self, rotation: Quaternion<f32>, center: Vector3<f32>)
+    {
+        let rotation = rotation.normalize();
+        self.translation = (rotation.invert() * (center + self.translation)) - center;
+        self.orientation = (self.orientation * rotation).normalize();
+    }
+
+    /// Orbit around `center` about the original, unaltered `world_up`
+    /// i.e. circling around `center` while staying at the same height
+    pub fn orbit_yaw(&mut self, theta: f32, center: Vector3<f32>)
+    {
+        self.orbit(Quaternion::from_axis_angle(self.world_up, Deg(theta)), center);
+    }
+
+    /// Orbit around `center` about the camera's lateral axis
+    /// i.e. moving over or under `center`
+    pub fn orbit_pitch(&mut self, theta: f32, center: Vector3<f32>)
+    {
+        self.orbit(Quaternion::from_axis_angle(self.orientation.invert() * self.world_right, Deg(theta)), center);
+    }
+
     pub fn set_orientation(&mut self, orientation: Quaternion<f32>)
     {
         self.orientation = orientation;
@@ -595,6 +621,38 @@ mod tests
         unimplemented!("test_rotate_cam_roll()");
     }
     #[test]
+    fn test_orbit()
+    {
+        use cgmath::{Point3, Transform};
+
+        let mut cam = TEST_CAM;
+        cam.move_cam(vec3(1.0, 2.0, -6.0));
+        let center = vec3(0.5, 0.0, 1.0);
+        let center_point = Point3::new(center.x, center.y, center.z);
+
+        let view_center = cam.view_matrix().transform_point(center_point);
+        let cam_pos = cam.get_cam_pos();
+
+        cam.orbit_yaw(35.0, center);
+        cam.orbit_pitch(-20.0, center);
+
+        // The orbit center stays in the same place on screen while the camera moves around it
+        let orbited_center = cam.view_matrix().transform_point(center_point);
+        assert!(approx_eq!(f32, view_center.x, orbited_center.x, epsilon = 0.0001));
+        assert!(approx_eq!(f32, view_center.y, orbited_center.y, epsilon = 0.0001));
+        assert!(approx_eq!(f32, view_center.z, orbited_center.z, epsilon = 0.0001));
+        assert_ne!(cam_pos, cam.get_cam_pos());
+
+        // Orbiting about the camera's own position is the same as rotating in place
+        let mut orbited = TEST_CAM;
+        let mut rotated = TEST_CAM;
+        orbited.orbit_yaw(35.0, orbited.get_cam_pos());
+        rotated.rotate_world_yaw(35.0);
+        assert_eq!(rotated.orientation, orbited.orientation);
+        assert!(approx_eq!(f32, rotated.translation.x, orbited.translation.x, epsilon = 0.0001));
+        assert!(approx_eq!(f32, rotated.translation.z, orbited.translation.z, epsilon = 0.0001));
+    }
+    #[test]
     fn test_set_orientation()
     {
         let mut cam = TEST_CAM;
@@ -3,21 +3,26 @@ pub trait Buffer : crate::gfx::gl_object::traits::GlObject
 {
     /// Set the contents of the buffer to `data`
     /// `draw_type` is one of the webgl `*_DRAW` enum types
-    fn buffer_data<T>(&mut self, data: &[T], draw_type: u32);
+    #[track_caller]
+    fn buffer_data<T: crate::gfx::gl_object::pod::Pod>(&mut self, data: &[T], draw_type: u32);
 
     /// Set the contents of the buffer to `data`
     /// `draw_type` is one of the webgl `*_DRAW` enum types
+    #[track_caller]
     fn buffer_data_raw(&mut self, data: &[u8], draw_type: u32);
 
     /// Set the contents of the buffer to `data`, starting at `offset`
     /// `draw_type` is one of the webgl `*_DRAW` enum types
-    fn buffer_sub_data<T>(&mut self, offset: i32, data: &[T]);
+    #[track_caller]
+    fn buffer_sub_data<T: crate::gfx::gl_object::pod::Pod>(&mut self, offset: i32, data: &[T]);
 
     /// Set the contents of the buffer to `data`, starting at `offset`
     /// `draw_type` is one of the webgl `*_DRAW` enum types
+    #[track_caller]
     fn buffer_sub_data_raw(&mut self, offset: i32, data: &[u8]);
 
     /// Bind `index` to the buffer memory range `offset`->`offset+size`
+    #[track_caller]
     fn bind_range(&mut self, index: u32, offset: i32, size: i32);
 }
 
@@ -39,11 +44,6 @@ macro_rules! impl_buffer
     {{
         $context.create_buffer().ok_or_else(|| crate::gfx::GfxError::BufferCreationError(crate::gfx::gl_get_errors($context).to_string()))
     }};
-    // Gets `data :T` as a u8 slice
-    (@as_u8_slice $data:ident) =>
-    {{
-        unsafe { std::slice::from_raw_parts($data.as_ptr() as *const u8, $data.len() * std::mem::size_of::<T>()) }
-    }};
     // Initialize a new `struct_name`'s required buffer fields and any additional fields
     (@init_struct $context:ident, $struct_name:ident {$($field:ident:$value:expr),*}) =>
     {{
@@ -53,6 +53,9 @@ macro_rules! impl_buffer
             context: $context.clone(),
             draw_type: 0,
             buffer: vec![],
+            size: 0,
+            keep_shadow: true,
+            needs_data: false,
             range_bindings: vec![],
             $(
             $field: $value,
@@ -77,6 +80,27 @@ macro_rules! impl_buffer
                 Ok(impl_buffer!(@init_struct context, $struct_name {}))
             }
         }
+        impl crate::gfx::gl_object::traits::Inspect for $struct_name
+        {
+            fn gl_id(&self) -> u32
+            {
+                self.internal.0
+            }
+
+            fn label_in_context(&self, label: &str)
+            {
+                self.context.label_object(crate::gfx::backend::ObjectId::Buffer(self.internal), label);
+            }
+
+            fn details(&self) -> crate::gfx::gl_object::inspector::ObjectDetails
+            {
+                crate::gfx::gl_object::inspector::ObjectDetails::Buffer
+                {
+                    target: crate::gfx::Context::constant_name(crate::gfx::Context::$buffer_type),
+                    usage: (self.draw_type != 0).then(|| crate::gfx::Context::constant_name(self.draw_type)),
+                }
+            }
+        }
     }};
 
     // Creates a new `struct_name` with the required fields for a buffer as well as
@@ -85,10 +109,15 @@ macro_rules! impl_buffer
     {
         pub struct $struct_name
         {
-            internal: web_sys::WebGlBuffer,
+            internal: crate::gfx::backend::BufferId,
             context: crate::gfx::Context,
             draw_type: u32,
+            // Shadow copy of the buffer's data, which is empty if `keep_shadow` is false
             buffer: Vec<u8>,
+            // Size of the buffer's data on the GPU
+            size: usize,
+            keep_shadow: bool,
+            needs_data: bool,
             range_bindings: Vec<Option<crate::gfx::gl_object::buffer::RangeBinding>>,
             $(
                 $field: $type,
@@ -106,39 +135,47 @@ macro_rules! impl_buffer
         {
             /// Set the contents of the buffer to `data`
             /// `draw_type` is one of the webgl `*_DRAW` enum types
-            fn buffer_data<T>(&mut self, data: &[T], draw_type: u32)
+            fn buffer_data<T: crate::gfx::gl_object::pod::Pod>(&mut self, data: &[T], draw_type: u32)
             {
-                self.buffer_data_raw(impl_buffer!(@as_u8_slice data), draw_type);
+                self.buffer_data_raw(crate::gfx::gl_object::pod::as_bytes(data), draw_type);
             }
 
             /// Set the contents of the buffer to `data`
             /// `draw_type` is one of the webgl `*_DRAW` enum types
             fn buffer_data_raw(&mut self, data: &[u8], draw_type: u32)
             {
-                self.buffer = data.to_vec();
-                self.context.buffer_data_with_u8_array(crate::gfx::Context::$buffer_type, &self.buffer, draw_type);
+                self.context.buffer_data(crate::gfx::Context::$buffer_type, data, draw_type);
+                if self.keep_shadow
+                {
+                    self.buffer = data.to_vec();
+                }
+                self.size = data.len();
+                self.needs_data = false;
                 self.draw_type = draw_type;
             }
 
             /// Set the contents of the buffer to `data`, starting at `offset`
             /// `draw_type` is one of the webgl `*_DRAW` enum types
-            fn buffer_sub_data<T>(&mut self, offset: i32, data: &[T])
+            fn buffer_sub_data<T: crate::gfx::gl_object::pod::Pod>(&mut self, offset: i32, data: &[T])
             {
-                self.buffer_sub_data_raw(offset, impl_buffer!(@as_u8_slice data));
+                self.buffer_sub_data_raw(offset, crate::gfx::gl_object::pod::as_bytes(data));
             }
 
             /// Set the contents of the buffer to `data`, starting at `offset`
             /// `draw_type` is one of the webgl `*_DRAW` enum types
             fn buffer_sub_data_raw(&mut self, offset: i32, data: &[u8])
             {
-                self.context.buffer_sub_data_with_i32_and_u8_array(crate::gfx::Context::$buffer_type, offset, data);
-                self.buffer.splice(offset as usize..(offset as usize + data.len()), data.to_vec());
+                self.context.buffer_sub_data(crate::gfx::Context::$buffer_type, offset, data);
+                if self.keep_shadow
+                {
+                    self.buffer.splice(offset as usize..(offset as usize + data.len()), data.to_vec());
+                }
             }
 
             /// Bind `index` to the buffer memory range `offset`->`offset+size`
             fn bind_range(&mut self, index: u32, offset: i32, size: i32)
             {
-                self.context.bind_buffer_range_with_i32_and_i32(crate::gfx::Context::$buffer_type, index, Some(&self.internal), offset, size);
+                self.context.bind_buffer_range(crate::gfx::Context::$buffer_type, index, Some(self.internal), offset, size);
 
                 if self.range_bindings.len() <= index as usize
                 {
@@ -154,7 +191,7 @@ macro_rules! impl_buffer
             {
                 fn bind_internal(&self)
                 {
-                    self.context.bind_buffer(crate::gfx::Context::$buffer_type, Some(&self.internal));
+                    self.context.bind_buffer(crate::gfx::Context::$buffer_type, Some(self.internal));
                 }
                 fn unbind_internal(&self)
                 {
@@ -169,7 +206,16 @@ macro_rules! impl_buffer
                     self.context = context.clone();
                     self.internal = impl_buffer!(@new_internal context)?;
                     self.bind_internal();
-                    self.context.buffer_data_with_u8_array(crate::gfx::Context::$buffer_type, &self.buffer, self.draw_type);
+                    if self.keep_shadow
+                    {
+                        self.context.buffer_data(crate::gfx::Context::$buffer_type, &self.buffer, self.draw_type);
+                    }
+                    else
+                    {
+                        // Allocate the storage so that ranges can still be bound, and wait for the data to be supplied again
+                        self.context.buffer_data_size(crate::gfx::Context::$buffer_type, self.size as i32, self.draw_type);
+                        self.needs_data = self.size > 0;
+                    }
 
                     let range_bindings = self.range_bindings.to_owned();
                     for range_binding in range_bindings
@@ -184,11 +230,35 @@ macro_rules! impl_buffer
                 }
             }
 
+            impl crate::gfx::gl_object::traits::MemoryUsage for $struct_name
+            {
+                fn gpu_bytes(&self) -> usize
+                {
+                    self.size
+                }
+
+                fn shadow_bytes(&self) -> usize
+                {
+                    self.buffer.capacity()
+                }
+
+                fn drop_shadow_copy(&mut self)
+                {
+                    self.keep_shadow = false;
+                    self.buffer = vec![];
+                }
+
+                fn needs_data(&self) -> bool
+                {
+                    self.needs_data
+                }
+            }
+
             impl Drop for $struct_name
             {
                 fn drop(&mut self)
                 {
-                    self.context.delete_buffer(Some(&self.internal));
+                    self.context.delete_buffer(self.internal);
                 }
             }
     };
@@ -204,7 +274,7 @@ mod tests
     {
         gl_object::
         {
-            traits::Bindable,
+            traits::{Bindable, MemoryUsage},
             buffer::{Buffer, RangeBinding},
             ArrayBuffer,
         },
@@ -239,4 +309,27 @@ mod tests
 
         assert_eq!(Some(RangeBinding(0, 0, 1)), buffer.range_bindings[0]);
     }
+
+    #[wasm_bindgen_test]
+    fn test_drop_shadow_copy()
+    {
+        let context = get_context();
+        let mut buffer = ArrayBuffer::new(&context).expect("array buffer");
+        buffer.bind_internal();
+        buffer.buffer_data(&[1.0f32, 2.0], Context::STATIC_DRAW);
+        assert_eq!(8, buffer.gpu_bytes());
+        assert_eq!(8, buffer.shadow_bytes());
+
+        buffer.drop_shadow_copy();
+        assert_eq!(8, buffer.gpu_bytes());
+        assert_eq!(0, buffer.shadow_bytes());
+
+        // New data isn't copied either
+        buffer.buffer_data(&[1.0f32, 2.0, 3.0], Context::STATIC_DRAW);
+        buffer.buffer_sub_data(0, &[4.0f32]);
+        assert_eq!(12, buffer.gpu_bytes());
+        assert_eq!(0, buffer.shadow_bytes());
+        assert!(!buffer.needs_data());
+        assert_eq!(GfxError::GlErrors(vec![GlError::NoError]), gl_get_errors(&context));
+    }
 }
\ No newline at end of file
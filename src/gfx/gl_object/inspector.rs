@@ -0,0 +1,122 @@
+//! Descriptions of the objects owned by a `GlObjectManager`, for inspecting them while debugging
+//!
+//! Everything here serializes to JSON, which is what the inspector exposed to javascript returns
+
+use gen_vec::Index;
+use serde::Serialize;
+
+use crate::gfx::gl_object::uniform_buffer::UniformBlock;
+
+/// Everything owned by a `GlObjectManager`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManagerInspection
+{
+    /// Active texture unit, if one has been set
+    pub active_texture: Option<String>,
+    pub objects: Vec<ObjectInfo>,
+}
+
+/// Description of one object owned by a manager
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ObjectInfo
+{
+    #[serde(rename = "type")]
+    pub type_name: &'static str,
+    /// Index of the object's handle within the manager
+    pub handle: Index,
+    /// Name given to the object when it was inserted
+    pub label: Option<String>,
+    /// Id of the object within its context
+    pub gl_id: u32,
+    /// Whether the object is the one of its type that the manager has bound
+    pub bound: bool,
+    pub gpu_bytes: usize,
+    pub shadow_bytes: usize,
+    pub needs_data: bool,
+    #[serde(flatten)]
+    pub details: ObjectDetails,
+}
+
+/// State specific to each type of object
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ObjectDetails
+{
+    Buffer
+    {
+        target: String,
+        /// `None` until data has been uploaded
+        usage: Option<String>,
+    },
+    UniformBuffer
+    {
+        usage: Option<String>,
+        blocks: Vec<UniformBlock>,
+    },
+    Texture
+    {
+        target: String,
+        width: i32,
+        height: i32,
+        internal_format: String,
+        format: String,
+        wrap: String,
+        filter: String,
+    },
+    ShaderProgram
+    {
+        vertex_source: Option<String>,
+        fragment_source: Option<String>,
+        uniforms: Vec<UniformInfo>,
+        attributes: Vec<AttribInfo>,
+        uniform_blocks: Vec<UniformBlockInfo>,
+    },
+    VertexArray
+    {
+        /// Handles of the buffers registered with the vertex array
+        array_buffers: Vec<Index>,
+        element_array_buffers: Vec<Index>,
+    },
+}
+
+/// An active uniform of a shader program
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UniformInfo
+{
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// Number of array elements, 1 for non-arrays
+    pub size: i32,
+    /// Name of the uniform block that the uniform is in, if any
+    pub block: Option<String>,
+    /// Byte offset of the uniform within its uniform block
+    pub block_offset: Option<i32>,
+    /// Last value set on the uniform, which is `None` for uniforms that haven't been set or are in a block
+    pub value: Option<String>,
+}
+
+/// An active vertex attribute of a shader program
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttribInfo
+{
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub size: i32,
+    pub location: u32,
+}
+
+/// An active uniform block of a shader program
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UniformBlockInfo
+{
+    pub name: String,
+    pub index: u32,
+    /// Minimum buffer size, in bytes, needed to back the block
+    pub data_size: i32,
+    /// Uniform buffer binding point the block was attached to, if any
+    pub binding: Option<u32>,
+    /// Names of the uniforms within the block
+    pub uniforms: Vec<String>,
+}
@@ -1,6 +1,9 @@
 use gen_vec::{Index, exposed::{IndexAllocator, ExposedGenVec}};
 use std::
 {
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
     cell::
     {
         Cell,
@@ -14,13 +17,84 @@ use crate::gfx::
 {
     Context,
     GfxError,
-    gl_object::traits::{Bindable, Reloadable},
+    gl_object::
+    {
+        inspector::{ManagerInspection, ObjectInfo},
+        traits::{Bindable, Reloadable, MemoryUsage, Inspect},
+    },
 };
 
+/// Number of objects of one type owned by a manager, and the memory they use
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TypeMemory
+{
+    pub type_name: &'static str,
+    pub count: usize,
+    pub gpu_bytes: usize,
+    pub shadow_bytes: usize,
+}
+
+/// Memory used by all of a manager's objects, by type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemorySummary
+{
+    pub types: Vec<TypeMemory>,
+}
+
+impl MemorySummary
+{
+    pub fn count(&self) -> usize
+    {
+        self.types.iter().map(|ty| ty.count).sum()
+    }
+
+    pub fn gpu_bytes(&self) -> usize
+    {
+        self.types.iter().map(|ty| ty.gpu_bytes).sum()
+    }
+
+    pub fn shadow_bytes(&self) -> usize
+    {
+        self.types.iter().map(|ty| ty.shadow_bytes).sum()
+    }
+}
+
+impl fmt::Display for MemorySummary
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        writeln!(f, "{:<20}{:>8}{:>12}{:>12}", "type", "count", "gpu bytes", "cpu bytes")?;
+        for ty in &self.types
+        {
+            writeln!(f, "{:<20}{:>8}{:>12}{:>12}", ty.type_name, ty.count, ty.gpu_bytes, ty.shadow_bytes)?;
+        }
+        write!(f, "{:<20}{:>8}{:>12}{:>12}", "total", self.count(), self.gpu_bytes(), self.shadow_bytes())
+    }
+}
+
+/// An object that is still owned by a manager
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LiveObject
+{
+    pub type_name: &'static str,
+    pub index: Index,
+    pub gpu_bytes: usize,
+    pub shadow_bytes: usize,
+}
+
+impl fmt::Display for LiveObject
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}({:?}): {} gpu bytes, {} cpu bytes", self.type_name, self.index, self.gpu_bytes, self.shadow_bytes)
+    }
+}
+
 /// Defines and implements a new struct instance manager.
 ///
 /// `manager_name` is the name of the manager struct to be created and implemented
-/// `handle_name` is the name of the type alias to `gen_vec::Index` specific to this manager
+/// `handle_name` is the name of the handle struct to be created, which is generic over the managed struct
+/// that it refers to so that i.e. a texture's handle can't be used as a vertex array's handle
 /// `module_path`, `managed_struct` should look something like
 ///     `$module_path::texture, Texture`
 macro_rules! define_manager
@@ -28,15 +102,77 @@ macro_rules! define_manager
     ($manager_name:ident, $handle_name:ident; $($module_path:path => $managed_struct:ident),+) =>
     {paste::paste!
     {
-        pub type [<$handle_name:camel>] = Index;
+        /// Handle to an object of type `T` owned by the manager
+        pub struct $handle_name<T>
+        {
+            index: Index,
+            _marker: PhantomData<fn() -> T>,
+        }
+
+        impl<T> $handle_name<T>
+        {
+            /// Only the manager, and objects that store handles in gen vecs, should create handles
+            pub(super) fn from_index(index: Index) -> $handle_name<T>
+            {
+                $handle_name { index, _marker: PhantomData }
+            }
+
+            /// Index of the object within the manager, which is shared by every type of object
+            pub fn index(self) -> Index
+            {
+                self.index
+            }
+        }
+
+        // Derives would require `T` to implement each trait, even though it is only a marker
+
+        impl<T> Clone for $handle_name<T>
+        {
+            fn clone(&self) -> Self
+            {
+                *self
+            }
+        }
+
+        impl<T> Copy for $handle_name<T> {}
+
+        impl<T> PartialEq for $handle_name<T>
+        {
+            fn eq(&self, other: &Self) -> bool
+            {
+                self.index == other.index
+            }
+        }
+
+        impl<T> Eq for $handle_name<T> {}
+
+        impl<T> Hash for $handle_name<T>
+        {
+            fn hash<H: Hasher>(&self, state: &mut H)
+            {
+                self.index.hash(state);
+            }
+        }
+
+        impl<T> fmt::Debug for $handle_name<T>
+        {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+            {
+                let name = std::any::type_name::<T>();
+                write!(f, "{}<{}>({:?})", stringify!($handle_name), name.rsplit("::").next().unwrap_or(name), self.index)
+            }
+        }
+
         pub struct $manager_name
         {
             allocator: IndexAllocator,
             $(
             [<$managed_struct:snake s>]: ExposedGenVec<RefCell<$module_path::$managed_struct>>,
-            [<bound_ $managed_struct:snake>]: Cell<Option<[<$handle_name:camel>]>>,
+            [<bound_ $managed_struct:snake>]: Cell<Option<$handle_name<$module_path::$managed_struct>>>,
             )+
             active_texture: Cell<u32>,
+            // Debug names of objects, shared by every type of object like the indices are
+            labels: ExposedGenVec<String>,
         }
 
         impl $manager_name
@@ -50,7 +186,8 @@ macro_rules! define_manager
                     [<$managed_struct:snake s>]: ExposedGenVec::new(),
                     [<bound_ $managed_struct:snake>]: Cell::new(None),
                     )+
-                    active_texture: Cell::new(u32::MAX)
+                    active_texture: Cell::new(u32::MAX),
+                    labels: ExposedGenVec::new(),
                 }
             }
 
@@ -58,37 +195,70 @@ macro_rules! define_manager
             /// Insert (and move) the given struct into the manager's ownership
             /// Returns a handle to the object
             #[allow(dead_code)]
-            pub fn [<insert_ $managed_struct:snake>](&mut self, [<$managed_struct:snake>]: $module_path::$managed_struct) -> [<$handle_name:camel>]
+            pub fn [<insert_ $managed_struct:snake>](&mut self, [<$managed_struct:snake>]: $module_path::$managed_struct) -> $handle_name<$module_path::$managed_struct>
+            {
+                self.[<insert_ $managed_struct:snake _with_label>](None, [<$managed_struct:snake>])
+            }
+            /// Insert the given struct like `insert_*`, naming it `label` for debugging, i.e. "robot VAO"
+            /// Returns a handle to the object
+            #[allow(dead_code)]
+            pub fn [<insert_labeled_ $managed_struct:snake>](&mut self, label: &str, [<$managed_struct:snake>]: $module_path::$managed_struct) -> $handle_name<$module_path::$managed_struct>
+            {
+                self.[<insert_ $managed_struct:snake _with_label>](Some(label), [<$managed_struct:snake>])
+            }
+            /// Insert the given struct with an optional label, naming it within its context once the label is set
+            fn [<insert_ $managed_struct:snake _with_label>](&mut self, label: Option<&str>, [<$managed_struct:snake>]: $module_path::$managed_struct) -> $handle_name<$module_path::$managed_struct>
+            {
+                let index = self.allocator.allocate();
+                self.[<$managed_struct:snake s>].set(index, RefCell::new([<$managed_struct:snake>]));
+                if let Some(label) = label
+                {
+                    self.labels.set(index, label.to_string());
+                }
+                self.[<label_ $managed_struct:snake _in_context>](index);
+                $handle_name::from_index(index)
+            }
+            /// Name the object at `index` within its context after its handle and label, so that GL errors can refer to it
+            fn [<label_ $managed_struct:snake _in_context>](&self, index: Index)
             {
-                let handle = self.allocator.allocate();
-                self.[<$managed_struct:snake s>].set(handle, RefCell::new([<$managed_struct:snake>]));
-                handle
+                if let Some(obj) = self.[<$managed_struct:snake s>].get(index)
+                {
+                    let handle: $handle_name<$module_path::$managed_struct> = $handle_name::from_index(index);
+                    let label = match self.labels.get(index)
+                    {
+                        Some(label) => format!("{:?} \"{}\"", handle, label),
+                        None => format!("{:?}", handle),
+                    };
+                    obj.borrow().label_in_context(&label);
+                }
             }
             /// Get an immutable reference to the struct associated with `handle` if `handle` is valid
             #[allow(dead_code)]
-            pub fn [<get_ $managed_struct:snake>](&self, handle: [<$handle_name:camel>]) -> Option<Ref<$module_path::$managed_struct>>
+            pub fn [<get_ $managed_struct:snake>](&self, handle: $handle_name<$module_path::$managed_struct>) -> Option<Ref<'_, $module_path::$managed_struct>>
             {
-                Some(self.[<$managed_struct:snake s>].get(handle)?.borrow())
+                Some(self.[<$managed_struct:snake s>].get(handle.index)?.borrow())
             }
             /// Get a mutable reference to the struct associated with `handle` if `handle` is valid
             #[allow(dead_code)]
-            pub fn [<get_mut_ $managed_struct:snake>](&self, handle: [<$handle_name:camel>]) -> Option<RefMut<$module_path::$managed_struct>>
+            pub fn [<get_mut_ $managed_struct:snake>](&self, handle: $handle_name<$module_path::$managed_struct>) -> Option<RefMut<'_, $module_path::$managed_struct>>
             {
-                Some(self.[<$managed_struct:snake s>].get(handle)?.borrow_mut())
+                Some(self.[<$managed_struct:snake s>].get(handle.index)?.borrow_mut())
             }
             /// Remove the struct associated with `handle` from the manager's ownership and drop its memory
             #[allow(dead_code)]
-            pub fn [<remove_ $managed_struct:snake>](&mut self, handle: [<$handle_name:camel>])
+            pub fn [<remove_ $managed_struct:snake>](&mut self, handle: $handle_name<$module_path::$managed_struct>)
             {
-                self.[<$managed_struct:snake s>].remove(handle);
-                self.allocator.deallocate(handle);
+                self.[<$managed_struct:snake s>].remove(handle.index);
+                self.labels.remove(handle.index);
+                self.allocator.deallocate(handle.index);
             }
             /// Bind the struct associated with `handle`
             /// `bound` is whether `handle` should be bound or unbound after this function call
             #[allow(dead_code)]
-            pub fn [<bind_ $managed_struct:snake>](&self, handle: [<$handle_name:camel>], bound: bool) -> Result<(), GfxError>
+            #[track_caller]
+            pub fn [<bind_ $managed_struct:snake>](&self, handle: $handle_name<$module_path::$managed_struct>, bound: bool) -> Result<(), GfxError>
             {
-                if let Some(obj) = self.[<$managed_struct:snake s>].get(handle)
+                if let Some(obj) = self.[<$managed_struct:snake s>].get(handle.index)
                 {
                     let bound_struct = self.[<bound_ $managed_struct:snake>].get();
                     if bound && bound_struct != Some(handle)
@@ -104,13 +274,14 @@ macro_rules! define_manager
                 }
                 else
                 {
-                    return Err(GfxError::InvalidHandle(handle))
+                    return Err(GfxError::InvalidHandle(handle.index))
                 }
 
                 Ok(())
             }
             )+
             /// Set the active texture unit, i.e. TEXTURE0
+            #[track_caller]
             pub fn set_active_texture(&self, context: &Context, texture: u32)
             {
                 if self.active_texture.get() != texture
@@ -119,18 +290,101 @@ macro_rules! define_manager
                     self.active_texture.set(texture);
                 }
             }
+            /// Count the owned structs of each type and the memory they use
+            #[allow(dead_code)]
+            pub fn memory_summary(&self) -> MemorySummary
+            {
+                let mut types = vec![];
+                $(
+                let mut ty = TypeMemory { type_name: stringify!($managed_struct), count: 0, gpu_bytes: 0, shadow_bytes: 0 };
+                for (_, obj) in &self.[<$managed_struct:snake s>]
+                {
+                    let obj = obj.borrow();
+                    ty.count += 1;
+                    ty.gpu_bytes += obj.gpu_bytes();
+                    ty.shadow_bytes += obj.shadow_bytes();
+                }
+                types.push(ty);
+                )+
+                MemorySummary { types }
+            }
+            /// List every owned struct along with the memory it uses
+            #[allow(dead_code)]
+            pub fn live_objects(&self) -> Vec<LiveObject>
+            {
+                let mut objects = vec![];
+                $(
+                for (index, obj) in &self.[<$managed_struct:snake s>]
+                {
+                    let obj = obj.borrow();
+                    objects.push(LiveObject { type_name: stringify!($managed_struct), index, gpu_bytes: obj.gpu_bytes(), shadow_bytes: obj.shadow_bytes() });
+                }
+                )+
+                objects
+            }
+            /// Name given to the object at `index` when it was inserted, if any
+            #[allow(dead_code)]
+            pub fn label(&self, index: Index) -> Option<&str>
+            {
+                self.labels.get(index).map(|label| label.as_str())
+            }
+            /// Describe every owned struct, including its label, handle, bound state and type-specific state
+            #[allow(dead_code)]
+            pub fn inspect(&self) -> ManagerInspection
+            {
+                let mut objects = vec![];
+                $(
+                for (index, obj) in &self.[<$managed_struct:snake s>]
+                {
+                    let obj = obj.borrow();
+                    objects.push(ObjectInfo
+                    {
+                        type_name: stringify!($managed_struct),
+                        handle: index,
+                        label: self.labels.get(index).cloned(),
+                        gl_id: obj.gl_id(),
+                        bound: self.[<bound_ $managed_struct:snake>].get().map(|handle| handle.index) == Some(index),
+                        gpu_bytes: obj.gpu_bytes(),
+                        shadow_bytes: obj.shadow_bytes(),
+                        needs_data: obj.needs_data(),
+                        details: obj.details(),
+                    });
+                }
+                )+
+                let active_texture = self.active_texture.get();
+                ManagerInspection
+                {
+                    active_texture: (active_texture != u32::MAX).then(|| Context::constant_name(active_texture)),
+                    objects,
+                }
+            }
+            /// Free the CPU-side shadow copies of every owned struct's data
+            /// Structs whose data is lost in a context loss report `needs_data()` after `reload_objects`,
+            /// and have to be supplied with their data again, i.e. from the `ResourceManager`
+            #[allow(dead_code)]
+            pub fn drop_shadow_copies(&self)
+            {
+                $(
+                for (_, obj) in &self.[<$managed_struct:snake s>] { obj.borrow_mut().drop_shadow_copy(); }
+                )+
+            }
             /// Reloads the state of all owned structs and re-binds the previously bound structs
             #[allow(dead_code)]
             pub fn reload_objects(&self, context: &Context)
             {
                 $(
-                for (_, obj) in &self.[<$managed_struct:snake s>] { obj.borrow_mut().reload(&context, &self).expect(concat!(stringify!([<$managed_struct:snake s>]), " reloaded")); }
+                for (index, obj) in &self.[<$managed_struct:snake s>]
+                {
+                    obj.borrow_mut().reload(&context, &self).expect(concat!(stringify!([<$managed_struct:snake s>]), " reloaded"));
+                    // The reloaded object has a new id
+                    self.[<label_ $managed_struct:snake _in_context>](index);
+                }
                 )+
 
                 $(
                 if let Some(handle) = self.[<bound_ $managed_struct:snake>].get()
                 {
-                    if let Some(obj) = self.[<$managed_struct:snake s>].get(handle)
+                    if let Some(obj) = self.[<$managed_struct:snake s>].get(handle.index)
                     {
                         obj.borrow().bind_internal();
                     }
@@ -142,11 +396,293 @@ macro_rules! define_manager
         }
     }};
 }
-define_manager!(GlObjectManager, GlObjectHandle;
+define_manager!(GlObjectManager, Handle;
     crate::gfx::gl_object => ArrayBuffer,
     crate::gfx::gl_object => ElementArrayBuffer,
     crate::gfx::gl_object::shader_program => ShaderProgram,
     crate::gfx::gl_object::texture => Texture2d,
     crate::gfx::gl_object::uniform_buffer => UniformBuffer,
     crate::gfx::gl_object::vertex_array => VertexArray
-);
\ No newline at end of file
+);
+
+impl GlObjectManager
+{
+    /// Log the objects that haven't been removed from the manager, if there are any
+    pub fn report_live_objects(&self)
+    {
+        let live_objects = self.live_objects();
+        if !live_objects.is_empty()
+        {
+            let mut report = format!("GlObjectManager has {} live objects:", live_objects.len());
+            for object in &live_objects
+            {
+                report += &format!("\n    {}", object);
+                if let Some(label) = self.label(object.index)
+                {
+                    report += &format!(" ({})", label);
+                }
+            }
+            crate::log_s(report);
+        }
+    }
+}
+
+/// Report the objects that were never removed from the manager
+#[cfg(feature = "debug")]
+impl Drop for GlObjectManager
+{
+    fn drop(&mut self)
+    {
+        self.report_live_objects();
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use gen_vec::exposed::IndexAllocator;
+    use crate::gfx::
+    {
+        GfxError,
+        GlError,
+        gl_get_errors,
+        backend::{BufferId, TextureId, VertexArrayId, recording::{GlCall, ProgramInfo, RecordingBackend}},
+        gl_object::
+        {
+            ArrayBuffer,
+            buffer::Buffer,
+            manager::*,
+            shader_program::ShaderProgram,
+            texture::{Texture2d, Texture2dParams},
+            traits::GlObject,
+            uniform_buffer::{UniformBlockDesc, UniformBuffer},
+            vertex_array::{AttribPointer, VertexArray},
+        },
+    };
+
+    fn recording(context: &Context) -> &RecordingBackend
+    {
+        context.backend::<RecordingBackend>().expect("recording backend")
+    }
+
+    #[test]
+    fn test_handle()
+    {
+        let mut allocator = IndexAllocator::new();
+        let (first, second) = (allocator.allocate(), allocator.allocate());
+        let texture: Handle<Texture2d> = Handle::from_index(first);
+
+        assert_eq!(first, texture.index());
+        assert_eq!(texture, Handle::from_index(first));
+        assert_ne!(texture, Handle::from_index(second));
+        // Handles to different types can share an index, but are still different types
+        let vert_arr: Handle<VertexArray> = Handle::from_index(first);
+        assert_eq!(texture.index(), vert_arr.index());
+        assert_eq!(format!("Handle<Texture2d>({:?})", first), format!("{:?}", texture));
+    }
+
+    #[test]
+    fn test_memory_summary()
+    {
+        let manager = GlObjectManager::new();
+        let summary = manager.memory_summary();
+        assert_eq!(6, summary.types.len());
+        assert_eq!(TypeMemory { type_name: "Texture2d", count: 0, gpu_bytes: 0, shadow_bytes: 0 }, summary.types[3]);
+        assert!(manager.live_objects().is_empty());
+
+        let summary = MemorySummary
+        {
+            types: vec![
+                TypeMemory { type_name: "ArrayBuffer", count: 2, gpu_bytes: 64, shadow_bytes: 0 },
+                TypeMemory { type_name: "Texture2d", count: 1, gpu_bytes: 1024, shadow_bytes: 768 },
+            ]
+        };
+        assert_eq!(3, summary.count());
+        assert_eq!(1088, summary.gpu_bytes());
+        assert_eq!(768, summary.shadow_bytes());
+        assert_eq!("total                      3        1088         768", summary.to_string().lines().last().unwrap());
+    }
+
+    #[test]
+    fn test_report_live_objects()
+    {
+        let context = Context::new(RecordingBackend::new());
+        let mut manager = GlObjectManager::new();
+        // Nothing to report
+        manager.report_live_objects();
+
+        let arr_buff = manager.insert_labeled_array_buffer("robot vertices", ArrayBuffer::new(&context).expect("array buffer"));
+        // Logging works outside of the browser too
+        manager.report_live_objects();
+        assert_eq!(1, manager.live_objects().len());
+        manager.remove_array_buffer(arr_buff);
+        assert!(manager.live_objects().is_empty());
+    }
+
+    #[test]
+    fn test_bind_caching()
+    {
+        let context = Context::new(RecordingBackend::new());
+        let mut manager = GlObjectManager::new();
+        let arr_buff = manager.insert_array_buffer(ArrayBuffer::new(&context).expect("array buffer"));
+        let other_buff = manager.insert_array_buffer(ArrayBuffer::new(&context).expect("array buffer"));
+        recording(&context).take_calls();
+
+        // Binding what is already bound doesn't reach the context
+        ArrayBuffer::bind(&manager, arr_buff);
+        ArrayBuffer::bind(&manager, arr_buff);
+        ArrayBuffer::bind(&manager, other_buff);
+        ArrayBuffer::unbind(&manager, other_buff);
+        ArrayBuffer::unbind(&manager, other_buff);
+        manager.set_active_texture(&context, Context::TEXTURE1);
+        manager.set_active_texture(&context, Context::TEXTURE1);
+        assert_eq!(vec![
+            GlCall::BindBuffer(Context::ARRAY_BUFFER, Some(BufferId(1))),
+            GlCall::BindBuffer(Context::ARRAY_BUFFER, Some(BufferId(2))),
+            GlCall::BindBuffer(Context::ARRAY_BUFFER, None),
+            GlCall::ActiveTexture(Context::TEXTURE1),
+        ], recording(&context).take_calls());
+
+        // Invalid handles are rejected without reaching the context
+        manager.remove_array_buffer(other_buff);
+        assert_eq!(Err(GfxError::InvalidHandle(other_buff.index())), manager.bind_array_buffer(other_buff, true));
+        assert_eq!(vec![GlCall::DeleteBuffer(BufferId(2))], recording(&context).take_calls());
+        assert_eq!(GfxError::GlErrors(vec![GlError::NoError]), gl_get_errors(&context));
+    }
+
+    #[test]
+    fn test_reload_objects()
+    {
+        let context = Context::new(RecordingBackend::new());
+        let mut manager = GlObjectManager::new();
+        let texture = manager.insert_texture2d(Texture2d::new(&context, Texture2dParams
+        {
+            target: Context::TEXTURE_2D,
+            internal_format: Context::RGBA8,
+            format: Context::RGBA,
+            size: (1, 1),
+            wrap_type: Context::REPEAT,
+            filter_type: Context::NEAREST,
+            data: vec![255; 4],
+        }).expect("texture"));
+        let vert_arr = manager.insert_vertex_array(VertexArray::new(&context).expect("vertex array"));
+        let arr_buff = manager.insert_array_buffer(ArrayBuffer::new(&context).expect("array buffer"));
+
+        manager.set_active_texture(&context, Context::TEXTURE0);
+        Texture2d::bind(&manager, texture);
+        manager.get_texture2d(texture).expect("texture").setup_texture().expect("texture setup");
+        VertexArray::bind(&manager, vert_arr);
+        ArrayBuffer::bind(&manager, arr_buff);
+        manager.get_mut_array_buffer(arr_buff).expect("array buffer").buffer_data(&[0.0f32, 1.0], Context::STATIC_DRAW);
+        manager.get_mut_vertex_array(vert_arr).expect("vertex array")
+            .register_array_buffer(arr_buff, Some(vec![AttribPointer::with_defaults::<f32>(0, 2, Context::FLOAT, 0)]));
+        VertexArray::unbind(&manager, vert_arr);
+        assert_eq!(GfxError::GlErrors(vec![GlError::NoError]), gl_get_errors(&context));
+
+        let new_context = Context::new(RecordingBackend::new());
+        manager.reload_objects(&new_context);
+        assert_eq!(GfxError::GlErrors(vec![GlError::NoError]), gl_get_errors(&new_context));
+
+        // Buffers are reloaded before the vertex arrays that point into them, and whatever
+        // was bound before is bound again afterwards
+        let data = crate::gfx::gl_object::pod::as_bytes(&[0.0f32, 1.0]).to_vec();
+        assert_eq!(vec![
+            GlCall::CreateBuffer(BufferId(1)),
+            GlCall::BindBuffer(Context::ARRAY_BUFFER, Some(BufferId(1))),
+            GlCall::BufferData(Context::ARRAY_BUFFER, data, Context::STATIC_DRAW),
+            GlCall::CreateTexture(TextureId(2)),
+            GlCall::BindTexture(Context::TEXTURE_2D, Some(TextureId(2))),
+            GlCall::BindTexture(Context::TEXTURE_2D, Some(TextureId(2))),
+            GlCall::TexParameteri(Context::TEXTURE_2D, Context::TEXTURE_WRAP_S, Context::REPEAT as i32),
+            GlCall::TexParameteri(Context::TEXTURE_2D, Context::TEXTURE_WRAP_T, Context::REPEAT as i32),
+            GlCall::TexParameteri(Context::TEXTURE_2D, Context::TEXTURE_MIN_FILTER, Context::NEAREST as i32),
+            GlCall::TexParameteri(Context::TEXTURE_2D, Context::TEXTURE_MAG_FILTER, Context::NEAREST as i32),
+            GlCall::TexImage2d { target: Context::TEXTURE_2D, level: 0, internal_format: Context::RGBA8 as i32, width: 1, height: 1, format: Context::RGBA, data: Some(vec![255; 4]) },
+            GlCall::BindTexture(Context::TEXTURE_2D, None),
+            GlCall::CreateVertexArray(VertexArrayId(3)),
+            GlCall::BindVertexArray(Some(VertexArrayId(3))),
+            GlCall::BindBuffer(Context::ARRAY_BUFFER, Some(BufferId(1))),
+            GlCall::VertexAttribPointer { index: 0, size: 2, data_type: Context::FLOAT, normalized: false, stride: 8, offset: 0 },
+            GlCall::EnableVertexAttribArray(0),
+            GlCall::BindVertexArray(None),
+            GlCall::BindBuffer(Context::ARRAY_BUFFER, Some(BufferId(1))),
+            GlCall::BindTexture(Context::TEXTURE_2D, Some(TextureId(2))),
+            GlCall::ActiveTexture(Context::TEXTURE0),
+        ], recording(&new_context).take_calls());
+
+        // Objects now belong to the new context, and delete themselves from it
+        drop(manager);
+        assert_eq!(0, recording(&new_context).live_objects());
+        assert_eq!(3, recording(&context).live_objects());
+    }
+
+    #[test]
+    fn test_inspect()
+    {
+        let context = Context::new(RecordingBackend::new());
+        recording(&context).set_program_info(ProgramInfo::default()
+            .uniform("tex", Context::SAMPLER_2D)
+            .block("VertData", 64)
+            .block_uniform("mvp", Context::FLOAT_MAT4, "VertData", 0)
+            .attrib("position", Context::FLOAT_VEC3, 0));
+        let mut manager = GlObjectManager::new();
+        let shader_program = manager.insert_labeled_shader_program("robot shader",
+            ShaderProgram::new(&context, Some("vert".to_string()), Some("frag".to_string())).expect("shader program"));
+        let uniform_buffer = manager.insert_uniform_buffer(
+            UniformBuffer::new(&context, &[UniformBlockDesc::new("VertData", 2, 64)], Context::DYNAMIC_DRAW).expect("uniform buffer"));
+        let arr_buff = manager.insert_labeled_array_buffer("robot vertices", ArrayBuffer::new(&context).expect("array buffer"));
+        let vert_arr = manager.insert_labeled_vertex_array("robot VAO", VertexArray::new(&context).expect("vertex array"));
+
+        ShaderProgram::bind(&manager, shader_program);
+        manager.get_mut_shader_program(shader_program).expect("shader program").set_uniform_i32("tex", &[0]).expect("tex set");
+        manager.get_uniform_buffer(uniform_buffer).expect("uniform buffer")
+            .attach_block(&mut manager.get_mut_shader_program(shader_program).expect("shader program"), "VertData").expect("block attached");
+        ArrayBuffer::bind(&manager, arr_buff);
+        manager.get_mut_array_buffer(arr_buff).expect("array buffer").buffer_data(&[0.0f32; 3], Context::STATIC_DRAW);
+        manager.get_mut_vertex_array(vert_arr).expect("vertex array").register_array_buffer(arr_buff, None);
+        manager.set_active_texture(&context, Context::TEXTURE0);
+
+        let inspection = serde_json::to_value(manager.inspect()).expect("inspection serialized");
+        assert_eq!("TEXTURE0", inspection["active_texture"]);
+        let objects = inspection["objects"].as_array().expect("objects");
+        assert_eq!(4, objects.len());
+
+        // Objects are listed by type, in the order the manager declares them
+        let buffer = &objects[0];
+        assert_eq!("ArrayBuffer", buffer["type"]);
+        assert_eq!("robot vertices", buffer["label"]);
+        assert_eq!(serde_json::to_value(arr_buff.index()).unwrap(), buffer["handle"]);
+        assert_eq!(true, buffer["bound"]);
+        assert_eq!(12, buffer["gpu_bytes"]);
+        assert_eq!("ARRAY_BUFFER", buffer["target"]);
+        assert_eq!("STATIC_DRAW", buffer["usage"]);
+
+        let program = &objects[1];
+        assert_eq!("ShaderProgram", program["type"]);
+        assert_eq!("vert", program["vertex_source"]);
+        assert_eq!("frag", program["fragment_source"]);
+        assert_eq!(serde_json::json!([
+            { "name": "tex", "type": "SAMPLER_2D", "size": 1, "block": null, "block_offset": null, "value": "Int(1, [0])" },
+            { "name": "mvp", "type": "FLOAT_MAT4", "size": 1, "block": "VertData", "block_offset": 0, "value": null },
+        ]), program["uniforms"]);
+        assert_eq!(serde_json::json!([{ "name": "position", "type": "FLOAT_VEC3", "size": 1, "location": 0 }]), program["attributes"]);
+        assert_eq!(serde_json::json!([{ "name": "VertData", "index": 0, "data_size": 64, "binding": 2, "uniforms": ["mvp"] }]), program["uniform_blocks"]);
+
+        let uniforms = &objects[2];
+        assert_eq!("UniformBuffer", uniforms["type"]);
+        assert_eq!(serde_json::Value::Null, uniforms["label"]);
+        assert_eq!(false, uniforms["bound"]);
+        assert_eq!(serde_json::json!([{ "name": "VertData", "binding": 2, "offset": 0, "size": 64 }]), uniforms["blocks"]);
+
+        let vao = &objects[3];
+        assert_eq!("robot VAO", vao["label"]);
+        assert_eq!(serde_json::json!([serde_json::to_value(arr_buff.index()).unwrap()]), vao["array_buffers"]);
+
+        // Labels are dropped with their objects, and aren't given to objects that reuse the index
+        manager.remove_array_buffer(arr_buff);
+        assert_eq!(None, manager.label(arr_buff.index()));
+        let other_buff = manager.insert_array_buffer(ArrayBuffer::new(&context).expect("array buffer"));
+        assert_eq!(None, manager.label(other_buff.index()));
+        assert_eq!(Some("robot VAO"), manager.label(vert_arr.index()));
+    }
+}
@@ -1,16 +1,24 @@
 #[macro_use]
 pub mod traits;
 pub mod manager;
+pub mod inspector;
 
 //pub mod buffer_old;
 #[macro_use]
+pub mod pod;
+#[macro_use]
 pub mod buffer;
 use traits::{Bindable};
 use buffer::Buffer;
 impl_buffer!(ARRAY_BUFFER, ArrayBuffer);
 impl_buffer!(ELEMENT_ARRAY_BUFFER, ElementArrayBuffer);
 
+#[macro_use]
+pub mod std140;
 pub mod shader_program;
+pub mod reflection;
+pub mod uniform;
 pub mod uniform_buffer;
 pub mod texture;
-pub mod vertex_array;
\ No newline at end of file
+pub mod vertex_array;#[macro_use]
+pub mod vertex_layout;
@@ -0,0 +1,128 @@
+//! Plain old data that can be uploaded to GPU buffers
+//!
+//! Buffers copy their data byte for byte, so only types whose bytes are all meaningful can be
+//! uploaded. References would upload addresses, padding would upload uninitialized memory and
+//! the fields of a non-`#[repr(C)]` struct can be in any order
+
+use cgmath::{Matrix2, Matrix3, Matrix4, Point2, Point3, Quaternion, Vector1, Vector2, Vector3, Vector4};
+
+/// A type that can be copied into a GPU buffer as raw bytes
+///
+/// Structs can implement this with the `pod_struct!` macro, which checks these requirements
+///
+/// # Safety
+/// The type must be `#[repr(C)]` or a primitive, have no padding bytes, and not contain
+/// any references or pointers
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod
+{
+    ($($type:ty),*) =>
+    {
+        $(unsafe impl Pod for $type {})*
+    };
+}
+impl_pod!(u8, i8, u16, i16, u32, i32, f32);
+
+// cgmath's types are `#[repr(C)]` and made up of a single type of component, so they never have padding
+macro_rules! impl_pod_generic
+{
+    ($($type:ident),*) =>
+    {
+        $(unsafe impl<S: Pod> Pod for $type<S> {})*
+    };
+}
+impl_pod_generic!(Vector1, Vector2, Vector3, Vector4, Point2, Point3, Matrix2, Matrix3, Matrix4, Quaternion);
+
+/// Arrays are laid out with no padding between their elements
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// Get the bytes of `data`
+pub fn as_bytes<T: Pod>(data: &[T]) -> &[u8]
+{
+    // `T: Pod` guarantees that every byte of `data` is initialized
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
+
+/// Define a `#[repr(C)]` struct and implement `Pod` for it, so that it can be uploaded to buffers
+///
+/// All fields must implement `Pod`. Compilation fails if the fields would need padding
+/// between them or at the end of the struct, so the fields must be ordered, or padded explicitly,
+/// to line up with their alignment
+///
+/// ```ignore
+/// pod_struct!
+/// {
+///     #[derive(Debug, Copy, Clone)]
+///     pub struct Vertex
+///     {
+///         pub position: [f32; 3],
+///         pub texcoord: [f32; 2],
+///     }
+/// }
+/// ```
+macro_rules! pod_struct
+{
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident
+        {
+            $($(#[$field_attr:meta])* $field_vis:vis $field:ident: $type:ty),* $(,)?
+        }
+    ) =>
+    {
+        $(#[$attr])*
+        #[repr(C)]
+        $vis struct $name
+        {
+            $($(#[$field_attr])* $field_vis $field: $type,)*
+        }
+
+        // The size of the struct is only the sum of its fields' sizes if there's no padding
+        const _: () = assert!(std::mem::size_of::<$name>() == 0 $(+ std::mem::size_of::<$type>())*,
+                              concat!(stringify!($name), " has padding, so it can't be Pod"));
+
+        // Safety: `#[repr(C)]` with `Pod` fields and no padding
+        unsafe impl crate::gfx::gl_object::pod::Pod for $name {}
+
+        // Fails to compile if a field isn't `Pod`
+        const _: fn() = ||
+        {
+            fn assert_pod<T: crate::gfx::gl_object::pod::Pod>() {}
+            $(assert_pod::<$type>();)*
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests
+{
+    use cgmath::{Matrix4, SquareMatrix, vec3};
+    use crate::gfx::gl_object::pod::*;
+
+    pod_struct!
+    {
+        #[derive(Copy, Clone)]
+        struct Instance
+        {
+            model: Matrix4<f32>,
+            color: [u8; 4],
+            id: u32,
+        }
+    }
+
+    #[test]
+    fn test_as_bytes()
+    {
+        assert_eq!(&[1, 2], as_bytes(&[1u8, 2]));
+        assert_eq!(&[1u16.to_ne_bytes(), 2u16.to_ne_bytes()].concat(), as_bytes(&[1u16, 2]));
+        assert_eq!(&1.0f32.to_ne_bytes(), &as_bytes(&[vec3(1.0f32, 2.0, 3.0)])[..4]);
+        assert_eq!(24, as_bytes(&[vec3(1.0f32, 2.0, 3.0), vec3(4.0, 5.0, 6.0)]).len());
+
+        let instance = Instance { model: Matrix4::identity(), color: [255, 0, 0, 255], id: 7 };
+        let bytes = as_bytes(std::slice::from_ref(&instance));
+        assert_eq!(72, bytes.len());
+        assert_eq!(&[255, 0, 0, 255], &bytes[64..68]);
+        assert_eq!(&7u32.to_ne_bytes(), &bytes[68..72]);
+    }
+}
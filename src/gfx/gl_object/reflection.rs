@@ -0,0 +1,456 @@
+//! Introspection of linked shader programs
+
+use crate::gfx::
+{
+    Context,
+    GfxError,
+    backend::{ProgramId, UniformLocationId},
+    gl_object::
+    {
+        vertex_array::AttribPointer,
+        vertex_layout::VertexAttrib,
+    },
+};
+
+/// How a GLSL type is laid out in terms of components
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GlTypeLayout
+{
+    /// Scalar type of each component. One of `FLOAT`, `INT`, `UNSIGNED_INT` or `BOOL`
+    pub component_type: u32,
+    /// Number of components in each column
+    pub components: i32,
+    /// Number of columns. This is 1 for everything other than matrices,
+    /// which take up one attribute location per column
+    pub columns: i32,
+}
+
+/// Get the layout of the GLSL type given by `data_type`, as returned by `active_uniform` or
+/// `active_attrib`
+/// Returns `None` for opaque types, such as samplers
+pub fn gl_type_layout(data_type: u32) -> Option<GlTypeLayout>
+{
+    let layout = |component_type: u32, components: i32, columns: i32| Some(GlTypeLayout { component_type, components, columns });
+    match data_type
+    {
+        Context::FLOAT => layout(Context::FLOAT, 1, 1),
+        Context::FLOAT_VEC2 => layout(Context::FLOAT, 2, 1),
+        Context::FLOAT_VEC3 => layout(Context::FLOAT, 3, 1),
+        Context::FLOAT_VEC4 => layout(Context::FLOAT, 4, 1),
+        Context::INT => layout(Context::INT, 1, 1),
+        Context::INT_VEC2 => layout(Context::INT, 2, 1),
+        Context::INT_VEC3 => layout(Context::INT, 3, 1),
+        Context::INT_VEC4 => layout(Context::INT, 4, 1),
+        Context::UNSIGNED_INT => layout(Context::UNSIGNED_INT, 1, 1),
+        Context::UNSIGNED_INT_VEC2 => layout(Context::UNSIGNED_INT, 2, 1),
+        Context::UNSIGNED_INT_VEC3 => layout(Context::UNSIGNED_INT, 3, 1),
+        Context::UNSIGNED_INT_VEC4 => layout(Context::UNSIGNED_INT, 4, 1),
+        Context::BOOL => layout(Context::BOOL, 1, 1),
+        Context::BOOL_VEC2 => layout(Context::BOOL, 2, 1),
+        Context::BOOL_VEC3 => layout(Context::BOOL, 3, 1),
+        Context::BOOL_VEC4 => layout(Context::BOOL, 4, 1),
+        Context::FLOAT_MAT2 => layout(Context::FLOAT, 2, 2),
+        Context::FLOAT_MAT2X3 => layout(Context::FLOAT, 3, 2),
+        Context::FLOAT_MAT2X4 => layout(Context::FLOAT, 4, 2),
+        Context::FLOAT_MAT3X2 => layout(Context::FLOAT, 2, 3),
+        Context::FLOAT_MAT3 => layout(Context::FLOAT, 3, 3),
+        Context::FLOAT_MAT3X4 => layout(Context::FLOAT, 4, 3),
+        Context::FLOAT_MAT4X2 => layout(Context::FLOAT, 2, 4),
+        Context::FLOAT_MAT4X3 => layout(Context::FLOAT, 3, 4),
+        Context::FLOAT_MAT4 => layout(Context::FLOAT, 4, 4),
+        _ => None
+    }
+}
+
+/// An active uniform variable
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct ActiveUniform
+{
+    pub name: String,
+    pub data_type: u32,
+    /// Number of array elements, 1 for non-arrays
+    pub size: i32,
+    /// Location of the uniform, `None` for uniforms within a uniform block
+    pub location: Option<UniformLocationId>,
+    /// Index of the uniform block that the uniform is in, if any
+    pub block_index: Option<u32>,
+    /// Byte offset of the uniform within its uniform block
+    pub block_offset: Option<i32>,
+    /// Bytes between array elements within its uniform block
+    pub array_stride: Option<i32>,
+    /// Bytes between matrix columns within its uniform block
+    pub matrix_stride: Option<i32>,
+}
+
+/// An active vertex attribute
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveAttrib
+{
+    pub name: String,
+    pub data_type: u32,
+    /// Number of array elements, 1 for non-arrays
+    pub size: i32,
+    /// First attribute location used by the attribute
+    pub location: u32,
+}
+
+impl ActiveAttrib
+{
+    /// Number of attribute locations taken up by this attribute
+    pub fn num_locations(&self) -> u32
+    {
+        let columns = gl_type_layout(self.data_type).map_or(1, |layout| layout.columns);
+        (columns * self.size.max(1)) as u32
+    }
+}
+
+/// An active uniform block
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveUniformBlock
+{
+    pub name: String,
+    pub index: u32,
+    /// Minimum buffer size, in bytes, needed to back the block
+    pub data_size: i32,
+    /// Indices into `ShaderReflection::uniforms()` of the uniforms within this block
+    pub uniforms: Vec<usize>,
+}
+
+/// Everything that is active within a linked shader program
+#[derive(Debug, Clone, Default)]
+pub struct ShaderReflection
+{
+    uniforms: Vec<ActiveUniform>,
+    attribs: Vec<ActiveAttrib>,
+    blocks: Vec<ActiveUniformBlock>,
+}
+
+impl ShaderReflection
+{
+    /// Collect all active uniforms, attributes and uniform blocks from a successfully linked program
+    pub fn reflect(context: &Context, program: ProgramId) -> ShaderReflection
+    {
+        let count = |pname: u32| context.program_parameter(program, pname).max(0) as u32;
+
+        let mut reflection = ShaderReflection::default();
+        let mut uniform_indices = vec![];
+        for index in 0..count(Context::ACTIVE_UNIFORMS)
+        {
+            if let Some(info) = context.active_uniform(program, index)
+            {
+                reflection.uniforms.push(ActiveUniform
+                {
+                    location: context.uniform_location(program, &info.name),
+                    name: info.name,
+                    data_type: info.data_type,
+                    size: info.size,
+                    block_index: None,
+                    block_offset: None,
+                    array_stride: None,
+                    matrix_stride: None,
+                });
+                uniform_indices.push(index);
+            }
+        }
+
+        if !uniform_indices.is_empty()
+        {
+            let block_indices = context.active_uniforms_parameter(program, &uniform_indices, Context::UNIFORM_BLOCK_INDEX);
+            let offsets = context.active_uniforms_parameter(program, &uniform_indices, Context::UNIFORM_OFFSET);
+            let array_strides = context.active_uniforms_parameter(program, &uniform_indices, Context::UNIFORM_ARRAY_STRIDE);
+            let matrix_strides = context.active_uniforms_parameter(program, &uniform_indices, Context::UNIFORM_MATRIX_STRIDE);
+
+            for (i, uniform) in reflection.uniforms.iter_mut().enumerate()
+            {
+                // Uniforms in the default block have a block index of -1
+                uniform.block_index = block_indices.get(i).copied().flatten().filter(|&index| index >= 0).map(|index| index as u32);
+                if uniform.block_index.is_some()
+                {
+                    uniform.block_offset = offsets.get(i).copied().flatten();
+                    uniform.array_stride = array_strides.get(i).copied().flatten();
+                    uniform.matrix_stride = matrix_strides.get(i).copied().flatten();
+                }
+            }
+        }
+
+        for index in 0..count(Context::ACTIVE_UNIFORM_BLOCKS)
+        {
+            if let Some(name) = context.active_uniform_block_name(program, index)
+            {
+                let data_size = context.active_uniform_block_parameter(program, index, Context::UNIFORM_BLOCK_DATA_SIZE).unwrap_or(0);
+                let uniforms = reflection.uniforms.iter().enumerate()
+                    .filter(|(_, uniform)| uniform.block_index == Some(index))
+                    .map(|(i, _)| i)
+                    .collect();
+                reflection.blocks.push(ActiveUniformBlock { name, index, data_size, uniforms });
+            }
+        }
+
+        for index in 0..count(Context::ACTIVE_ATTRIBUTES)
+        {
+            if let Some(info) = context.active_attrib(program, index)
+            {
+                let location = context.attrib_location(program, &info.name);
+                // Built-in attributes, such as gl_VertexID, don't have a location
+                if location >= 0
+                {
+                    reflection.attribs.push(ActiveAttrib
+                    {
+                        name: info.name,
+                        data_type: info.data_type,
+                        size: info.size,
+                        location: location as u32,
+                    });
+                }
+            }
+        }
+
+        reflection
+    }
+
+    #[allow(dead_code)]
+    pub fn uniforms(&self) -> &[ActiveUniform]
+    {
+        &self.uniforms
+    }
+
+    #[allow(dead_code)]
+    pub fn attribs(&self) -> &[ActiveAttrib]
+    {
+        &self.attribs
+    }
+
+    #[allow(dead_code)]
+    pub fn blocks(&self) -> &[ActiveUniformBlock]
+    {
+        &self.blocks
+    }
+
+    #[allow(dead_code)]
+    pub fn uniform(&self, name: &str) -> Option<&ActiveUniform>
+    {
+        self.uniforms.iter().find(|uniform| uniform.name == name)
+    }
+
+    #[allow(dead_code)]
+    pub fn attrib(&self, name: &str) -> Option<&ActiveAttrib>
+    {
+        self.attribs.iter().find(|attrib| attrib.name == name)
+    }
+
+    pub fn block(&self, name: &str) -> Option<&ActiveUniformBlock>
+    {
+        self.blocks.iter().find(|block| block.name == name)
+    }
+
+    /// Find the attribute that uses attribute location `location`
+    pub fn attrib_at(&self, location: u32) -> Option<&ActiveAttrib>
+    {
+        self.attribs.iter().find(|attrib| (attrib.location..attrib.location + attrib.num_locations()).contains(&location))
+    }
+
+    /// Check that `attrib_ptrs` supply every active attribute with data of the right shape
+    ///
+    /// Pointers to locations that the shader doesn't use are allowed, since the compiler removes
+    /// attributes that don't contribute to the output
+    pub fn validate_attrib_ptrs(&self, attrib_ptrs: &[AttribPointer]) -> Result<(), GfxError>
+    {
+        for (i, ptr) in attrib_ptrs.iter().enumerate()
+        {
+            if attrib_ptrs[..i].iter().any(|other| other.index() == ptr.index())
+            {
+                return Err(GfxError::AttribLayoutMismatch(format!("Multiple attribute pointers for location {}", ptr.index())));
+            }
+
+            self.validate_attrib_ptr(ptr)?;
+        }
+
+        for attrib in &self.attribs
+        {
+            for location in attrib.location..attrib.location + attrib.num_locations()
+            {
+                if !attrib_ptrs.iter().any(|ptr| ptr.index() == location)
+                {
+                    return Err(GfxError::AttribLayoutMismatch(format!("Attribute {} at location {} has no attribute pointer", attrib.name, location)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that `attribs` are at the locations the shader declares for them, and that they
+    /// supply those attributes with data of the right shape
+    ///
+    /// Unlike `validate_attrib_ptrs`, attributes that `attribs` don't supply are allowed, since
+    /// they can come from other buffers
+    pub fn validate_vertex_attribs(&self, attribs: &[VertexAttrib]) -> Result<(), GfxError>
+    {
+        for vertex_attrib in attribs
+        {
+            if let Some(attrib) = self.attrib(vertex_attrib.name)
+            {
+                if attrib.location != vertex_attrib.location
+                {
+                    return Err(GfxError::AttribLayoutMismatch(format!("Attribute {} is at location {}, but the vertex layout puts it at {}",
+                                                                      attrib.name, attrib.location, vertex_attrib.location)));
+                }
+            }
+            self.validate_attrib_ptr(&vertex_attrib.attrib_ptr(0))?;
+        }
+        Ok(())
+    }
+
+    /// Check that `ptr` supplies the attribute at its location, if any, with data of the right shape
+    fn validate_attrib_ptr(&self, ptr: &AttribPointer) -> Result<(), GfxError>
+    {
+        if let Some(attrib) = self.attrib_at(ptr.index())
+        {
+            let layout = gl_type_layout(attrib.data_type)
+                .ok_or_else(|| GfxError::AttribLayoutMismatch(format!("Attribute {} has unknown type {:#x}", attrib.name, attrib.data_type)))?;
+            // Integer attributes can only be sourced through vertexAttribIPointer
+            if layout.component_type != Context::FLOAT
+            {
+                return Err(GfxError::AttribLayoutMismatch(format!("Attribute {} at location {} is not a floating point attribute", attrib.name, ptr.index())));
+            }
+            if ptr.size() != layout.components
+            {
+                return Err(GfxError::AttribLayoutMismatch(format!("Attribute {} at location {} has {} components, but its pointer has {}",
+                                                                  attrib.name, ptr.index(), layout.components, ptr.size())));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a buffer range of `size` bytes is large enough to back the uniform block `block_name`
+    pub fn validate_uniform_block_size(&self, block_name: &str, size: i32) -> Result<(), GfxError>
+    {
+        let block = self.block(block_name).ok_or_else(|| GfxError::InvalidUniformBlockName(block_name.to_string()))?;
+        if size < block.data_size
+        {
+            Err(GfxError::UniformBlockSizeMismatch(block_name.to_string(), block.data_size, size))
+        }
+        else
+        {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::gfx::
+    {
+        Context,
+        GfxError,
+        gl_object::
+        {
+            reflection::*,
+            vertex_array::AttribPointer,
+            vertex_layout::VertexAttrib,
+        },
+    };
+
+    fn attrib(name: &str, data_type: u32, location: u32) -> ActiveAttrib
+    {
+        ActiveAttrib { name: name.to_string(), data_type, size: 1, location }
+    }
+
+    fn reflection() -> ShaderReflection
+    {
+        ShaderReflection
+        {
+            uniforms: vec![],
+            attribs: vec![
+                attrib("Vertex", Context::FLOAT_VEC3, 0),
+                attrib("UV", Context::FLOAT_VEC2, 2),
+                attrib("Model", Context::FLOAT_MAT4, 4),
+            ],
+            blocks: vec![ActiveUniformBlock { name: "VertData".to_string(), index: 0, data_size: 64, uniforms: vec![] }],
+        }
+    }
+
+    fn ptr(index: u32, size: i32) -> AttribPointer
+    {
+        AttribPointer::with_defaults::<f32>(index, size, Context::FLOAT, 0)
+    }
+
+    #[test]
+    fn test_gl_type_layout()
+    {
+        assert_eq!(Some(GlTypeLayout { component_type: Context::FLOAT, components: 3, columns: 1 }), gl_type_layout(Context::FLOAT_VEC3));
+        assert_eq!(Some(GlTypeLayout { component_type: Context::FLOAT, components: 2, columns: 3 }), gl_type_layout(Context::FLOAT_MAT3X2));
+        assert_eq!(Some(GlTypeLayout { component_type: Context::UNSIGNED_INT, components: 4, columns: 1 }), gl_type_layout(Context::UNSIGNED_INT_VEC4));
+        assert_eq!(None, gl_type_layout(Context::SAMPLER_2D));
+    }
+
+    #[test]
+    fn test_attrib_locations()
+    {
+        let reflection = reflection();
+        assert_eq!(4, reflection.attrib("Model").unwrap().num_locations());
+        assert_eq!(Some("Model"), reflection.attrib_at(7).map(|attrib| attrib.name.as_str()));
+        assert_eq!(None, reflection.attrib_at(1));
+        assert_eq!(None, reflection.attrib_at(8));
+    }
+
+    #[test]
+    fn test_validate_attrib_ptrs()
+    {
+        let reflection = reflection();
+        let mut ptrs = vec![ptr(0, 3), ptr(1, 3), ptr(2, 2), ptr(4, 4), ptr(5, 4), ptr(6, 4), ptr(7, 4)];
+        assert_eq!(Ok(()), reflection.validate_attrib_ptrs(&ptrs));
+
+        // Wrong number of components
+        ptrs[2] = ptr(2, 3);
+        assert!(matches!(reflection.validate_attrib_ptrs(&ptrs), Err(GfxError::AttribLayoutMismatch(_))));
+        ptrs[2] = ptr(2, 2);
+
+        // Missing matrix column
+        assert!(matches!(reflection.validate_attrib_ptrs(&ptrs[..6]), Err(GfxError::AttribLayoutMismatch(_))));
+
+        // Duplicate location
+        ptrs[1] = ptr(0, 3);
+        assert!(matches!(reflection.validate_attrib_ptrs(&ptrs), Err(GfxError::AttribLayoutMismatch(_))));
+
+        // Integer attribute
+        let mut reflection = reflection;
+        reflection.attribs.push(attrib("Id", Context::INT, 8));
+        let ptrs = vec![ptr(0, 3), ptr(2, 2), ptr(4, 4), ptr(5, 4), ptr(6, 4), ptr(7, 4), ptr(8, 1)];
+        assert!(matches!(reflection.validate_attrib_ptrs(&ptrs), Err(GfxError::AttribLayoutMismatch(_))));
+    }
+
+    #[test]
+    fn test_validate_vertex_attribs()
+    {
+        fn vertex_attrib(name: &'static str, location: u32, size: i32) -> VertexAttrib
+        {
+            VertexAttrib { name, location, size, data_type: Context::FLOAT, normalized: false, offset: 0 }
+        }
+
+        // The model matrix comes from another buffer
+        let reflection = reflection();
+        let mut attribs = vec![vertex_attrib("Vertex", 0, 3), vertex_attrib("Normal", 1, 3), vertex_attrib("UV", 2, 2)];
+        assert_eq!(Ok(()), reflection.validate_vertex_attribs(&attribs));
+
+        // Attribute at the wrong location
+        attribs[2] = vertex_attrib("UV", 3, 2);
+        assert!(matches!(reflection.validate_vertex_attribs(&attribs), Err(GfxError::AttribLayoutMismatch(_))));
+
+        // Another attribute's location
+        attribs[2] = vertex_attrib("Color", 2, 4);
+        assert!(matches!(reflection.validate_vertex_attribs(&attribs), Err(GfxError::AttribLayoutMismatch(_))));
+    }
+
+    #[test]
+    fn test_validate_uniform_block_size()
+    {
+        let reflection = reflection();
+        assert_eq!(Ok(()), reflection.validate_uniform_block_size("VertData", 64));
+        assert_eq!(Ok(()), reflection.validate_uniform_block_size("VertData", 256));
+        assert_eq!(Err(GfxError::UniformBlockSizeMismatch("VertData".to_string(), 64, 48)), reflection.validate_uniform_block_size("VertData", 48));
+        assert_eq!(Err(GfxError::InvalidUniformBlockName("FragData".to_string())), reflection.validate_uniform_block_size("FragData", 64));
+    }
+}
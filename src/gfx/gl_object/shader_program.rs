@@ -1,4 +1,3 @@
-use web_sys::{WebGlProgram, WebGlShader};
 use twox_hash::XxHash32;
 use std::
 {
@@ -10,10 +9,15 @@ use crate::gfx::
     Context,
     GfxError,
     gl_get_errors,
+    backend::{ObjectId, ProgramId, ShaderId, UniformLocationId},
+    preprocessor::{PreprocessedShader, SourceMap},
     gl_object::
     {
         manager::{GlObjectManager},
-        traits::{Bindable, Reloadable},
+        traits::{Bindable, Reloadable, MemoryUsage, Inspect},
+        inspector::{ObjectDetails, UniformInfo, AttribInfo, UniformBlockInfo},
+        reflection::ShaderReflection,
+        uniform::{Uniform, UniformValue},
     },
 };
 
@@ -59,104 +63,178 @@ impl Into<u32> for ShaderType
 
 pub struct ShaderProgram
 {
-    internal: WebGlProgram,
+    internal: ProgramId,
     context: Context,
 
     vert_src: Option<String>,
     frag_src: Option<String>,
+    // Used to point compilation errors at the original files of preprocessed sources
+    vert_source_map: Option<SourceMap>,
+    frag_source_map: Option<SourceMap>,
     // Indexed by block binding, holds block names
     block_bindings: Vec<Option<String>>,
-    uniforms_i32: HashMap<String, Vec<i32>, BuildHasherDefault<XxHash32>>
+    // Active uniforms, attributes and blocks, updated every time the program is linked
+    reflection: ShaderReflection,
+    // Locations of uniforms that have been set, cleared whenever the program is relinked
+    uniform_locations: HashMap<String, UniformLocationId, BuildHasherDefault<XxHash32>>,
+    // Last value set on each uniform, restored on reload
+    uniform_values: HashMap<String, UniformValue, BuildHasherDefault<XxHash32>>
 }
 
 impl ShaderProgram
 {
-    fn new_program(context: &Context) -> Result<WebGlProgram, GfxError>
+    fn new_program(context: &Context) -> Result<ProgramId, GfxError>
     {
         context.create_program().ok_or_else(|| GfxError::ShaderProgramCreationError(gl_get_errors(&context).to_string()))
     }
 
+    #[allow(dead_code)]
     pub fn new(context: &Context, vert_src: Option<String>, frag_src: Option<String>) -> Result<ShaderProgram, GfxError>
+    {
+        ShaderProgram::new_internal(context, vert_src, frag_src, None, None)
+    }
+
+    /// Create a shader program from `ShaderPreprocessor` output
+    /// Compilation errors will refer to the file and line that the error came from
+    pub fn from_preprocessed(context: &Context, vert: Option<PreprocessedShader>, frag: Option<PreprocessedShader>) -> Result<ShaderProgram, GfxError>
+    {
+        let (vert_src, vert_source_map) = vert.map_or((None, None), |vert| (Some(vert.source), Some(vert.source_map)));
+        let (frag_src, frag_source_map) = frag.map_or((None, None), |frag| (Some(frag.source), Some(frag.source_map)));
+        ShaderProgram::new_internal(context, vert_src, frag_src, vert_source_map, frag_source_map)
+    }
+
+    fn new_internal(context: &Context, vert_src: Option<String>, frag_src: Option<String>,
+                    vert_source_map: Option<SourceMap>, frag_source_map: Option<SourceMap>) -> Result<ShaderProgram, GfxError>
     {
         if vert_src.is_none() && frag_src.is_none()
         {
             return Err(GfxError::NoShaderSource("At least one shader source must be present".to_string()))
         }
-        let program = ShaderProgram
+        let mut program = ShaderProgram
         {
             internal: ShaderProgram::new_program(&context)?,
             context: context.clone(),
             vert_src: vert_src,
             frag_src: frag_src,
+            vert_source_map,
+            frag_source_map,
             block_bindings: vec![],
-            uniforms_i32: Default::default(),
+            reflection: ShaderReflection::default(),
+            uniform_locations: Default::default(),
+            uniform_values: Default::default(),
         };
         program.compile()?;
         Ok(program)
     }
 
-    /// Compiles the shader fragments and attaches them to the shader program
-    fn compile(&self) -> Result<(), GfxError>
+    /// Replace the program with one built from new sources, keeping its block bindings and uniform values
+    /// The program is bound afterwards
+    ///
+    /// If the new sources fail to compile or link, the current program is left untouched
+    /// Returns the names of blocks and uniforms that couldn't be carried over, i.e. because
+    /// they were removed or changed type in the new sources
+    #[allow(dead_code)]
+    pub fn recompile(&mut self, vert: Option<PreprocessedShader>, frag: Option<PreprocessedShader>) -> Result<Vec<String>, GfxError>
     {
+        let mut program = ShaderProgram::from_preprocessed(&self.context, vert, frag)?;
+        program.bind_internal();
+
+        let mut dropped = vec![];
+        for (block_binding, block_name) in self.block_bindings.iter().enumerate()
+        {
+            if let Some(block_name) = block_name
+            {
+                if program.add_uniform_block_binding(block_name.as_str(), block_binding as u32).is_err()
+                {
+                    dropped.push(block_name.clone());
+                }
+            }
+        }
+        for (name, value) in self.uniform_values.drain()
+        {
+            if program.set_uniform_value(&name, value).is_err()
+            {
+                dropped.push(name);
+            }
+        }
 
+        // The old program is deleted when `program` is dropped
+        std::mem::swap(self, &mut program);
+        Ok(dropped)
+    }
+
+    /// Compiles the shader fragments and attaches them to the shader program
+    fn compile(&mut self) -> Result<(), GfxError>
+    {
         let vert = if let Some(src) = &self.vert_src
         {
-            Some(self.compile_shader(src.as_str(), ShaderType::VertexShader)?)
+            Some(self.compile_shader(src.as_str(), self.vert_source_map.as_ref(), ShaderType::VertexShader)?)
         } else { None };
 
         let frag = if let Some(src) = &self.frag_src
         {
-            Some(self.compile_shader(src.as_str(), ShaderType::FragmentShader)?)
+            Some(self.compile_shader(src.as_str(), self.frag_source_map.as_ref(), ShaderType::FragmentShader)?)
         } else { None };
 
-        self.context.link_program(&self.internal);
+        self.context.link_program(self.internal);
 
-        if self.context.get_program_parameter(&self.internal, Context::LINK_STATUS).as_bool().unwrap_or(false)
+        if self.context.program_link_status(self.internal)
         {
-            self.context.delete_shader(vert.as_ref());
-            self.context.delete_shader(frag.as_ref());
+            for shader in vert.into_iter().chain(frag)
+            {
+                self.context.delete_shader(shader);
+            }
+            self.reflection = ShaderReflection::reflect(&self.context, self.internal);
+            self.uniform_locations.clear();
             Ok(())
         }
         else
         {
-            let info_log = self.context.get_program_info_log(&self.internal)
+            let info_log = self.context.program_info_log(self.internal)
                 .unwrap_or_else(|| format!("Error getting shader program info logs after linking. GlErrors: {}", gl_get_errors(&self.context)).to_string());
             Err(GfxError::ShaderProgramLinkingError(info_log))
         }
     }
 
     /// Compiles a shader fragment
-    fn compile_shader(&self, src: &str, shader_type: ShaderType) -> Result<WebGlShader, GfxError>
+    /// If `source_map` is given, it is used to rewrite error locations in the info log
+    fn compile_shader(&self, src: &str, source_map: Option<&SourceMap>, shader_type: ShaderType) -> Result<ShaderId, GfxError>
     {
         let shader = self.context.create_shader(shader_type.into())
             .ok_or_else(|| GfxError::ShaderCreationError(shader_type, gl_get_errors(&self.context).to_string()))?;
-        self.context.shader_source(&shader, &src);
-        self.context.compile_shader(&shader);
-        self.context.attach_shader(&self.internal, &shader);
+        self.context.shader_source(shader, src);
+        self.context.compile_shader(shader);
+        self.context.attach_shader(self.internal, shader);
 
-        if self.context.get_shader_parameter(&shader, Context::COMPILE_STATUS).as_bool().unwrap_or(false)
+        if self.context.shader_compile_status(shader)
         {
             Ok(shader)
         }
         else
         {
-            let info_log = self.context.get_shader_info_log(&shader)
+            let info_log = self.context.shader_info_log(shader)
+                .map(|info_log| match source_map
+                {
+                    Some(source_map) => source_map.rewrite_log(&info_log),
+                    None => info_log,
+                })
                 .unwrap_or_else(|| format!("Error getting shader compilation info log. GlErrors: {}", gl_get_errors(&self.context)).to_string());
             Err(GfxError::ShaderCompilationError(shader_type, info_log))
         }
     }
 
     /// Binds the uniform block `block_name` to the given `block_binding`
+    #[track_caller]
     pub fn add_uniform_block_binding(&mut self, block_name: &str, block_binding: u32) -> Result<(), GfxError>
     {
-        let index = self.context.get_uniform_block_index(&self.internal, block_name);
+        let index = self.context.uniform_block_index(self.internal, block_name);
         if index == Context::INVALID_INDEX
         {
             Err(GfxError::InvalidUniformBlockName(String::from(block_name)))
         }
         else
         {
-            self.context.uniform_block_binding(&self.internal, index, block_binding);
+            self.context.uniform_block_binding(self.internal, index, block_binding);
 
             if self.block_bindings.len() <= block_binding as usize
             {
@@ -167,25 +245,75 @@ impl ShaderProgram
         }
     }
 
-    // TODO: Add more set_uniform functions as necessary
+    /// Active uniforms, attributes and uniform blocks of the program as of the last time it was linked
+    pub fn reflection(&self) -> &ShaderReflection
+    {
+        &self.reflection
+    }
+
+    /// Get the location of the uniform `name`, caching it for later calls
+    #[track_caller]
+    fn uniform_location(&mut self, name: &str) -> Result<UniformLocationId, GfxError>
+    {
+        if let Some(location) = self.uniform_locations.get(name)
+        {
+            return Ok(*location);
+        }
+        let location = self.context.uniform_location(self.internal, name).ok_or_else(|| GfxError::InvalidUniformName(name.to_string()))?;
+        self.uniform_locations.insert(name.to_string(), location);
+        Ok(location)
+    }
 
     /// Set the uniform with `name` to `value`
-    /// If `name` is a scalar, then give a one element slice. i.e. `&[5]`
-    pub fn set_uniform_i32(&mut self, name: &str, value: &[i32]) -> Result<(), GfxError>
+    /// The shader program must be bound
+    ///
+    /// Arrays can be set all at once by giving a slice of values. i.e. `&[vec3(...), vec3(...)][..]`
+    /// Fails if `value` doesn't match the type of the uniform in the shader, or is an empty array
+    #[track_caller]
+    pub fn set_uniform<U: Uniform + ?Sized>(&mut self, name: &str, value: &U) -> Result<(), GfxError>
+    {
+        let value = value.uniform_value().ok_or_else(|| GfxError::UniformTypeMismatch(format!("{} can't be set to an empty array", name)))?;
+        self.set_uniform_value(name, value)
+    }
+
+    #[track_caller]
+    fn set_uniform_value(&mut self, name: &str, value: UniformValue) -> Result<(), GfxError>
     {
-        let location = self.context.get_uniform_location(&self.internal, name).ok_or_else(|| GfxError::InvalidUniformName(name.to_string()))?;
-        self.context.uniform1iv_with_i32_array(Some(&location), value);
-        self.uniforms_i32.insert(name.to_string(), value.to_vec());
+        let location = self.uniform_location(name)?;
+
+        // Arrays are listed under the name of their first element
+        let active = self.reflection.uniform(name).or_else(|| self.reflection.uniform(&format!("{}[0]", name)));
+        if let Some(active) = active
+        {
+            if !value.matches_type(active.data_type) || value.len() > active.size as usize
+            {
+                return Err(GfxError::UniformTypeMismatch(format!("{} of type {:#x}[{}] can't be set to {:?}", name, active.data_type, active.size, value)));
+            }
+        }
+
+        self.context.uniform(location, &value);
+        self.uniform_values.insert(name.to_string(), value);
         Ok(())
     }
 
+    /// Set the uniform with `name` to `value`
+    /// If `name` is a scalar, then give a one element slice. i.e. `&[5]`
+    #[allow(dead_code)]
+    #[track_caller]
+    pub fn set_uniform_i32(&mut self, name: &str, value: &[i32]) -> Result<(), GfxError>
+    {
+        self.set_uniform(name, value)
+    }
 }
 
 impl_globject!(ShaderProgram);
 
+// Shader sources are needed to recompile after a context loss, and the driver's memory use isn't visible
+impl MemoryUsage for ShaderProgram {}
+
 impl Bindable for ShaderProgram
 {
-    fn bind_internal(&self) { self.context.use_program(Some(&self.internal)); }
+    fn bind_internal(&self) { self.context.use_program(Some(self.internal)); }
     fn unbind_internal(&self) { self.context.use_program(None); }
 }
 
@@ -206,21 +334,76 @@ impl Reloadable for ShaderProgram
                 self.add_uniform_block_binding(block_name.as_str(), block_binding as u32)?;
             }
         }
-        // Restore i32 uniforms
-        for (name, value) in self.uniforms_i32.to_owned()
+        // Restore uniform values
+        for (name, value) in self.uniform_values.to_owned()
         {
-            self.set_uniform_i32(&name, &value)?;
+            self.set_uniform_value(&name, value)?;
         }
 
         Ok(())
     }
 }
 
+impl Inspect for ShaderProgram
+{
+    fn gl_id(&self) -> u32
+    {
+        self.internal.0
+    }
+
+    fn label_in_context(&self, label: &str)
+    {
+        self.context.label_object(ObjectId::Program(self.internal), label);
+    }
+
+    fn details(&self) -> ObjectDetails
+    {
+        let reflection = &self.reflection;
+        let block_name = |index: u32| reflection.blocks().iter().find(|block| block.index == index).map(|block| block.name.clone());
+        let uniforms = reflection.uniforms().iter().map(|uniform| UniformInfo
+            {
+                name: uniform.name.clone(),
+                type_name: Context::constant_name(uniform.data_type),
+                size: uniform.size,
+                block: uniform.block_index.and_then(block_name),
+                block_offset: uniform.block_offset,
+                // Arrays are set under the name of the array, but listed under the name of their first element
+                value: self.uniform_values.get(&uniform.name)
+                    .or_else(|| self.uniform_values.get(uniform.name.trim_end_matches("[0]")))
+                    .map(|value| format!("{:?}", value)),
+            }).collect();
+        let attributes = reflection.attribs().iter().map(|attrib| AttribInfo
+            {
+                name: attrib.name.clone(),
+                type_name: Context::constant_name(attrib.data_type),
+                size: attrib.size,
+                location: attrib.location,
+            }).collect();
+        let uniform_blocks = reflection.blocks().iter().map(|block| UniformBlockInfo
+            {
+                name: block.name.clone(),
+                index: block.index,
+                data_size: block.data_size,
+                binding: self.block_bindings.iter().position(|name| name.as_deref() == Some(block.name.as_str())).map(|binding| binding as u32),
+                uniforms: block.uniforms.iter().map(|&i| reflection.uniforms()[i].name.clone()).collect(),
+            }).collect();
+
+        ObjectDetails::ShaderProgram
+        {
+            vertex_source: self.vert_src.clone(),
+            fragment_source: self.frag_src.clone(),
+            uniforms,
+            attributes,
+            uniform_blocks,
+        }
+    }
+}
+
 impl Drop for ShaderProgram
 {
     fn drop(&mut self)
     {
-        self.context.delete_program(Some(&self.internal));
+        self.context.delete_program(self.internal);
     }
 }
 
@@ -231,12 +414,15 @@ mod tests
 
     use crate::gfx::
     {
+        backend::recording::{ProgramInfo, RecordingBackend},
         gl_object::
         {
             shader_program::ShaderProgram,
         },
+        preprocessor::ShaderPreprocessor,
     };
     use crate::gfx::gl_object::traits::Bindable;
+    use crate::resource::manager::ResourceManager;
 
     // Embed shaders into test executable so that we can test ShaderProgram alone instead of
     //      going through ResourceLoader. Since this is in a cfg(test) module, it won't be
@@ -250,14 +436,21 @@ mod tests
     }
     pub const BASIC_VERT: &'static str = shader_source!("/resources/shaders/basic_vert.glsl");
     pub const BASIC_FRAG: &'static str = shader_source!("/resources/shaders/basic_frag.glsl");
+    pub const VERT_DATA: &'static str = shader_source!("/resources/shaders/vert_data.glsl");
 
     fn get_shader_program() -> (Context, ShaderProgram)
     {
         let context = get_context();
-        let shader_program = ShaderProgram::new(
+        let mut resource_manager = ResourceManager::new();
+        resource_manager.insert_with_name("basic_vert.glsl".to_string(), BASIC_VERT.as_bytes().to_vec());
+        resource_manager.insert_with_name("basic_frag.glsl".to_string(), BASIC_FRAG.as_bytes().to_vec());
+        resource_manager.insert_with_name("vert_data.glsl".to_string(), VERT_DATA.as_bytes().to_vec());
+        let preprocessor = ShaderPreprocessor::new(&resource_manager);
+
+        let shader_program = ShaderProgram::from_preprocessed(
             &context,
-            Some(BASIC_VERT.to_string()),
-            Some(BASIC_FRAG.to_string())
+            Some(preprocessor.process("basic_vert.glsl").expect("preprocessed vertex shader")),
+            Some(preprocessor.process("basic_frag.glsl").expect("preprocessed fragment shader"))
         ).expect("shader program");
         shader_program.bind_internal();
         (context, shader_program)
@@ -283,6 +476,17 @@ mod tests
         //assert_eq!(shader_program.block_bindings[0], Some(String::from("fake_block")))
     }
 
+    #[test]
+    fn test_set_empty_uniform_array()
+    {
+        let context = Context::new(RecordingBackend::new());
+        context.backend::<RecordingBackend>().expect("recording backend").set_program_info(ProgramInfo::default().uniform("weights[0]", Context::FLOAT));
+        let mut shader_program = ShaderProgram::new(&context, Some("vert".to_string()), Some("frag".to_string())).expect("shader program");
+
+        assert_eq!(Err(GfxError::UniformTypeMismatch("weights can't be set to an empty array".to_string())), shader_program.set_uniform("weights", &[0.0f32; 0][..]));
+        assert_eq!(Ok(()), shader_program.set_uniform("weights", &[1.0f32][..]));
+    }
+
     #[wasm_bindgen_test]
     fn test_set_uniform()
     {
@@ -0,0 +1,355 @@
+//! std140 layout of uniform block data
+//!
+//! GLSL's std140 layout pads members in ways that a `#[repr(C)]` Rust struct doesn't, such as
+//! a `vec3` taking up 16 bytes within an array, so uniform block data is written through
+//! the `Std140` trait instead of being copied directly
+
+use cgmath::{Matrix2, Matrix3, Matrix4, Point3, Vector2, Vector3, Vector4};
+
+/// Base alignment of arrays and structs, which are rounded up to the alignment of a vec4
+pub const VEC4_ALIGNMENT: usize = 16;
+
+/// Round `offset` up to the next multiple of `alignment`
+pub const fn align_to(offset: usize, alignment: usize) -> usize
+{
+    offset.div_ceil(alignment) * alignment
+}
+
+/// Base alignment of a struct made up of members with the given `(size, alignment)`s
+pub const fn struct_alignment(members: &[(usize, usize)]) -> usize
+{
+    let mut alignment = VEC4_ALIGNMENT;
+    let mut i = 0;
+    while i < members.len()
+    {
+        if members[i].1 > alignment
+        {
+            alignment = members[i].1;
+        }
+        i += 1;
+    }
+    alignment
+}
+
+/// Offset of member `index` within a struct made up of members with the given `(size, alignment)`s
+pub const fn struct_member_offset(members: &[(usize, usize)], index: usize) -> usize
+{
+    let mut offset = 0;
+    let mut i = 0;
+    while i < index
+    {
+        offset = align_to(offset, members[i].1) + members[i].0;
+        i += 1;
+    }
+    align_to(offset, members[index].1)
+}
+
+/// Size of a struct made up of members with the given `(size, alignment)`s, including the
+/// padding at the end that rounds it up to its alignment
+pub const fn struct_size(members: &[(usize, usize)]) -> usize
+{
+    let mut offset = 0;
+    let mut i = 0;
+    while i < members.len()
+    {
+        offset = align_to(offset, members[i].1) + members[i].0;
+        i += 1;
+    }
+    align_to(offset, struct_alignment(members))
+}
+
+/// A type that can be written into a uniform block with the std140 layout
+///
+/// Structs can implement this with the `std140_struct!` macro
+pub trait Std140
+{
+    /// Base alignment in bytes
+    const ALIGNMENT: usize;
+    /// Size in bytes, including any padding at the end of structs and arrays
+    const SIZE: usize;
+
+    /// Write this value's std140 representation to the start of `out`
+    /// Will panic if `out` is shorter than `SIZE`
+    fn write_std140(&self, out: &mut [u8]);
+
+    /// Get this value's std140 representation
+    fn to_std140_bytes(&self) -> Vec<u8>
+    {
+        let mut out = vec![0u8; Self::SIZE];
+        self.write_std140(&mut out);
+        out
+    }
+}
+
+macro_rules! impl_std140_scalar
+{
+    ($($type:ty),*) =>
+    {
+        $(
+        impl Std140 for $type
+        {
+            const ALIGNMENT: usize = 4;
+            const SIZE: usize = 4;
+
+            fn write_std140(&self, out: &mut [u8])
+            {
+                out[..4].copy_from_slice(&self.to_ne_bytes());
+            }
+        }
+        )*
+    };
+}
+impl_std140_scalar!(f32, i32, u32);
+
+/// GLSL bools take up 4 bytes in a uniform block
+impl Std140 for bool
+{
+    const ALIGNMENT: usize = 4;
+    const SIZE: usize = 4;
+
+    fn write_std140(&self, out: &mut [u8])
+    {
+        (*self as u32).write_std140(out);
+    }
+}
+
+/// Empty block, for uniform buffers that only have data for one shader stage
+impl Std140 for ()
+{
+    const ALIGNMENT: usize = 1;
+    const SIZE: usize = 0;
+
+    fn write_std140(&self, _out: &mut [u8]) {}
+}
+
+macro_rules! impl_std140_vector
+{
+    ($vector:ident, $n:expr, $alignment:expr) =>
+    {
+        impl<S: Std140 + Copy> Std140 for $vector<S>
+        {
+            const ALIGNMENT: usize = $alignment;
+            const SIZE: usize = $n * 4;
+
+            fn write_std140(&self, out: &mut [u8])
+            {
+                let components: &[S; $n] = self.as_ref();
+                for (i, component) in components.iter().enumerate()
+                {
+                    component.write_std140(&mut out[i * 4..]);
+                }
+            }
+        }
+    };
+}
+impl_std140_vector!(Vector2, 2, 8);
+impl_std140_vector!(Vector3, 3, 16);
+impl_std140_vector!(Vector4, 4, 16);
+impl_std140_vector!(Point3, 3, 16);
+
+/// Column-major matrices are laid out as arrays of column vectors
+macro_rules! impl_std140_matrix
+{
+    ($matrix:ident, $n:expr) =>
+    {
+        impl Std140 for $matrix<f32>
+        {
+            const ALIGNMENT: usize = VEC4_ALIGNMENT;
+            const SIZE: usize = $n * VEC4_ALIGNMENT;
+
+            fn write_std140(&self, out: &mut [u8])
+            {
+                let columns: &[[f32; $n]; $n] = self.as_ref();
+                for (i, column) in columns.iter().enumerate()
+                {
+                    for (j, component) in column.iter().enumerate()
+                    {
+                        component.write_std140(&mut out[i * VEC4_ALIGNMENT + j * 4..]);
+                    }
+                }
+            }
+        }
+    };
+}
+impl_std140_matrix!(Matrix2, 2);
+impl_std140_matrix!(Matrix3, 3);
+impl_std140_matrix!(Matrix4, 4);
+
+/// Array elements are each rounded up to the alignment of a vec4
+impl<T: Std140, const N: usize> Std140 for [T; N]
+{
+    const ALIGNMENT: usize = align_to(T::ALIGNMENT, VEC4_ALIGNMENT);
+    const SIZE: usize = align_to(T::SIZE, Self::ALIGNMENT) * N;
+
+    fn write_std140(&self, out: &mut [u8])
+    {
+        let stride = align_to(T::SIZE, Self::ALIGNMENT);
+        for (i, element) in self.iter().enumerate()
+        {
+            element.write_std140(&mut out[i * stride..]);
+        }
+    }
+}
+
+/// Define a struct and implement `Std140` for it, so that it can be written into a uniform block
+///
+/// All fields must implement `Std140`. Also adds `std140_offsets()`, which lists the name and
+/// offset of each field in declaration order
+///
+/// ```ignore
+/// std140_struct!
+/// {
+///     pub struct Light
+///     {
+///         pub position: Vector3<f32>,
+///         pub intensity: f32,
+///     }
+/// }
+/// ```
+macro_rules! std140_struct
+{
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident
+        {
+            $($(#[$field_attr:meta])* $field_vis:vis $field:ident: $type:ty),* $(,)?
+        }
+    ) =>
+    {
+        $(#[$attr])*
+        $vis struct $name
+        {
+            $($(#[$field_attr])* $field_vis $field: $type,)*
+        }
+
+        impl $name
+        {
+            /// `(size, alignment)` of each field
+            const STD140_MEMBERS: &[(usize, usize)] =
+                &[$((<$type as crate::gfx::gl_object::std140::Std140>::SIZE, <$type as crate::gfx::gl_object::std140::Std140>::ALIGNMENT)),*];
+
+            /// Name and std140 offset of each field, in declaration order
+            #[allow(dead_code)]
+            pub fn std140_offsets() -> Vec<(&'static str, usize)>
+            {
+                let names = [$(stringify!($field)),*];
+                names.iter().enumerate()
+                    .map(|(i, name)| (*name, crate::gfx::gl_object::std140::struct_member_offset($name::STD140_MEMBERS, i)))
+                    .collect()
+            }
+        }
+
+        impl crate::gfx::gl_object::std140::Std140 for $name
+        {
+            const ALIGNMENT: usize = crate::gfx::gl_object::std140::struct_alignment($name::STD140_MEMBERS);
+            const SIZE: usize = crate::gfx::gl_object::std140::struct_size($name::STD140_MEMBERS);
+
+            #[allow(unused_assignments)]
+            fn write_std140(&self, out: &mut [u8])
+            {
+                let mut offset = 0;
+                $(
+                offset = crate::gfx::gl_object::std140::align_to(offset, <$type as crate::gfx::gl_object::std140::Std140>::ALIGNMENT);
+                crate::gfx::gl_object::std140::Std140::write_std140(&self.$field, &mut out[offset..]);
+                offset += <$type as crate::gfx::gl_object::std140::Std140>::SIZE;
+                )*
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests
+{
+    use cgmath::{Matrix3, Matrix4, SquareMatrix, Vector2, Vector3, vec2, vec3};
+    use crate::gfx::gl_object::std140::*;
+
+    std140_struct!
+    {
+        struct Light
+        {
+            position: Vector3<f32>,
+            intensity: f32,
+            color: Vector3<f32>,
+        }
+    }
+
+    std140_struct!
+    {
+        struct Lighting
+        {
+            ambient: f32,
+            lights: [Light; 2],
+            enabled: [bool; 3],
+            uv_scale: Vector2<f32>,
+            normal_mat: Matrix3<f32>,
+            count: i32,
+        }
+    }
+
+    fn f32_at(bytes: &[u8], offset: usize) -> f32
+    {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(&bytes[offset..offset + 4]);
+        f32::from_ne_bytes(buf)
+    }
+
+    #[test]
+    fn test_primitive_layouts()
+    {
+        assert_eq!((4, 4), (f32::SIZE, f32::ALIGNMENT));
+        assert_eq!((8, 8), (Vector2::<f32>::SIZE, Vector2::<f32>::ALIGNMENT));
+        assert_eq!((12, 16), (Vector3::<f32>::SIZE, Vector3::<f32>::ALIGNMENT));
+        assert_eq!((48, 16), (Matrix3::<f32>::SIZE, Matrix3::<f32>::ALIGNMENT));
+        assert_eq!((64, 16), (Matrix4::<f32>::SIZE, Matrix4::<f32>::ALIGNMENT));
+        // Every array element is padded out to 16 bytes
+        assert_eq!((48, 16), (<[f32; 3]>::SIZE, <[f32; 3]>::ALIGNMENT));
+        assert_eq!((32, 16), (<[Vector3<f32>; 2]>::SIZE, <[Vector3<f32>; 2]>::ALIGNMENT));
+    }
+
+    #[test]
+    fn test_struct_layout()
+    {
+        // The float packs into the end of the first vec3
+        assert_eq!(vec![("position", 0), ("intensity", 12), ("color", 16)], Light::std140_offsets());
+        assert_eq!((32, 16), (Light::SIZE, Light::ALIGNMENT));
+
+        assert_eq!(vec![("ambient", 0), ("lights", 16), ("enabled", 80), ("uv_scale", 128), ("normal_mat", 144), ("count", 192)],
+                   Lighting::std140_offsets());
+        assert_eq!(208, Lighting::SIZE);
+    }
+
+    #[test]
+    fn test_write()
+    {
+        let light = Light { position: vec3(1.0, 2.0, 3.0), intensity: 4.0, color: vec3(5.0, 6.0, 7.0) };
+        let lighting = Lighting
+        {
+            ambient: 0.5,
+            lights: [light, Light { position: vec3(8.0, 9.0, 10.0), intensity: 11.0, color: vec3(12.0, 13.0, 14.0) }],
+            enabled: [true, false, true],
+            uv_scale: vec2(2.0, 3.0),
+            normal_mat: Matrix3::identity(),
+            count: 2,
+        };
+        let bytes = lighting.to_std140_bytes();
+        assert_eq!(Lighting::SIZE, bytes.len());
+
+        assert_eq!(0.5, f32_at(&bytes, 0));
+        assert_eq!(1.0, f32_at(&bytes, 16));
+        assert_eq!(4.0, f32_at(&bytes, 28));
+        assert_eq!(7.0, f32_at(&bytes, 40));
+        assert_eq!(8.0, f32_at(&bytes, 48));
+        assert_eq!(14.0, f32_at(&bytes, 72));
+        assert_eq!(&1u32.to_ne_bytes(), &bytes[80..84]);
+        assert_eq!(&0u32.to_ne_bytes(), &bytes[96..100]);
+        assert_eq!(&1u32.to_ne_bytes(), &bytes[112..116]);
+        assert_eq!(3.0, f32_at(&bytes, 132));
+        // Matrix columns are padded to vec4s
+        assert_eq!(1.0, f32_at(&bytes, 144));
+        assert_eq!(0.0, f32_at(&bytes, 156));
+        assert_eq!(1.0, f32_at(&bytes, 144 + 16 + 4));
+        assert_eq!(1.0, f32_at(&bytes, 144 + 32 + 8));
+        assert_eq!(&2i32.to_ne_bytes(), &bytes[192..196]);
+    }
+}
@@ -1,13 +1,14 @@
-use web_sys::WebGlTexture;
 use crate::gfx::
 {
     Context,
     GfxError,
     gl_get_errors,
+    backend::{ObjectId, TextureId},
     gl_object::
     {
         manager::{GlObjectManager},
-        traits::{Bindable, Reloadable}
+        inspector::ObjectDetails,
+        traits::{Bindable, Reloadable, MemoryUsage, Inspect}
     },
 };
 
@@ -28,16 +29,36 @@ pub struct Texture2dParams
     // Image data
     pub data: Vec<u8>,
 }
+
+/// Bytes used by each texel of a texture with `internal_format`
+fn texel_bytes(internal_format: u32) -> usize
+{
+    match internal_format
+    {
+        Context::R8 | Context::ALPHA | Context::LUMINANCE => 1,
+        Context::RG8 | Context::LUMINANCE_ALPHA | Context::R16F | Context::RGB565 | Context::RGBA4 | Context::RGB5_A1 => 2,
+        Context::RGB8 | Context::SRGB8 | Context::RGB => 3,
+        Context::RGBA16F => 8,
+        Context::RGB32F => 12,
+        Context::RGBA32F => 16,
+        // RGBA8 and other 32-bit formats
+        _ => 4,
+    }
+}
+
 pub struct Texture2d
 {
-    internal: WebGlTexture,
+    internal: TextureId,
     context: Context,
-    params: Texture2dParams
+    params: Texture2dParams,
+    // Whether `params.data` is kept after the texture is set up, so that it can be reloaded
+    keep_shadow: bool,
+    needs_data: bool,
 }
 
 impl Texture2d
 {
-    fn new_texture(context: &Context) -> Result<WebGlTexture, GfxError>
+    fn new_texture(context: &Context) -> Result<TextureId, GfxError>
     {
         context.create_texture().ok_or_else(|| GfxError::TextureCreationError(gl_get_errors(&context).to_string()))
     }
@@ -48,42 +69,61 @@ impl Texture2d
         {
             internal: Texture2d::new_texture(&context)?,
             context: context.clone(),
-            params
+            params,
+            keep_shadow: true,
+            needs_data: false,
         };
 
         Ok(texture)
     }
 
+    #[track_caller]
     pub fn setup_texture(&self) -> Result<(), GfxError>
     {
-        self.context.bind_texture(self.params.target, Some(&self.internal));
+        self.context.bind_texture(self.params.target, Some(self.internal));
 
         self.context.tex_parameteri(self.params.target, Context::TEXTURE_WRAP_S, self.params.wrap_type as i32);
         self.context.tex_parameteri(self.params.target, Context::TEXTURE_WRAP_T, self.params.wrap_type as i32);
         self.context.tex_parameteri(self.params.target, Context::TEXTURE_MIN_FILTER, self.params.filter_type as i32);
         self.context.tex_parameteri(self.params.target, Context::TEXTURE_MAG_FILTER, self.params.filter_type as i32);
 
-        self.context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+        self.context.tex_image_2d(
             self.params.target,
             0,
             self.params.internal_format as i32,
             self.params.size.0,
             self.params.size.1,
-            0,
             self.params.format,
-            Context::UNSIGNED_BYTE,
-            Some(self.params.data.as_slice())
+            // Without data, the texture's storage is still allocated so that it can be supplied later
+            if self.params.data.is_empty() { None } else { Some(self.params.data.as_slice()) }
         ).or_else(|_| Err(GfxError::TextureCreationError(gl_get_errors(&self.context).to_string())))?;
 
         Ok(())
     }
+
+    /// Replace the texture's image data with `data`, which has the same size and format
+    /// This is used to supply data that was lost in a reload because the shadow copy was dropped
+    /// The texture must be bound
+    #[allow(dead_code)]
+    #[track_caller]
+    pub fn set_data(&mut self, data: Vec<u8>) -> Result<(), GfxError>
+    {
+        self.params.data = data;
+        self.setup_texture()?;
+        self.needs_data = false;
+        if !self.keep_shadow
+        {
+            self.params.data = vec![];
+        }
+        Ok(())
+    }
 }
 
 impl_globject!(Texture2d);
 
 impl Bindable for Texture2d
 {
-    fn bind_internal(&self) { self.context.bind_texture(self.params.target, Some(&self.internal)); }
+    fn bind_internal(&self) { self.context.bind_texture(self.params.target, Some(self.internal)); }
 
     fn unbind_internal(&self) { self.context.bind_texture(self.params.target, None); }
 }
@@ -97,14 +137,85 @@ impl Reloadable for Texture2d
         self.bind_internal();
         self.setup_texture()?;
         self.unbind_internal();
+        self.needs_data = !self.keep_shadow;
         Ok(())
     }
 }
 
+impl MemoryUsage for Texture2d
+{
+    fn gpu_bytes(&self) -> usize
+    {
+        self.params.size.0 as usize * self.params.size.1 as usize * texel_bytes(self.params.internal_format)
+    }
+
+    fn shadow_bytes(&self) -> usize
+    {
+        self.params.data.capacity()
+    }
+
+    fn drop_shadow_copy(&mut self)
+    {
+        self.keep_shadow = false;
+        self.params.data = vec![];
+    }
+
+    fn needs_data(&self) -> bool
+    {
+        self.needs_data
+    }
+}
+
+impl Inspect for Texture2d
+{
+    fn gl_id(&self) -> u32
+    {
+        self.internal.0
+    }
+
+    fn label_in_context(&self, label: &str)
+    {
+        self.context.label_object(ObjectId::Texture(self.internal), label);
+    }
+
+    fn details(&self) -> ObjectDetails
+    {
+        ObjectDetails::Texture
+        {
+            target: Context::constant_name(self.params.target),
+            width: self.params.size.0,
+            height: self.params.size.1,
+            internal_format: Context::constant_name(self.params.internal_format),
+            format: Context::constant_name(self.params.format),
+            wrap: Context::constant_name(self.params.wrap_type),
+            filter: Context::constant_name(self.params.filter_type),
+        }
+    }
+}
+
 impl Drop for Texture2d
 {
     fn drop(&mut self)
     {
-        self.context.delete_texture(Some(&self.internal));
+        self.context.delete_texture(self.internal);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::gfx::
+    {
+        Context,
+        gl_object::texture::*,
+    };
+
+    #[test]
+    fn test_texel_bytes()
+    {
+        assert_eq!(3, texel_bytes(Context::RGB8));
+        assert_eq!(4, texel_bytes(Context::RGBA8));
+        assert_eq!(1, texel_bytes(Context::R8));
+        assert_eq!(16, texel_bytes(Context::RGBA32F));
+    }
+}
@@ -1,10 +1,16 @@
 /// GlObject and its associated traits
 
-use crate::gfx::gl_object::manager::{GlObjectHandle, GlObjectManager};
+use crate::gfx::gl_object::
+{
+    inspector::ObjectDetails,
+    manager::{Handle, GlObjectManager},
+};
 
 pub trait Bindable
 {
+    #[track_caller]
     fn bind_internal(&self);
+    #[track_caller]
     fn unbind_internal(&self);
 }
 
@@ -15,10 +21,44 @@ pub trait Reloadable
     fn reload(&mut self, context: &crate::Context, manager: &GlObjectManager) -> Result<(), crate::gfx::GfxError>;
 }
 
-pub trait GlObject: Bindable + Reloadable + Drop
+/// Memory used by a webgl object, both on the GPU and for the CPU-side shadow copy of its data
+/// that `reload` uses to restore the GPU data after a context loss
+pub trait MemoryUsage
+{
+    /// Bytes of GPU memory used by the object's data
+    fn gpu_bytes(&self) -> usize { 0 }
+
+    /// Bytes of CPU memory used by the shadow copy of the object's data
+    fn shadow_bytes(&self) -> usize { 0 }
+
+    /// Free the shadow copy and stop keeping one when new data is uploaded
+    /// After a reload, the object's data is lost and has to be supplied again
+    fn drop_shadow_copy(&mut self) {}
+
+    /// Whether the object lost its data in a reload because it had no shadow copy,
+    /// and has to be supplied with it again
+    fn needs_data(&self) -> bool { false }
+}
+
+/// Description of a webgl object for the manager's inspector
+pub trait Inspect
+{
+    /// Id of the object's internal webgl object
+    fn gl_id(&self) -> u32;
+
+    /// Name the internal webgl object `label` within its context, which debug builds report GL errors with
+    fn label_in_context(&self, label: &str);
+
+    /// State specific to the object's type, such as its format or shader sources
+    fn details(&self) -> ObjectDetails;
+}
+
+pub trait GlObject: Bindable + Reloadable + MemoryUsage + Inspect + Drop
 {
-    fn bind(manager: &GlObjectManager, handle: GlObjectHandle) where Self: Sized;
-    fn unbind(manager: &GlObjectManager, handle: GlObjectHandle) where Self: Sized;
+    #[track_caller]
+    fn bind(manager: &GlObjectManager, handle: Handle<Self>) where Self: Sized;
+    #[track_caller]
+    fn unbind(manager: &GlObjectManager, handle: Handle<Self>) where Self: Sized;
 }
 
 macro_rules! impl_globject
@@ -28,12 +68,12 @@ macro_rules! impl_globject
     {
         impl crate::gfx::gl_object::traits::GlObject for $implementor
         {
-            fn bind(manager: &crate::gfx::gl_object::manager::GlObjectManager, handle: crate::gfx::gl_object::manager::GlObjectHandle) where Self: Sized
+            fn bind(manager: &crate::gfx::gl_object::manager::GlObjectManager, handle: crate::gfx::gl_object::manager::Handle<Self>) where Self: Sized
             {
                 manager.[<bind_ $implementor:snake>](handle, true).expect(concat!(stringify!($implementor), " bound"));
             }
 
-            fn unbind(manager: &crate::gfx::gl_object::manager::GlObjectManager, handle: crate::gfx::gl_object::manager::GlObjectHandle) where Self: Sized
+            fn unbind(manager: &crate::gfx::gl_object::manager::GlObjectManager, handle: crate::gfx::gl_object::manager::Handle<Self>) where Self: Sized
             {
                 manager.[<bind_ $implementor:snake>](handle, false).expect(concat!(stringify!($implementor), " unbound"));
             }
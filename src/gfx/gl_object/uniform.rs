@@ -0,0 +1,242 @@
+//! Values that can be set on regular (non-block) uniform variables
+
+use cgmath::{Matrix2, Matrix3, Matrix4, Point2, Point3, Vector2, Vector3, Vector4};
+use crate::gfx::
+{
+    Context,
+    gl_object::reflection::gl_type_layout,
+};
+
+/// Data for a uniform variable, along with the GLSL type it is meant for
+///
+/// The first parameter of each variant is the number of components per element (or the
+/// dimension, for matrices) and the second is the data for every element
+#[derive(Debug, Clone, PartialEq)]
+pub enum UniformValue
+{
+    Float(usize, Vec<f32>),
+    Int(usize, Vec<i32>),
+    UInt(usize, Vec<u32>),
+    /// Square, column-major matrix
+    Matrix(usize, Vec<f32>),
+}
+
+/// Texture unit to be set on a sampler uniform, i.e. `Sampler(0)` for `TEXTURE0`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sampler(pub i32);
+
+/// A type that can be set on a uniform variable with `ShaderProgram::set_uniform`
+///
+/// Slices of uniforms set uniform arrays
+pub trait Uniform
+{
+    /// Returns `None` for empty slices, which have no element to get the type from
+    fn uniform_value(&self) -> Option<UniformValue>;
+}
+
+/// Whether `data_type` is any of the sampler types
+fn is_sampler(data_type: u32) -> bool
+{
+    matches!(data_type,
+        Context::SAMPLER_2D | Context::SAMPLER_3D | Context::SAMPLER_CUBE | Context::SAMPLER_2D_SHADOW |
+        Context::SAMPLER_2D_ARRAY | Context::SAMPLER_2D_ARRAY_SHADOW | Context::SAMPLER_CUBE_SHADOW |
+        Context::INT_SAMPLER_2D | Context::INT_SAMPLER_3D | Context::INT_SAMPLER_CUBE | Context::INT_SAMPLER_2D_ARRAY |
+        Context::UNSIGNED_INT_SAMPLER_2D | Context::UNSIGNED_INT_SAMPLER_3D | Context::UNSIGNED_INT_SAMPLER_CUBE |
+        Context::UNSIGNED_INT_SAMPLER_2D_ARRAY)
+}
+
+impl UniformValue
+{
+    /// Number of components in each element
+    fn element_size(&self) -> usize
+    {
+        match self
+        {
+            UniformValue::Float(n, _) | UniformValue::Int(n, _) | UniformValue::UInt(n, _) => *n,
+            UniformValue::Matrix(n, _) => n * n,
+        }
+    }
+
+    /// Number of array elements held
+    pub fn len(&self) -> usize
+    {
+        let data_len = match self
+        {
+            UniformValue::Float(_, data) | UniformValue::Matrix(_, data) => data.len(),
+            UniformValue::Int(_, data) => data.len(),
+            UniformValue::UInt(_, data) => data.len(),
+        };
+        data_len / self.element_size().max(1)
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+
+    /// Append the elements of `other` to this value
+    /// Returns false, leaving this value unchanged, if `other` is meant for a different type
+    fn extend(&mut self, other: UniformValue) -> bool
+    {
+        match (self, other)
+        {
+            (UniformValue::Float(n, data), UniformValue::Float(m, other)) if *n == m => data.extend(other),
+            (UniformValue::Int(n, data), UniformValue::Int(m, other)) if *n == m => data.extend(other),
+            (UniformValue::UInt(n, data), UniformValue::UInt(m, other)) if *n == m => data.extend(other),
+            (UniformValue::Matrix(n, data), UniformValue::Matrix(m, other)) if *n == m => data.extend(other),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Whether this value can be set on a uniform of GLSL type `data_type`,
+    /// as returned by `get_active_uniform`
+    pub fn matches_type(&self, data_type: u32) -> bool
+    {
+        if is_sampler(data_type)
+        {
+            return matches!(self, UniformValue::Int(1, _));
+        }
+        let layout = match gl_type_layout(data_type)
+        {
+            Some(layout) => layout,
+            None => return false,
+        };
+        // Booleans can be set with any scalar type
+        let is_bool = layout.component_type == Context::BOOL;
+        match self
+        {
+            UniformValue::Matrix(n, _) => layout.component_type == Context::FLOAT && layout.columns == *n as i32 && layout.components == *n as i32,
+            UniformValue::Float(n, _) => layout.columns == 1 && layout.components == *n as i32 && (is_bool || layout.component_type == Context::FLOAT),
+            UniformValue::Int(n, _) => layout.columns == 1 && layout.components == *n as i32 && (is_bool || layout.component_type == Context::INT),
+            UniformValue::UInt(n, _) => layout.columns == 1 && layout.components == *n as i32 && (is_bool || layout.component_type == Context::UNSIGNED_INT),
+        }
+    }
+}
+
+macro_rules! impl_uniform
+{
+    ($type:ty, $variant:ident, $n:expr, |$value:ident| $data:expr) =>
+    {
+        impl Uniform for $type
+        {
+            fn uniform_value(&self) -> Option<UniformValue>
+            {
+                let $value = self;
+                Some(UniformValue::$variant($n, $data))
+            }
+        }
+    };
+}
+
+impl_uniform!(f32, Float, 1, |value| vec![*value]);
+impl_uniform!(Vector2<f32>, Float, 2, |value| AsRef::<[f32; 2]>::as_ref(value).to_vec());
+impl_uniform!(Vector3<f32>, Float, 3, |value| AsRef::<[f32; 3]>::as_ref(value).to_vec());
+impl_uniform!(Vector4<f32>, Float, 4, |value| AsRef::<[f32; 4]>::as_ref(value).to_vec());
+impl_uniform!(Point2<f32>, Float, 2, |value| AsRef::<[f32; 2]>::as_ref(value).to_vec());
+impl_uniform!(Point3<f32>, Float, 3, |value| AsRef::<[f32; 3]>::as_ref(value).to_vec());
+
+impl_uniform!(i32, Int, 1, |value| vec![*value]);
+impl_uniform!(bool, Int, 1, |value| vec![*value as i32]);
+impl_uniform!(Sampler, Int, 1, |value| vec![value.0]);
+impl_uniform!(Vector2<i32>, Int, 2, |value| AsRef::<[i32; 2]>::as_ref(value).to_vec());
+impl_uniform!(Vector3<i32>, Int, 3, |value| AsRef::<[i32; 3]>::as_ref(value).to_vec());
+impl_uniform!(Vector4<i32>, Int, 4, |value| AsRef::<[i32; 4]>::as_ref(value).to_vec());
+
+impl_uniform!(u32, UInt, 1, |value| vec![*value]);
+impl_uniform!(Vector2<u32>, UInt, 2, |value| AsRef::<[u32; 2]>::as_ref(value).to_vec());
+impl_uniform!(Vector3<u32>, UInt, 3, |value| AsRef::<[u32; 3]>::as_ref(value).to_vec());
+impl_uniform!(Vector4<u32>, UInt, 4, |value| AsRef::<[u32; 4]>::as_ref(value).to_vec());
+
+impl_uniform!(Matrix2<f32>, Matrix, 2, |value| AsRef::<[f32; 4]>::as_ref(value).to_vec());
+impl_uniform!(Matrix3<f32>, Matrix, 3, |value| AsRef::<[f32; 9]>::as_ref(value).to_vec());
+impl_uniform!(Matrix4<f32>, Matrix, 4, |value| AsRef::<[f32; 16]>::as_ref(value).to_vec());
+
+impl<T: Uniform> Uniform for [T]
+{
+    fn uniform_value(&self) -> Option<UniformValue>
+    {
+        let (first, rest) = self.split_first()?;
+        let mut value = first.uniform_value()?;
+        for element in rest
+        {
+            // Every element of a slice has the same type
+            value.extend(element.uniform_value()?);
+        }
+        Some(value)
+    }
+}
+
+impl<T: Uniform> Uniform for Vec<T>
+{
+    fn uniform_value(&self) -> Option<UniformValue>
+    {
+        self.as_slice().uniform_value()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use cgmath::{Matrix3, Matrix4, SquareMatrix, Vector3, vec2, vec3};
+    use crate::gfx::
+    {
+        Context,
+        gl_object::uniform::*,
+    };
+
+    #[test]
+    fn test_uniform_value()
+    {
+        assert_eq!(UniformValue::Float(1, vec![2.0]), 2.0f32.uniform_value().unwrap());
+        assert_eq!(UniformValue::Float(3, vec![1.0, 2.0, 3.0]), vec3(1.0f32, 2.0, 3.0).uniform_value().unwrap());
+        assert_eq!(UniformValue::Int(2, vec![1, 2]), vec2(1, 2).uniform_value().unwrap());
+        assert_eq!(UniformValue::Int(1, vec![1]), true.uniform_value().unwrap());
+        assert_eq!(UniformValue::Int(1, vec![3]), Sampler(3).uniform_value().unwrap());
+
+        let mat: Matrix4<f32> = Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0));
+        match mat.uniform_value().unwrap()
+        {
+            // Column-major, so the translation is at the end
+            UniformValue::Matrix(4, data) => assert_eq!(&[1.0, 2.0, 3.0, 1.0], &data[12..]),
+            value => panic!("{:?}", value),
+        }
+    }
+
+    #[test]
+    fn test_uniform_arrays()
+    {
+        let value = [vec3(1.0f32, 2.0, 3.0), vec3(4.0, 5.0, 6.0)][..].uniform_value().unwrap();
+        assert_eq!(UniformValue::Float(3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]), value);
+        assert_eq!(2, value.len());
+
+        let value = vec![Matrix3::<f32>::identity(); 3].uniform_value().unwrap();
+        assert_eq!(3, value.len());
+        assert_eq!(27, match value { UniformValue::Matrix(3, data) => data.len(), _ => 0 });
+
+        assert_eq!(UniformValue::Int(1, vec![4, 5]), [4, 5][..].uniform_value().unwrap());
+
+        // Empty arrays have no type
+        assert_eq!(None, [0i32; 0][..].uniform_value());
+        assert_eq!(None, Vec::<f32>::new().uniform_value());
+    }
+
+    #[test]
+    fn test_matches_type()
+    {
+        assert!(1.0f32.uniform_value().unwrap().matches_type(Context::FLOAT));
+        assert!(vec3(1.0f32, 2.0, 3.0).uniform_value().unwrap().matches_type(Context::FLOAT_VEC3));
+        assert!(!vec3(1.0f32, 2.0, 3.0).uniform_value().unwrap().matches_type(Context::FLOAT_VEC4));
+        assert!(!vec3(1, 2, 3).uniform_value().unwrap().matches_type(Context::FLOAT_VEC3));
+        assert!(Matrix4::<f32>::from_scale(1.0).uniform_value().unwrap().matches_type(Context::FLOAT_MAT4));
+        assert!(!Matrix4::<f32>::from_scale(1.0).uniform_value().unwrap().matches_type(Context::FLOAT_VEC4));
+        assert!(Sampler(0).uniform_value().unwrap().matches_type(Context::SAMPLER_2D));
+        assert!(!0.0f32.uniform_value().unwrap().matches_type(Context::SAMPLER_2D));
+        assert!(1u32.uniform_value().unwrap().matches_type(Context::UNSIGNED_INT));
+        // Booleans can be set from any scalar type
+        assert!(true.uniform_value().unwrap().matches_type(Context::BOOL));
+        assert!(1.0f32.uniform_value().unwrap().matches_type(Context::BOOL));
+        assert!(vec2(1u32, 0).uniform_value().unwrap().matches_type(Context::BOOL_VEC2));
+    }
+}
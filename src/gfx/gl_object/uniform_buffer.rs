@@ -4,25 +4,18 @@ use crate::gfx::
 {
     Context,
     GfxError,
+    backend::ObjectId,
     gl_object::
     {
-        traits::{Bindable},
+        traits::{Bindable, Inspect},
         buffer::Buffer,
+        inspector::ObjectDetails,
+        pod::Pod,
         shader_program::ShaderProgram,
+        std140::Std140,
     },
 };
-use std::
-{
-    sync::
-    {
-        Once,
-        atomic::
-        {
-            AtomicU32,
-            Ordering,
-        }
-    },
-};
+use std::sync::Once;
 // THIS VARIABLE MUST NEVER BE CHANGED OUTSIDE OF `get_alignment`
 // Using it in this way to cache the uniform buffer alignment is thread-safe as long
 // as it is only changed once with INIT.call_once();
@@ -36,109 +29,247 @@ fn get_alignment(context: &Context) -> i32
         {
             INIT.call_once(||
                 {
-                    ALIGNMENT = context.get_parameter(Context::UNIFORM_BUFFER_OFFSET_ALIGNMENT)
-                        .expect("Uniform Buffer Alignment").as_f64().expect("Uniform Buffer Alignment as f64") as i32;
+                    ALIGNMENT = context.parameter(Context::UNIFORM_BUFFER_OFFSET_ALIGNMENT).expect("Uniform Buffer Alignment");
                 });
             ALIGNMENT
         }
 }
 
-/// Align `size` to the `UNIFORM_BUFFER_OFFSET_ALIGNMENT`
-fn align(context: &Context, size: i32) -> i32
+/// Align `size` to `alignment`, which must be a power of two
+fn align_to(size: i32, alignment: i32) -> i32
 {
-    (size + get_alignment(&context) - 1) & (-get_alignment(&context))
+    (size + alignment - 1) & (-alignment)
 }
 
-/// Atomic counter for generating new uniform block bindings
-static BLOCK_BINDINGS: AtomicU32 = AtomicU32::new(0);
+/// Description of a uniform block to be allocated within a `UniformBuffer`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformBlockDesc
+{
+    /// Name of the block within shaders
+    pub name: String,
+    /// Uniform buffer binding point that the block is bound to
+    /// Binding points are shared by the whole context, so every block in use at the same time needs its own
+    pub binding: u32,
+    /// Size of the block's data in bytes
+    pub size: i32,
+}
 
-impl_buffer!(UNIFORM_BUFFER,
-UniformBuffer
+impl UniformBlockDesc
 {
-    vert_size: i32,
-    frag_size: i32,
+    pub fn new(name: &str, binding: u32, size: i32) -> UniformBlockDesc
+    {
+        UniformBlockDesc { name: name.to_string(), binding, size }
+    }
 
-    vert_offset: i32,
-    frag_offset: i32,
+    /// Describe a block holding std140 data of type `T`
+    pub fn std140<T: Std140>(name: &str, binding: u32) -> UniformBlockDesc
+    {
+        UniformBlockDesc::new(name, binding, T::SIZE as i32)
+    }
+}
+
+/// A uniform block allocated within a `UniformBuffer`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct UniformBlock
+{
+    pub name: String,
+    pub binding: u32,
+    /// Offset of the block within the buffer, which is a multiple of `UNIFORM_BUFFER_OFFSET_ALIGNMENT`
+    pub offset: i32,
+    pub size: i32,
+}
 
-    vert_binding: Option<u32>,
-    frag_binding: Option<u32>
+/// Lay out `blocks` one after another, with each offset aligned to `alignment`
+/// Returns the laid out blocks along with the total buffer size
+fn layout_blocks(blocks: &[UniformBlockDesc], alignment: i32) -> Result<(Vec<UniformBlock>, i32), GfxError>
+{
+    let mut layout: Vec<UniformBlock> = Vec::with_capacity(blocks.len());
+    let mut offset = 0;
+    for desc in blocks
+    {
+        if layout.iter().any(|block| block.name == desc.name)
+        {
+            return Err(GfxError::DuplicateUniformBlock(format!("Uniform block {} is declared more than once", desc.name)));
+        }
+        if let Some(block) = layout.iter().find(|block| block.binding == desc.binding)
+        {
+            return Err(GfxError::DuplicateUniformBlock(format!("Uniform blocks {} and {} both use binding {}", block.name, desc.name, desc.binding)));
+        }
+
+        offset = align_to(offset, alignment);
+        layout.push(UniformBlock { name: desc.name.clone(), binding: desc.binding, offset, size: desc.size });
+        offset += desc.size;
+    }
+    Ok((layout, offset))
+}
+
+impl_buffer!(UNIFORM_BUFFER,
+UniformBuffer
+{
+    blocks: Vec<UniformBlock>
 });
 
 impl UniformBuffer
 {
-    pub fn new(context: &Context, vert_size: i32, frag_size: i32, draw_type: u32) -> Result<UniformBuffer, GfxError>
+    /// Create a new uniform buffer with a region for each of `blocks`, and bind each region to its binding point
+    ///
+    /// Blocks that are updated at different frequencies, such as per-frame camera data and
+    /// per-object data, can be kept in separate buffers or in separate blocks of the same buffer
+    pub fn new(context: &Context, blocks: &[UniformBlockDesc], draw_type: u32) -> Result<UniformBuffer, GfxError>
     {
-        let vert_size = align(&context, vert_size);
-        // not necessary to align frag size since it isn't used as an offset
-        //let frag_size = align(&context, frag_size);
+        let (blocks, size) = layout_blocks(blocks, get_alignment(context))?;
 
         let mut ub = impl_buffer!(@init_struct context, UniformBuffer
         {
-            vert_size: vert_size,
-            vert_offset: 0,
-
-            frag_size: frag_size,
-            frag_offset: vert_size,
-
-            vert_binding: None,
-            frag_binding: None
+            blocks: blocks
         });
         ub.bind_internal();
-        ub.buffer_data_raw(&vec![0u8; (vert_size + frag_size) as usize], draw_type);
+        ub.buffer_data_raw(&vec![0u8; size as usize], draw_type);
+        let ranges: Vec<(u32, i32, i32)> = ub.blocks.iter().map(|block| (block.binding, block.offset, block.size)).collect();
+        for (binding, offset, size) in ranges
+        {
+            ub.bind_range(binding, offset, size);
+        }
         ub.unbind_internal();
 
         Ok(ub)
     }
 
-    /// Set `data` as the contents of the vertex shader uniform block
-    pub fn buffer_vert_data<T>(&mut self, data: &[T])
+    pub fn blocks(&self) -> &[UniformBlock]
     {
-        self.buffer_sub_data(self.vert_offset, &data);
+        &self.blocks
     }
 
-    /// Set `data` as the contents of the vertex shader uniform block, starting at `offset`
-    #[allow(dead_code)]
-    pub fn buffer_vert_data_with_offset<T>(&mut self, offset: i32, data: &[T])
+    /// Get the block `name` within this buffer
+    pub fn block(&self, name: &str) -> Result<&UniformBlock, GfxError>
     {
-        self.buffer_sub_data(self.vert_offset+offset, &data)
+        self.blocks.iter().find(|block| block.name == name).ok_or_else(|| GfxError::InvalidUniformBlockName(name.to_string()))
     }
 
-    /// Set `data` as the contents of the fragment shader uniform block
-    pub fn buffer_frag_data<T>(&mut self, data: &[T])
+    /// Set `data` as the contents of block `name`, starting at `offset` within the block
+    /// The buffer must be bound
+    #[track_caller]
+    pub fn buffer_block_data_with_offset<T: Pod>(&mut self, name: &str, offset: i32, data: &[T]) -> Result<(), GfxError>
     {
-        self.buffer_sub_data(self.frag_offset, &data);
+        let block = self.block(name)?;
+        let size = std::mem::size_of_val(data) as i32;
+        if offset + size > block.size
+        {
+            return Err(GfxError::UniformBlockSizeMismatch(name.to_string(), offset + size, block.size));
+        }
+        let block_offset = block.offset;
+        self.buffer_sub_data(block_offset + offset, data);
+        Ok(())
     }
 
-    /// Set `data` as the contents of the fragment shader uniform block, starting at `offset`
-    #[allow(dead_code)]
-    pub fn buffer_frag_data_with_offset<T>(&mut self, offset: i32, data: &[T])
+    /// Set `data` as the contents of block `name`
+    /// The buffer must be bound
+    #[track_caller]
+    pub fn buffer_block_data<T: Pod>(&mut self, name: &str, data: &[T]) -> Result<(), GfxError>
     {
-        self.buffer_sub_data(self.frag_offset+offset, &data);
+        self.buffer_block_data_with_offset(name, 0, data)
     }
 
-    /// Register a vertex shader uniform block of `block_name` from within `shader_program` to this uniform buffer
-    pub fn add_vert_block(&mut self, shader_program: &mut ShaderProgram, block_name: &str) -> Result<(), GfxError>
+    /// Write `data` with the std140 layout as the contents of block `name`
+    /// The buffer must be bound
+    #[track_caller]
+    pub fn buffer_block_std140<T: Std140>(&mut self, name: &str, data: &T) -> Result<(), GfxError>
     {
-        if self.vert_binding == None
+        let block = self.block(name)?;
+        let alignment = get_alignment(&self.context);
+        if block.offset % alignment != 0
         {
-            self.vert_binding = Some(BLOCK_BINDINGS.fetch_add(1, Ordering::Relaxed));
+            return Err(GfxError::UniformBlockMisaligned(block.offset, alignment));
         }
-        shader_program.add_uniform_block_binding(block_name, self.vert_binding.unwrap())?;
-        self.bind_range(self.vert_binding.unwrap(), self.vert_offset, self.vert_size);
-        Ok(())
+        self.buffer_block_data(name, &data.to_std140_bytes())
     }
 
-    /// Register a fragment shader uniform block of `block_name` from within `shader_program` to this uniform buffer
-    pub fn add_frag_block(&mut self, shader_program: &mut ShaderProgram, block_name: &str) -> Result<(), GfxError>
+    /// Connect the uniform block `name` within `shader_program` to the block of the same name in this buffer
+    /// Fails if the block in this buffer is too small for the block in the shader
+    #[track_caller]
+    pub fn attach_block(&self, shader_program: &mut ShaderProgram, name: &str) -> Result<(), GfxError>
     {
-        if self.frag_binding == None
+        let block = self.block(name)?;
+        shader_program.reflection().validate_uniform_block_size(name, block.size)?;
+        shader_program.add_uniform_block_binding(name, block.binding)
+    }
+
+    /// Connect every block of this buffer that is active within `shader_program`
+    /// This allows a buffer to be shared by any number of shader programs
+    /// Returns the number of blocks that were connected
+    #[track_caller]
+    pub fn attach_blocks(&self, shader_program: &mut ShaderProgram) -> Result<usize, GfxError>
+    {
+        let mut attached = 0;
+        for block in &self.blocks
         {
-            self.frag_binding = Some(BLOCK_BINDINGS.fetch_add(1, Ordering::Relaxed));
+            if shader_program.reflection().block(&block.name).is_some()
+            {
+                self.attach_block(shader_program, &block.name)?;
+                attached += 1;
+            }
         }
-        shader_program.add_uniform_block_binding(block_name, self.frag_binding.unwrap())?;
-        self.bind_internal();
-        self.bind_range(self.frag_binding.unwrap(), self.frag_offset, self.frag_size);
-        Ok(())
+        Ok(attached)
+    }
+}
+
+impl Inspect for UniformBuffer
+{
+    fn gl_id(&self) -> u32
+    {
+        self.internal.0
     }
-}
\ No newline at end of file
+
+    fn label_in_context(&self, label: &str)
+    {
+        self.context.label_object(ObjectId::Buffer(self.internal), label);
+    }
+
+    fn details(&self) -> ObjectDetails
+    {
+        ObjectDetails::UniformBuffer
+        {
+            usage: (self.draw_type != 0).then(|| Context::constant_name(self.draw_type)),
+            blocks: self.blocks.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::gfx::
+    {
+        GfxError,
+        gl_object::uniform_buffer::*,
+    };
+
+    #[test]
+    fn test_layout_blocks()
+    {
+        let (blocks, size) = layout_blocks(&[
+            UniformBlockDesc::new("Camera", 0, 80),
+            UniformBlockDesc::new("Object", 2, 64),
+            UniformBlockDesc::new("Lights", 1, 300),
+        ], 256).unwrap();
+
+        assert_eq!(vec![
+            UniformBlock { name: "Camera".to_string(), binding: 0, offset: 0, size: 80 },
+            UniformBlock { name: "Object".to_string(), binding: 2, offset: 256, size: 64 },
+            UniformBlock { name: "Lights".to_string(), binding: 1, offset: 512, size: 300 },
+        ], blocks);
+        assert_eq!(812, size);
+
+        assert_eq!(Ok((vec![], 0)), layout_blocks(&[], 256));
+    }
+
+    #[test]
+    fn test_layout_duplicate_blocks()
+    {
+        let result = layout_blocks(&[UniformBlockDesc::new("Camera", 0, 80), UniformBlockDesc::new("Camera", 1, 80)], 256);
+        assert!(matches!(result, Err(GfxError::DuplicateUniformBlock(_))));
+
+        let result = layout_blocks(&[UniformBlockDesc::new("Camera", 0, 80), UniformBlockDesc::new("Object", 0, 80)], 256);
+        assert!(matches!(result, Err(GfxError::DuplicateUniformBlock(_))));
+    }
+}
@@ -1,17 +1,22 @@
-/// WebGlVertexArrayObject wrapper
+/// Vertex array object wrapper
 
 use crate::gfx::
 {
     Context,
     GfxError,
     gl_get_errors,
+    backend::{ObjectId, VertexArrayId},
     gl_object::
     {
-        manager::{GlObjectHandle, GlObjectManager},
-        traits::{Bindable, Reloadable},
+        ArrayBuffer,
+        ElementArrayBuffer,
+        manager::{Handle, GlObjectManager},
+        traits::{Bindable, Reloadable, MemoryUsage, Inspect},
+        inspector::ObjectDetails,
+        shader_program::ShaderProgram,
+        vertex_layout::VertexLayout,
     },
 };
-use web_sys::WebGlVertexArrayObject;
 use gen_vec::{exposed::ExposedGenVec};
 
 #[derive(Debug, Copy, Clone)]
@@ -51,11 +56,23 @@ impl AttribPointer
             index, size, data_type, normalized, stride, offset
         }
     }
+
+    /// Attribute location that this pointer supplies
+    pub fn index(&self) -> u32
+    {
+        self.index
+    }
+
+    /// Number of components per vertex
+    pub fn size(&self) -> i32
+    {
+        self.size
+    }
 }
 
 pub struct VertexArray
 {
-    internal: WebGlVertexArrayObject,
+    internal: VertexArrayId,
     context: Context,
     array_buffer_attribs: ExposedGenVec<Option<Vec<AttribPointer>>>,
     element_array_buffer_attribs: ExposedGenVec<Option<Vec<AttribPointer>>>
@@ -63,7 +80,7 @@ pub struct VertexArray
 
 impl VertexArray
 {
-    fn new_vertex_array(context: &Context) -> Result<WebGlVertexArrayObject, GfxError>
+    fn new_vertex_array(context: &Context) -> Result<VertexArrayId, GfxError>
     {
         context.create_vertex_array().ok_or_else(|| GfxError::VertexArrayCreationError(gl_get_errors(context).to_string()))
     }
@@ -82,55 +99,96 @@ impl VertexArray
 
     /// Registers the array buffer `buffer` to this `VertexArray` with the given `AttribPointer`s, if any
     /// The target buffer MUST be bound directly before calling this function
-    pub fn register_array_buffer(&mut self, handle: GlObjectHandle, attrib_ptrs: Option<Vec<AttribPointer>>)
+    #[track_caller]
+    pub fn register_array_buffer(&mut self, handle: Handle<ArrayBuffer>, attrib_ptrs: Option<Vec<AttribPointer>>)
     {
         if let Some(attrib_ptrs) = &attrib_ptrs
         {
             self.set_attrib_ptrs(&attrib_ptrs);
         }
-        self.array_buffer_attribs.set(handle, attrib_ptrs);
+        self.array_buffer_attribs.set(handle.index(), attrib_ptrs);
+    }
+
+    /// Registers the array buffer `buffer`, which holds vertices of type `V`, to this `VertexArray` with `V`'s layout
+    /// If `shader_program` is given, nothing is registered unless the layout matches its attributes
+    /// The target buffer MUST be bound directly before calling this function
+    #[track_caller]
+    pub fn register_vertex_buffer<V: VertexLayout>(&mut self, handle: Handle<ArrayBuffer>, shader_program: Option<&ShaderProgram>) -> Result<(), GfxError>
+    {
+        if let Some(shader_program) = shader_program
+        {
+            shader_program.reflection().validate_vertex_attribs(&V::attribs())?;
+        }
+        self.register_array_buffer(handle, Some(V::attrib_ptrs()));
+        Ok(())
     }
 
     /// Registers the element buffer `buffer` to this `VertexArray` with the given `AttribPointer`s, if any
     /// The target buffer MUST be bound directly before calling this function
-    pub fn register_element_array_buffer(&mut self, handle: GlObjectHandle, attrib_ptrs: Option<Vec<AttribPointer>>)
+    #[track_caller]
+    pub fn register_element_array_buffer(&mut self, handle: Handle<ElementArrayBuffer>, attrib_ptrs: Option<Vec<AttribPointer>>)
     {
         if let Some(attrib_ptrs) = &attrib_ptrs
         {
             self.set_attrib_ptrs(&attrib_ptrs);
         }
-        self.element_array_buffer_attribs.set(handle, attrib_ptrs);
+        self.element_array_buffer_attribs.set(handle.index(), attrib_ptrs);
     }
 
+    #[track_caller]
     fn set_attrib_ptrs(&self, attrib_ptrs: &Vec<AttribPointer>)
     {
         for ptr in attrib_ptrs
         {
-            self.context.vertex_attrib_pointer_with_i32(ptr.index, ptr.size, ptr.data_type, ptr.normalized, ptr.stride, ptr.offset);
+            self.context.vertex_attrib_pointer(ptr.index, ptr.size, ptr.data_type, ptr.normalized, ptr.stride, ptr.offset);
             self.context.enable_vertex_attrib_array(ptr.index);
         }
     }
 
+    /// Check that the registered `AttribPointer`s match the attributes of `shader_program`
+    pub fn validate(&self, shader_program: &ShaderProgram) -> Result<(), GfxError>
+    {
+        let mut all_attrib_ptrs = vec![];
+        for (_, attrib_ptrs) in &self.array_buffer_attribs
+        {
+            if let Some(attrib_ptrs) = attrib_ptrs
+            {
+                all_attrib_ptrs.extend_from_slice(attrib_ptrs);
+            }
+        }
+        for (_, attrib_ptrs) in &self.element_array_buffer_attribs
+        {
+            if let Some(attrib_ptrs) = attrib_ptrs
+            {
+                all_attrib_ptrs.extend_from_slice(attrib_ptrs);
+            }
+        }
+        shader_program.reflection().validate_attrib_ptrs(&all_attrib_ptrs)
+    }
+
     #[allow(dead_code)]
-    pub fn unregister_array_buffer(&mut self, handle: GlObjectHandle)
+    pub fn unregister_array_buffer(&mut self, handle: Handle<ArrayBuffer>)
     {
-        self.array_buffer_attribs.remove(handle);
+        self.array_buffer_attribs.remove(handle.index());
     }
 
     #[allow(dead_code)]
-    pub fn unregister_element_array_buffer(&mut self, handle: GlObjectHandle)
+    pub fn unregister_element_array_buffer(&mut self, handle: Handle<ElementArrayBuffer>)
     {
-        self.element_array_buffer_attribs.remove(handle);
+        self.element_array_buffer_attribs.remove(handle.index());
     }
 }
 
 impl_globject!(VertexArray);
 
+// Vertex arrays only hold attribute state, which is negligible
+impl MemoryUsage for VertexArray {}
+
 impl Bindable for VertexArray
 {
     fn bind_internal(&self)
     {
-        self.context.bind_vertex_array(Some(&self.internal));
+        self.context.bind_vertex_array(Some(self.internal));
     }
     fn unbind_internal(&self)
     {
@@ -146,18 +204,18 @@ impl Reloadable for VertexArray
         self.internal = VertexArray::new_vertex_array(&self.context)?;
         self.bind_internal();
 
-        for (handle, attrib_ptrs) in &self.array_buffer_attribs
+        for (index, attrib_ptrs) in &self.array_buffer_attribs
         {
-            manager.get_array_buffer(handle).ok_or_else(|| GfxError::InvalidHandle(handle))?.bind_internal();
+            manager.get_array_buffer(Handle::from_index(index)).ok_or(GfxError::InvalidHandle(index))?.bind_internal();
             if let Some(attrib_ptrs) = attrib_ptrs
             {
                 self.set_attrib_ptrs(&attrib_ptrs);
             }
         }
 
-        for (handle, attrib_ptrs) in &self.element_array_buffer_attribs
+        for (index, attrib_ptrs) in &self.element_array_buffer_attribs
         {
-            manager.get_element_array_buffer(handle).ok_or_else(|| GfxError::InvalidHandle(handle))?.bind_internal();
+            manager.get_element_array_buffer(Handle::from_index(index)).ok_or(GfxError::InvalidHandle(index))?.bind_internal();
             if let Some(attrib_ptrs) = attrib_ptrs
             {
                 self.set_attrib_ptrs(&attrib_ptrs);
@@ -172,10 +230,32 @@ impl Reloadable for VertexArray
     }
 }
 
+impl Inspect for VertexArray
+{
+    fn gl_id(&self) -> u32
+    {
+        self.internal.0
+    }
+
+    fn label_in_context(&self, label: &str)
+    {
+        self.context.label_object(ObjectId::VertexArray(self.internal), label);
+    }
+
+    fn details(&self) -> ObjectDetails
+    {
+        ObjectDetails::VertexArray
+        {
+            array_buffers: (&self.array_buffer_attribs).into_iter().map(|(index, _)| index).collect(),
+            element_array_buffers: (&self.element_array_buffer_attribs).into_iter().map(|(index, _)| index).collect(),
+        }
+    }
+}
+
 impl Drop for VertexArray
 {
     fn drop(&mut self)
     {
-        self.context.delete_vertex_array(Some(&self.internal));
+        self.context.delete_vertex_array(self.internal);
     }
 }
\ No newline at end of file
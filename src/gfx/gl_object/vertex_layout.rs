@@ -0,0 +1,165 @@
+//! Descriptions of how vertex structs are laid out, for setting up attribute pointers
+
+use cgmath::{Vector2, Vector3, Vector4};
+use crate::gfx::
+{
+    Context,
+    gl_object::
+    {
+        pod::Pod,
+        vertex_array::AttribPointer,
+    },
+};
+
+/// A type that can be the field of a vertex, which supplies one vertex attribute
+pub trait VertexComponent
+{
+    /// Number of components, 1 to 4
+    const SIZE: i32;
+    /// Type of each component, i.e. `FLOAT`
+    const DATA_TYPE: u32;
+    /// Whether integer components are normalized to [0, 1] or [-1, 1]
+    const NORMALIZED: bool;
+}
+
+macro_rules! impl_vertex_component
+{
+    ($($type:ty => $size:expr, $data_type:ident, $normalized:expr);* $(;)?) =>
+    {
+        $(
+        impl VertexComponent for $type
+        {
+            const SIZE: i32 = $size;
+            const DATA_TYPE: u32 = Context::$data_type;
+            const NORMALIZED: bool = $normalized;
+        }
+        )*
+    };
+}
+impl_vertex_component!
+{
+    f32 => 1, FLOAT, false;
+    [f32; 2] => 2, FLOAT, false;
+    [f32; 3] => 3, FLOAT, false;
+    [f32; 4] => 4, FLOAT, false;
+    Vector2<f32> => 2, FLOAT, false;
+    Vector3<f32> => 3, FLOAT, false;
+    Vector4<f32> => 4, FLOAT, false;
+    // i.e. RGBA colors
+    [u8; 4] => 4, UNSIGNED_BYTE, true;
+}
+
+/// A single attribute of a vertex struct
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VertexAttrib
+{
+    /// Name of the attribute within shaders
+    pub name: &'static str,
+    pub location: u32,
+    pub size: i32,
+    pub data_type: u32,
+    pub normalized: bool,
+    /// Offset of the field within the vertex struct
+    pub offset: i32,
+}
+
+impl VertexAttrib
+{
+    /// Describe the field of `V` that `field` gets
+    /// `field` is only used to find the field's type
+    pub fn of_field<V, C: VertexComponent>(_field: fn(&V) -> &C, name: &'static str, location: u32, offset: usize) -> VertexAttrib
+    {
+        VertexAttrib { name, location, size: C::SIZE, data_type: C::DATA_TYPE, normalized: C::NORMALIZED, offset: offset as i32 }
+    }
+
+    /// Attribute pointer for this attribute within a buffer of vertices that are `stride` bytes apart
+    pub fn attrib_ptr(&self, stride: i32) -> AttribPointer
+    {
+        AttribPointer::without_defaults(self.location, self.size, self.data_type, self.normalized, stride, self.offset)
+    }
+}
+
+/// A vertex struct whose layout is known, so that array buffers of it can be registered
+/// with a `VertexArray` without listing attribute pointers by hand
+///
+/// Structs can implement this with the `impl_vertex_layout!` macro
+pub trait VertexLayout: Pod
+{
+    /// Attributes of the vertex, in field order
+    fn attribs() -> Vec<VertexAttrib>;
+
+    /// Attribute pointers for a tightly packed buffer of vertices
+    fn attrib_ptrs() -> Vec<AttribPointer>
+    {
+        let stride = std::mem::size_of::<Self>() as i32;
+        Self::attribs().iter().map(|attrib| attrib.attrib_ptr(stride)).collect()
+    }
+}
+
+/// Implement `VertexLayout` for a `Pod` struct
+///
+/// Each field is given the attribute location and shader attribute name that it supplies, and
+/// its size and type come from its `VertexComponent` implementation
+///
+/// ```ignore
+/// impl_vertex_layout!(Vertex
+/// {
+///     position: 0 => "Vertex",
+///     texcoord: 2 => "UV",
+/// });
+/// ```
+macro_rules! impl_vertex_layout
+{
+    ($name:ident { $($field:ident: $location:expr => $attrib_name:expr),* $(,)? }) =>
+    {
+        impl crate::gfx::gl_object::vertex_layout::VertexLayout for $name
+        {
+            fn attribs() -> Vec<crate::gfx::gl_object::vertex_layout::VertexAttrib>
+            {
+                vec![$(
+                    crate::gfx::gl_object::vertex_layout::VertexAttrib::of_field(|vertex: &$name| &vertex.$field, $attrib_name, $location, offset_of!($name, $field)),
+                )*]
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::gfx::
+    {
+        Context,
+        gl_object::vertex_layout::*,
+    };
+
+    pod_struct!
+    {
+        #[derive(Copy, Clone)]
+        struct ColorVertex
+        {
+            position: Vector3<f32>,
+            color: [u8; 4],
+            uv: [f32; 2],
+        }
+    }
+    impl_vertex_layout!(ColorVertex
+    {
+        position: 0 => "Position",
+        color: 3 => "Color",
+        uv: 1 => "UV",
+    });
+
+    #[test]
+    fn test_attribs()
+    {
+        assert_eq!(vec![
+            VertexAttrib { name: "Position", location: 0, size: 3, data_type: Context::FLOAT, normalized: false, offset: 0 },
+            VertexAttrib { name: "Color", location: 3, size: 4, data_type: Context::UNSIGNED_BYTE, normalized: true, offset: 12 },
+            VertexAttrib { name: "UV", location: 1, size: 2, data_type: Context::FLOAT, normalized: false, offset: 16 },
+        ], ColorVertex::attribs());
+
+        let ptrs = ColorVertex::attrib_ptrs();
+        assert_eq!(vec![(0, 3), (3, 4), (1, 2)], ptrs.iter().map(|ptr| (ptr.index(), ptr.size())).collect::<Vec<_>>());
+    }
+}
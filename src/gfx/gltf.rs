@@ -0,0 +1,1103 @@
+//! glTF 2.0 model loading
+//!
+//! Supports `.gltf` files with external or embedded (data URI) buffers as well as binary `.glb`
+//! files. External buffers and images are looked up in the `ResourceManager` by file name, the same
+//! as material libraries of OBJ files. Node animations are loaded as `AnimationClip`s targeting
+//! node indices
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use cgmath::{Matrix3, Quaternion, Vector3, vec3};
+use serde::Deserialize;
+use crate::
+{
+    gfx::mesh::{Mesh, SubMesh, Material, Vertex, resource_name},
+    math::
+    {
+        transform::SubTransformation,
+        animation::{AnimationClip, Channel, Interpolation, Keyframes, Track},
+    },
+    resource::manager::ResourceManager,
+};
+
+/// "glTF" in little-endian, the first 4 bytes of every `.glb` file
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_HEADER_SIZE: usize = 12;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+// Accessor component types
+const BYTE: u32 = 5120;
+const UNSIGNED_BYTE: u32 = 5121;
+const SHORT: u32 = 5122;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+/// Primitive mode for triangle lists, the only mode that is supported
+const TRIANGLES: u32 = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GltfError
+{
+    /// JSON is malformed or doesn't match the glTF schema
+    Json(String),
+    /// Binary glTF container is malformed
+    Glb(String),
+    /// Buffer or image isn't in the resource manager
+    MissingResource(String),
+    /// Reference to another object, or an accessor's data, is out of bounds
+    OutOfBounds(String),
+    /// Part of glTF that isn't supported, such as sparse accessors or non-triangle primitives
+    Unsupported(String),
+}
+
+impl std::fmt::Display for GltfError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        write!(f, "{:?}", self)
+    }
+}
+
+// glTF JSON schema, only including the properties that are used
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Root
+{
+    asset: AssetDef,
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<SceneDef>,
+    #[serde(default)]
+    nodes: Vec<NodeDef>,
+    #[serde(default)]
+    meshes: Vec<MeshDef>,
+    #[serde(default)]
+    accessors: Vec<AccessorDef>,
+    #[serde(default)]
+    buffer_views: Vec<BufferViewDef>,
+    #[serde(default)]
+    buffers: Vec<BufferDef>,
+    #[serde(default)]
+    materials: Vec<MaterialDef>,
+    #[serde(default)]
+    textures: Vec<TextureDef>,
+    #[serde(default)]
+    images: Vec<ImageDef>,
+    #[serde(default)]
+    animations: Vec<AnimationDef>,
+}
+
+#[derive(Deserialize)]
+struct AssetDef
+{
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct SceneDef
+{
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct NodeDef
+{
+    name: Option<String>,
+    mesh: Option<usize>,
+    #[serde(default)]
+    children: Vec<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+struct MeshDef
+{
+    name: Option<String>,
+    primitives: Vec<PrimitiveDef>,
+}
+
+#[derive(Deserialize)]
+struct PrimitiveDef
+{
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    mode: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccessorDef
+{
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferViewDef
+{
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferDef
+{
+    uri: Option<String>,
+    byte_length: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaterialDef
+{
+    name: Option<String>,
+    pbr_metallic_roughness: Option<PbrDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PbrDef
+{
+    base_color_factor: Option<[f32; 4]>,
+    base_color_texture: Option<TextureInfoDef>,
+    metallic_factor: Option<f32>,
+    roughness_factor: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct TextureInfoDef
+{
+    index: usize,
+}
+
+#[derive(Deserialize)]
+struct TextureDef
+{
+    source: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageDef
+{
+    name: Option<String>,
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+    mime_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AnimationDef
+{
+    name: Option<String>,
+    channels: Vec<AnimationChannelDef>,
+    samplers: Vec<AnimationSamplerDef>,
+}
+
+#[derive(Deserialize)]
+struct AnimationChannelDef
+{
+    sampler: usize,
+    target: AnimationTargetDef,
+}
+
+#[derive(Deserialize)]
+struct AnimationTargetDef
+{
+    node: Option<usize>,
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct AnimationSamplerDef
+{
+    input: usize,
+    output: usize,
+    interpolation: Option<String>,
+}
+
+/// Image used by the materials of a glTF file
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfImage
+{
+    /// Name that materials use to refer to the image
+    /// External images use their file name, and embedded images are named `<gltf name>#image<index>`
+    pub name: String,
+    pub mime_type: Option<String>,
+    /// Encoded image, i.e. PNG or JPEG
+    pub data: Vec<u8>,
+}
+
+/// Node of a glTF scene graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfNode
+{
+    pub name: String,
+    /// Index of the node's mesh within `GltfScene::meshes`
+    pub mesh: Option<usize>,
+    /// Indices of the node's children within `GltfScene::nodes`
+    pub children: Vec<usize>,
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl GltfNode
+{
+    /// Transformation of the node relative to its parent
+    pub fn transformation(&self) -> SubTransformation
+    {
+        let mut transformation = SubTransformation::new();
+        // Scale first, since setting the scale also scales the translation
+        transformation.set_scale(self.scale);
+        transformation.set_orientation_quat(self.rotation);
+        transformation.set_translation(self.translation);
+        transformation
+    }
+}
+
+/// Contents of a glTF file
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfScene
+{
+    /// Each glTF mesh, with a submesh for each of its primitives
+    pub meshes: Vec<Mesh>,
+    pub nodes: Vec<GltfNode>,
+    /// Indices of the nodes at the top of the hierarchy
+    pub roots: Vec<usize>,
+    pub materials: Vec<Material>,
+    pub images: Vec<GltfImage>,
+    /// Animations whose channels target indices within `nodes`
+    pub animations: Vec<AnimationClip>,
+}
+
+impl GltfScene
+{
+    /// Load the `.gltf` or `.glb` file `name` in `resource_manager`
+    pub fn from_resource(name: &str, resource_manager: &ResourceManager) -> Result<GltfScene, GltfError>
+    {
+        let data = resource_manager.get_by_name(&name.to_string()).ok_or_else(|| GltfError::MissingResource(name.to_string()))?;
+        GltfScene::from_slice(name, data, resource_manager)
+    }
+
+    /// Load a `.gltf` or `.glb` file from its contents
+    /// `name` is used to name embedded images
+    pub fn from_slice(name: &str, data: &[u8], resource_manager: &ResourceManager) -> Result<GltfScene, GltfError>
+    {
+        let (json, bin) = if data.len() >= 4 && read_u32(data, 0) == GLB_MAGIC
+        {
+            parse_glb(data)?
+        }
+        else { (data, None) };
+
+        let root: Root = serde_json::from_slice(json).map_err(|err| GltfError::Json(err.to_string()))?;
+        if !root.asset.version.starts_with("2.")
+        {
+            return Err(GltfError::Unsupported(format!("glTF version {}", root.asset.version)));
+        }
+        let buffers = load_buffers(&root, bin, resource_manager)?;
+        Loader { name, root: &root, buffers }.load(resource_manager)
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32
+{
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// Split a `.glb` file into its JSON chunk and optional binary chunk
+fn parse_glb(data: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfError>
+{
+    if data.len() < GLB_HEADER_SIZE
+    {
+        return Err(GltfError::Glb("file is too short for the header".to_string()));
+    }
+    let version = read_u32(data, 4);
+    if version != 2
+    {
+        return Err(GltfError::Unsupported(format!("glb version {}", version)));
+    }
+    let length = read_u32(data, 8) as usize;
+    if length > data.len()
+    {
+        return Err(GltfError::Glb(format!("header length {} is longer than the file ({} bytes)", length, data.len())));
+    }
+
+    let mut chunks = vec![];
+    let mut offset = GLB_HEADER_SIZE;
+    while offset + 8 <= length
+    {
+        let chunk_length = read_u32(data, offset) as usize;
+        let chunk_type = read_u32(data, offset + 4);
+        let start = offset + 8;
+        // Lengths come from the file, so the end can overflow where `usize` is 32 bits
+        let end = start.checked_add(chunk_length).filter(|end| *end <= length)
+            .ok_or_else(|| GltfError::Glb(format!("chunk at {} runs past the end of the file", offset)))?;
+        chunks.push((chunk_type, &data[start..end]));
+        offset = end;
+    }
+
+    match chunks.as_slice()
+    {
+        [(GLB_CHUNK_JSON, json)] => Ok((json, None)),
+        [(GLB_CHUNK_JSON, json), (GLB_CHUNK_BIN, bin), ..] => Ok((json, Some(bin))),
+        [(GLB_CHUNK_JSON, json), ..] => Ok((json, None)),
+        _ => Err(GltfError::Glb("first chunk must be JSON".to_string())),
+    }
+}
+
+/// Get the contents of every buffer
+/// Buffers without a URI are the `.glb` binary chunk
+fn load_buffers(root: &Root, bin: Option<&[u8]>, resource_manager: &ResourceManager) -> Result<Vec<Vec<u8>>, GltfError>
+{
+    let mut buffers = Vec::with_capacity(root.buffers.len());
+    for (index, buffer) in root.buffers.iter().enumerate()
+    {
+        let data = match &buffer.uri
+        {
+            None => bin.ok_or_else(|| GltfError::Glb(format!("buffer {} has no uri and there is no binary chunk", index)))?.to_vec(),
+            Some(uri) => load_uri(uri, resource_manager)?,
+        };
+        if data.len() < buffer.byte_length
+        {
+            return Err(GltfError::OutOfBounds(format!("buffer {} is {} bytes but should be {}", index, data.len(), buffer.byte_length)));
+        }
+        buffers.push(data);
+    }
+    Ok(buffers)
+}
+
+/// Get the contents of a data URI or an external file
+fn load_uri(uri: &str, resource_manager: &ResourceManager) -> Result<Vec<u8>, GltfError>
+{
+    if let Some(data) = uri.strip_prefix("data:")
+    {
+        let (_, encoded) = data.split_once(";base64,").ok_or_else(|| GltfError::Unsupported(format!("data uri that isn't base64: {}", uri)))?;
+        decode_base64(encoded).ok_or_else(|| GltfError::Json(format!("invalid base64 in data uri: {}", uri)))
+    }
+    else
+    {
+        resource_name(uri)
+            .and_then(|name| resource_manager.get_by_name(&name))
+            .cloned()
+            .ok_or_else(|| GltfError::MissingResource(uri.to_string()))
+    }
+}
+
+/// Decode standard base64, returning `None` if `encoded` isn't valid
+fn decode_base64(encoded: &str) -> Option<Vec<u8>>
+{
+    fn value(c: u8) -> Option<u32>
+    {
+        match c
+        {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+            b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let encoded = encoded.trim_end_matches('=').as_bytes();
+    if encoded.len() % 4 == 1
+    {
+        return None;
+    }
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    for group in encoded.chunks(4)
+    {
+        let mut bits = 0;
+        for (i, c) in group.iter().enumerate()
+        {
+            bits |= value(*c)? << (18 - 6 * i);
+        }
+        let bytes = bits.to_be_bytes();
+        decoded.extend_from_slice(&bytes[1..group.len()]);
+    }
+    Some(decoded)
+}
+
+/// Number of components in each element of an accessor
+fn num_components(kind: &str) -> Option<usize>
+{
+    match kind
+    {
+        "SCALAR" => Some(1),
+        "VEC2" => Some(2),
+        "VEC3" => Some(3),
+        "VEC4" | "MAT2" => Some(4),
+        "MAT3" => Some(9),
+        "MAT4" => Some(16),
+        _ => None,
+    }
+}
+
+fn component_size(component_type: u32) -> Option<usize>
+{
+    match component_type
+    {
+        BYTE | UNSIGNED_BYTE => Some(1),
+        SHORT | UNSIGNED_SHORT => Some(2),
+        UNSIGNED_INT | FLOAT => Some(4),
+        _ => None,
+    }
+}
+
+struct Loader<'a>
+{
+    name: &'a str,
+    root: &'a Root,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<'a> Loader<'a>
+{
+    fn load(&self, resource_manager: &ResourceManager) -> Result<GltfScene, GltfError>
+    {
+        let images = self.root.images.iter().enumerate()
+            .map(|(index, image)| self.load_image(index, image, resource_manager))
+            .collect::<Result<Vec<_>, _>>()?;
+        let materials = self.root.materials.iter()
+            .map(|material| self.load_material(material, &images))
+            .collect::<Result<Vec<_>, _>>()?;
+        let meshes = self.root.meshes.iter().enumerate()
+            .map(|(index, mesh)| self.load_mesh(index, mesh, &materials))
+            .collect::<Result<Vec<_>, _>>()?;
+        let nodes = self.root.nodes.iter().enumerate()
+            .map(|(index, node)| self.load_node(index, node))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let roots = match self.root.scenes.get(self.root.scene.unwrap_or(0))
+        {
+            Some(scene) => scene.nodes.clone(),
+            // Without any scenes, every node that isn't a child is a root
+            None => (0..nodes.len()).filter(|index| !nodes.iter().any(|node| node.children.contains(index))).collect(),
+        };
+        if let Some(root) = roots.iter().find(|root| **root >= nodes.len())
+        {
+            return Err(GltfError::OutOfBounds(format!("scene node {}", root)));
+        }
+
+        let animations = self.root.animations.iter().enumerate()
+            .map(|(index, animation)| self.load_animation(index, animation))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(GltfScene { meshes, nodes, roots, materials, images, animations })
+    }
+
+    fn load_image(&self, index: usize, image: &ImageDef, resource_manager: &ResourceManager) -> Result<GltfImage, GltfError>
+    {
+        let (name, data) = match (&image.uri, image.buffer_view)
+        {
+            (Some(uri), _) if !uri.starts_with("data:") =>
+                (resource_name(uri).unwrap_or_else(|| uri.clone()), load_uri(uri, resource_manager)?),
+            (Some(uri), _) => (self.embedded_image_name(index), load_uri(uri, resource_manager)?),
+            (None, Some(buffer_view)) => (self.embedded_image_name(index), self.buffer_view(buffer_view)?.to_vec()),
+            (None, None) => return Err(GltfError::Json(format!("image {} has no uri or buffer view", index))),
+        };
+        Ok(GltfImage { name, mime_type: image.mime_type.clone(), data })
+    }
+
+    fn embedded_image_name(&self, index: usize) -> String
+    {
+        format!("{}#image{}", self.name, index)
+    }
+
+    /// Metallic-roughness materials are approximated with the renderer's material parameters
+    fn load_material(&self, material: &MaterialDef, images: &[GltfImage]) -> Result<Material, GltfError>
+    {
+        let mut result = Material { name: material.name.clone().unwrap_or_default(), ..Material::default() };
+        if let Some(pbr) = &material.pbr_metallic_roughness
+        {
+            let base_color = pbr.base_color_factor.unwrap_or([1.0; 4]);
+            let metallic = pbr.metallic_factor.unwrap_or(1.0);
+            let roughness = pbr.roughness_factor.unwrap_or(1.0);
+
+            result.diffuse = [base_color[0], base_color[1], base_color[2]];
+            // Non-metals reflect about 4% of light, and metals reflect their own color
+            let specular = |color: f32| 0.04 + (color - 0.04) * metallic;
+            result.specular = [specular(base_color[0]), specular(base_color[1]), specular(base_color[2])];
+            result.shininess = (1.0 - roughness) * 128.0;
+
+            if let Some(texture) = &pbr.base_color_texture
+            {
+                let image = self.root.textures.get(texture.index)
+                    .ok_or_else(|| GltfError::OutOfBounds(format!("texture {}", texture.index)))?
+                    .source
+                    .ok_or_else(|| GltfError::Unsupported(format!("texture {} without a source image", texture.index)))?;
+                let image = images.get(image).ok_or_else(|| GltfError::OutOfBounds(format!("image {}", image)))?;
+                result.diffuse_texture = Some(image.name.clone());
+            }
+        }
+        Ok(result)
+    }
+
+    fn load_mesh(&self, index: usize, mesh: &MeshDef, materials: &[Material]) -> Result<Mesh, GltfError>
+    {
+        let name = mesh.name.clone().unwrap_or_else(|| format!("mesh{}", index));
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        let mut submeshes = vec![];
+        // Whether each vertex came from a primitive without normals
+        let mut missing_normals = vec![];
+
+        for primitive in &mesh.primitives
+        {
+            let mode = primitive.mode.unwrap_or(TRIANGLES);
+            if mode != TRIANGLES
+            {
+                return Err(GltfError::Unsupported(format!("primitive mode {} in mesh {}", mode, name)));
+            }
+            if let Some(material) = primitive.material
+            {
+                if material >= materials.len()
+                {
+                    return Err(GltfError::OutOfBounds(format!("material {} in mesh {}", material, name)));
+                }
+            }
+
+            let position = *primitive.attributes.get("POSITION").ok_or_else(|| GltfError::Json(format!("mesh {} has no positions", name)))?;
+            let positions = self.read_floats(position, "VEC3")?;
+            let count = positions.len() / 3;
+            let normals = match primitive.attributes.get("NORMAL")
+            {
+                Some(normal) => self.read_floats(*normal, "VEC3")?,
+                None => vec![0.0; count * 3],
+            };
+            let texcoords = match primitive.attributes.get("TEXCOORD_0")
+            {
+                Some(texcoord) => self.read_floats(*texcoord, "VEC2")?,
+                None => vec![0.0; count * 2],
+            };
+            if normals.len() != count * 3 || texcoords.len() != count * 2
+            {
+                return Err(GltfError::OutOfBounds(format!("attributes of mesh {} have different lengths", name)));
+            }
+
+            missing_normals.extend(std::iter::repeat_n(!primitive.attributes.contains_key("NORMAL"), count));
+            let base_vertex = vertices.len() as u32;
+            vertices.extend((0..count).map(|i| Vertex
+            {
+                position: [positions[3 * i], positions[3 * i + 1], positions[3 * i + 2]],
+                normal: [normals[3 * i], normals[3 * i + 1], normals[3 * i + 2]],
+                texcoord: [texcoords[2 * i], texcoords[2 * i + 1]],
+            }));
+
+            let primitive_indices = match primitive.indices
+            {
+                Some(accessor) => self.read_indices(accessor)?,
+                None => (0..count as u32).collect(),
+            };
+            if primitive_indices.iter().any(|index| *index as usize >= count)
+            {
+                return Err(GltfError::OutOfBounds(format!("index in mesh {} is past its {} vertices", name, count)));
+            }
+            submeshes.push(SubMesh
+            {
+                name: name.clone(),
+                first_index: indices.len() as u32,
+                num_indices: primitive_indices.len() as u32,
+                material: primitive.material,
+            });
+            indices.extend(primitive_indices.iter().map(|index| index + base_vertex));
+        }
+
+        let mut mesh = Mesh { vertices, indices, submeshes, materials: materials.to_vec() };
+        if missing_normals.contains(&true)
+        {
+            mesh.smooth_normals(&missing_normals);
+        }
+        Ok(mesh)
+    }
+
+    fn load_node(&self, index: usize, node: &NodeDef) -> Result<GltfNode, GltfError>
+    {
+        if let Some(mesh) = node.mesh
+        {
+            if mesh >= self.root.meshes.len()
+            {
+                return Err(GltfError::OutOfBounds(format!("mesh {} of node {}", mesh, index)));
+            }
+        }
+        if let Some(child) = node.children.iter().find(|child| **child >= self.root.nodes.len())
+        {
+            return Err(GltfError::OutOfBounds(format!("child {} of node {}", child, index)));
+        }
+
+        let (translation, rotation, scale) = match node.matrix
+        {
+            Some(matrix) => decompose(&matrix),
+            None =>
+                {
+                    let [x, y, z] = node.translation.unwrap_or([0.0; 3]);
+                    let [qx, qy, qz, qw] = node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+                    let [sx, sy, sz] = node.scale.unwrap_or([1.0; 3]);
+                    (vec3(x, y, z), Quaternion::new(qw, qx, qy, qz), vec3(sx, sy, sz))
+                }
+        };
+
+        Ok(GltfNode
+        {
+            name: node.name.clone().unwrap_or_else(|| format!("node{}", index)),
+            mesh: node.mesh,
+            children: node.children.clone(),
+            translation, rotation, scale,
+        })
+    }
+
+    fn load_animation(&self, index: usize, animation: &AnimationDef) -> Result<AnimationClip, GltfError>
+    {
+        let mut channels = Vec::with_capacity(animation.channels.len());
+        for channel in &animation.channels
+        {
+            // Channels without a node are for extensions
+            let target = match channel.target.node
+            {
+                Some(target) => target,
+                None => continue,
+            };
+            if target >= self.root.nodes.len()
+            {
+                return Err(GltfError::OutOfBounds(format!("node {} of animation {}", target, index)));
+            }
+            let sampler = animation.samplers.get(channel.sampler)
+                .ok_or_else(|| GltfError::OutOfBounds(format!("sampler {} of animation {}", channel.sampler, index)))?;
+            let interpolation = match sampler.interpolation.as_deref()
+            {
+                Some("STEP") => Interpolation::Step,
+                Some("LINEAR") | None => Interpolation::Linear,
+                Some("CUBICSPLINE") => Interpolation::CubicSpline,
+                Some(interpolation) => return Err(GltfError::Json(format!("animation {} has interpolation {}", index, interpolation))),
+            };
+            let times = self.read_floats(sampler.input, "SCALAR")?;
+            let keyframes_error = |err: String| GltfError::Json(format!("animation {}: {}", index, err));
+
+            let track = match channel.target.path.as_str()
+            {
+                "translation" | "scale" =>
+                    {
+                        let values = self.read_floats(sampler.output, "VEC3")?.chunks(3).map(|v| vec3(v[0], v[1], v[2])).collect();
+                        let keyframes = Keyframes::new(interpolation, times, values).map_err(keyframes_error)?;
+                        if channel.target.path == "translation" { Track::Translation(keyframes) } else { Track::Scale(keyframes) }
+                    }
+                "rotation" =>
+                    {
+                        // glTF quaternions are stored as x, y, z, w
+                        let values = self.read_floats(sampler.output, "VEC4")?.chunks(4).map(|q| Quaternion::new(q[3], q[0], q[1], q[2])).collect();
+                        Track::Rotation(Keyframes::new(interpolation, times, values).map_err(keyframes_error)?)
+                    }
+                // Morph target weights aren't supported, so those channels are skipped
+                "weights" => continue,
+                path => return Err(GltfError::Json(format!("animation {} targets path {}", index, path))),
+            };
+            channels.push(Channel { target, track });
+        }
+
+        let name = animation.name.clone().unwrap_or_else(|| format!("animation{}", index));
+        Ok(AnimationClip::new(&name, channels))
+    }
+
+    /// Get the bytes of buffer view `index`
+    fn buffer_view(&self, index: usize) -> Result<&[u8], GltfError>
+    {
+        let view = self.root.buffer_views.get(index).ok_or_else(|| GltfError::OutOfBounds(format!("buffer view {}", index)))?;
+        let buffer = self.buffers.get(view.buffer).ok_or_else(|| GltfError::OutOfBounds(format!("buffer {}", view.buffer)))?;
+        view.byte_offset.checked_add(view.byte_length)
+            .and_then(|end| buffer.get(view.byte_offset..end))
+            .ok_or_else(|| GltfError::OutOfBounds(format!("buffer view {} is past the end of buffer {}", index, view.buffer)))
+    }
+
+    /// Get the bytes of each element of accessor `index`, after checking that it is of type `kind`
+    fn accessor_elements(&self, index: usize, kind: &str) -> Result<(&AccessorDef, Vec<&[u8]>), GltfError>
+    {
+        let accessor = self.root.accessors.get(index).ok_or_else(|| GltfError::OutOfBounds(format!("accessor {}", index)))?;
+        if accessor.kind != kind
+        {
+            return Err(GltfError::Json(format!("accessor {} is a {} but should be a {}", index, accessor.kind, kind)));
+        }
+        if accessor.sparse.is_some()
+        {
+            return Err(GltfError::Unsupported(format!("sparse accessor {}", index)));
+        }
+        let component_size = component_size(accessor.component_type)
+            .ok_or_else(|| GltfError::Json(format!("accessor {} has component type {}", index, accessor.component_type)))?;
+        let element_size = component_size * num_components(kind).unwrap_or(1);
+
+        let buffer_view = match accessor.buffer_view
+        {
+            Some(buffer_view) => buffer_view,
+            // Accessors without a buffer view are all zeros
+            // Nothing in the file backs them, so their size is limited to that of the buffers to keep `count` from allocating without bound
+            None =>
+            {
+                let buffers_size: usize = self.buffers.iter().map(|buffer| buffer.len()).sum();
+                if accessor.count.checked_mul(element_size).is_none_or(|size| size > buffers_size)
+                {
+                    return Err(GltfError::OutOfBounds(format!("accessor {} of {} zeroed elements is larger than the buffers", index, accessor.count)));
+                }
+                return Ok((accessor, vec![&ZEROS[..element_size]; accessor.count]));
+            },
+        };
+        let view = self.root.buffer_views.get(buffer_view).ok_or_else(|| GltfError::OutOfBounds(format!("buffer view {}", buffer_view)))?;
+        let stride = view.byte_stride.unwrap_or(element_size);
+        let data = self.buffer_view(buffer_view)?;
+
+        // Check that the last element is within the buffer view before allocating `count` elements
+        // Offsets come from the file, so they are checked for overflow too
+        if let Some(last) = accessor.count.checked_sub(1)
+        {
+            let end = last.checked_mul(stride)
+                .and_then(|offset| offset.checked_add(accessor.byte_offset))
+                .and_then(|offset| offset.checked_add(element_size));
+            if end.is_none_or(|end| end > data.len())
+            {
+                return Err(GltfError::OutOfBounds(format!("accessor {} is past the end of buffer view {}", index, buffer_view)));
+            }
+        }
+        let elements = (0..accessor.count)
+            .map(|i| accessor.byte_offset + i * stride)
+            .map(|start| &data[start..start + element_size])
+            .collect();
+        Ok((accessor, elements))
+    }
+
+    /// Read accessor `index` of type `kind` as floats, converting normalized integers to [0, 1] or [-1, 1]
+    fn read_floats(&self, index: usize, kind: &str) -> Result<Vec<f32>, GltfError>
+    {
+        let (accessor, elements) = self.accessor_elements(index, kind)?;
+        let scale = |max: f32| if accessor.normalized { 1.0 / max } else { 1.0 };
+        let mut floats = Vec::with_capacity(elements.len() * num_components(kind).unwrap_or(1));
+        for element in elements
+        {
+            match accessor.component_type
+            {
+                FLOAT => floats.extend(element.chunks(4).map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))),
+                UNSIGNED_BYTE => floats.extend(element.iter().map(|c| *c as f32 * scale(255.0))),
+                BYTE => floats.extend(element.iter().map(|c| (*c as i8 as f32 * scale(127.0)).max(-1.0))),
+                UNSIGNED_SHORT => floats.extend(element.chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]]) as f32 * scale(65535.0))),
+                SHORT => floats.extend(element.chunks(2).map(|c| (i16::from_le_bytes([c[0], c[1]]) as f32 * scale(32767.0)).max(-1.0))),
+                component_type => return Err(GltfError::Unsupported(format!("accessor {} of floats with component type {}", index, component_type))),
+            }
+        }
+        Ok(floats)
+    }
+
+    fn read_indices(&self, index: usize) -> Result<Vec<u32>, GltfError>
+    {
+        let (accessor, elements) = self.accessor_elements(index, "SCALAR")?;
+        elements.iter().map(|c| match accessor.component_type
+        {
+            UNSIGNED_BYTE => Ok(c[0] as u32),
+            UNSIGNED_SHORT => Ok(u16::from_le_bytes([c[0], c[1]]) as u32),
+            UNSIGNED_INT => Ok(read_u32(c, 0)),
+            component_type => Err(GltfError::Json(format!("index accessor {} has component type {}", index, component_type))),
+        }).collect()
+    }
+}
+
+/// Enough zeros for the largest accessor element, a MAT4 of floats
+static ZEROS: [u8; 64] = [0; 64];
+
+/// Split a column-major transformation matrix into its translation, rotation and scale
+/// Any shear in the matrix is lost
+fn decompose(matrix: &[f32; 16]) -> (Vector3<f32>, Quaternion<f32>, Vector3<f32>)
+{
+    use cgmath::InnerSpace;
+
+    let column = |i: usize| vec3(matrix[4 * i], matrix[4 * i + 1], matrix[4 * i + 2]);
+    let translation = column(3);
+    let scale = vec3(column(0).magnitude(), column(1).magnitude(), column(2).magnitude());
+    let rotation = Matrix3::from_cols(column(0) / scale.x, column(1) / scale.y, column(2) / scale.z);
+    (translation, Quaternion::from(rotation), scale)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use cgmath::{Deg, Matrix4, Rotation3, Quaternion, vec3};
+    use crate::
+    {
+        gfx::gltf::*,
+        resource::manager::ResourceManager,
+    };
+
+    /// One triangle with u16 indices, followed by UVs
+    fn triangle_buffer() -> Vec<u8>
+    {
+        let mut buffer = vec![];
+        for value in &[0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+        {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        for index in &[0u16, 1, 2, 0]
+        {
+            buffer.extend_from_slice(&index.to_le_bytes());
+        }
+        for value in &[0u8, 0, 255, 0, 0, 255, 0, 0]
+        {
+            buffer.push(*value);
+        }
+        buffer
+    }
+
+    fn triangle_json(buffer_uri: Option<&str>) -> String
+    {
+        let uri = buffer_uri.map(|uri| format!(r#""uri": "{}","#, uri)).unwrap_or_default();
+        format!(r#"{{
+            "asset": {{ "version": "2.0" }},
+            "scene": 0,
+            "scenes": [{{ "nodes": [0] }}],
+            "nodes": [
+                {{ "name": "Body", "children": [1] }},
+                {{ "name": "Wheel", "mesh": 0, "translation": [1, 2, 3], "rotation": [0, 0.7071068, 0, 0.7071068] }}
+            ],
+            "meshes": [{{ "name": "Tri", "primitives": [
+                {{ "attributes": {{ "POSITION": 0, "TEXCOORD_0": 2 }}, "indices": 1, "material": 0 }},
+                {{ "attributes": {{ "POSITION": 0 }} }}
+            ] }}],
+            "materials": [{{ "name": "Paint", "pbrMetallicRoughness": {{
+                "baseColorFactor": [1, 0.5, 0, 1], "metallicFactor": 0, "roughnessFactor": 0.5, "baseColorTexture": {{ "index": 0 }}
+            }} }}],
+            "textures": [{{ "source": 0 }}],
+            "images": [{{ "uri": "textures/paint.png" }}],
+            "accessors": [
+                {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }},
+                {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }},
+                {{ "bufferView": 2, "componentType": 5121, "normalized": true, "count": 3, "type": "VEC2" }}
+            ],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }},
+                {{ "buffer": 0, "byteOffset": 44, "byteLength": 6, "byteStride": 2 }}
+            ],
+            "buffers": [{{ {} "byteLength": 50 }}]
+        }}"#, uri)
+    }
+
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8>
+    {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().div_ceil(4) * 4, b' ');
+        let mut bin = bin.to_vec();
+        bin.resize(bin.len().div_ceil(4) * 4, 0);
+
+        let mut glb = vec![];
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        glb
+    }
+
+    fn check_triangle_scene(scene: &GltfScene)
+    {
+        assert_eq!(vec![0], scene.roots);
+        assert_eq!(vec![1], scene.nodes[0].children);
+        assert_eq!(Some(0), scene.nodes[1].mesh);
+        assert_eq!(vec3(1.0, 2.0, 3.0), scene.nodes[1].translation);
+
+        let mesh = &scene.meshes[0];
+        assert_eq!(6, mesh.vertices.len());
+        assert_eq!(vec![0, 1, 2, 3, 4, 5], mesh.indices);
+        assert_eq!(vec![
+            SubMesh { name: "Tri".to_string(), first_index: 0, num_indices: 3, material: Some(0) },
+            SubMesh { name: "Tri".to_string(), first_index: 3, num_indices: 3, material: None },
+        ], mesh.submeshes);
+        assert_eq!([1.0, 0.0, 0.0], mesh.vertices[1].position);
+        assert_eq!([1.0, 0.0], mesh.vertices[1].texcoord);
+        assert_eq!([0.0, 1.0], mesh.vertices[2].texcoord);
+        assert_eq!([0.0, 0.0], mesh.vertices[5].texcoord);
+        // Neither primitive has normals, so they are generated from the faces
+        assert_eq!([0.0, 0.0, 1.0], mesh.vertices[0].normal);
+
+        let material = mesh.material(&mesh.submeshes[0]);
+        assert_eq!("Paint", material.name);
+        assert_eq!([1.0, 0.5, 0.0], material.diffuse);
+        assert_eq!([0.04, 0.04, 0.04], material.specular);
+        assert_eq!(64.0, material.shininess);
+        assert_eq!(Some("paint.png".to_string()), material.diffuse_texture);
+        assert_eq!("paint.png", scene.images[0].name);
+    }
+
+    fn resource_manager() -> ResourceManager
+    {
+        let mut resource_manager = ResourceManager::new();
+        resource_manager.insert_with_name("paint.png".to_string(), vec![1, 2, 3]);
+        resource_manager
+    }
+
+    #[test]
+    fn test_gltf_external_buffer()
+    {
+        let mut resource_manager = resource_manager();
+        resource_manager.insert_with_name("robot.gltf".to_string(), triangle_json(Some("robot.bin")).into_bytes());
+        resource_manager.insert_with_name("robot.bin".to_string(), triangle_buffer());
+
+        let scene = GltfScene::from_resource("robot.gltf", &resource_manager).unwrap();
+        check_triangle_scene(&scene);
+        assert_eq!(vec![1, 2, 3], scene.images[0].data);
+
+        resource_manager.remove_by_name(&"robot.bin".to_string());
+        assert_eq!(Err(GltfError::MissingResource("robot.bin".to_string())), GltfScene::from_resource("robot.gltf", &resource_manager));
+    }
+
+    #[test]
+    fn test_glb()
+    {
+        let mut resource_manager = resource_manager();
+        resource_manager.insert_with_name("robot.glb".to_string(), glb(&triangle_json(None), &triangle_buffer()));
+        check_triangle_scene(&GltfScene::from_resource("robot.glb", &resource_manager).unwrap());
+
+        let mut truncated = glb(&triangle_json(None), &triangle_buffer());
+        truncated.truncate(40);
+        assert!(matches!(GltfScene::from_slice("robot.glb", &truncated, &resource_manager), Err(GltfError::Glb(_))));
+
+        // The JSON chunk's length claims far more than the file holds
+        let mut huge_chunk = glb(&triangle_json(None), &triangle_buffer());
+        huge_chunk[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(GltfScene::from_slice("robot.glb", &huge_chunk, &resource_manager), Err(GltfError::Glb(_))));
+    }
+
+    #[test]
+    fn test_data_uri()
+    {
+        assert_eq!(Some(b"glTF data".to_vec()), decode_base64("Z2xURiBkYXRh"));
+        assert_eq!(Some(b"glTF dat".to_vec()), decode_base64("Z2xURiBkYXQ="));
+        assert_eq!(None, decode_base64("Z2x*"));
+
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "buffers": [{ "uri": "data:application/octet-stream;base64,Z2xURiBkYXRh", "byteLength": 9 }],
+            "bufferViews": [{ "buffer": 0, "byteLength": 9 }],
+            "images": [{ "bufferView": 0, "mimeType": "image/png" }]
+        }"#;
+        let scene = GltfScene::from_slice("robot.gltf", json.as_bytes(), &ResourceManager::new()).unwrap();
+        assert_eq!(GltfImage { name: "robot.gltf#image0".to_string(), mime_type: Some("image/png".to_string()), data: b"glTF data".to_vec() }, scene.images[0]);
+    }
+
+    #[test]
+    fn test_unsupported()
+    {
+        let json = triangle_json(Some("robot.bin")).replace(r#""indices": 1,"#, r#""indices": 1, "mode": 1,"#);
+        let mut resource_manager = resource_manager();
+        resource_manager.insert_with_name("robot.bin".to_string(), triangle_buffer());
+        assert!(matches!(GltfScene::from_slice("robot.gltf", json.as_bytes(), &resource_manager), Err(GltfError::Unsupported(_))));
+
+        let json = triangle_json(Some("robot.bin")).replace(r#""version": "2.0""#, r#""version": "1.0""#);
+        assert!(matches!(GltfScene::from_slice("robot.gltf", json.as_bytes(), &resource_manager), Err(GltfError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_out_of_bounds()
+    {
+        let mut resource_manager = resource_manager();
+        resource_manager.insert_with_name("robot.bin".to_string(), triangle_buffer());
+
+        let json = triangle_json(Some("robot.bin")).replace(r#"{ "bufferView": 1,"#, r#"{ "bufferView": 7,"#);
+        assert_eq!(Err(GltfError::OutOfBounds("buffer view 7".to_string())), GltfScene::from_slice("robot.gltf", json.as_bytes(), &resource_manager));
+
+        let json = triangle_json(Some("robot.bin")).replace(r#""byteOffset": 36, "byteLength": 6"#, r#""byteOffset": 36, "byteLength": 4"#);
+        assert!(matches!(GltfScene::from_slice("robot.gltf", json.as_bytes(), &resource_manager), Err(GltfError::OutOfBounds(_))));
+
+        // Offsets and counts large enough to overflow are errors rather than panics
+        let json = triangle_json(Some("robot.bin")).replace(r#""byteOffset": 36, "byteLength": 6"#, &format!(r#""byteOffset": {}, "byteLength": 6"#, usize::MAX));
+        assert!(matches!(GltfScene::from_slice("robot.gltf", json.as_bytes(), &resource_manager), Err(GltfError::OutOfBounds(_))));
+        let json = triangle_json(Some("robot.bin")).replace(r#""componentType": 5123, "count": 3"#, &format!(r#""componentType": 5123, "byteOffset": {}, "count": 3"#, usize::MAX - 1));
+        assert!(matches!(GltfScene::from_slice("robot.gltf", json.as_bytes(), &resource_manager), Err(GltfError::OutOfBounds(_))));
+        let json = triangle_json(Some("robot.bin")).replace(r#""componentType": 5123, "count": 3"#, &format!(r#""componentType": 5123, "count": {}"#, usize::MAX / 2));
+        assert!(matches!(GltfScene::from_slice("robot.gltf", json.as_bytes(), &resource_manager), Err(GltfError::OutOfBounds(_))));
+        // An accessor of zeros can't claim more elements than fit in the buffers
+        let json = triangle_json(Some("robot.bin")).replace(r#"{ "bufferView": 1, "componentType": 5123, "count": 3"#, &format!(r#"{{ "componentType": 5123, "count": {}"#, usize::MAX / 2));
+        assert!(matches!(GltfScene::from_slice("robot.gltf", json.as_bytes(), &resource_manager), Err(GltfError::OutOfBounds(_))));
+    }
+
+    #[test]
+    fn test_animation()
+    {
+        let mut buffer = vec![];
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        for value in &[0.0f32, 2.0, 0.0, 0.0, 0.0, 1.0, 0.0, half, 0.0, half]
+        {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut resource_manager = ResourceManager::new();
+        resource_manager.insert_with_name("anim.bin".to_string(), buffer);
+
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "nodes": [{ "name": "Lidar" }],
+            "animations": [{ "name": "Spin",
+                "channels": [
+                    { "sampler": 0, "target": { "node": 0, "path": "rotation" } },
+                    { "sampler": 0, "target": { "node": 0, "path": "weights" } }
+                ],
+                "samplers": [{ "input": 0, "output": 1 }]
+            }],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR" },
+                { "bufferView": 1, "componentType": 5126, "count": 2, "type": "VEC4" }
+            ],
+            "bufferViews": [
+                { "buffer": 0, "byteLength": 8 },
+                { "buffer": 0, "byteOffset": 8, "byteLength": 32 }
+            ],
+            "buffers": [{ "uri": "anim.bin", "byteLength": 40 }]
+        }"#;
+        let scene = GltfScene::from_slice("lidar.gltf", json.as_bytes(), &resource_manager).unwrap();
+        let clip = &scene.animations[0];
+        assert_eq!("Spin", clip.name);
+        assert_eq!(2.0, clip.duration());
+        assert_eq!(1, clip.channels().len());
+        assert_eq!(0, clip.channels()[0].target);
+        match &clip.channels()[0].track
+        {
+            Track::Rotation(keyframes) => assert_eq!(Quaternion::new(half, 0.0, half, 0.0), keyframes.sample(2.0)),
+            track => panic!("expected a rotation track, got {:?}", track),
+        }
+
+        let json = json.replace(r#""node": 0, "path": "rotation""#, r#""node": 1, "path": "rotation""#);
+        assert!(matches!(GltfScene::from_slice("lidar.gltf", json.as_bytes(), &resource_manager), Err(GltfError::OutOfBounds(_))));
+    }
+
+    #[test]
+    fn test_decompose()
+    {
+        let rotation = Quaternion::from_angle_y(Deg(90.0));
+        let matrix = Matrix4::from_translation(vec3(1.0, 2.0, 3.0)) * Matrix4::from(rotation) * Matrix4::from_nonuniform_scale(2.0, 3.0, 4.0);
+        let columns: &[f32; 16] = matrix.as_ref();
+        let (translation, decomposed, scale) = decompose(columns);
+
+        assert_eq!(vec3(1.0, 2.0, 3.0), translation);
+        assert!(approx_eq!(f32, 2.0, scale.x, epsilon = 1e-6) && approx_eq!(f32, 3.0, scale.y, epsilon = 1e-6) && approx_eq!(f32, 4.0, scale.z, epsilon = 1e-6));
+        let (expected, actual) = (rotation * vec3(1.0, 0.0, 0.0), decomposed * vec3(1.0, 0.0, 0.0));
+        assert!(approx_eq!(f32, expected.x, actual.x, epsilon = 1e-6) && approx_eq!(f32, expected.z, actual.z, epsilon = 1e-6));
+    }
+}
@@ -0,0 +1,231 @@
+//! RGB images, stored as binary PPM files, and perceptual comparison between them
+
+use std::fmt;
+
+/// 8-bit RGB image, with rows from top to bottom
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image
+{
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+/// How far an image can be from its reference and still pass
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tolerance
+{
+    /// Largest CIE76 color difference, in Lab units, that a pixel can have without counting as different
+    /// A difference of about 2.3 is just noticeable
+    pub max_delta_e: f32,
+    /// Fraction of pixels that can be different, to allow for differences in rasterization along edges
+    pub max_differing: f32,
+}
+
+/// Result of comparing an image against a reference
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageDiff
+{
+    pub differing: usize,
+    pub total: usize,
+    pub max_delta_e: f32,
+    pub mean_delta_e: f32,
+    /// Pixels over the tolerance in red, on top of a faded copy of the reference
+    pub diff_image: Image,
+}
+
+impl ImageDiff
+{
+    pub fn within(&self, tolerance: Tolerance) -> bool
+    {
+        self.differing as f32 <= tolerance.max_differing * self.total as f32
+    }
+}
+
+impl fmt::Display for ImageDiff
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{} of {} pixels differ ({:.2}%), max delta E {:.1}, mean delta E {:.2}",
+               self.differing, self.total, 100.0 * self.differing as f32 / self.total.max(1) as f32, self.max_delta_e, self.mean_delta_e)
+    }
+}
+
+impl Image
+{
+    pub fn new(width: usize, height: usize, color: [u8; 3]) -> Image
+    {
+        Image { width, height, pixels: vec![color; width * height] }
+    }
+
+    /// Convert `RGBA` pixels as returned by `read_pixels`, with rows from bottom to top
+    pub fn from_gl_pixels(width: usize, height: usize, pixels: &[u8]) -> Image
+    {
+        let mut image = Image::new(width, height, [0; 3]);
+        for (y, row) in pixels.chunks(width * 4).take(height).enumerate()
+        {
+            for (x, pixel) in row.chunks(4).enumerate()
+            {
+                image.pixels[(height - 1 - y) * width + x] = [pixel[0], pixel[1], pixel[2]];
+            }
+        }
+        image
+    }
+
+    /// Parse a binary (P6) PPM file with a max value of 255
+    pub fn from_ppm(bytes: &[u8]) -> Result<Image, String>
+    {
+        // The header is 4 whitespace separated tokens, and may have comments
+        let mut tokens = vec![];
+        let mut pos = 0;
+        while tokens.len() < 4
+        {
+            while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'#')
+            {
+                if bytes[pos] == b'#'
+                {
+                    while pos < bytes.len() && bytes[pos] != b'\n' { pos += 1; }
+                }
+                else
+                {
+                    pos += 1;
+                }
+            }
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() { pos += 1; }
+            if start == pos
+            {
+                return Err("Truncated PPM header".to_string());
+            }
+            tokens.push(String::from_utf8_lossy(&bytes[start..pos]).to_string());
+        }
+        // A single whitespace character separates the header from the pixels
+        pos += 1;
+
+        let number = |token: &str| token.parse::<usize>().map_err(|_| format!("Invalid PPM header value {}", token));
+        if tokens[0] != "P6"
+        {
+            return Err(format!("Expected a binary PPM (P6), got {}", tokens[0]));
+        }
+        let (width, height) = (number(&tokens[1])?, number(&tokens[2])?);
+        if number(&tokens[3])? != 255
+        {
+            return Err(format!("Only PPMs with a max value of 255 are supported, got {}", tokens[3]));
+        }
+        let data = bytes.get(pos..pos + width * height * 3).ok_or("Truncated PPM data")?;
+        Ok(Image { width, height, pixels: data.chunks(3).map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect() })
+    }
+
+    pub fn to_ppm(&self) -> Vec<u8>
+    {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        ppm.extend(self.pixels.iter().flatten());
+        ppm
+    }
+
+    /// Compare against `reference`, counting the pixels that differ by more than `tolerance.max_delta_e`
+    pub fn compare(&self, reference: &Image, tolerance: Tolerance) -> Result<ImageDiff, String>
+    {
+        if (self.width, self.height) != (reference.width, reference.height)
+        {
+            return Err(format!("Image is {}x{}, but the reference is {}x{}", self.width, self.height, reference.width, reference.height));
+        }
+
+        let mut diff = ImageDiff { differing: 0, total: self.pixels.len(), max_delta_e: 0.0, mean_delta_e: 0.0, diff_image: reference.clone() };
+        let mut delta_e_sum = 0.0;
+        for (i, (pixel, reference_pixel)) in self.pixels.iter().zip(&reference.pixels).enumerate()
+        {
+            let delta_e = delta_e(*pixel, *reference_pixel);
+            delta_e_sum += delta_e;
+            diff.max_delta_e = diff.max_delta_e.max(delta_e);
+            if delta_e > tolerance.max_delta_e
+            {
+                diff.differing += 1;
+                diff.diff_image.pixels[i] = [255, 0, 0];
+            }
+            else
+            {
+                diff.diff_image.pixels[i] = reference_pixel.map(|channel| channel / 4);
+            }
+        }
+        diff.mean_delta_e = delta_e_sum / diff.total.max(1) as f32;
+        Ok(diff)
+    }
+}
+
+/// Convert an sRGB color to CIE L*a*b* with a D65 white point
+fn to_lab(rgb: [u8; 3]) -> [f32; 3]
+{
+    let linear = rgb.map(|channel|
+        {
+            let c = channel as f32 / 255.0;
+            if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        });
+    let x = (0.4124 * linear[0] + 0.3576 * linear[1] + 0.1805 * linear[2]) / 0.95047;
+    let y = 0.2126 * linear[0] + 0.7152 * linear[1] + 0.0722 * linear[2];
+    let z = (0.0193 * linear[0] + 0.1192 * linear[1] + 0.9505 * linear[2]) / 1.08883;
+    let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// CIE76 difference between two sRGB colors
+fn delta_e(a: [u8; 3], b: [u8; 3]) -> f32
+{
+    if a == b
+    {
+        return 0.0;
+    }
+    let (a, b) = (to_lab(a), to_lab(b));
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::gfx::golden::image::*;
+
+    #[test]
+    fn test_ppm()
+    {
+        let mut image = Image::new(3, 2, [10, 20, 30]);
+        image.pixels[4] = [255, 0, 128];
+        assert_eq!(image, Image::from_ppm(&image.to_ppm()).expect("ppm"));
+
+        let commented = b"P6 # comment\n2 1\n255\n\x01\x02\x03\x04\x05\x06";
+        assert_eq!(vec![[1, 2, 3], [4, 5, 6]], Image::from_ppm(commented).expect("ppm").pixels);
+        assert!(Image::from_ppm(b"P3\n1 1\n255\n0 0 0").is_err());
+        assert!(Image::from_ppm(b"P6\n2 2\n255\n\x00").is_err());
+    }
+
+    #[test]
+    fn test_from_gl_pixels()
+    {
+        // Bottom row first
+        let image = Image::from_gl_pixels(1, 2, &[1, 1, 1, 255, 2, 2, 2, 255]);
+        assert_eq!(vec![[2, 2, 2], [1, 1, 1]], image.pixels);
+    }
+
+    #[test]
+    fn test_compare()
+    {
+        let tolerance = Tolerance { max_delta_e: 5.0, max_differing: 0.25 };
+        let reference = Image::new(2, 2, [100, 100, 100]);
+        let mut image = reference.clone();
+        // Barely noticeable
+        image.pixels[0] = [101, 100, 100];
+        let diff = image.compare(&reference, tolerance).expect("diff");
+        assert_eq!(0, diff.differing);
+        assert!(diff.within(tolerance));
+
+        image.pixels[1] = [200, 0, 0];
+        let diff = image.compare(&reference, tolerance).expect("diff");
+        assert_eq!(1, diff.differing);
+        assert_eq!([255, 0, 0], diff.diff_image.pixels[1]);
+        assert!(diff.within(tolerance));
+
+        image.pixels[2] = [0, 0, 200];
+        assert!(!image.compare(&reference, tolerance).expect("diff").within(tolerance));
+        assert!(image.compare(&Image::new(1, 1, [0; 3]), tolerance).is_err());
+    }
+}
@@ -0,0 +1,52 @@
+//! Golden image tests, which render canned scenes and compare them against checked-in reference images
+//!
+//! Natively, scenes are drawn by a `RecordingBackend` and replayed through the CPU `Rasterizer`.
+//! The WASM tests draw the same scenes with WebGL, which is software rendered in headless browsers.
+//! References are binary PPMs in `tests/golden`, and are regenerated by running the native tests
+//! with `UPDATE_GOLDEN=1` set
+
+/// Include the reference image `name` from `tests/golden`
+macro_rules! golden_reference
+{
+    ($name:expr) =>
+    {
+        include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/", $name, ".ppm"))
+    };
+}
+
+pub mod image;
+pub mod rasterizer;
+pub mod scene;
+
+use crate::gfx::golden::image::{Image, Tolerance};
+
+/// Directory with the reference images
+pub const REFERENCE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+/// Panic unless `image` is within `tolerance` of `reference`, the contents of the reference image `name`
+///
+/// Natively, `UPDATE_GOLDEN` makes this write `image` as the new reference instead, and failures
+/// write the image and a diff to `target/golden` to look at
+pub fn assert_golden(name: &str, reference: &[u8], image: &Image, tolerance: Tolerance)
+{
+    #[cfg(not(target_arch = "wasm32"))]
+    if std::env::var_os("UPDATE_GOLDEN").is_some()
+    {
+        std::fs::write(format!("{}/{}.ppm", REFERENCE_DIR, name), image.to_ppm()).expect("reference image written");
+        return;
+    }
+
+    let reference = Image::from_ppm(reference).unwrap_or_else(|err| panic!("invalid reference image {}: {}", name, err));
+    let diff = image.compare(&reference, tolerance).unwrap_or_else(|err| panic!("{}: {}", name, err));
+    if !diff.within(tolerance)
+    {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/target/golden");
+            std::fs::create_dir_all(dir).expect("golden output directory");
+            std::fs::write(format!("{}/{}.actual.ppm", dir, name), image.to_ppm()).expect("actual image written");
+            std::fs::write(format!("{}/{}.diff.ppm", dir, name), diff.diff_image.to_ppm()).expect("diff image written");
+        }
+        panic!("{} doesn't match its reference image: {}\nIf the change is intended, update the references with UPDATE_GOLDEN=1 cargo test golden", name, diff);
+    }
+}
@@ -0,0 +1,692 @@
+//! CPU rasterizer that draws the calls recorded by a `RecordingBackend`
+//!
+//! Only what `gfx` uses is supported: indexed triangles, float and normalized byte attributes,
+//! uniform blocks, 2D textures, depth testing and back face culling. Shaders are Rust ports of
+//! the GLSL ones that implement `SoftwareShader`
+
+use std::collections::HashMap;
+use cgmath::{Vector2, Vector4};
+use crate::gfx::
+{
+    Context,
+    backend::{*, recording::{GlCall, RecordingBackend}},
+    gl_object::uniform::UniformValue,
+    golden::image::Image,
+};
+
+/// Rust port of a shader program, run by a `Rasterizer` for each vertex and fragment
+pub trait SoftwareShader
+{
+    /// Number of floats that the vertex shader passes to the fragment shader
+    fn varyings(&self) -> usize;
+    /// Returns the clip space position of a vertex and writes its outputs to `varyings`
+    /// `attribs` are indexed by attribute location
+    fn vertex(&self, env: &ShaderEnv, attribs: &[Vector4<f32>], varyings: &mut [f32]) -> Vector4<f32>;
+    /// Returns the RGBA color of a fragment from its interpolated `varyings`
+    fn fragment(&self, env: &ShaderEnv, varyings: &[f32]) -> [f32; 4];
+}
+
+/// Uniforms, uniform blocks and textures of the program being drawn with
+pub struct ShaderEnv<'a>
+{
+    state: &'a State,
+    backend: &'a RecordingBackend,
+    program: ProgramId,
+}
+
+impl ShaderEnv<'_>
+{
+    /// Contents of the buffer range bound to the uniform block `name`
+    pub fn block(&self, name: &str) -> &[u8]
+    {
+        let index = self.backend.uniform_block_index(self.program, name);
+        assert_ne!(Context::INVALID_INDEX, index, "no uniform block {}", name);
+        let binding = self.state.block_bindings.get(&(self.program, index)).copied().unwrap_or(0);
+        let (buffer, offset, size) = *self.state.uniform_ranges.get(&binding).unwrap_or_else(|| panic!("nothing bound to uniform block {}", name));
+        &self.state.buffers[&buffer][offset..offset + size]
+    }
+
+    /// Value of the default block uniform `name`, if it has been set
+    pub fn uniform(&self, name: &str) -> Option<&UniformValue>
+    {
+        self.state.uniforms.get(&(self.program, name.to_string()))
+    }
+
+    /// Sample the 2D texture bound to the unit of the sampler uniform `sampler` at `uv`
+    /// Level 0 is always sampled with the magnification filter, since there are no derivatives to pick a mip level with
+    pub fn sample(&self, sampler: &str, uv: Vector2<f32>) -> [f32; 4]
+    {
+        let unit = match self.uniform(sampler)
+        {
+            Some(UniformValue::Int(_, value)) => value[0] as u32,
+            // Uniforms start out as 0
+            _ => 0,
+        };
+        match self.state.texture_units.get(&unit).and_then(|texture| self.state.textures.get(texture))
+        {
+            Some(texture) => texture.sample(uv),
+            // Sampling an incomplete texture returns black
+            None => [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct AttribPointer
+{
+    buffer: BufferId,
+    size: i32,
+    data_type: u32,
+    normalized: bool,
+    stride: i32,
+    offset: i32,
+}
+
+#[derive(Default)]
+struct VertexArrayState
+{
+    attribs: HashMap<u32, AttribPointer>,
+    enabled: Vec<u32>,
+    element_buffer: Option<BufferId>,
+}
+
+struct TextureState
+{
+    width: usize,
+    height: usize,
+    channels: usize,
+    row_stride: usize,
+    data: Vec<u8>,
+    wrap_s: u32,
+    wrap_t: u32,
+    mag_filter: u32,
+}
+
+impl TextureState
+{
+    fn texel(&self, x: i32, y: i32) -> [f32; 4]
+    {
+        let wrap = |coord: i32, size: usize, mode: u32| match mode
+        {
+            Context::REPEAT => coord.rem_euclid(size as i32) as usize,
+            Context::MIRRORED_REPEAT =>
+            {
+                let coord = coord.rem_euclid(2 * size as i32) as usize;
+                if coord < size { coord } else { 2 * size - 1 - coord }
+            },
+            _ => coord.clamp(0, size as i32 - 1) as usize,
+        };
+        let start = wrap(y, self.height, self.wrap_t) * self.row_stride + wrap(x, self.width, self.wrap_s) * self.channels;
+        let mut texel = [0.0, 0.0, 0.0, 1.0];
+        if let Some(data) = self.data.get(start..start + self.channels)
+        {
+            for (channel, value) in texel.iter_mut().zip(data)
+            {
+                *channel = *value as f32 / 255.0;
+            }
+        }
+        texel
+    }
+
+    fn sample(&self, uv: Vector2<f32>) -> [f32; 4]
+    {
+        let (x, y) = (uv.x * self.width as f32, uv.y * self.height as f32);
+        if self.mag_filter == Context::NEAREST
+        {
+            return self.texel(x.floor() as i32, y.floor() as i32);
+        }
+        // Blend the 4 texels around the sample point, with texel centers at half coordinates
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor() as i32, y.floor() as i32);
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let lerp = |a: [f32; 4], b: [f32; 4], t: f32| [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * t);
+        lerp(
+            lerp(self.texel(x0, y0), self.texel(x0 + 1, y0), fx),
+            lerp(self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1), fx),
+            fy)
+    }
+}
+
+/// GL state that the draw calls depend on
+struct State
+{
+    buffers: HashMap<BufferId, Vec<u8>>,
+    buffer_bindings: HashMap<u32, BufferId>,
+    // The default vertex array is `None`
+    vertex_arrays: HashMap<Option<VertexArrayId>, VertexArrayState>,
+    vertex_array_binding: Option<VertexArrayId>,
+    textures: HashMap<TextureId, TextureState>,
+    // 2D texture bound to each texture unit
+    texture_units: HashMap<u32, TextureId>,
+    active_texture: u32,
+    unpack_alignment: usize,
+    // Buffer, offset and size bound to each uniform buffer binding point
+    uniform_ranges: HashMap<u32, (BufferId, usize, usize)>,
+    block_bindings: HashMap<(ProgramId, u32), u32>,
+    uniforms: HashMap<(ProgramId, String), UniformValue>,
+    program: Option<ProgramId>,
+    viewport: (i32, i32, i32, i32),
+    clear_color: [f32; 4],
+    depth_test: bool,
+    cull_face: bool,
+}
+
+impl State
+{
+    fn bound_buffer(&mut self, target: u32) -> &mut Vec<u8>
+    {
+        let buffer = match target
+        {
+            Context::ELEMENT_ARRAY_BUFFER => self.vertex_arrays.entry(self.vertex_array_binding).or_default().element_buffer,
+            _ => self.buffer_bindings.get(&target).copied(),
+        };
+        buffer.and_then(move |buffer| self.buffers.get_mut(&buffer)).unwrap_or_else(|| panic!("no buffer bound to {:#x}", target))
+    }
+
+    fn bound_texture(&mut self) -> Option<&mut TextureState>
+    {
+        let texture = self.texture_units.get(&self.active_texture)?;
+        self.textures.get_mut(texture)
+    }
+
+    /// Attributes of the `index`th vertex of the bound vertex array, indexed by location
+    fn fetch_attribs(&self, index: u32) -> Vec<Vector4<f32>>
+    {
+        let vertex_array = &self.vertex_arrays[&self.vertex_array_binding];
+        let locations = vertex_array.enabled.iter().max().map_or(0, |location| location + 1);
+        let mut attribs = vec![Vector4::new(0.0, 0.0, 0.0, 1.0); locations as usize];
+        for location in &vertex_array.enabled
+        {
+            let pointer = vertex_array.attribs.get(location).unwrap_or_else(|| panic!("attribute {} enabled without a pointer", location));
+            let component_size = match pointer.data_type
+            {
+                Context::FLOAT => 4,
+                Context::UNSIGNED_BYTE => 1,
+                data_type => panic!("unsupported attribute type {:#x}", data_type),
+            };
+            let stride = if pointer.stride == 0 { pointer.size * component_size } else { pointer.stride };
+            let start = (pointer.offset + index as i32 * stride) as usize;
+            let data = &self.buffers[&pointer.buffer];
+            for component in 0..pointer.size as usize
+            {
+                let bytes = &data[start + component * component_size as usize..];
+                attribs[*location as usize][component] = match (pointer.data_type, pointer.normalized)
+                {
+                    (Context::FLOAT, _) => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                    (_, true) => bytes[0] as f32 / 255.0,
+                    (_, false) => bytes[0] as f32,
+                };
+            }
+        }
+        attribs
+    }
+}
+
+/// A vertex after the vertex shader has run
+#[derive(Clone)]
+struct ClipVertex
+{
+    position: Vector4<f32>,
+    varyings: Vec<f32>,
+}
+
+impl ClipVertex
+{
+    fn lerp(&self, other: &ClipVertex, t: f32) -> ClipVertex
+    {
+        ClipVertex
+        {
+            position: self.position + (other.position - self.position) * t,
+            varyings: self.varyings.iter().zip(&other.varyings).map(|(a, b)| a + (b - a) * t).collect(),
+        }
+    }
+}
+
+/// Clip `polygon` to the side of a plane where `distance` is positive
+fn clip_polygon(polygon: Vec<ClipVertex>, distance: impl Fn(&Vector4<f32>) -> f32) -> Vec<ClipVertex>
+{
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, current) in polygon.iter().enumerate()
+    {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let (current_distance, next_distance) = (distance(&current.position), distance(&next.position));
+        if current_distance >= 0.0
+        {
+            clipped.push(current.clone());
+        }
+        if (current_distance >= 0.0) != (next_distance >= 0.0)
+        {
+            clipped.push(current.lerp(next, current_distance / (current_distance - next_distance)));
+        }
+    }
+    clipped
+}
+
+/// A vertex in window coordinates
+struct WindowVertex
+{
+    x: f32,
+    y: f32,
+    z: f32,
+    inv_w: f32,
+}
+
+/// Software renderer for the calls recorded by a `RecordingBackend`
+///
+/// The framebuffer is RGBA8 with a depth buffer, and its rows go from bottom to top like GL's
+pub struct Rasterizer
+{
+    width: usize,
+    height: usize,
+    color: Vec<u8>,
+    depth: Vec<f32>,
+    state: State,
+}
+
+impl Rasterizer
+{
+    /// Create a rasterizer with a black `width` x `height` framebuffer, which is also the starting viewport
+    pub fn new(width: usize, height: usize) -> Rasterizer
+    {
+        Rasterizer
+        {
+            width,
+            height,
+            color: [0, 0, 0, 255].repeat(width * height),
+            depth: vec![1.0; width * height],
+            state: State
+            {
+                buffers: HashMap::new(),
+                buffer_bindings: HashMap::new(),
+                vertex_arrays: HashMap::new(),
+                vertex_array_binding: None,
+                textures: HashMap::new(),
+                texture_units: HashMap::new(),
+                active_texture: 0,
+                unpack_alignment: 4,
+                uniform_ranges: HashMap::new(),
+                block_bindings: HashMap::new(),
+                uniforms: HashMap::new(),
+                program: None,
+                viewport: (0, 0, width as i32, height as i32),
+                clear_color: [0.0; 4],
+                depth_test: false,
+                cull_face: false,
+            },
+        }
+    }
+
+    /// Carry out the calls `backend` has recorded since the calls were last taken, drawing with `shader`
+    pub fn run(&mut self, backend: &RecordingBackend, shader: &dyn SoftwareShader)
+    {
+        for call in backend.take_calls()
+        {
+            self.execute(backend, shader, call);
+        }
+    }
+
+    pub fn image(&self) -> Image
+    {
+        Image::from_gl_pixels(self.width, self.height, &self.color)
+    }
+
+    fn execute(&mut self, backend: &RecordingBackend, shader: &dyn SoftwareShader, call: GlCall)
+    {
+        let state = &mut self.state;
+        match call
+        {
+            GlCall::CreateBuffer(buffer) => { state.buffers.insert(buffer, vec![]); },
+            GlCall::DeleteBuffer(buffer) => { state.buffers.remove(&buffer); },
+            GlCall::BindBuffer(Context::ELEMENT_ARRAY_BUFFER, buffer) => state.vertex_arrays.entry(state.vertex_array_binding).or_default().element_buffer = buffer,
+            GlCall::BindBuffer(target, Some(buffer)) => { state.buffer_bindings.insert(target, buffer); },
+            GlCall::BindBuffer(target, None) => { state.buffer_bindings.remove(&target); },
+            GlCall::BufferData(target, data, _) => *state.bound_buffer(target) = data,
+            GlCall::BufferDataSize(target, size, _) => *state.bound_buffer(target) = vec![0; size as usize],
+            GlCall::BufferSubData(target, offset, data) =>
+            {
+                let offset = offset as usize;
+                let buffer = state.bound_buffer(target);
+                assert!(offset + data.len() <= buffer.len(), "buffer_sub_data out of range");
+                buffer[offset..offset + data.len()].copy_from_slice(&data);
+            },
+            GlCall::BindBufferRange(target, index, buffer, offset, size) =>
+            {
+                // Binding a range also binds the buffer to the generic binding point
+                match buffer
+                {
+                    Some(buffer) =>
+                    {
+                        state.buffer_bindings.insert(target, buffer);
+                        state.uniform_ranges.insert(index, (buffer, offset as usize, size as usize));
+                    },
+                    None => { state.uniform_ranges.remove(&index); },
+                }
+            },
+
+            GlCall::CreateVertexArray(vertex_array) => { state.vertex_arrays.insert(Some(vertex_array), VertexArrayState::default()); },
+            GlCall::DeleteVertexArray(vertex_array) => { state.vertex_arrays.remove(&Some(vertex_array)); },
+            GlCall::BindVertexArray(vertex_array) => state.vertex_array_binding = vertex_array,
+            GlCall::VertexAttribPointer { index, size, data_type, normalized, stride, offset } =>
+            {
+                let buffer = *state.buffer_bindings.get(&Context::ARRAY_BUFFER).expect("array buffer bound for vertex_attrib_pointer");
+                state.vertex_arrays.entry(state.vertex_array_binding).or_default()
+                    .attribs.insert(index, AttribPointer { buffer, size, data_type, normalized, stride, offset });
+            },
+            GlCall::EnableVertexAttribArray(index) =>
+            {
+                let enabled = &mut state.vertex_arrays.entry(state.vertex_array_binding).or_default().enabled;
+                if !enabled.contains(&index)
+                {
+                    enabled.push(index);
+                }
+            },
+
+            GlCall::CreateTexture(texture) =>
+            {
+                state.textures.insert(texture, TextureState
+                {
+                    width: 0,
+                    height: 0,
+                    channels: 4,
+                    row_stride: 0,
+                    data: vec![],
+                    wrap_s: Context::REPEAT,
+                    wrap_t: Context::REPEAT,
+                    mag_filter: Context::LINEAR,
+                });
+            },
+            GlCall::DeleteTexture(texture) => { state.textures.remove(&texture); },
+            GlCall::BindTexture(Context::TEXTURE_2D, Some(texture)) => { state.texture_units.insert(state.active_texture, texture); },
+            GlCall::BindTexture(Context::TEXTURE_2D, None) => { state.texture_units.remove(&state.active_texture); },
+            GlCall::ActiveTexture(texture) => state.active_texture = texture - Context::TEXTURE0,
+            GlCall::TexParameteri(Context::TEXTURE_2D, pname, param) =>
+            {
+                let texture = state.bound_texture().expect("texture bound for tex_parameteri");
+                match pname
+                {
+                    Context::TEXTURE_WRAP_S => texture.wrap_s = param as u32,
+                    Context::TEXTURE_WRAP_T => texture.wrap_t = param as u32,
+                    Context::TEXTURE_MAG_FILTER => texture.mag_filter = param as u32,
+                    _ => {},
+                }
+            },
+            GlCall::TexImage2d { target: Context::TEXTURE_2D, level: 0, width, height, format, data, .. } =>
+            {
+                let alignment = state.unpack_alignment;
+                let channels = match format
+                {
+                    Context::RGB => 3,
+                    Context::RGBA => 4,
+                    format => panic!("unsupported texture format {:#x}", format),
+                };
+                let texture = state.bound_texture().expect("texture bound for tex_image_2d");
+                texture.width = width as usize;
+                texture.height = height as usize;
+                texture.channels = channels;
+                // Rows start at multiples of UNPACK_ALIGNMENT
+                texture.row_stride = (width as usize * channels).div_ceil(alignment) * alignment;
+                texture.data = data.unwrap_or_default();
+            },
+
+            GlCall::Uniform(location, value) =>
+            {
+                let name = backend.uniform_name(location).expect("uniform location of a live program");
+                state.uniforms.insert(name, value);
+            },
+            GlCall::UniformBlockBinding(program, index, binding) => { state.block_bindings.insert((program, index), binding); },
+            GlCall::UseProgram(program) => state.program = program,
+
+            GlCall::Viewport(x, y, width, height) => state.viewport = (x, y, width, height),
+            GlCall::PixelStorei(Context::UNPACK_ALIGNMENT, param) => state.unpack_alignment = param as usize,
+            GlCall::Enable(Context::DEPTH_TEST) => state.depth_test = true,
+            GlCall::Enable(Context::CULL_FACE) => state.cull_face = true,
+            GlCall::ClearColor(red, green, blue, alpha) => state.clear_color = [red, green, blue, alpha],
+            GlCall::Clear(mask) =>
+            {
+                if mask & Context::COLOR_BUFFER_BIT != 0
+                {
+                    let color = state.clear_color.map(to_u8);
+                    self.color.chunks_mut(4).for_each(|pixel| pixel.copy_from_slice(&color));
+                }
+                if mask & Context::DEPTH_BUFFER_BIT != 0
+                {
+                    self.depth.fill(1.0);
+                }
+            },
+            GlCall::DrawElements { mode, count, data_type, offset } =>
+            {
+                assert_eq!(Context::TRIANGLES, mode, "only triangles are supported");
+                self.draw_elements(backend, shader, count as usize, data_type, offset as usize);
+            },
+
+            // Everything else doesn't affect the image
+            _ => {},
+        }
+    }
+
+    fn draw_elements(&mut self, backend: &RecordingBackend, shader: &dyn SoftwareShader, count: usize, data_type: u32, offset: usize)
+    {
+        let state = &self.state;
+        let program = state.program.expect("program bound for draw_elements");
+        let element_buffer = state.vertex_arrays.get(&state.vertex_array_binding).and_then(|vertex_array| vertex_array.element_buffer)
+            .expect("element array buffer bound for draw_elements");
+        let elements = &state.buffers[&element_buffer];
+        let indices: Vec<u32> = match data_type
+        {
+            Context::UNSIGNED_INT => elements[offset..offset + count * 4].chunks(4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect(),
+            Context::UNSIGNED_SHORT => elements[offset..offset + count * 2].chunks(2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as u32).collect(),
+            Context::UNSIGNED_BYTE => elements[offset..offset + count].iter().map(|index| *index as u32).collect(),
+            data_type => panic!("unsupported index type {:#x}", data_type),
+        };
+
+        let env = ShaderEnv { state, backend, program };
+        // Each vertex is only shaded once, like a post-transform cache
+        let mut vertices: HashMap<u32, ClipVertex> = HashMap::new();
+        for triangle in indices.chunks_exact(3)
+        {
+            let polygon = triangle.iter().map(|index| vertices.entry(*index).or_insert_with(||
+                {
+                    let mut varyings = vec![0.0; shader.varyings()];
+                    let position = shader.vertex(&env, &state.fetch_attribs(*index), &mut varyings);
+                    ClipVertex { position, varyings }
+                }).clone()).collect();
+            // Only the near and far planes need clipping, since pixels outside the viewport are never visited
+            let polygon = clip_polygon(polygon, |position| position.z + position.w);
+            let polygon = clip_polygon(polygon, |position| position.w - position.z);
+            for i in 1..polygon.len().saturating_sub(1)
+            {
+                Rasterizer::rasterize(&mut self.color, &mut self.depth, (self.width, self.height), &env, shader, [&polygon[0], &polygon[i], &polygon[i + 1]]);
+            }
+        }
+    }
+
+    /// Draw a clipped triangle, with counter-clockwise triangles facing the front
+    fn rasterize(color: &mut [u8], depth: &mut [f32], size: (usize, usize), env: &ShaderEnv, shader: &dyn SoftwareShader, triangle: [&ClipVertex; 3])
+    {
+        let (viewport_x, viewport_y, viewport_width, viewport_height) = env.state.viewport;
+        let window = triangle.map(|vertex|
+            {
+                let inv_w = 1.0 / vertex.position.w;
+                WindowVertex
+                {
+                    x: viewport_x as f32 + (vertex.position.x * inv_w + 1.0) * 0.5 * viewport_width as f32,
+                    y: viewport_y as f32 + (vertex.position.y * inv_w + 1.0) * 0.5 * viewport_height as f32,
+                    z: (vertex.position.z * inv_w + 1.0) * 0.5,
+                    inv_w,
+                }
+            });
+        let edge = |a: &WindowVertex, b: &WindowVertex, x: f32, y: f32| (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x);
+        let area = edge(&window[0], &window[1], window[2].x, window[2].y);
+        if area == 0.0 || (env.state.cull_face && area < 0.0)
+        {
+            return;
+        }
+
+        // Pixels within both the triangle's bounds and the viewport
+        let min = |value: fn(&WindowVertex) -> f32| window.iter().map(value).fold(f32::INFINITY, f32::min);
+        let max = |value: fn(&WindowVertex) -> f32| window.iter().map(value).fold(f32::NEG_INFINITY, f32::max);
+        let x_range = (min(|vertex| vertex.x).floor().max(viewport_x.max(0) as f32) as usize)..
+            (max(|vertex| vertex.x).ceil().min((viewport_x + viewport_width).min(size.0 as i32) as f32).max(0.0) as usize);
+        let y_range = (min(|vertex| vertex.y).floor().max(viewport_y.max(0) as f32) as usize)..
+            (max(|vertex| vertex.y).ceil().min((viewport_y + viewport_height).min(size.1 as i32) as f32).max(0.0) as usize);
+
+        let mut varyings = vec![0.0; triangle[0].varyings.len()];
+        for y in y_range
+        {
+            for x in x_range.clone()
+            {
+                // Sample at the pixel center
+                let (sample_x, sample_y) = (x as f32 + 0.5, y as f32 + 0.5);
+                let weights = [
+                    edge(&window[1], &window[2], sample_x, sample_y) / area,
+                    edge(&window[2], &window[0], sample_x, sample_y) / area,
+                    edge(&window[0], &window[1], sample_x, sample_y) / area,
+                ];
+                if weights.iter().any(|weight| *weight < 0.0)
+                {
+                    continue;
+                }
+
+                let pixel = y * size.0 + x;
+                let z = weights.iter().zip(&window).map(|(weight, vertex)| weight * vertex.z).sum::<f32>();
+                if env.state.depth_test
+                {
+                    if z >= depth[pixel]
+                    {
+                        continue;
+                    }
+                    depth[pixel] = z;
+                }
+
+                // Interpolate varyings in clip space, so that they are perspective correct
+                let perspective = [0, 1, 2].map(|i| weights[i] * window[i].inv_w);
+                let inv_w = perspective.iter().sum::<f32>();
+                for (i, varying) in varyings.iter_mut().enumerate()
+                {
+                    *varying = (0..3).map(|vertex| perspective[vertex] * triangle[vertex].varyings[i]).sum::<f32>() / inv_w;
+                }
+                let fragment = shader.fragment(env, &varyings).map(to_u8);
+                color[pixel * 4..pixel * 4 + 4].copy_from_slice(&fragment);
+            }
+        }
+    }
+}
+
+fn to_u8(value: f32) -> u8
+{
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests
+{
+    use cgmath::{Vector2, Vector4};
+    use crate::gfx::
+    {
+        Context,
+        backend::{GlBackend, recording::{ProgramInfo, RecordingBackend}},
+        golden::rasterizer::*,
+    };
+
+    /// Passes positions through and draws with the first varying as red
+    struct PassThrough;
+
+    impl SoftwareShader for PassThrough
+    {
+        fn varyings(&self) -> usize
+        {
+            1
+        }
+
+        fn vertex(&self, _env: &ShaderEnv, attribs: &[Vector4<f32>], varyings: &mut [f32]) -> Vector4<f32>
+        {
+            varyings[0] = attribs[1].x;
+            attribs[0]
+        }
+
+        fn fragment(&self, env: &ShaderEnv, varyings: &[f32]) -> [f32; 4]
+        {
+            let texel = env.sample("tex", Vector2::new(0.25, 0.25));
+            [varyings[0], texel[1], 0.0, 1.0]
+        }
+    }
+
+    /// Draw `positions` as triangles, with each vertex having red `red`
+    fn draw(backend: &RecordingBackend, positions: &[[f32; 4]], red: &[u8])
+    {
+        let program = backend.create_program().expect("program");
+        let shader = backend.create_shader(Context::VERTEX_SHADER).expect("shader");
+        backend.compile_shader(shader);
+        backend.attach_shader(program, shader);
+        backend.link_program(program);
+        backend.use_program(Some(program));
+
+        let vertex_array = backend.create_vertex_array().expect("vertex array");
+        backend.bind_vertex_array(Some(vertex_array));
+        let positions_buffer = backend.create_buffer().expect("buffer");
+        backend.bind_buffer(Context::ARRAY_BUFFER, Some(positions_buffer));
+        backend.buffer_data(Context::ARRAY_BUFFER, &positions.iter().flatten().flat_map(|value| value.to_le_bytes()).collect::<Vec<u8>>(), Context::STATIC_DRAW);
+        backend.vertex_attrib_pointer(0, 4, Context::FLOAT, false, 0, 0);
+        backend.enable_vertex_attrib_array(0);
+        let red_buffer = backend.create_buffer().expect("buffer");
+        backend.bind_buffer(Context::ARRAY_BUFFER, Some(red_buffer));
+        backend.buffer_data(Context::ARRAY_BUFFER, red, Context::STATIC_DRAW);
+        backend.vertex_attrib_pointer(1, 1, Context::UNSIGNED_BYTE, true, 0, 0);
+        backend.enable_vertex_attrib_array(1);
+
+        let elements = backend.create_buffer().expect("buffer");
+        backend.bind_buffer(Context::ELEMENT_ARRAY_BUFFER, Some(elements));
+        let indices: Vec<u8> = (0..positions.len() as u16).flat_map(|index| index.to_le_bytes()).collect();
+        backend.buffer_data(Context::ELEMENT_ARRAY_BUFFER, &indices, Context::STATIC_DRAW);
+        backend.draw_elements(Context::TRIANGLES, positions.len() as i32, Context::UNSIGNED_SHORT, 0);
+    }
+
+    #[test]
+    fn test_triangles()
+    {
+        let backend = RecordingBackend::new();
+        backend.set_program_info(ProgramInfo::default().uniform("tex", Context::SAMPLER_2D));
+        backend.enable(Context::DEPTH_TEST);
+        backend.enable(Context::CULL_FACE);
+        // 2x2 texture, with green in the bottom left texel
+        let texture = backend.create_texture().expect("texture");
+        backend.bind_texture(Context::TEXTURE_2D, Some(texture));
+        backend.tex_parameteri(Context::TEXTURE_2D, Context::TEXTURE_MAG_FILTER, Context::NEAREST as i32);
+        backend.tex_image_2d(Context::TEXTURE_2D, 0, Context::RGBA8 as i32, 2, 2, Context::RGBA,
+                             Some(&[0, 255, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255])).expect("texture data");
+
+        draw(&backend, &[
+            // Counter-clockwise, covering the left half at depth 0.5
+            [-1.0, -1.0, 0.0, 1.0], [0.0, -1.0, 0.0, 1.0], [-1.0, 1.0, 0.0, 1.0],
+            [0.0, -1.0, 0.0, 1.0], [0.0, 1.0, 0.0, 1.0], [-1.0, 1.0, 0.0, 1.0],
+            // Behind the first triangles
+            [-1.0, -1.0, 0.5, 1.0], [1.0, -1.0, 0.5, 1.0], [-1.0, 1.0, 0.5, 1.0],
+            // Clockwise, so culled
+            [0.0, -1.0, -0.5, 1.0], [0.0, 1.0, -0.5, 1.0], [1.0, -1.0, -0.5, 1.0],
+        ], &[255, 255, 255, 255, 255, 255, 128, 128, 128, 0, 0, 0]);
+
+        let mut rasterizer = Rasterizer::new(4, 2);
+        rasterizer.run(&backend, &PassThrough);
+        let image = rasterizer.image();
+        // The front triangles cover the left half, the far triangle only shows in the bottom right,
+        // and every fragment sampled the green texel
+        assert_eq!(vec![
+            [255, 255, 0], [255, 255, 0], [0, 0, 0], [0, 0, 0],
+            [255, 255, 0], [255, 255, 0], [128, 255, 0], [0, 0, 0],
+        ], image.pixels);
+    }
+
+    #[test]
+    fn test_near_clipping()
+    {
+        let backend = RecordingBackend::new();
+        backend.set_program_info(ProgramInfo::default());
+        // The top vertex is behind the camera, so only the bottom half of the screen gets covered
+        draw(&backend, &[[-1.0, -1.0, 0.0, 1.0], [1.0, -1.0, 0.0, 1.0], [0.0, 1.0, -3.0, 1.0]], &[255, 255, 255]);
+        let mut rasterizer = Rasterizer::new(2, 2);
+        rasterizer.run(&backend, &PassThrough);
+        let image = rasterizer.image();
+        assert_eq!([0, 0, 0], image.pixels[0]);
+        assert_eq!([255, 0, 0], image.pixels[2]);
+    }
+}
@@ -0,0 +1,253 @@
+//! Canned scenes for golden image tests, and a port of the renderer's shaders to draw them on the CPU
+
+use cgmath::{Matrix4, Vector2, Vector4};
+use crate::
+{
+    gfx::
+    {
+        Context,
+        backend::recording::ProgramInfo,
+        camera::Camera,
+        gl_object::
+        {
+            manager::GlObjectManager,
+            std140::Std140,
+        },
+        golden::rasterizer::{ShaderEnv, SoftwareShader},
+        renderer::renderer::{MaterialData, Node, RenderDto, Renderer, VertData},
+    },
+    math::transform::Transformation,
+    resource::manager::ResourceManager,
+};
+
+macro_rules! resource
+{
+    ($path:expr) =>
+    {
+        include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/", $path))
+    };
+}
+
+/// Resources used by the room scene, by the names `start` gives them
+const RESOURCES: [(&str, &[u8]); 6] = [
+    ("robot.obj", resource!("models/robot.obj")),
+    ("room.obj", resource!("models/room.obj")),
+    ("tex_atlas.pbm", resource!("images/tex_atlas.pbm")),
+    ("texture_vert.glsl", resource!("shaders/texture_vert.glsl")),
+    ("texture_frag.glsl", resource!("shaders/texture_frag.glsl")),
+    ("vert_data.glsl", resource!("shaders/vert_data.glsl")),
+];
+
+fn read_f32(bytes: &[u8], offset: usize) -> f32
+{
+    f32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+/// Port of `texture_vert.glsl` and `texture_frag.glsl`, which the renderer draws with
+pub struct TextureShader;
+
+impl TextureShader
+{
+    /// What the shaders report when linked by a `RecordingBackend`
+    pub fn program_info() -> ProgramInfo
+    {
+        ProgramInfo::default()
+            .uniform("tex", Context::SAMPLER_2D)
+            .block("VertData", VertData::SIZE as i32)
+            .block_uniform("MVP", Context::FLOAT_MAT4, "VertData", 0)
+            .block("Material", MaterialData::SIZE as i32)
+            .block_uniform("Diffuse", Context::FLOAT_VEC3, "Material", 0)
+            .block_uniform("Shininess", Context::FLOAT, "Material", 12)
+            .block_uniform("Specular", Context::FLOAT_VEC3, "Material", 16)
+            .block_uniform("HasTexture", Context::BOOL, "Material", 28)
+            .attrib("Vertex", Context::FLOAT_VEC3, 0)
+            .attrib("Normal", Context::FLOAT_VEC3, 1)
+            .attrib("UV", Context::FLOAT_VEC2, 2)
+    }
+}
+
+impl SoftwareShader for TextureShader
+{
+    fn varyings(&self) -> usize
+    {
+        // TexCoord
+        2
+    }
+
+    fn vertex(&self, env: &ShaderEnv, attribs: &[Vector4<f32>], varyings: &mut [f32]) -> Vector4<f32>
+    {
+        let block = env.block("VertData");
+        let mvp: [[f32; 4]; 4] = [0, 1, 2, 3].map(|column| [0, 1, 2, 3].map(|row| read_f32(block, 16 * column + 4 * row)));
+        varyings.copy_from_slice(&[attribs[2].x, attribs[2].y]);
+        Matrix4::from(mvp) * attribs[0].truncate().extend(1.0)
+    }
+
+    fn fragment(&self, env: &ShaderEnv, varyings: &[f32]) -> [f32; 4]
+    {
+        let block = env.block("Material");
+        let mut color = [read_f32(block, 0), read_f32(block, 4), read_f32(block, 8), 1.0];
+        // HasTexture
+        if block[28..32] != [0; 4]
+        {
+            let texel = env.sample("tex", Vector2::new(varyings[0], varyings[1]));
+            color = [0, 1, 2, 3].map(|i| color[i] * texel[i]);
+        }
+        color
+    }
+}
+
+/// The room with both robots at their starting positions, set up the same way as in `start`
+pub struct RoomScene
+{
+    manager: GlObjectManager,
+    renderer: Renderer,
+    robot: RenderDto,
+    room: RenderDto,
+    robot1_matrix: Matrix4<f32>,
+    robot2_matrix: Matrix4<f32>,
+    room_matrix: Matrix4<f32>,
+    perspective: Matrix4<f32>,
+}
+
+impl RoomScene
+{
+    /// Upload the scene to `context` for drawing to a `width` x `height` canvas
+    pub fn new(context: &Context, width: i32, height: i32) -> RoomScene
+    {
+        let mut resource_manager = ResourceManager::new();
+        for (name, bytes) in RESOURCES
+        {
+            resource_manager.insert_with_name(name.to_string(), bytes.to_vec());
+        }
+
+        crate::configure_context(context, width, height);
+
+        let mut manager = GlObjectManager::new();
+        let scene = crate::setup_scene(context, &mut manager, &resource_manager);
+
+        let renderer = Renderer::new(context, &mut manager, &resource_manager).expect("renderer");
+
+        // The robots don't spin, so that the image doesn't depend on time
+        let (robot1_transform, robot2_transform) = crate::robot_transforms();
+
+        RoomScene
+        {
+            manager,
+            renderer,
+            robot: scene.robot.renderable,
+            room: scene.room.renderable,
+            robot1_matrix: robot1_transform.matrix_uncached(),
+            robot2_matrix: robot2_transform.matrix_uncached(),
+            room_matrix: Transformation::new().matrix_uncached(),
+            perspective: crate::projection(width as f32 / height as f32),
+        }
+    }
+
+    /// Draw a frame as seen from `camera`
+    pub fn render(&self, context: &Context, camera: &Camera)
+    {
+        context.clear_color(0.0, 0.0, 0.0, 1.0);
+        context.clear(Context::COLOR_BUFFER_BIT | Context::DEPTH_BUFFER_BIT);
+        let nodes = vec![
+            Node(&self.robot, &self.robot1_matrix, None),
+            Node(&self.robot, &self.robot2_matrix, None),
+            Node(&self.room, &self.room_matrix, Some(vec![])),
+        ];
+        self.renderer.render(context, &self.manager, self.perspective * camera.view_matrix(), &nodes);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    inject_wasm_test_boilerplate!();
+    use std::hash::Hasher;
+    use cgmath::vec3;
+    use twox_hash::XxHash32;
+    use crate::gfx::
+    {
+        backend::recording::RecordingBackend,
+        camera::Camera,
+        golden::
+        {
+            assert_golden,
+            image::{Image, Tolerance},
+            rasterizer::Rasterizer,
+            scene::*,
+        },
+    };
+
+    const WIDTH: i32 = 200;
+    const HEIGHT: i32 = 150;
+
+    /// The rasterizer is deterministic, so only tiny differences are allowed
+    const NATIVE_TOLERANCE: Tolerance = Tolerance { max_delta_e: 3.0, max_differing: 0.002 };
+    /// Browsers rasterize edges and filter textures a bit differently than the rasterizer
+    const WEBGL_TOLERANCE: Tolerance = Tolerance { max_delta_e: 10.0, max_differing: 0.03 };
+
+    /// Camera views of the room scene along with their reference images
+    fn views() -> Vec<(&'static str, &'static [u8], Camera)>
+    {
+        let mut orbited = crate::start_camera();
+        orbited.orbit_yaw(120.0, vec3(0.0, 0.0, 0.0));
+        vec![
+            ("room_robots", golden_reference!("room_robots"), crate::start_camera()),
+            ("room_robots_orbited", golden_reference!("room_robots_orbited"), orbited),
+        ]
+    }
+
+    fn render_native(camera: &Camera) -> Image
+    {
+        let context = Context::new(RecordingBackend::new());
+        let backend = context.backend::<RecordingBackend>().expect("recording backend");
+        backend.set_program_info(TextureShader::program_info());
+        let scene = RoomScene::new(&context, WIDTH, HEIGHT);
+        scene.render(&context, camera);
+        assert_eq!(GfxError::GlErrors(vec![GlError::NoError]), gl_get_errors(&context));
+
+        let mut rasterizer = Rasterizer::new(WIDTH as usize, HEIGHT as usize);
+        rasterizer.run(backend, &TextureShader);
+        rasterizer.image()
+    }
+
+    #[test]
+    fn test_golden_native()
+    {
+        for (name, reference, camera) in views()
+        {
+            assert_golden(name, reference, &render_native(&camera), NATIVE_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn test_shader_port_up_to_date()
+    {
+        // TextureShader has to be updated along with the shaders, and then this hash
+        let mut hasher = XxHash32::with_seed(0);
+        for (name, bytes) in RESOURCES.iter().filter(|(name, _)| name.ends_with(".glsl"))
+        {
+            hasher.write(name.as_bytes());
+            hasher.write(bytes);
+        }
+        assert_eq!(3996954661, hasher.finish(), "shaders changed without updating TextureShader");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_golden_webgl()
+    {
+        let document = window().expect("window context").document().expect("document context");
+        let canvas = document.create_element("CANVAS").expect("new canvas element").dyn_into::<HtmlCanvasElement>().expect("cast canvas element");
+        canvas.set_width(WIDTH as u32);
+        canvas.set_height(HEIGHT as u32);
+        let context = crate::gfx::new_context(&canvas).expect("context");
+        let scene = RoomScene::new(&context, WIDTH, HEIGHT);
+        for (name, reference, camera) in views()
+        {
+            scene.render(&context, &camera);
+            let mut pixels = vec![0; (WIDTH * HEIGHT * 4) as usize];
+            context.read_pixels(0, 0, WIDTH, HEIGHT, Context::RGBA, &mut pixels).expect("read pixels");
+            assert_golden(name, reference, &Image::from_gl_pixels(WIDTH as usize, HEIGHT as usize, &pixels), WEBGL_TOLERANCE);
+        }
+        assert_eq!(GfxError::GlErrors(vec![GlError::NoError]), gl_get_errors(&context));
+    }
+}
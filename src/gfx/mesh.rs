@@ -1,20 +1,100 @@
 use std::
 {
     io,
+    path::Path,
     hash::{Hash, Hasher, BuildHasherDefault},
     collections::HashMap,
 };
+use cgmath::{InnerSpace, Vector3, vec3};
 use twox_hash::XxHash32;
+use crate::resource::manager::ResourceManager;
 
-#[derive(Debug, Copy, Clone)]
-#[repr(C)]
-pub struct Vertex
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshError
 {
-    pub position: [f32; 3],
-    pub normal: [f32; 3],
-    pub texcoord: [f32; 2]
+    /// OBJ or MTL file couldn't be parsed
+    Obj(tobj::LoadError),
+    /// OBJ file isn't in the resource manager
+    MissingResource(String),
+    /// Faces refer to attributes that don't exist, or only some of a model's faces have normals or UVs
+    Malformed(String),
 }
 
+impl std::fmt::Display for MeshError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl From<tobj::LoadError> for MeshError
+{
+    fn from(err: tobj::LoadError) -> Self
+    {
+        MeshError::Obj(err)
+    }
+}
+
+/// How normals are generated for meshes that don't have them
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NormalMode
+{
+    /// Average the normals of the faces around each position, for curved surfaces
+    Smooth,
+    /// Use each face's normal for its vertices, for hard edges
+    Flat,
+}
+
+/// Axis-aligned bounding box
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb
+{
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+#[allow(dead_code)]
+impl Aabb
+{
+    pub fn center(&self) -> Vector3<f32>
+    {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn size(&self) -> Vector3<f32>
+    {
+        self.max - self.min
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundingSphere
+{
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+
+pod_struct!
+{
+    #[derive(Debug, Copy, Clone)]
+    pub struct Vertex
+    {
+        pub position: [f32; 3],
+        pub normal: [f32; 3],
+        pub texcoord: [f32; 2],
+    }
+}
+impl_vertex_layout!(Vertex
+{
+    position: 0 => "Vertex",
+    normal: 1 => "Normal",
+    texcoord: 2 => "UV",
+});
+
 impl Hash for Vertex
 {
     fn hash<H: Hasher>(&self, state: &mut H)
@@ -53,28 +133,116 @@ impl PartialEq for Vertex
 
 impl Eq for Vertex {}
 
+/// Surface properties of a submesh, loaded from an MTL file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material
+{
+    pub name: String,
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+    /// Name of the diffuse texture within the resource manager
+    pub diffuse_texture: Option<String>,
+}
+
+impl Default for Material
+{
+    /// Plain white material, used by submeshes that don't have a material
+    fn default() -> Self
+    {
+        Material
+        {
+            name: String::from("default"),
+            diffuse: [1.0, 1.0, 1.0],
+            specular: [0.0, 0.0, 0.0],
+            shininess: 0.0,
+            diffuse_texture: None,
+        }
+    }
+}
+
+impl From<tobj::Material> for Material
+{
+    fn from(material: tobj::Material) -> Self
+    {
+        Material
+        {
+            name: material.name,
+            diffuse: material.diffuse,
+            specular: material.specular,
+            shininess: material.shininess,
+            diffuse_texture: resource_name(&material.diffuse_texture),
+        }
+    }
+}
+
+/// Part of a mesh that is drawn with a single material
+/// Each model within an OBJ file becomes at least one submesh
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct SubMesh
+{
+    /// Name of the model within the OBJ file
+    pub name: String,
+    /// Position of the submesh's first index within the mesh's indices
+    pub first_index: u32,
+    pub num_indices: u32,
+    /// Index of the submesh's material within the mesh's materials
+    pub material: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Mesh
 {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
+    pub submeshes: Vec<SubMesh>,
+    pub materials: Vec<Material>,
+}
+
+/// Resources are stored by file name, so material libraries and textures are looked up
+/// by the last component of their path
+pub fn resource_name(path: &str) -> Option<String>
+{
+    Path::new(path).file_name().map(|name| name.to_string_lossy().into_owned())
 }
 
 impl Mesh
 {
     /// Loads mesh from reader for an OBJ file
+    /// Any material libraries referenced by the OBJ file are ignored
     ///
     /// `obj_reader` is the reader for the OBJ file
-    pub fn from_reader<R: io::Read>(obj_reader: R) -> Result<Mesh, tobj::LoadError>
+    #[allow(dead_code)]
+    pub fn from_reader<R: io::Read>(obj_reader: R) -> Result<Mesh, MeshError>
     {
-
-        // Load OBJ and associate with materials
-        let mut bufreader = io::BufReader::new(obj_reader);
-        let (models, _materials) = tobj::load_obj_buf(&mut bufreader, true, |_p|
+        Mesh::load(obj_reader, |_p|
             {
                 // Placeholder to ignore any material files
                 tobj::load_mtl_buf(&mut io::BufReader::new("".to_string().as_bytes()))
-            })?;
+            })
+    }
+
+    /// Loads the mesh for the OBJ file `name` in `resource_manager`
+    /// Material libraries referenced by the OBJ file must also be in `resource_manager`
+    pub fn from_resource(name: &str, resource_manager: &ResourceManager) -> Result<Mesh, MeshError>
+    {
+        let obj = resource_manager.get_by_name(&name.to_string()).ok_or_else(|| MeshError::MissingResource(name.to_string()))?;
+        Mesh::load(&**obj, |path|
+            {
+                let mtl = resource_name(&path.to_string_lossy())
+                    .and_then(|name| resource_manager.get_by_name(&name))
+                    .ok_or(tobj::LoadError::OpenFileFailed)?;
+                tobj::load_mtl_buf(&mut io::BufReader::new(&**mtl))
+            })
+    }
+
+    /// Models without normals get smooth normals, and models without UVs get UVs of 0
+    fn load<R, ML>(obj_reader: R, material_loader: ML) -> Result<Mesh, MeshError>
+        where R: io::Read, ML: Fn(&Path) -> tobj::MTLLoadResult
+    {
+        // Load OBJ and associate with materials
+        let mut bufreader = io::BufReader::new(obj_reader);
+        let (models, materials) = tobj::load_obj_buf(&mut bufreader, true, material_loader)?;
 
 
         // Keep track of the index associated with each unique vertex
@@ -82,10 +250,39 @@ impl Mesh
 
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
+        let mut submeshes = Vec::new();
+        // Whether each vertex came from a model without normals
+        let mut missing_normals = Vec::new();
 
         for model in &models
         {
             let mesh = &model.mesh;
+            // tobj ends every file with a model, even if it has no faces
+            if mesh.indices.is_empty()
+            {
+                continue;
+            }
+            // tobj gives each vertex either all of its attributes or leaves an attribute out
+            // entirely, so anything in between means that only some faces had the attribute
+            let num_positions = mesh.positions.len() / 3;
+            let has_normals = !mesh.normals.is_empty();
+            let has_texcoords = !mesh.texcoords.is_empty();
+            if (has_normals && mesh.normals.len() != num_positions * 3) || (has_texcoords && mesh.texcoords.len() != num_positions * 2)
+            {
+                return Err(MeshError::Malformed(format!("only some faces of model {} have normals or UVs", model.name)));
+            }
+            if let Some(index) = mesh.indices.iter().find(|index| **index as usize >= num_positions)
+            {
+                return Err(MeshError::Malformed(format!("model {} refers to vertex {} but has {} vertices", model.name, index, num_positions)));
+            }
+
+            submeshes.push(SubMesh
+            {
+                name: model.name.clone(),
+                first_index: indices.len() as u32,
+                num_indices: mesh.indices.len() as u32,
+                material: mesh.material_id,
+            });
             for index in &mesh.indices
             {
                 let index = *index as usize;
@@ -97,17 +294,23 @@ impl Mesh
                         mesh.positions[3 * index + 1],
                         mesh.positions[3 * index + 2],
                     ],
-                    normal:
-                    [
-                        mesh.normals[3 * index],
-                        mesh.normals[3 * index + 1],
-                        mesh.normals[3 * index + 2],
-                    ],
-                    texcoord:
-                    [
-                        mesh.texcoords[2 * index],
-                        1.0 - mesh.texcoords[2 * index + 1],
-                    ],
+                    normal: if has_normals
+                    {
+                        [
+                            mesh.normals[3 * index],
+                            mesh.normals[3 * index + 1],
+                            mesh.normals[3 * index + 2],
+                        ]
+                    }
+                    else { [0.0; 3] },
+                    texcoord: if has_texcoords
+                    {
+                        [
+                            mesh.texcoords[2 * index],
+                            1.0 - mesh.texcoords[2 * index + 1],
+                        ]
+                    }
+                    else { [0.0; 2] },
                 };
 
                 // Get the index for the vertex
@@ -121,6 +324,7 @@ impl Mesh
                             let len = vertices.len() as u32;
                             unique_vertices.insert(vertex, len);
                             vertices.push(vertex);
+                            missing_normals.push(!has_normals);
                             len
                         }
                         // If it already exists, return the assigned index
@@ -129,6 +333,343 @@ impl Mesh
                 indices.push(index);
             }
         }
-        Ok(Mesh { vertices, indices })
+        let materials = materials.into_iter().map(Material::from).collect();
+        let mut mesh = Mesh { vertices, indices, submeshes, materials };
+        if missing_normals.contains(&true)
+        {
+            mesh.smooth_normals(&missing_normals);
+        }
+        Ok(mesh)
+    }
+
+    /// Replace the normals of every vertex with normals generated from the faces
+    ///
+    /// `NormalMode::Flat` splits vertices that are shared by faces facing different directions,
+    /// so it can add vertices
+    #[allow(dead_code)]
+    pub fn generate_normals(&mut self, mode: NormalMode)
+    {
+        match mode
+        {
+            NormalMode::Smooth => self.smooth_normals(&vec![true; self.vertices.len()]),
+            NormalMode::Flat => self.flat_normals(),
+        }
+    }
+
+    /// Set the normal of each vertex where `replace` is true to the area-weighted average of the
+    /// normals of the faces around its position
+    /// Vertices at the same position share a normal, even if their UVs differ
+    pub fn smooth_normals(&mut self, replace: &[bool])
+    {
+        let position_key = |vertex: &Vertex| vertex.position.map(f32::to_bits);
+        let mut sums: HashMap<[u32; 3], Vector3<f32>, BuildHasherDefault<XxHash32>> = Default::default();
+        for triangle in self.indices.chunks_exact(3)
+        {
+            let normal = self.face_normal(triangle);
+            for index in triangle
+            {
+                *sums.entry(position_key(&self.vertices[*index as usize])).or_insert(vec3(0.0, 0.0, 0.0)) += normal;
+            }
+        }
+
+        for (vertex, replace) in self.vertices.iter_mut().zip(replace)
+        {
+            if *replace
+            {
+                let sum = sums.get(&position_key(vertex)).copied().unwrap_or(vec3(0.0, 0.0, 0.0));
+                // Vertices that aren't part of any face, or only of degenerate faces, keep a zero normal
+                vertex.normal = if sum.magnitude2() > 0.0 { sum.normalize().into() } else { [0.0; 3] };
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    fn flat_normals(&mut self)
+    {
+        let mut unique_vertices: HashMap<Vertex, u32, BuildHasherDefault<XxHash32>> = Default::default();
+        let mut vertices = Vec::new();
+        let mut indices = Vec::with_capacity(self.indices.len());
+        for triangle in self.indices.chunks_exact(3)
+        {
+            let normal = self.face_normal(triangle);
+            let normal = if normal.magnitude2() > 0.0 { normal.normalize().into() } else { [0.0; 3] };
+            for index in triangle
+            {
+                let vertex = Vertex { normal, ..self.vertices[*index as usize] };
+                let index = *unique_vertices.entry(vertex).or_insert_with(||
+                    {
+                        vertices.push(vertex);
+                        vertices.len() as u32 - 1
+                    });
+                indices.push(index);
+            }
+        }
+        self.vertices = vertices;
+        self.indices = indices;
+    }
+
+    /// Normal of `triangle` with a length of twice its area
+    fn face_normal(&self, triangle: &[u32]) -> Vector3<f32>
+    {
+        let position = |i: usize| Vector3::from(self.vertices[triangle[i] as usize].position);
+        (position(1) - position(0)).cross(position(2) - position(0))
+    }
+
+    /// Tangent of each vertex, pointing along increasing U, for normal mapping
+    ///
+    /// The fourth component is the handedness of the bitangent, which is
+    /// `cross(normal, tangent) * w`. Vertices without UVs get an arbitrary tangent
+    #[allow(dead_code)]
+    pub fn tangents(&self) -> Vec<[f32; 4]>
+    {
+        let zero = vec3(0.0, 0.0, 0.0);
+        let mut tangents = vec![zero; self.vertices.len()];
+        let mut bitangents = vec![zero; self.vertices.len()];
+        for triangle in self.indices.chunks_exact(3)
+        {
+            let [v0, v1, v2] = [0, 1, 2].map(|i| &self.vertices[triangle[i] as usize]);
+            let (edge1, edge2) = (Vector3::from(v1.position) - Vector3::from(v0.position), Vector3::from(v2.position) - Vector3::from(v0.position));
+            let (du1, dv1) = (v1.texcoord[0] - v0.texcoord[0], v1.texcoord[1] - v0.texcoord[1]);
+            let (du2, dv2) = (v2.texcoord[0] - v0.texcoord[0], v2.texcoord[1] - v0.texcoord[1]);
+            let det = du1 * dv2 - du2 * dv1;
+            if det.abs() <= f32::EPSILON
+            {
+                continue;
+            }
+            let tangent = (edge1 * dv2 - edge2 * dv1) / det;
+            let bitangent = (edge2 * du1 - edge1 * du2) / det;
+            for index in triangle
+            {
+                tangents[*index as usize] += tangent;
+                bitangents[*index as usize] += bitangent;
+            }
+        }
+
+        self.vertices.iter().zip(tangents.iter().zip(&bitangents)).map(|(vertex, (tangent, bitangent))|
+            {
+                let normal = Vector3::from(vertex.normal);
+                // Make the tangent perpendicular to the normal
+                let mut tangent = tangent - normal * normal.dot(*tangent);
+                if tangent.magnitude2() <= f32::EPSILON
+                {
+                    // Any direction perpendicular to the normal will do
+                    let axis = if normal.x.abs() < 0.9 { vec3(1.0, 0.0, 0.0) } else { vec3(0.0, 1.0, 0.0) };
+                    tangent = axis - normal * normal.dot(axis);
+                }
+                let tangent = tangent.normalize();
+                let handedness = if normal.cross(tangent).dot(*bitangent) < 0.0 { -1.0 } else { 1.0 };
+                [tangent.x, tangent.y, tangent.z, handedness]
+            }).collect()
+    }
+
+    /// Smallest box containing every vertex, or `None` if there aren't any vertices
+    #[allow(dead_code)]
+    pub fn aabb(&self) -> Option<Aabb>
+    {
+        let first = Vector3::from(self.vertices.first()?.position);
+        Some(self.vertices.iter().fold(Aabb { min: first, max: first }, |aabb, vertex|
+            {
+                let position = Vector3::from(vertex.position);
+                Aabb
+                {
+                    min: vec3(aabb.min.x.min(position.x), aabb.min.y.min(position.y), aabb.min.z.min(position.z)),
+                    max: vec3(aabb.max.x.max(position.x), aabb.max.y.max(position.y), aabb.max.z.max(position.z)),
+                }
+            }))
+    }
+
+    /// Sphere centered on the bounding box that contains every vertex, or `None` if there aren't any vertices
+    /// This isn't the smallest possible sphere, but it is within a factor of sqrt(3) of it
+    #[allow(dead_code)]
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere>
+    {
+        let center = self.aabb()?.center();
+        let radius = self.vertices.iter()
+            .map(|vertex| (Vector3::from(vertex.position) - center).magnitude())
+            .fold(0.0, f32::max);
+        Some(BoundingSphere { center, radius })
+    }
+
+    /// Material of `submesh`, or the default material if it doesn't have one
+    pub fn material(&self, submesh: &SubMesh) -> Material
+    {
+        submesh.material.and_then(|material| self.materials.get(material)).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::
+    {
+        gfx::mesh::*,
+        resource::manager::ResourceManager,
+    };
+
+    const OBJ: &str = "
+mtllib robot.mtl
+o Body
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl Metal
+f 1/1/1 2/2/1 3/3/1
+usemtl Eyes
+f 1/1/1 3/3/1 4/4/1
+o Antenna
+f 3/3/1 2/2/1 1/1/1
+";
+
+    const MTL: &str = "
+newmtl Metal
+Kd 0.5 0.5 0.5
+Ks 1.0 1.0 1.0
+Ns 32.0
+map_Kd textures/metal.pbm
+
+newmtl Eyes
+Kd 1.0 0.0 0.0
+";
+
+    #[test]
+    fn test_submeshes()
+    {
+        let mut resource_manager = ResourceManager::new();
+        resource_manager.insert_with_name("robot.obj".to_string(), OBJ.as_bytes().to_vec());
+        resource_manager.insert_with_name("robot.mtl".to_string(), MTL.as_bytes().to_vec());
+        let mesh = Mesh::from_resource("robot.obj", &resource_manager).unwrap();
+
+        assert_eq!(vec![
+            SubMesh { name: "Body".to_string(), first_index: 0, num_indices: 3, material: Some(0) },
+            SubMesh { name: "Body".to_string(), first_index: 3, num_indices: 3, material: Some(1) },
+            SubMesh { name: "Antenna".to_string(), first_index: 6, num_indices: 3, material: Some(1) },
+        ], mesh.submeshes);
+        assert_eq!(9, mesh.indices.len());
+        assert_eq!(4, mesh.vertices.len());
+
+        let metal = mesh.material(&mesh.submeshes[0]);
+        assert_eq!("Metal", metal.name);
+        assert_eq!([0.5, 0.5, 0.5], metal.diffuse);
+        assert_eq!([1.0, 1.0, 1.0], metal.specular);
+        assert_eq!(32.0, metal.shininess);
+        assert_eq!(Some("metal.pbm".to_string()), metal.diffuse_texture);
+        let eyes = mesh.material(&mesh.submeshes[1]);
+        assert_eq!([1.0, 0.0, 0.0], eyes.diffuse);
+        assert_eq!(None, eyes.diffuse_texture);
+    }
+
+    #[test]
+    fn test_missing_attributes()
+    {
+        // A unit square on the XY plane without normals or UVs
+        let obj = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+f 1 2 3
+f 1 3 4
+";
+        let mesh = Mesh::from_reader(obj.as_bytes()).unwrap();
+        assert_eq!(4, mesh.vertices.len());
+        assert!(mesh.vertices.iter().all(|vertex| vertex.normal == [0.0, 0.0, 1.0] && vertex.texcoord == [0.0, 0.0]));
+
+        let aabb = mesh.aabb().unwrap();
+        assert_eq!(Aabb { min: vec3(0.0, 0.0, 0.0), max: vec3(1.0, 1.0, 0.0) }, aabb);
+        assert_eq!(vec3(0.5, 0.5, 0.0), aabb.center());
+        let sphere = mesh.bounding_sphere().unwrap();
+        assert_eq!(vec3(0.5, 0.5, 0.0), sphere.center);
+        assert!(approx_eq!(f32, 0.5f32.sqrt(), sphere.radius, ulps = 2));
+        assert_eq!(None, Mesh::from_reader("".as_bytes()).unwrap().aabb());
+    }
+
+    #[test]
+    fn test_generate_normals()
+    {
+        // Two faces of a cube that share an edge
+        let obj = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0 0 -1
+v 0 1 -1
+f 1 2 3
+f 1 3 4
+f 1 4 6
+f 1 6 5
+";
+        let mut mesh = Mesh::from_reader(obj.as_bytes()).unwrap();
+        // Both faces have the same area around the corner, so its normal points halfway between them
+        let edge = mesh.vertices.iter().find(|vertex| vertex.position == [0.0, 0.0, 0.0]).unwrap();
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert!(approx_eq!(f32, -half, edge.normal[0], epsilon = 1e-6) && approx_eq!(f32, half, edge.normal[2], epsilon = 1e-6));
+
+        mesh.generate_normals(NormalMode::Flat);
+        // Each vertex on the shared edge is split in two
+        assert_eq!(8, mesh.vertices.len());
+        assert_eq!(12, mesh.indices.len());
+        assert!(mesh.vertices.iter().all(|vertex| vertex.normal == [0.0, 0.0, 1.0] || vertex.normal == [-1.0, 0.0, 0.0]));
+
+        mesh.generate_normals(NormalMode::Smooth);
+        assert!(mesh.vertices.iter().filter(|vertex| vertex.position == [0.0, 0.0, 0.0]).all(|vertex| approx_eq!(f32, half, vertex.normal[2], epsilon = 1e-6)));
+    }
+
+    #[test]
+    fn test_tangents()
+    {
+        let obj = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+vt 0 1
+vt 1 1
+vt 1 0
+vn 0 0 1
+f 1/1/1 2/2/1 3/3/1
+";
+        let mesh = Mesh::from_reader(obj.as_bytes()).unwrap();
+        // V is flipped on load, so U runs along X and V along Y
+        for tangent in mesh.tangents()
+        {
+            assert_eq!([1.0, 0.0, 0.0, 1.0], tangent);
+        }
+    }
+
+    #[test]
+    fn test_malformed()
+    {
+        // Only the second face has normals
+        let obj = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+vn 0 0 1
+f 1 2 3
+f 1//1 2//1 3//1
+";
+        assert!(matches!(Mesh::from_reader(obj.as_bytes()), Err(MeshError::Malformed(_))));
+    }
+
+    #[test]
+    fn test_missing_materials()
+    {
+        let mut resource_manager = ResourceManager::new();
+        resource_manager.insert_with_name("robot.obj".to_string(), OBJ.as_bytes().to_vec());
+        assert_eq!(Err(MeshError::Obj(tobj::LoadError::OpenFileFailed)), Mesh::from_resource("robot.obj", &resource_manager));
+        assert_eq!(Err(MeshError::MissingResource("room.obj".to_string())), Mesh::from_resource("room.obj", &resource_manager));
+
+        // Material libraries are ignored when loading from a reader
+        let mesh = Mesh::from_reader(OBJ.as_bytes()).unwrap();
+        assert!(mesh.materials.is_empty());
+        // Without materials, `usemtl` no longer splits a model
+        assert_eq!(2, mesh.submeshes.len());
+        assert_eq!(Material::default(), mesh.material(&mesh.submeshes[1]));
     }
 }
\ No newline at end of file
@@ -3,7 +3,7 @@
 use gen_vec::Index;
 use crate::gfx::gl_object::shader_program::ShaderType;
 
-pub type Context = web_sys::WebGl2RenderingContext;
+pub use backend::Context;
 
 #[derive(Debug, Clone, PartialOrd, PartialEq)]
 pub enum GfxError
@@ -22,12 +22,26 @@ pub enum GfxError
     ShaderCreationError(ShaderType, String),
     /// Error compiling shader fragment
     ShaderCompilationError(ShaderType, String),
+    /// Error resolving includes in shader source
+    ShaderPreprocessingError(String),
     /// Error linking shader fragments to shader program
     ShaderProgramLinkingError(String),
     /// Invalid block name for uniform buffer binding
     InvalidUniformBlockName(String),
     /// Invalid name for a regular uniform variable
     InvalidUniformName(String),
+    /// Value given for a uniform variable doesn't match its type in the shader
+    UniformTypeMismatch(String),
+    /// Uniform block is larger than the buffer range backing it
+    /// Holds the block name, the size required by the shader and the size of the buffer range
+    UniformBlockSizeMismatch(String, i32, i32),
+    /// Uniform block data isn't aligned to `UNIFORM_BUFFER_OFFSET_ALIGNMENT`
+    /// Holds the offset of the data and the required alignment
+    UniformBlockMisaligned(i32, i32),
+    /// Uniform block name or binding point is used more than once within a uniform buffer
+    DuplicateUniformBlock(String),
+    /// Attribute pointers don't match the attributes of a shader program
+    AttribLayoutMismatch(String),
 
     /// Error creating a new buffer
     BufferCreationError(String),
@@ -37,6 +51,8 @@ pub enum GfxError
 
     /// Error creating a new texture
     TextureCreationError(String),
+    /// Texture used by a material hasn't been loaded
+    MissingTexture(String),
 
     RenderLoopAlreadyRunning,
     RenderLoopNotRunning,
@@ -128,8 +144,7 @@ pub fn gl_get_errors(context: &Context) -> GfxError
 }
 
 /// Gets a new context from the canvas
-/// Returns the Context within an Rc to allow the context to be stored and referenced
-/// by GlObjects
+/// The context is backed by the canvas's WebGL2 context, and is shared by every GlObject created with it
 pub fn new_context(canvas: &web_sys::HtmlCanvasElement) -> Result<Context, &'static str>
 {
     match canvas.get_context("webgl2")
@@ -137,14 +152,26 @@ pub fn new_context(canvas: &web_sys::HtmlCanvasElement) -> Result<Context, &'sta
         Ok(Some(context)) =>
             {
                 use wasm_bindgen::JsCast;
-                Ok(context.dyn_into::<Context>().or(Err("failed to cast webgl2 context into WebGl2RenderingContext"))?)
+                let context = context.dyn_into::<web_sys::WebGl2RenderingContext>().or(Err("failed to cast webgl2 context into WebGl2RenderingContext"))?;
+                let backend = backend::web::WebGlBackend::new(context);
+                // Debug builds check every call for errors, and log where they came from
+                #[cfg(feature = "debug")]
+                let backend = backend::validation::ValidatingBackend::new(backend, backend::validation::OnGlError::Log);
+                Ok(Context::new(backend))
             },
         _ => Err("failed to get webgl2 context from canvas")
     }
 }
 
+pub mod backend;
+#[macro_use]
 pub mod gl_object;
 pub mod mesh;
+pub mod gltf;
 pub mod render_loop;
 pub mod renderer;
 pub mod camera;
+pub mod resize;
+pub mod preprocessor;
+#[cfg(test)]
+pub mod golden;
@@ -0,0 +1,492 @@
+//! GLSL preprocessing done before shader sources are handed to WebGL
+//!
+//! WebGL's GLSL preprocessor doesn't support `#include`, so shaders that share code
+//! (such as uniform block declarations) are stitched together here instead.
+//! Feature `#define`s can also be injected so that a single source file can produce several
+//! shader variants
+
+use crate::
+{
+    gfx::GfxError,
+    resource::manager::ResourceManager,
+};
+
+/// Name given to the lines containing injected `#define`s in `SourceMap` lookups
+pub const DEFINES_SOURCE_NAME: &str = "<defines>";
+
+/// Maps lines of a preprocessed shader back to the file and line they came from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMap
+{
+    // Names of all of the files that make up the shader
+    files: Vec<String>,
+    // Indexed by output line - 1, holds (index into `files`, line number within that file)
+    lines: Vec<(usize, u32)>,
+}
+
+impl SourceMap
+{
+    /// Add a line from `file` to the end of the map
+    fn push(&mut self, file: &str, line: u32)
+    {
+        let index = match self.files.iter().position(|existing| existing == file)
+        {
+            Some(index) => index,
+            None =>
+                {
+                    self.files.push(file.to_string());
+                    self.files.len() - 1
+                }
+        };
+        self.lines.push((index, line));
+    }
+
+    /// Get the file name and line number that `line` of the preprocessed shader came from
+    /// Line numbers start at 1, the same as in GLSL compiler errors
+    pub fn locate(&self, line: u32) -> Option<(&str, u32)>
+    {
+        let (file, file_line) = *self.lines.get((line as usize).checked_sub(1)?)?;
+        Some((self.files[file].as_str(), file_line))
+    }
+
+    /// Rewrite the `<source string>:<line>` locations in a shader info log into `<file>:<line>`
+    ///
+    /// i.e. `ERROR: 0:14: 'foo' : undeclared identifier` becomes
+    /// `ERROR: texture_vert.glsl:8: 'foo' : undeclared identifier`
+    pub fn rewrite_log(&self, info_log: &str) -> String
+    {
+        info_log.lines()
+            .map(|line| self.rewrite_log_line(line).unwrap_or_else(|| line.to_string()))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn rewrite_log_line(&self, line: &str) -> Option<String>
+    {
+        // Locations follow the "ERROR: " or "WARNING: " prefix
+        let (prefix, rest) = line.split_at(line.find(": ")? + 2);
+        let mut parts = rest.splitn(3, ':');
+        let _source_string: u32 = parts.next()?.parse().ok()?;
+        let source_line: u32 = parts.next()?.parse().ok()?;
+        let message = parts.next()?;
+
+        let (file, file_line) = self.locate(source_line)?;
+        Some(format!("{}{}:{}:{}", prefix, file, file_line, message))
+    }
+}
+
+/// Shader source that has been run through `ShaderPreprocessor`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreprocessedShader
+{
+    pub source: String,
+    pub source_map: SourceMap,
+}
+
+/// Resolves `#include "name"` directives against a `ResourceManager` and injects `#define`s
+///
+/// Each file is only included once per shader, so shared files don't need include guards.
+/// Whether an `#if` block is active is only known to the GLSL compiler, so files included within
+/// one are wrapped in generated include guards instead of being skipped here.
+/// Includes within comments are left alone.
+/// Defines are injected directly after the `#version` directive, which must stay the first line
+pub struct ShaderPreprocessor<'a>
+{
+    resource_manager: &'a ResourceManager,
+    defines: Vec<(String, Option<String>)>,
+}
+
+impl<'a> ShaderPreprocessor<'a>
+{
+    pub fn new(resource_manager: &'a ResourceManager) -> ShaderPreprocessor<'a>
+    {
+        ShaderPreprocessor
+        {
+            resource_manager,
+            defines: vec![],
+        }
+    }
+
+    /// Inject `#define name` into processed shaders
+    #[allow(dead_code)]
+    pub fn define(&mut self, name: &str) -> &mut Self
+    {
+        self.defines.push((name.to_string(), None));
+        self
+    }
+
+    /// Inject `#define name value` into processed shaders
+    #[allow(dead_code)]
+    pub fn define_value(&mut self, name: &str, value: &str) -> &mut Self
+    {
+        self.defines.push((name.to_string(), Some(value.to_string())));
+        self
+    }
+
+    /// Preprocess the shader stored in the resource manager under `name`
+    pub fn process(&self, name: &str) -> Result<PreprocessedShader, GfxError>
+    {
+        let mut shader = PreprocessedShader
+        {
+            source: String::new(),
+            source_map: SourceMap::default(),
+        };
+        self.process_file(name, &mut shader, &mut IncludeState::default())?;
+        Ok(shader)
+    }
+
+    fn read_source(&self, name: &str) -> Result<String, GfxError>
+    {
+        let bytes = self.resource_manager.get_by_name(&name.to_string())
+            .ok_or_else(|| GfxError::ShaderPreprocessingError(format!("{} not available by name in resource manager", name)))?;
+        String::from_utf8(bytes.clone())
+            .map_err(|err| GfxError::ShaderPreprocessingError(format!("Error reading {} into string: {}", name, err)))
+    }
+
+    fn push_line(shader: &mut PreprocessedShader, text: &str, file: &str, line: u32)
+    {
+        shader.source.push_str(text);
+        shader.source.push('\n');
+        shader.source_map.push(file, line);
+    }
+
+    fn push_defines(&self, shader: &mut PreprocessedShader)
+    {
+        for (i, (name, value)) in self.defines.iter().enumerate()
+        {
+            let define = match value
+            {
+                Some(value) => format!("#define {} {}", name, value),
+                None => format!("#define {}", name),
+            };
+            ShaderPreprocessor::push_line(shader, &define, DEFINES_SOURCE_NAME, i as u32 + 1);
+        }
+    }
+
+    /// Include `name` from `line` of `from`, wrapping it in an include guard if it may already have been included
+    fn include_file(&self, name: &str, from: &str, line: u32, shader: &mut PreprocessedShader, state: &mut IncludeState) -> Result<(), GfxError>
+    {
+        if state.stack.iter().any(|file| file == name)
+        {
+            return Err(GfxError::ShaderPreprocessingError(format!("Circular include of {} from {}", name, state.stack.join(" -> "))));
+        }
+        if state.included.iter().any(|file| file == name)
+        {
+            return Ok(());
+        }
+
+        let guarded = state.conditional_depth > 0 || state.guarded.iter().any(|file| file == name);
+        if !guarded
+        {
+            state.included.push(name.to_string());
+            return self.process_file(name, shader, state);
+        }
+
+        let guard = include_guard(name);
+        ShaderPreprocessor::push_line(shader, &format!("#ifndef {}", guard), from, line);
+        ShaderPreprocessor::push_line(shader, &format!("#define {}", guard), from, line);
+        if state.conditional_depth == 0
+        {
+            state.included.push(name.to_string());
+        }
+        else if !state.guarded.iter().any(|file| file == name)
+        {
+            state.guarded.push(name.to_string());
+        }
+        self.process_file(name, shader, state)?;
+        ShaderPreprocessor::push_line(shader, "#endif", from, line);
+        Ok(())
+    }
+
+    fn process_file(&self, name: &str, shader: &mut PreprocessedShader, state: &mut IncludeState) -> Result<(), GfxError>
+    {
+        state.stack.push(name.to_string());
+
+        let source = self.read_source(name)?;
+        let is_root = state.stack.len() == 1;
+        let mut defines_pushed = false;
+        let mut in_block_comment = false;
+
+        for (i, text) in source.lines().enumerate()
+        {
+            let line = i as u32 + 1;
+            let code = strip_comments(text, &mut in_block_comment);
+            let trimmed = code.trim();
+
+            if let Some(directive) = trimmed.strip_prefix('#').map(str::trim_start)
+            {
+                if directive.starts_with("version")
+                {
+                    if !is_root
+                    {
+                        return Err(GfxError::ShaderPreprocessingError(format!("{}:{}: #version is only allowed in the top level shader", name, line)));
+                    }
+                    ShaderPreprocessor::push_line(shader, text, name, line);
+                    if !defines_pushed
+                    {
+                        self.push_defines(shader);
+                        defines_pushed = true;
+                    }
+                    continue;
+                }
+                if let Some(include) = directive.strip_prefix("include")
+                {
+                    let include = include.trim();
+                    let quoted = (include.starts_with('"') && include.ends_with('"')) || (include.starts_with('<') && include.ends_with('>'));
+                    if !quoted || include.len() < 3
+                    {
+                        return Err(GfxError::ShaderPreprocessingError(format!("{}:{}: Expected #include \"name\", found {}", name, line, trimmed)));
+                    }
+                    self.include_file(&include[1..include.len() - 1], name, line, shader, state)
+                        .map_err(|err| match err
+                        {
+                            GfxError::ShaderPreprocessingError(msg) => GfxError::ShaderPreprocessingError(format!("{}\n  included from {}:{}", msg, name, line)),
+                            err => err,
+                        })?;
+                    continue;
+                }
+                if directive.starts_with("if")
+                {
+                    state.conditional_depth += 1;
+                }
+                else if directive.starts_with("endif")
+                {
+                    state.conditional_depth = state.conditional_depth.saturating_sub(1);
+                }
+            }
+
+            // Shaders without a #version get their defines at the very top
+            if is_root && !defines_pushed
+            {
+                self.push_defines(shader);
+                defines_pushed = true;
+            }
+            ShaderPreprocessor::push_line(shader, text, name, line);
+        }
+        if is_root && !defines_pushed
+        {
+            self.push_defines(shader);
+        }
+
+        state.stack.pop();
+        Ok(())
+    }
+}
+
+/// Bookkeeping for the includes of the shader being processed
+#[derive(Default)]
+struct IncludeState
+{
+    // Files currently being processed, the shader first
+    stack: Vec<String>,
+    // Files that have been included outside of any conditional, and can't be included again
+    included: Vec<String>,
+    // Files that have been included within a conditional, which guard every inclusion
+    guarded: Vec<String>,
+    // Number of #if blocks that the current line is within
+    conditional_depth: usize,
+}
+
+/// Name of the macro guarding the file `name` against being included twice
+fn include_guard(name: &str) -> String
+{
+    let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+    format!("INCLUDED_{}", name)
+}
+
+/// Replace the comments in `text` with spaces, the same as the GLSL preprocessor does
+/// `in_block_comment` carries whether a `/* */` comment is open from one line to the next
+fn strip_comments(text: &str, in_block_comment: &mut bool) -> String
+{
+    let mut code = String::with_capacity(text.len());
+    let mut rest = text;
+    loop
+    {
+        if *in_block_comment
+        {
+            match rest.find("*/")
+            {
+                Some(end) =>
+                    {
+                        *in_block_comment = false;
+                        code.push(' ');
+                        rest = &rest[end + 2..];
+                    },
+                None => return code,
+            }
+        }
+        // Whichever kind of comment starts first hides the other
+        let line_comment = rest.find("//");
+        match rest.find("/*").filter(|&block| line_comment.is_none_or(|line| block < line))
+        {
+            Some(block) =>
+                {
+                    code.push_str(&rest[..block]);
+                    *in_block_comment = true;
+                    rest = &rest[block + 2..];
+                },
+            None =>
+                {
+                    code.push_str(&rest[..line_comment.unwrap_or(rest.len())]);
+                    return code;
+                },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::
+    {
+        gfx::
+        {
+            GfxError,
+            preprocessor::*,
+        },
+        resource::manager::ResourceManager,
+    };
+
+    fn resource_manager(files: &[(&str, &str)]) -> ResourceManager
+    {
+        let mut manager = ResourceManager::new();
+        for (name, source) in files
+        {
+            manager.insert_with_name(name.to_string(), source.as_bytes().to_vec());
+        }
+        manager
+    }
+
+    #[test]
+    fn test_include()
+    {
+        let manager = resource_manager(&[
+            ("main.glsl", "#version 300 es\n#include \"block.glsl\"\nvoid main() {}"),
+            ("block.glsl", "uniform Block\n{\n    mat4 MVP;\n};"),
+        ]);
+        let shader = ShaderPreprocessor::new(&manager).process("main.glsl").unwrap();
+
+        assert_eq!("#version 300 es\nuniform Block\n{\n    mat4 MVP;\n};\nvoid main() {}\n", shader.source);
+        assert_eq!(Some(("main.glsl", 1)), shader.source_map.locate(1));
+        assert_eq!(Some(("block.glsl", 1)), shader.source_map.locate(2));
+        assert_eq!(Some(("block.glsl", 4)), shader.source_map.locate(5));
+        assert_eq!(Some(("main.glsl", 3)), shader.source_map.locate(6));
+        assert_eq!(None, shader.source_map.locate(0));
+        assert_eq!(None, shader.source_map.locate(7));
+    }
+
+    #[test]
+    fn test_include_once()
+    {
+        let manager = resource_manager(&[
+            ("main.glsl", "#include \"a.glsl\"\n#include <b.glsl>\n#include \"a.glsl\""),
+            ("a.glsl", "a"),
+            ("b.glsl", "#include \"a.glsl\"\nb"),
+        ]);
+        let shader = ShaderPreprocessor::new(&manager).process("main.glsl").unwrap();
+        assert_eq!("a\nb\n", shader.source);
+    }
+
+    #[test]
+    fn test_include_in_comments()
+    {
+        let manager = resource_manager(&[
+            ("main.glsl", "// #include \"missing.glsl\"\n/* start\n#include \"missing.glsl\"\n*/ #include \"a.glsl\" // shared\nb /* #include \"missing.glsl\" */"),
+            ("a.glsl", "a"),
+        ]);
+        let shader = ShaderPreprocessor::new(&manager).process("main.glsl").unwrap();
+        assert_eq!("// #include \"missing.glsl\"\n/* start\n#include \"missing.glsl\"\na\nb /* #include \"missing.glsl\" */\n", shader.source);
+
+        let mut in_block_comment = false;
+        assert_eq!("a   b ", strip_comments("a /* x */ b // y", &mut in_block_comment));
+        assert_eq!("c ", strip_comments("c /* open", &mut in_block_comment));
+        assert!(in_block_comment);
+        assert_eq!("  d", strip_comments("close */ d", &mut in_block_comment));
+        assert!(!in_block_comment);
+    }
+
+    #[test]
+    fn test_include_in_conditionals()
+    {
+        let manager = resource_manager(&[
+            ("main.glsl", "#ifdef SKINNING\n#include \"a.glsl\"\n#else\n#include \"a.glsl\"\n#endif\n#include \"a.glsl\"\n#include \"a.glsl\"\n#include \"b.glsl\"\n#if 0\n#include \"b.glsl\"\n#endif"),
+            ("a.glsl", "a"),
+            ("b.glsl", "b"),
+        ]);
+        let shader = ShaderPreprocessor::new(&manager).process("main.glsl").unwrap();
+
+        // Both variants get the file, and later includes are left to the guard until one is made outside of the #if
+        assert_eq!(concat!(
+            "#ifdef SKINNING\n#ifndef INCLUDED_A_GLSL\n#define INCLUDED_A_GLSL\na\n#endif\n",
+            "#else\n#ifndef INCLUDED_A_GLSL\n#define INCLUDED_A_GLSL\na\n#endif\n",
+            "#endif\n#ifndef INCLUDED_A_GLSL\n#define INCLUDED_A_GLSL\na\n#endif\n",
+            "b\n#if 0\n#endif\n"), shader.source);
+        assert_eq!(Some(("main.glsl", 2)), shader.source_map.locate(2));
+        assert_eq!(Some(("a.glsl", 1)), shader.source_map.locate(4));
+        assert_eq!(Some(("main.glsl", 2)), shader.source_map.locate(5));
+    }
+
+    #[test]
+    fn test_include_errors()
+    {
+        let manager = resource_manager(&[
+            ("circular.glsl", "#include \"other.glsl\""),
+            ("other.glsl", "\n#include \"circular.glsl\""),
+            ("missing.glsl", "#include \"nothing.glsl\""),
+            ("malformed.glsl", "#include nothing.glsl"),
+            ("version.glsl", "#version 300 es\n#include \"included_version.glsl\""),
+            ("included_version.glsl", "#version 300 es"),
+        ]);
+        let preprocessor = ShaderPreprocessor::new(&manager);
+
+        for name in &["circular.glsl", "missing.glsl", "malformed.glsl", "version.glsl"]
+        {
+            match preprocessor.process(name)
+            {
+                Err(GfxError::ShaderPreprocessingError(_)) => {},
+                result => panic!("{}: {:?}", name, result),
+            }
+        }
+
+        match preprocessor.process("circular.glsl")
+        {
+            Err(GfxError::ShaderPreprocessingError(msg)) => assert!(msg.ends_with("included from other.glsl:2\n  included from circular.glsl:1")),
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_defines()
+    {
+        let manager = resource_manager(&[
+            ("versioned.glsl", "#version 300 es\nvoid main() {}"),
+            ("unversioned.glsl", "void main() {}"),
+        ]);
+        let mut preprocessor = ShaderPreprocessor::new(&manager);
+        preprocessor.define("LIGHTING").define_value("NUM_LIGHTS", "4");
+
+        let shader = preprocessor.process("versioned.glsl").unwrap();
+        assert_eq!("#version 300 es\n#define LIGHTING\n#define NUM_LIGHTS 4\nvoid main() {}\n", shader.source);
+        assert_eq!(Some((DEFINES_SOURCE_NAME, 2)), shader.source_map.locate(3));
+        assert_eq!(Some(("versioned.glsl", 2)), shader.source_map.locate(4));
+
+        let shader = preprocessor.process("unversioned.glsl").unwrap();
+        assert_eq!("#define LIGHTING\n#define NUM_LIGHTS 4\nvoid main() {}\n", shader.source);
+    }
+
+    #[test]
+    fn test_rewrite_log()
+    {
+        let manager = resource_manager(&[
+            ("main.glsl", "#version 300 es\n#include \"block.glsl\"\nvoid main() { foo; }"),
+            ("block.glsl", "uniform Block\n{\n    mat4 MVP;\n};"),
+        ]);
+        let shader = ShaderPreprocessor::new(&manager).process("main.glsl").unwrap();
+
+        let log = "ERROR: 0:6: 'foo' : undeclared identifier\nWARNING: 0:3: something\nERROR: 1 compilation errors. No code generated.";
+        assert_eq!(
+            "ERROR: main.glsl:3: 'foo' : undeclared identifier\nWARNING: block.glsl:2: something\nERROR: 1 compilation errors. No code generated.",
+            shader.source_map.rewrite_log(log)
+        );
+    }
+}
@@ -27,6 +27,8 @@ use crate::
     },
 };
 
+type RestoreFunc = Box<dyn FnMut(&Context, &GlObjectManager)>;
+
 pub struct RenderLoop
 {
     window: Window,
@@ -42,7 +44,9 @@ pub struct RenderLoop
     raf_callback: Rc<RefCell<Option<Closure<dyn FnMut()>>>>,
     // handle from each request_animation_frame() call
     raf_handle: Rc<Cell<i32>>,
-    context_config: Rc<RefCell<Box<dyn FnMut(&Context)>>>
+    context_config: Rc<RefCell<Box<dyn FnMut(&Context)>>>,
+    // Supplies objects with the data they lost in a reload
+    restore_func: Rc<RefCell<Option<RestoreFunc>>>,
 }
 
 impl RenderLoop
@@ -72,6 +76,7 @@ impl RenderLoop
             raf_callback: Rc::new(RefCell::new(None)),
             raf_handle: Rc::new(Cell::new(-1)),
             context_config: Rc::new(RefCell::new(Box::new(context_config))),
+            restore_func: Rc::new(RefCell::new(None)),
         };
         render_loop.init_on_context_lost();
         render_loop.init_on_context_restored();
@@ -101,7 +106,7 @@ impl RenderLoop
     {
         let callback =
             {
-                clone!(self.canvas, self.context, self.valid_context, self.globject_manager, self.context_config);
+                clone!(self.canvas, self.context, self.valid_context, self.globject_manager, self.context_config, self.restore_func);
                 move |_event: web_sys::WebGlContextEvent|
                     {
                         let mut context = context.borrow_mut();
@@ -113,6 +118,12 @@ impl RenderLoop
                         // Recreate and reload all given GlObjects with new context
                         globject_manager.borrow_mut().reload_objects(&context);
 
+                        // Supply the data of objects without shadow copies
+                        if let Some(restore_func) = &mut *restore_func.borrow_mut()
+                        {
+                            restore_func(&context, &globject_manager.borrow());
+                        }
+
                         // Print out any webgl errors
                         if let GfxError::GlErrors(errors) = gl_get_errors(&context)
                         {
@@ -131,6 +142,14 @@ impl RenderLoop
         self.context_restored_ev = Some(ev);
     }
 
+    /// Set the function that supplies objects with data that they lost when the context was restored,
+    /// because their shadow copies were dropped with `GlObjectManager::drop_shadow_copies`
+    /// It is called after all objects are reloaded
+    pub fn set_restore_func<F: 'static + FnMut(&Context, &GlObjectManager)>(&mut self, restore_func: F)
+    {
+        *self.restore_func.borrow_mut() = Some(Box::new(restore_func));
+    }
+
     /// Starts the render loop
     /// An error is returned if the loop is already running
     /// or `cleanup()` has already been called
@@ -224,4 +243,362 @@ impl Drop for RenderLoop
     {
         self.cleanup();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests
+{
+    inject_wasm_test_boilerplate!();
+    use wasm_bindgen_futures::JsFuture;
+    use js_sys::Promise;
+
+    use std::{rc::Rc, cell::{Cell, RefCell}};
+    use crate::gfx::
+    {
+        new_context,
+        backend::web::WebGlBackend,
+        render_loop::RenderLoop,
+        gl_object::
+        {
+            ArrayBuffer,
+            ElementArrayBuffer,
+            buffer::Buffer,
+            manager::{GlObjectManager, Handle},
+            shader_program::ShaderProgram,
+            texture::{Texture2d, Texture2dParams},
+            traits::{Bindable, GlObject, MemoryUsage},
+            uniform::Sampler,
+            uniform_buffer::{UniformBuffer, UniformBlockDesc},
+            vertex_array::VertexArray,
+        },
+    };
+
+    const SIZE: i32 = 16;
+
+    const VERT_SRC: &str = r#"#version 300 es
+layout(location=0) in vec2 Position;
+layout(location=1) in vec2 UV;
+out vec2 TexCoord;
+void main()
+{
+    TexCoord = UV;
+    gl_Position = vec4(Position, 0.0, 1.0);
+}
+"#;
+
+    const FRAG_SRC: &str = r#"#version 300 es
+precision mediump float;
+uniform sampler2D Tex;
+layout(std140) uniform Tint
+{
+    vec4 color;
+};
+in vec2 TexCoord;
+out vec4 FragColor;
+void main()
+{
+    FragColor = texture(Tex, TexCoord) * color;
+}
+"#;
+
+    pod_struct!
+    {
+        #[derive(Copy, Clone)]
+        struct QuadVertex
+        {
+            position: [f32; 2],
+            uv: [f32; 2],
+        }
+    }
+    impl_vertex_layout!(QuadVertex
+    {
+        position: 0 => "Position",
+        uv: 1 => "UV",
+    });
+
+    const QUAD: [QuadVertex; 4] = [
+        QuadVertex { position: [-1.0, -1.0], uv: [0.0, 0.0] },
+        QuadVertex { position: [1.0, -1.0], uv: [1.0, 0.0] },
+        QuadVertex { position: [1.0, 1.0], uv: [1.0, 1.0] },
+        QuadVertex { position: [-1.0, 1.0], uv: [0.0, 1.0] },
+    ];
+    const QUAD_INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+    // 2x2 RGBA texture, with a different color in each corner
+    const TEXELS: [u8; 16] = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255];
+
+    /// Every type of object that the manager owns, making up a textured quad
+    struct Scene
+    {
+        shader_program: Handle<ShaderProgram>,
+        uniform_buffer: Handle<UniformBuffer>,
+        texture: Handle<Texture2d>,
+        vert_arr: Handle<VertexArray>,
+        arr_buff: Handle<ArrayBuffer>,
+        elem_buff: Handle<ElementArrayBuffer>,
+    }
+
+    fn new_canvas() -> HtmlCanvasElement
+    {
+        let document: Document = window().expect("window context").document().expect("document context");
+        let canvas = document.create_element("CANVAS").expect("new canvas element").dyn_into::<HtmlCanvasElement>().expect("cast canvas element");
+        canvas.set_width(SIZE as u32);
+        canvas.set_height(SIZE as u32);
+        document.body().expect("document body").append_child(&canvas).expect("canvas added to body");
+        canvas
+    }
+
+    fn configure_context(context: &Context)
+    {
+        context.viewport(0, 0, SIZE, SIZE);
+        context.pixel_storei(Context::UNPACK_ALIGNMENT, 1);
+    }
+
+    fn new_scene(context: &Context, manager: &mut GlObjectManager) -> Scene
+    {
+        let shader_program = manager.insert_shader_program(
+            ShaderProgram::new(context, Some(VERT_SRC.to_string()), Some(FRAG_SRC.to_string())).expect("shader program"));
+        let uniform_buffer = manager.insert_uniform_buffer(
+            UniformBuffer::new(context, &[UniformBlockDesc::new("Tint", 1, 16)], Context::DYNAMIC_DRAW).expect("uniform buffer"));
+        {
+            ShaderProgram::bind(manager, shader_program);
+            let mut program = manager.get_mut_shader_program(shader_program).expect("shader program");
+            manager.get_uniform_buffer(uniform_buffer).expect("uniform buffer").attach_blocks(&mut program).expect("attached blocks");
+            program.set_uniform("Tex", &Sampler(0)).expect("sampler uniform");
+        }
+        UniformBuffer::bind(manager, uniform_buffer);
+        manager.get_mut_uniform_buffer(uniform_buffer).expect("uniform buffer").buffer_block_data("Tint", &[[1.0f32, 0.5, 1.0, 1.0]]).expect("tint");
+
+        let texture = manager.insert_texture2d(Texture2d::new(context, Texture2dParams
+        {
+            target: Context::TEXTURE_2D,
+            internal_format: Context::RGBA8,
+            format: Context::RGBA,
+            size: (2, 2),
+            wrap_type: Context::CLAMP_TO_EDGE,
+            filter_type: Context::NEAREST,
+            data: TEXELS.to_vec(),
+        }).expect("texture"));
+        manager.set_active_texture(context, Context::TEXTURE0);
+        Texture2d::bind(manager, texture);
+        manager.get_texture2d(texture).expect("texture").setup_texture().expect("texture setup");
+
+        let vert_arr = manager.insert_vertex_array(VertexArray::new(context).expect("vertex array"));
+        let arr_buff = manager.insert_array_buffer(ArrayBuffer::new(context).expect("array buffer"));
+        let elem_buff = manager.insert_element_array_buffer(ElementArrayBuffer::new(context).expect("element array buffer"));
+        VertexArray::bind(manager, vert_arr);
+        ArrayBuffer::bind(manager, arr_buff);
+        manager.get_mut_array_buffer(arr_buff).expect("array buffer").buffer_data(&QUAD, Context::STATIC_DRAW);
+        ElementArrayBuffer::bind(manager, elem_buff);
+        manager.get_mut_element_array_buffer(elem_buff).expect("element array buffer").buffer_data(&QUAD_INDICES, Context::STATIC_DRAW);
+        {
+            let mut vert_arr = manager.get_mut_vertex_array(vert_arr).expect("vertex array");
+            let program = manager.get_shader_program(shader_program).expect("shader program");
+            vert_arr.register_vertex_buffer::<QuadVertex>(arr_buff, Some(&program)).expect("vertex layout");
+            vert_arr.register_element_array_buffer(elem_buff, None);
+        }
+        VertexArray::unbind(manager, vert_arr);
+
+        Scene { shader_program, uniform_buffer, texture, vert_arr, arr_buff, elem_buff }
+    }
+
+    fn render(context: &Context, manager: &GlObjectManager, scene: &Scene) -> Vec<u8>
+    {
+        context.clear_color(0.0, 0.0, 0.0, 1.0);
+        context.clear(Context::COLOR_BUFFER_BIT);
+        ShaderProgram::bind(manager, scene.shader_program);
+        Texture2d::bind(manager, scene.texture);
+        VertexArray::bind(manager, scene.vert_arr);
+        context.draw_elements(Context::TRIANGLES, QUAD_INDICES.len() as i32, Context::UNSIGNED_SHORT, 0);
+        VertexArray::unbind(manager, scene.vert_arr);
+
+        // The drawing buffer is cleared once the frame is presented, so it has to be read within the frame
+        let mut pixels = vec![0u8; (SIZE * SIZE * 4) as usize];
+        context.read_pixels(0, 0, SIZE, SIZE, Context::RGBA, &mut pixels).expect("read pixels");
+        pixels
+    }
+
+    /// Wait for `event` to be dispatched on `canvas`
+    /// The listener is added immediately, so the event can be triggered before awaiting
+    fn next_event(canvas: &HtmlCanvasElement, event: &str) -> JsFuture
+    {
+        JsFuture::from(Promise::new(&mut |resolve, _|
+            {
+                canvas.add_event_listener_with_callback(event, &resolve).expect("event listener");
+            }))
+    }
+
+    async fn next_animation_frame()
+    {
+        JsFuture::from(Promise::new(&mut |resolve, _|
+            {
+                window().expect("window context").request_animation_frame(&resolve).expect("animation frame");
+            })).await.expect("animation frame");
+    }
+
+    /// Wait until the render loop renders another frame
+    async fn wait_for_frame(frames: &Cell<u32>)
+    {
+        let start = frames.get();
+        for _ in 0..100
+        {
+            next_animation_frame().await;
+            if frames.get() != start
+            {
+                return;
+            }
+        }
+        panic!("Render loop didn't render a frame");
+    }
+
+    /// The WebGL context behind `context`, for queries that the backend doesn't need
+    fn raw(context: &Context) -> &WebGl2RenderingContext
+    {
+        context.backend::<WebGlBackend>().expect("WebGL backend").raw()
+    }
+
+    /// Get the WebGL object currently bound to `binding`, i.e. `ARRAY_BUFFER_BINDING`
+    fn bound<T: JsCast>(gl: &WebGl2RenderingContext, binding: u32) -> T
+    {
+        gl.get_parameter(binding).expect("binding parameter").dyn_into::<T>().expect("bound object")
+    }
+
+    /// Check that each of the scene's objects exists in the current context with the state it was created with
+    fn assert_scene_recreated(context: &Context, manager: &GlObjectManager, scene: &Scene)
+    {
+        let gl = raw(context);
+        manager.get_shader_program(scene.shader_program).expect("shader program").bind_internal();
+        let program: WebGlProgram = bound(gl, Context::CURRENT_PROGRAM);
+        assert!(gl.is_program(Some(&program)));
+        assert_eq!(Some(true), gl.get_program_parameter(&program, Context::LINK_STATUS).as_bool());
+        let block_index = gl.get_uniform_block_index(&program, "Tint");
+        assert_eq!(Some(1.0), gl.get_active_uniform_block_parameter(&program, block_index, Context::UNIFORM_BLOCK_BINDING).expect("block binding").as_f64());
+        let sampler = gl.get_uniform_location(&program, "Tex").expect("sampler location");
+        assert_eq!(Some(0.0), gl.get_uniform(&program, &sampler).as_f64());
+
+        manager.get_uniform_buffer(scene.uniform_buffer).expect("uniform buffer").bind_internal();
+        let uniform_buffer: WebGlBuffer = bound(gl, Context::UNIFORM_BUFFER_BINDING);
+        assert!(gl.is_buffer(Some(&uniform_buffer)));
+        let range_buffer = gl.get_indexed_parameter(Context::UNIFORM_BUFFER_BINDING, 1).expect("range binding").dyn_into::<WebGlBuffer>().expect("range buffer");
+        assert_eq!(uniform_buffer, range_buffer);
+        assert_eq!(Some(0.0), gl.get_indexed_parameter(Context::UNIFORM_BUFFER_START, 1).expect("range start").as_f64());
+        assert_eq!(Some(16.0), gl.get_indexed_parameter(Context::UNIFORM_BUFFER_SIZE, 1).expect("range size").as_f64());
+
+        manager.get_texture2d(scene.texture).expect("texture").bind_internal();
+        let texture: WebGlTexture = bound(gl, Context::TEXTURE_BINDING_2D);
+        assert!(gl.is_texture(Some(&texture)));
+        for (param, value) in [(Context::TEXTURE_WRAP_S, Context::CLAMP_TO_EDGE), (Context::TEXTURE_WRAP_T, Context::CLAMP_TO_EDGE),
+                               (Context::TEXTURE_MIN_FILTER, Context::NEAREST), (Context::TEXTURE_MAG_FILTER, Context::NEAREST)]
+        {
+            assert_eq!(Some(value as f64), gl.get_tex_parameter(Context::TEXTURE_2D, param).as_f64());
+        }
+
+        manager.get_array_buffer(scene.arr_buff).expect("array buffer").bind_internal();
+        let arr_buff: WebGlBuffer = bound(gl, Context::ARRAY_BUFFER_BINDING);
+        assert!(gl.is_buffer(Some(&arr_buff)));
+        assert_eq!(Some(std::mem::size_of_val(&QUAD) as f64), gl.get_buffer_parameter(Context::ARRAY_BUFFER, Context::BUFFER_SIZE).as_f64());
+
+        manager.get_vertex_array(scene.vert_arr).expect("vertex array").bind_internal();
+        let vert_arr: WebGlVertexArrayObject = bound(gl, Context::VERTEX_ARRAY_BINDING);
+        assert!(gl.is_vertex_array(Some(&vert_arr)));
+        let elem_buff: WebGlBuffer = bound(gl, Context::ELEMENT_ARRAY_BUFFER_BINDING);
+        assert!(gl.is_buffer(Some(&elem_buff)));
+        for (location, size) in [(0, 2.0), (1, 2.0)]
+        {
+            assert_eq!(Some(true), gl.get_vertex_attrib(location, Context::VERTEX_ATTRIB_ARRAY_ENABLED).expect("attrib enabled").as_bool());
+            assert_eq!(Some(size), gl.get_vertex_attrib(location, Context::VERTEX_ATTRIB_ARRAY_SIZE).expect("attrib size").as_f64());
+            let attrib_buff = gl.get_vertex_attrib(location, Context::VERTEX_ATTRIB_ARRAY_BUFFER_BINDING).expect("attrib buffer").dyn_into::<WebGlBuffer>().expect("attrib buffer");
+            assert_eq!(arr_buff, attrib_buff);
+        }
+        manager.get_vertex_array(scene.vert_arr).expect("vertex array").unbind_internal();
+
+        assert_eq!(GfxError::GlErrors(vec![GlError::NoError]), gl_get_errors(context));
+    }
+
+    /// Render the scene in a render loop, lose and restore the context mid-render and check that everything is recreated
+    /// If `drop_shadow_copies` is set, the texture and vertex data are supplied again by the render loop's restore func
+    async fn lose_and_restore(drop_shadow_copies: bool)
+    {
+        let canvas = new_canvas();
+        let context = new_context(&canvas).expect("context");
+        configure_context(&context);
+        let lose_context = raw(&context).get_extension("WEBGL_lose_context").expect("extension").expect("WEBGL_lose_context").unchecked_into::<WebglLoseContext>();
+
+        let manager = Rc::new(RefCell::new(GlObjectManager::new()));
+        let scene = Rc::new(new_scene(&context, &mut manager.borrow_mut()));
+        if drop_shadow_copies
+        {
+            manager.borrow().drop_shadow_copies();
+            assert_eq!(0, manager.borrow().get_texture2d(scene.texture).expect("texture").shadow_bytes());
+        }
+        let context = Rc::new(RefCell::new(context));
+
+        let frames = Rc::new(Cell::new(0));
+        let pixels = Rc::new(RefCell::new(vec![]));
+        let render_func =
+            {
+                clone!(context, manager, scene, frames, pixels);
+                move ||
+                    {
+                        *pixels.borrow_mut() = render(&context.borrow(), &manager.borrow(), &scene);
+                        frames.set(frames.get() + 1);
+                    }
+            };
+        let mut render_loop = RenderLoop::init(&window().expect("window context"), &canvas, &context, &manager, render_func, configure_context).expect("render loop");
+        if drop_shadow_copies
+        {
+            clone!(scene);
+            render_loop.set_restore_func(move |_context: &Context, manager: &GlObjectManager|
+                {
+                    assert!(manager.get_texture2d(scene.texture).expect("texture").needs_data());
+                    assert!(manager.get_array_buffer(scene.arr_buff).expect("array buffer").needs_data());
+                    Texture2d::bind(manager, scene.texture);
+                    manager.get_mut_texture2d(scene.texture).expect("texture").set_data(TEXELS.to_vec()).expect("texture data");
+                    ArrayBuffer::bind(manager, scene.arr_buff);
+                    manager.get_mut_array_buffer(scene.arr_buff).expect("array buffer").buffer_data(&QUAD, Context::STATIC_DRAW);
+                    VertexArray::bind(manager, scene.vert_arr);
+                    ElementArrayBuffer::bind(manager, scene.elem_buff);
+                    manager.get_mut_element_array_buffer(scene.elem_buff).expect("element array buffer").buffer_data(&QUAD_INDICES, Context::STATIC_DRAW);
+                    VertexArray::unbind(manager, scene.vert_arr);
+                });
+        }
+        render_loop.start().expect("render loop started");
+
+        wait_for_frame(&frames).await;
+        let before = pixels.borrow().clone();
+        // The quad covers the whole canvas, so nothing should be left of the clear color
+        assert!(before.chunks(4).all(|pixel| pixel != [0, 0, 0, 255]));
+
+        let lost = next_event(&canvas, "webglcontextlost");
+        lose_context.lose_context();
+        lost.await.expect("context lost");
+        assert!(context.borrow().is_context_lost());
+
+        // Nothing is rendered while the context is lost
+        let frames_while_lost = frames.get();
+        next_animation_frame().await;
+        next_animation_frame().await;
+        assert_eq!(frames_while_lost, frames.get());
+
+        let restored = next_event(&canvas, "webglcontextrestored");
+        lose_context.restore_context();
+        restored.await.expect("context restored");
+        assert!(!context.borrow().is_context_lost());
+
+        wait_for_frame(&frames).await;
+        assert_eq!(before, *pixels.borrow());
+        assert_scene_recreated(&context.borrow(), &manager.borrow(), &scene);
+
+        render_loop.cleanup();
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_context_restored()
+    {
+        lose_and_restore(false).await;
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_context_restored_without_shadow_copies()
+    {
+        lose_and_restore(true).await;
+    }
+}
@@ -1,9 +1,10 @@
 use std::
 {
     cell::RefMut,
+    collections::HashMap,
 };
 
-use cgmath::Matrix4;
+use cgmath::{Matrix4, Vector3};
 use crate::
 {
     gfx::
@@ -12,13 +13,17 @@ use crate::
         GfxError,
         gl_object::
         {
-            manager::{GlObjectManager, GlObjectHandle},
+            manager::{GlObjectManager, Handle},
             traits::GlObject,
             shader_program::ShaderProgram,
-            uniform_buffer::UniformBuffer,
+            uniform_buffer::{UniformBuffer, UniformBlockDesc},
             vertex_array::VertexArray,
-            texture::Texture2d
+            vertex_layout::VertexLayout,
+            texture::Texture2d,
+            uniform::Sampler,
         },
+        preprocessor::ShaderPreprocessor,
+        mesh::{Mesh, Material},
     },
     resource::manager::ResourceManager,
 };
@@ -27,19 +32,101 @@ use crate::
 /// This contains information needed to render an object
 pub struct RenderDto
 {
-    pub tex_handle: GlObjectHandle,
-    pub vert_arr_handle: GlObjectHandle,
+    pub vert_arr_handle: Handle<VertexArray>,
+    pub submeshes: Vec<RenderSubMesh>,
+}
+
+/// Part of a `RenderDto` that is drawn with a single material
+pub struct RenderSubMesh
+{
+    /// Position of the submesh's first index within the element array buffer
+    pub first_index: i32,
     pub num_indices: i32,
+    /// Texture to draw with, if the material has one
+    pub tex_handle: Option<Handle<Texture2d>>,
+    pub material: MaterialData,
+}
+
+impl RenderDto
+{
+    /// Create render info for `mesh`, which has been uploaded to the vertex array `vert_arr_handle`
+    ///
+    /// `textures` maps the texture names used by the mesh's materials to loaded textures
+    /// Submeshes without a material are drawn in white with `default_tex_handle`
+    pub fn from_mesh(vert_arr_handle: Handle<VertexArray>, mesh: &Mesh, textures: &HashMap<String, Handle<Texture2d>>,
+                     default_tex_handle: Option<Handle<Texture2d>>) -> Result<RenderDto, GfxError>
+    {
+        let mut submeshes = Vec::with_capacity(mesh.submeshes.len());
+        for submesh in &mesh.submeshes
+        {
+            let material = mesh.material(submesh);
+            let tex_handle = match (&material.diffuse_texture, submesh.material)
+            {
+                (Some(texture), _) => Some(*textures.get(texture).ok_or_else(|| GfxError::MissingTexture(texture.clone()))?),
+                (None, None) => default_tex_handle,
+                (None, Some(_)) => None,
+            };
+            submeshes.push(RenderSubMesh
+            {
+                first_index: submesh.first_index as i32,
+                num_indices: submesh.num_indices as i32,
+                tex_handle,
+                material: MaterialData::new(&material, tex_handle.is_some()),
+            });
+        }
+        Ok(RenderDto { vert_arr_handle, submeshes })
+    }
 }
 /// Node in the scene to be rendered
 /// The second parameter is a model matrix and the third parameter is any child nodes
 pub struct Node<'a>(pub &'a RenderDto, pub &'a Matrix4<f32>, pub Option<Vec<Node<'a>>>);
 
+std140_struct!
+{
+    /// Contents of the `VertData` uniform block
+    pub struct VertData
+    {
+        mvp: Matrix4<f32>,
+    }
+}
+
+std140_struct!
+{
+    /// Contents of the `Material` uniform block
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct MaterialData
+    {
+        diffuse: Vector3<f32>,
+        shininess: f32,
+        specular: Vector3<f32>,
+        has_texture: bool,
+    }
+}
+
+impl MaterialData
+{
+    pub fn new(material: &Material, has_texture: bool) -> MaterialData
+    {
+        MaterialData
+        {
+            diffuse: material.diffuse.into(),
+            shininess: material.shininess,
+            specular: material.specular.into(),
+            has_texture,
+        }
+    }
+}
+
+/// Uniform buffer binding point of the `VertData` block
+const VERT_DATA_BINDING: u32 = 0;
+/// Uniform buffer binding point of the `Material` block
+const MATERIAL_BINDING: u32 = 1;
+
 /// Scene Renderer
 pub struct Renderer
 {
-    shader_program_handle: GlObjectHandle,
-    uniform_buff_handle: GlObjectHandle,
+    shader_program_handle: Handle<ShaderProgram>,
+    uniform_buff_handle: Handle<UniformBuffer>,
 }
 
 impl Renderer
@@ -47,40 +134,76 @@ impl Renderer
     /// Create a new Renderer instance
     pub fn new(context: &Context, gl_manager: &mut GlObjectManager, resource_manager: &ResourceManager) -> Result<Renderer, GfxError>
     {
-        // Get shader source code and read into string
-        let vert_shader = String::from_utf8(resource_manager.get_by_name(&"texture_vert.glsl".to_string())
-            .ok_or_else(|| GfxError::Other("texture_vert.glsl not available by name in resource manager".to_string()))?.clone())
-            .or_else(|err| Err(GfxError::Other(format!("Error reading texture_vert.glsl into string: {}", err.to_string()))))?;
-        let frag_shader = String::from_utf8(resource_manager.get_by_name(&"texture_frag.glsl".to_string()).
-            ok_or_else(|| GfxError::Other("texture_frag.glsl not available by name in resource manager".to_string()))?.clone())
-            .or_else(|err| Err(GfxError::Other(format!("Error reading texture_frag.glsl into string: {}", err.to_string()))))?;
+        // Get shader source code with includes resolved
+        let preprocessor = ShaderPreprocessor::new(resource_manager);
+        let vert_shader = preprocessor.process("texture_vert.glsl")?;
+        let frag_shader = preprocessor.process("texture_frag.glsl")?;
 
         let renderer = Renderer
         {
-            shader_program_handle: gl_manager.insert_shader_program(
-                ShaderProgram::new(&context, Some(vert_shader), Some(frag_shader))?,
+            shader_program_handle: gl_manager.insert_labeled_shader_program("renderer shader program",
+                ShaderProgram::from_preprocessed(&context, Some(vert_shader), Some(frag_shader))?,
             ),
-            uniform_buff_handle: gl_manager.insert_uniform_buffer(
-                UniformBuffer::new(&context, std::mem::size_of::<Matrix4<f32>>() as i32, 0, Context::DYNAMIC_DRAW)?
+            uniform_buff_handle: gl_manager.insert_labeled_uniform_buffer("renderer uniforms",
+                UniformBuffer::new(&context, &[
+                    UniformBlockDesc::std140::<VertData>("VertData", VERT_DATA_BINDING),
+                    UniformBlockDesc::std140::<MaterialData>("Material", MATERIAL_BINDING),
+                ], Context::DYNAMIC_DRAW)?
             ),
         };
         // Setup the renderer's uniform buffer
         ShaderProgram::bind(gl_manager, renderer.shader_program_handle);
         UniformBuffer::bind(gl_manager, renderer.uniform_buff_handle);
         let mut shader_program = gl_manager.get_mut_shader_program(renderer.shader_program_handle).expect("renderer shader program");
-        let mut uniform_buffer = gl_manager.get_mut_uniform_buffer(renderer.uniform_buff_handle).expect("renderer uniform buffer");
+        let uniform_buffer = gl_manager.get_uniform_buffer(renderer.uniform_buff_handle).expect("renderer uniform buffer");
         // Set the shader sampler2d to TEXTURE0
-        shader_program.set_uniform_i32("tex", &[0])?;
-        uniform_buffer.add_vert_block(&mut shader_program, "VertData")?;
+        shader_program.set_uniform("tex", &Sampler(0))?;
+        uniform_buffer.attach_block(&mut shader_program, "VertData")?;
+        uniform_buffer.attach_block(&mut shader_program, "Material")?;
 
         Ok(renderer)
     }
 
+    /// Rebuild the renderer's shader program from the current shader sources in `resource_manager`
+    /// If the new sources don't compile, the current program is kept and the error is returned
+    /// Returns the names of uniforms and blocks that no longer exist in the new program
+    #[cfg(feature = "debug")]
+    pub fn reload_shaders(&self, manager: &GlObjectManager, resource_manager: &ResourceManager) -> Result<Vec<String>, GfxError>
+    {
+        let preprocessor = ShaderPreprocessor::new(resource_manager);
+        let vert_shader = preprocessor.process("texture_vert.glsl")?;
+        let frag_shader = preprocessor.process("texture_frag.glsl")?;
+
+        let mut shader_program = manager.get_mut_shader_program(self.shader_program_handle).ok_or(GfxError::InvalidHandle(self.shader_program_handle.index()))?;
+        let dropped = shader_program.recompile(Some(vert_shader), Some(frag_shader))?;
+        // Make sure the uniform buffer still fits the blocks of the new program
+        let uniform_buffer = manager.get_uniform_buffer(self.uniform_buff_handle).ok_or(GfxError::InvalidHandle(self.uniform_buff_handle.index()))?;
+        uniform_buffer.attach_blocks(&mut shader_program)?;
+
+        Ok(dropped)
+    }
+
+    /// Check that the vertex array `vert_arr_handle` supplies the attributes that the renderer's shader needs
+    pub fn validate_vertex_array(&self, manager: &GlObjectManager, vert_arr_handle: Handle<VertexArray>) -> Result<(), GfxError>
+    {
+        let shader_program = manager.get_shader_program(self.shader_program_handle).ok_or(GfxError::InvalidHandle(self.shader_program_handle.index()))?;
+        let vert_arr = manager.get_vertex_array(vert_arr_handle).ok_or(GfxError::InvalidHandle(vert_arr_handle.index()))?;
+        vert_arr.validate(&shader_program)
+    }
+
+    /// Check that vertices of type `V` put their attributes where the renderer's shader expects them
+    pub fn validate_vertex_layout<V: VertexLayout>(&self, manager: &GlObjectManager) -> Result<(), GfxError>
+    {
+        let shader_program = manager.get_shader_program(self.shader_program_handle).ok_or(GfxError::InvalidHandle(self.shader_program_handle.index()))?;
+        shader_program.reflection().validate_vertex_attribs(&V::attribs())
+    }
+
     /// Render's a scene
     /// `context` is the current rendering context
     /// `manager` is the object manager for the `RenderDto`s in `nodes`
     /// `proj_view_mat` is the projection-view matrix
     /// `nodes` is the scene graph to render
+    #[track_caller]
     pub fn render<'a>(&self, context: &Context, manager: &GlObjectManager, proj_view_mat: Matrix4<f32>, nodes: &Vec<Node<'a>>)
     {
         ShaderProgram::bind(manager, self.shader_program_handle);
@@ -98,30 +221,151 @@ impl Renderer
             {
                 for child in children
                 {
-                    // Multiply the parent matrix into the child's model
-                    // matrix and buffer it
-                    {
-                        let mvp = proj_view_mat * parent.1 * child.1;
-                        let buff: &[f32; 16] = mvp.as_ref();
-                        uniform_buffer.buffer_vert_data(buff);
-                    }
-                    // Bind the appropriate resources and draw the object
-                    Texture2d::bind(manager, child.0.tex_handle);
-                    VertexArray::bind(manager, child.0.vert_arr_handle);
-                    context.draw_elements_with_i32(Context::TRIANGLES, child.0.num_indices, Context::UNSIGNED_INT, 0);
+                    // Multiply the parent matrix into the child's model matrix
+                    Renderer::draw(context, manager, &mut uniform_buffer, child.0, proj_view_mat * parent.1 * child.1);
                 }
             }
 
-            // Buffer the model matrix
+            Renderer::draw(context, manager, &mut uniform_buffer, parent.0, proj_view_mat * parent.1);
+        }
+    }
+
+    /// Draw each submesh of `render_dto` with its material
+    /// The renderer's shader program and uniform buffer must be bound
+    #[track_caller]
+    fn draw(context: &Context, manager: &GlObjectManager, uniform_buffer: &mut UniformBuffer, render_dto: &RenderDto, mvp: Matrix4<f32>)
+    {
+        // Buffer the model matrix
+        uniform_buffer.buffer_block_std140("VertData", &VertData { mvp })
+            .expect("renderer uniform buffer fits VertData");
+        VertexArray::bind(manager, render_dto.vert_arr_handle);
+        for submesh in &render_dto.submeshes
+        {
+            // Bind the appropriate resources and draw the submesh
+            uniform_buffer.buffer_block_std140("Material", &submesh.material)
+                .expect("renderer uniform buffer fits Material");
+            if let Some(tex_handle) = submesh.tex_handle
             {
-                let mvp = proj_view_mat * parent.1;
-                let buff: &[f32; 16] = mvp.as_ref();
-                uniform_buffer.buffer_vert_data(buff);
+                Texture2d::bind(manager, tex_handle);
             }
-            // Bind the appropriate resources and draw the object
-            Texture2d::bind(manager, parent.0.tex_handle);
-            VertexArray::bind(manager, parent.0.vert_arr_handle);
-            context.draw_elements_with_i32(Context::TRIANGLES, parent.0.num_indices, Context::UNSIGNED_INT, 0);
+            // The offset is in bytes, and indices are u32s
+            context.draw_elements(Context::TRIANGLES, submesh.num_indices, Context::UNSIGNED_INT, submesh.first_index * 4);
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests
+{
+    use cgmath::{Matrix4, SquareMatrix};
+    use crate::
+    {
+        gfx::
+        {
+            Context,
+            GfxError,
+            GlError,
+            gl_get_errors,
+            backend::recording::{GlCall, ProgramInfo, RecordingBackend},
+            gl_object::
+            {
+                ElementArrayBuffer,
+                buffer::Buffer,
+                manager::GlObjectManager,
+                std140::Std140,
+                texture::{Texture2d, Texture2dParams},
+                traits::GlObject,
+                vertex_array::VertexArray,
+            },
+            mesh::Material,
+            renderer::renderer::*,
+        },
+        resource::manager::ResourceManager,
+    };
+
+    macro_rules! shader_source
+    {
+        ($path:expr) =>
+        {
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), concat!("/", $path)))
+        };
+    }
+    const TEXTURE_VERT: &str = shader_source!("/resources/shaders/texture_vert.glsl");
+    const TEXTURE_FRAG: &str = shader_source!("/resources/shaders/texture_frag.glsl");
+    const VERT_DATA: &str = shader_source!("/resources/shaders/vert_data.glsl");
+
+    fn recording(context: &Context) -> &RecordingBackend
+    {
+        context.backend::<RecordingBackend>().expect("recording backend")
+    }
+
+    #[test]
+    fn test_render()
+    {
+        let context = Context::new(RecordingBackend::new());
+        // What the renderer's shaders would report when linked
+        recording(&context).set_program_info(ProgramInfo::default()
+            .uniform("tex", Context::SAMPLER_2D)
+            .block("VertData", VertData::SIZE as i32)
+            .block_uniform("MVP", Context::FLOAT_MAT4, "VertData", 0)
+            .block("Material", MaterialData::SIZE as i32)
+            .block_uniform("Diffuse", Context::FLOAT_VEC3, "Material", 0)
+            .attrib("Vertex", Context::FLOAT_VEC3, 0)
+            .attrib("Normal", Context::FLOAT_VEC3, 1)
+            .attrib("UV", Context::FLOAT_VEC2, 2));
+        let mut resource_manager = ResourceManager::new();
+        resource_manager.insert_with_name("texture_vert.glsl".to_string(), TEXTURE_VERT.as_bytes().to_vec());
+        resource_manager.insert_with_name("texture_frag.glsl".to_string(), TEXTURE_FRAG.as_bytes().to_vec());
+        resource_manager.insert_with_name("vert_data.glsl".to_string(), VERT_DATA.as_bytes().to_vec());
+
+        let mut manager = GlObjectManager::new();
+        let renderer = Renderer::new(&context, &mut manager, &resource_manager).expect("renderer");
+
+        let texture = manager.insert_texture2d(Texture2d::new(&context, Texture2dParams
+        {
+            target: Context::TEXTURE_2D,
+            internal_format: Context::RGBA8,
+            format: Context::RGBA,
+            size: (1, 1),
+            wrap_type: Context::REPEAT,
+            filter_type: Context::NEAREST,
+            data: vec![255; 4],
+        }).expect("texture"));
+        let vert_arr = manager.insert_vertex_array(VertexArray::new(&context).expect("vertex array"));
+        let elem_buff = manager.insert_element_array_buffer(ElementArrayBuffer::new(&context).expect("element array buffer"));
+        VertexArray::bind(&manager, vert_arr);
+        ElementArrayBuffer::bind(&manager, elem_buff);
+        manager.get_mut_element_array_buffer(elem_buff).expect("element array buffer").buffer_data(&[0u32, 1, 2, 2, 1, 0], Context::STATIC_DRAW);
+        manager.get_mut_vertex_array(vert_arr).expect("vertex array").register_element_array_buffer(elem_buff, None);
+        VertexArray::unbind(&manager, vert_arr);
+
+        let textured = Material { diffuse_texture: Some("tex.png".to_string()), ..Material::default() };
+        let render_dto = RenderDto
+        {
+            vert_arr_handle: vert_arr,
+            submeshes: vec![
+                RenderSubMesh { first_index: 0, num_indices: 3, tex_handle: Some(texture), material: MaterialData::new(&textured, true) },
+                RenderSubMesh { first_index: 3, num_indices: 3, tex_handle: None, material: MaterialData::new(&Material::default(), false) },
+            ],
+        };
+        let model = Matrix4::from_scale(2.0);
+        let nodes = vec![Node(&render_dto, &model, None)];
+        assert_eq!(GfxError::GlErrors(vec![GlError::NoError]), gl_get_errors(&context));
+        recording(&context).take_calls();
+
+        renderer.render(&context, &manager, Matrix4::identity(), &nodes);
+        assert_eq!(GfxError::GlErrors(vec![GlError::NoError]), gl_get_errors(&context));
+
+        // The program and uniform buffer are still bound from `Renderer::new`, and Material is at the next
+        // multiple of UNIFORM_BUFFER_OFFSET_ALIGNMENT after VertData
+        let calls = recording(&context).take_calls();
+        assert_eq!(8, calls.len(), "{:#?}", calls);
+        assert_eq!(GlCall::ActiveTexture(Context::TEXTURE0), calls[0]);
+        assert_eq!(GlCall::BufferSubData(Context::UNIFORM_BUFFER, 0, VertData { mvp: model }.to_std140_bytes()), calls[1]);
+        assert!(matches!(calls[2], GlCall::BindVertexArray(Some(_))));
+        assert_eq!(GlCall::BufferSubData(Context::UNIFORM_BUFFER, 256, MaterialData::new(&textured, true).to_std140_bytes()), calls[3]);
+        assert!(matches!(calls[4], GlCall::BindTexture(Context::TEXTURE_2D, Some(_))));
+        assert_eq!(GlCall::DrawElements { mode: Context::TRIANGLES, count: 3, data_type: Context::UNSIGNED_INT, offset: 0 }, calls[5]);
+        assert_eq!(GlCall::BufferSubData(Context::UNIFORM_BUFFER, 256, MaterialData::new(&Material::default(), false).to_std140_bytes()), calls[6]);
+        assert_eq!(GlCall::DrawElements { mode: Context::TRIANGLES, count: 3, data_type: Context::UNSIGNED_INT, offset: 12 }, calls[7]);
+    }
+}
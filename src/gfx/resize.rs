@@ -0,0 +1,211 @@
+//! Keeps the canvas's backing size in sync with its displayed size
+
+use wasm_bindgen::
+{
+    prelude::*,
+    JsCast,
+};
+use std::
+{
+    rc::Rc,
+    cell::Cell,
+};
+use web_sys::
+{
+    Window,
+    HtmlCanvasElement,
+    ResizeObserver,
+};
+use crate::input::listener::EventListener;
+
+/// Get the size, in device pixels, that the canvas should be drawn at
+///
+/// `client_size` is the displayed size of the canvas in CSS pixels
+/// `device_pixel_ratio` is the number of device pixels per CSS pixel
+pub fn backing_size(client_size: (i32, i32), device_pixel_ratio: f64) -> (u32, u32)
+{
+    let scale = |css_pixels: i32| ((css_pixels.max(0) as f64 * device_pixel_ratio).round() as u32).max(1);
+    (scale(client_size.0), scale(client_size.1))
+}
+
+/// Watches the canvas for changes to its displayed size or the screen's pixel density,
+/// and resizes the canvas's drawing buffer to match so that output is neither stretched nor blurry
+///
+/// Resizes are only applied when `update()` is called, so that the viewport, projection and any
+/// size-dependent render targets can be updated at the same time as the canvas
+///
+/// The canvas needs a CSS size, i.e. `width: 100%; height: 100%` from a stylesheet. Without one, its displayed
+/// size comes from its backing size, so each resize would feed back into the next
+pub struct CanvasResizer
+{
+    window: Window,
+    canvas: HtmlCanvasElement,
+    size: Rc<Cell<(u32, u32)>>,
+    dirty: Rc<Cell<bool>>,
+    // Catches page zoom and moving between screens, which change the device pixel ratio
+    window_resize_ev: Option<EventListener>,
+    // Catches layout changes to the canvas itself
+    observer: Option<(ResizeObserver, Closure<dyn FnMut()>)>,
+}
+
+impl CanvasResizer
+{
+    pub fn new(window: &Window, canvas: &HtmlCanvasElement) -> Result<CanvasResizer, JsValue>
+    {
+        let mut resizer = CanvasResizer
+        {
+            window: window.clone(),
+            canvas: canvas.clone(),
+            size: Rc::new(Cell::new((canvas.width(), canvas.height()))),
+            // Always check the size on the first update
+            dirty: Rc::new(Cell::new(true)),
+            window_resize_ev: None,
+            observer: None,
+        };
+
+        let ev = EventListener::new(window, "resize",
+                                    {
+                                        clone!(resizer.dirty);
+                                        move |_event: web_sys::Event| { dirty.set(true); }
+                                    })?;
+        resizer.window_resize_ev = Some(ev);
+
+        // ResizeObserver isn't available in every browser, so fall back to only the window event
+        let closure =
+            {
+                clone!(resizer.dirty);
+                Closure::wrap(Box::new(move || { dirty.set(true); }) as Box<dyn FnMut()>)
+            };
+        match ResizeObserver::new(closure.as_ref().unchecked_ref())
+        {
+            Ok(observer) =>
+                {
+                    observer.observe(canvas);
+                    resizer.observer = Some((observer, closure));
+                },
+            Err(err) => crate::log_s(format!("ResizeObserver unavailable, only window resizes will be tracked: {:?}", err)),
+        }
+
+        Ok(resizer)
+    }
+
+    /// Shared handle to the current canvas size, for use in places such as context configuration
+    /// functions that need to know the size after a resize
+    pub fn shared_size(&self) -> Rc<Cell<(u32, u32)>>
+    {
+        self.size.clone()
+    }
+
+    /// Current backing size of the canvas, in device pixels
+    #[allow(dead_code)]
+    pub fn size(&self) -> (u32, u32)
+    {
+        self.size.get()
+    }
+
+    /// Current aspect ratio of the canvas
+    pub fn aspect_ratio(&self) -> f32
+    {
+        let (width, height) = self.size.get();
+        width as f32 / height as f32
+    }
+
+    /// Resize the canvas's backing size if its displayed size or pixel density has changed
+    /// Returns the new size if the canvas was resized
+    pub fn update(&self) -> Option<(u32, u32)>
+    {
+        if !self.dirty.replace(false)
+        {
+            return None;
+        }
+
+        let size = backing_size((self.canvas.client_width(), self.canvas.client_height()), self.window.device_pixel_ratio());
+        if size == (self.canvas.width(), self.canvas.height()) && size == self.size.get()
+        {
+            return None;
+        }
+
+        self.canvas.set_width(size.0);
+        self.canvas.set_height(size.1);
+        self.size.set(size);
+        Some(size)
+    }
+}
+
+impl Drop for CanvasResizer
+{
+    fn drop(&mut self)
+    {
+        if let Some((observer, _closure)) = &self.observer
+        {
+            observer.disconnect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    inject_wasm_test_boilerplate!();
+    use wasm_bindgen_futures::JsFuture;
+    use js_sys::Promise;
+    use crate::gfx::resize::*;
+
+    /// Wait for `ms` milliseconds, giving the browser a chance to lay out the page and run resize observers
+    async fn timer(ms: i32) -> Result<(), JsValue>
+    {
+        let promise = Promise::new(&mut |resolve, _|
+            {
+                window().expect("window").set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms).expect("timeout");
+            });
+        JsFuture::from(promise).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_backing_size()
+    {
+        assert_eq!((1280, 720), backing_size((1280, 720), 1.0));
+        assert_eq!((2560, 1440), backing_size((1280, 720), 2.0));
+        assert_eq!((1920, 1080), backing_size((1280, 720), 1.5));
+        // Fractional scales get rounded to the nearest device pixel
+        assert_eq!((1001, 1), backing_size((801, 1), 1.25));
+        // The drawing buffer can never be empty
+        assert_eq!((1, 1), backing_size((0, -5), 2.0));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_follows_stylesheet_size()
+    {
+        let window: Window = window().expect("window context");
+        let document: Document = window.document().expect("document context");
+        let body = document.body().expect("document body");
+
+        // The canvas is sized by a stylesheet rather than its own style attribute
+        let style = document.create_element("style").expect("style element");
+        style.set_text_content(Some("#resize-parent canvas { display: block; width: 100%; height: 100%; }"));
+        body.append_child(&style).expect("style added to body");
+        let parent = document.create_element("div").expect("parent element").dyn_into::<HtmlElement>().expect("cast parent element");
+        parent.set_id("resize-parent");
+        parent.style().set_property("width", "200px").expect("parent width");
+        parent.style().set_property("height", "100px").expect("parent height");
+        body.append_child(&parent).expect("parent added to body");
+        let canvas = document.create_element("canvas").expect("canvas element").dyn_into::<HtmlCanvasElement>().expect("cast canvas element");
+        parent.append_child(&canvas).expect("canvas added to parent");
+
+        let resizer = CanvasResizer::new(&window, &canvas).expect("canvas resizer");
+        let ratio = window.device_pixel_ratio();
+        assert_eq!(Some(backing_size((200, 100), ratio)), resizer.update());
+
+        parent.style().set_property("width", "300px").expect("parent width");
+        timer(100).await.expect("timer");
+        assert_eq!(Some(backing_size((300, 100), ratio)), resizer.update());
+        assert_eq!(backing_size((300, 100), ratio), (canvas.width(), canvas.height()));
+        // The style attribute is left alone
+        assert_eq!("", canvas.style().get_property_value("width").expect("canvas width"));
+
+        drop(resizer);
+        body.remove_child(&parent).expect("parent removed");
+        body.remove_child(&style).expect("style removed");
+    }
+}
@@ -0,0 +1,281 @@
+use std::
+{
+    rc::Rc,
+    cell::{Cell, RefCell},
+};
+use wasm_bindgen::
+{
+    JsValue,
+    JsCast,
+};
+use crate::input::
+{
+    listener::EventListener,
+    states::{InputState, next_input_state},
+};
+
+/// Number of buttons in the "standard" gamepad mapping
+pub const NUM_GAMEPAD_BUTTONS: usize = 17;
+/// Number of axes in the "standard" gamepad mapping
+pub const NUM_GAMEPAD_AXES: usize = 4;
+/// Default deadzone applied to all axes
+pub const DEFAULT_DEADZONE: f32 = 0.15;
+
+/// Rescales `value` so that anything within `deadzone` of the center reads as 0.0
+/// and the remaining range is stretched back out to -1.0..=1.0
+pub fn apply_axis_deadzone(value: f32, deadzone: f32) -> f32
+{
+    let magnitude = value.abs();
+    if magnitude <= deadzone || deadzone >= 1.0
+    {
+        0.0
+    }
+    else
+    {
+        value.signum() * ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0)
+    }
+}
+
+/// Same as `apply_axis_deadzone`, but treats the two axes of a stick as a single vector
+/// so that diagonal movement isn't clipped into a "plus" shape
+pub fn apply_radial_deadzone(value: (f32, f32), deadzone: f32) -> (f32, f32)
+{
+    let magnitude = (value.0 * value.0 + value.1 * value.1).sqrt();
+    if magnitude <= deadzone || deadzone >= 1.0
+    {
+        (0.0, 0.0)
+    }
+    else
+    {
+        let scale = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0) / magnitude;
+        (value.0 * scale, value.1 * scale)
+    }
+}
+
+/// Stores current states from a gamepad/controller
+///
+/// Unlike the keyboard and mouse, the browser doesn't fire events when gamepad buttons
+/// or axes change, so `poll()` must be called once per frame to read the current state.
+/// The first gamepad to be connected is the one that is read from
+pub struct GamepadStateListener
+{
+    navigator: web_sys::Navigator,
+    listeners: Vec<EventListener>,
+
+    // Index of the gamepad being read from, if any are connected
+    active: Rc<Cell<Option<u32>>>,
+    deadzone: Cell<f32>,
+
+    buttons: RefCell<[InputState; NUM_GAMEPAD_BUTTONS]>,
+    button_values: RefCell<[f32; NUM_GAMEPAD_BUTTONS]>,
+    axes: RefCell<[f32; NUM_GAMEPAD_AXES]>,
+}
+
+impl GamepadStateListener
+{
+    pub fn new(window: &web_sys::Window) -> Result<GamepadStateListener, JsValue>
+    {
+        let mut manager = GamepadStateListener
+        {
+            navigator: window.navigator(),
+            listeners: Vec::with_capacity(2),
+
+            active: Rc::new(Cell::new(None)),
+            deadzone: Cell::new(DEFAULT_DEADZONE),
+
+            buttons: RefCell::new([InputState::Up; NUM_GAMEPAD_BUTTONS]),
+            button_values: RefCell::new([0.0; NUM_GAMEPAD_BUTTONS]),
+            axes: RefCell::new([0.0; NUM_GAMEPAD_AXES]),
+        };
+
+        // gamepadconnected listener
+        let ev = EventListener::new(window, "gamepadconnected",
+                                    {
+                                        clone!(manager.active);
+                                        move |event: web_sys::GamepadEvent|
+                                            {
+                                                if let Some(gamepad) = event.gamepad()
+                                                {
+                                                    if active.get().is_none()
+                                                    {
+                                                        active.set(Some(gamepad.index()));
+                                                    }
+                                                    crate::log_s(format!("Gamepad connected: {}", gamepad.id()));
+                                                }
+                                            }
+                                    })?;
+        manager.listeners.push(ev);
+
+        // gamepaddisconnected listener
+        let ev = EventListener::new(window, "gamepaddisconnected",
+                                    {
+                                        clone!(manager.active);
+                                        move |event: web_sys::GamepadEvent|
+                                            {
+                                                if let Some(gamepad) = event.gamepad()
+                                                {
+                                                    if active.get() == Some(gamepad.index())
+                                                    {
+                                                        active.set(None);
+                                                    }
+                                                    crate::log_s(format!("Gamepad disconnected: {}", gamepad.id()));
+                                                }
+                                            }
+                                    })?;
+        manager.listeners.push(ev);
+
+        Ok(manager)
+    }
+
+    /// Find the gamepad that should be read from, switching to another connected gamepad
+    /// if the active one has gone away
+    fn active_gamepad(&self) -> Option<web_sys::Gamepad>
+    {
+        let gamepads = self.navigator.get_gamepads().ok()?;
+        let gamepad_at = |index: u32| -> Option<web_sys::Gamepad>
+            {
+                gamepads.get(index).dyn_into::<web_sys::Gamepad>().ok().filter(|gamepad| gamepad.connected())
+            };
+
+        if let Some(gamepad) = self.active.get().and_then(gamepad_at)
+        {
+            return Some(gamepad);
+        }
+
+        let gamepad = (0..gamepads.length()).find_map(gamepad_at)?;
+        self.active.set(Some(gamepad.index()));
+        Some(gamepad)
+    }
+
+    /// Read the current state of the active gamepad
+    /// This should be called once per frame, before any states are queried
+    pub fn poll(&self)
+    {
+        let mut buttons = self.buttons.borrow_mut();
+        let mut button_values = self.button_values.borrow_mut();
+        let mut axes = self.axes.borrow_mut();
+
+        if let Some(gamepad) = self.active_gamepad()
+        {
+            let gamepad_buttons = gamepad.buttons();
+            for (i, (state, value)) in buttons.iter_mut().zip(button_values.iter_mut()).enumerate()
+            {
+                let (pressed, button_value) = match gamepad_buttons.get(i as u32).dyn_into::<web_sys::GamepadButton>()
+                {
+                    Ok(button) => (button.pressed(), button.value() as f32),
+                    Err(_) => (false, 0.0),
+                };
+                *state = next_input_state(*state, pressed, false, false);
+                *value = button_value;
+            }
+
+            let gamepad_axes = gamepad.axes();
+            for (i, axis) in axes.iter_mut().enumerate()
+            {
+                *axis = gamepad_axes.get(i as u32).as_f64().unwrap_or(0.0) as f32;
+            }
+        }
+        else
+        {
+            *buttons = [InputState::Up; NUM_GAMEPAD_BUTTONS];
+            *button_values = [0.0; NUM_GAMEPAD_BUTTONS];
+            *axes = [0.0; NUM_GAMEPAD_AXES];
+        }
+    }
+
+    /// Whether or not a gamepad is currently connected
+    #[allow(dead_code)]
+    pub fn connected(&self) -> bool
+    {
+        self.active.get().is_some()
+    }
+
+    /// Set the deadzone applied to all axes
+    /// `deadzone` is clamped to 0.0..=1.0
+    #[allow(dead_code)]
+    pub fn set_deadzone(&self, deadzone: f32)
+    {
+        self.deadzone.set(deadzone.clamp(0.0, 1.0));
+    }
+
+    #[allow(dead_code)]
+    pub fn deadzone(&self) -> f32
+    {
+        self.deadzone.get()
+    }
+
+    /// Get the state of `button` as of the last `poll()`
+    /// Will panic if `button` is not a valid standard mapping button
+    #[allow(dead_code)]
+    pub fn button_state(&self, button: usize) -> InputState
+    {
+        self.buttons.borrow()[button]
+    }
+
+    /// Get the analog value of `button` as of the last `poll()`, from 0.0 to 1.0
+    /// This is mostly useful for triggers
+    /// Will panic if `button` is not a valid standard mapping button
+    #[allow(dead_code)]
+    pub fn button_value(&self, button: usize) -> f32
+    {
+        self.button_values.borrow()[button]
+    }
+
+    /// Get the value of `axis` as of the last `poll()` with the deadzone applied
+    /// Will panic if `axis` is not a valid standard mapping axis
+    #[allow(dead_code)]
+    pub fn axis(&self, axis: usize) -> f32
+    {
+        apply_axis_deadzone(self.axes.borrow()[axis], self.deadzone.get())
+    }
+
+    /// Get the raw value of `axis` as of the last `poll()`, without a deadzone
+    /// Will panic if `axis` is not a valid standard mapping axis
+    #[allow(dead_code)]
+    pub fn axis_raw(&self, axis: usize) -> f32
+    {
+        self.axes.borrow()[axis]
+    }
+
+    /// Get the value of the stick made up of `x_axis` and `y_axis`
+    /// as of the last `poll()`, with a radial deadzone applied
+    /// Will panic if either axis is not a valid standard mapping axis
+    #[allow(dead_code)]
+    pub fn stick(&self, x_axis: usize, y_axis: usize) -> (f32, f32)
+    {
+        let axes = self.axes.borrow();
+        apply_radial_deadzone((axes[x_axis], axes[y_axis]), self.deadzone.get())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::input::gamepad::*;
+
+    #[test]
+    fn test_axis_deadzone()
+    {
+        assert_eq!(0.0, apply_axis_deadzone(0.1, 0.2));
+        assert_eq!(0.0, apply_axis_deadzone(-0.2, 0.2));
+        assert_eq!(1.0, apply_axis_deadzone(1.0, 0.2));
+        assert_eq!(-1.0, apply_axis_deadzone(-1.0, 0.2));
+        assert!(approx_eq!(f32, 0.5, apply_axis_deadzone(0.6, 0.2)));
+        assert!(approx_eq!(f32, -0.5, apply_axis_deadzone(-0.6, 0.2)));
+        assert_eq!(0.0, apply_axis_deadzone(0.5, 1.0));
+    }
+
+    #[test]
+    fn test_radial_deadzone()
+    {
+        assert_eq!((0.0, 0.0), apply_radial_deadzone((0.1, 0.1), 0.2));
+
+        // Diagonals outside of the deadzone shouldn't get cut off on either axis
+        let (x, y) = apply_radial_deadzone((0.15, 0.15), 0.2);
+        assert!(x > 0.0 && approx_eq!(f32, x, y));
+
+        let (x, y) = apply_radial_deadzone((0.0, -0.6), 0.2);
+        assert!(approx_eq!(f32, 0.0, x));
+        assert!(approx_eq!(f32, -0.5, y));
+    }
+}
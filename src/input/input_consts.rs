@@ -1,103 +1,28 @@
 #![allow(dead_code)]
 #![allow(non_upper_case_globals)]
-pub const Key_a: &'static str = "a";
-pub const Key_b: &'static str = "b";
-pub const Key_c: &'static str = "c";
-pub const Key_d: &'static str = "d";
-pub const Key_e: &'static str = "e";
-pub const Key_f: &'static str = "f";
-pub const Key_g: &'static str = "g";
-pub const Key_h: &'static str = "h";
-pub const Key_i: &'static str = "i";
-pub const Key_j: &'static str = "j";
-pub const Key_k: &'static str = "k";
-pub const Key_l: &'static str = "l";
-pub const Key_m: &'static str = "m";
-pub const Key_n: &'static str = "n";
-pub const Key_o: &'static str = "o";
-pub const Key_p: &'static str = "p";
-pub const Key_q: &'static str = "q";
-pub const Key_r: &'static str = "r";
-pub const Key_s: &'static str = "s";
-pub const Key_t: &'static str = "t";
-pub const Key_u: &'static str = "u";
-pub const Key_v: &'static str = "v";
-pub const Key_w: &'static str = "w";
-pub const Key_x: &'static str = "x";
-pub const Key_y: &'static str = "y";
-pub const Key_z: &'static str = "z";
-pub const Key_A: &'static str = "A";
-pub const Key_B: &'static str = "B";
-pub const Key_C: &'static str = "C";
-pub const Key_D: &'static str = "D";
-pub const Key_E: &'static str = "E";
-pub const Key_F: &'static str = "F";
-pub const Key_G: &'static str = "G";
-pub const Key_H: &'static str = "H";
-pub const Key_I: &'static str = "I";
-pub const Key_J: &'static str = "J";
-pub const Key_K: &'static str = "K";
-pub const Key_L: &'static str = "L";
-pub const Key_M: &'static str = "M";
-pub const Key_N: &'static str = "N";
-pub const Key_O: &'static str = "O";
-pub const Key_P: &'static str = "P";
-pub const Key_Q: &'static str = "Q";
-pub const Key_R: &'static str = "R";
-pub const Key_S: &'static str = "S";
-pub const Key_T: &'static str = "T";
-pub const Key_U: &'static str = "U";
-pub const Key_V: &'static str = "V";
-pub const Key_W: &'static str = "W";
-pub const Key_X: &'static str = "X";
-pub const Key_Y: &'static str = "Y";
-pub const Key_Z: &'static str = "Z";
-pub const Key_1: &'static str = "1";
-pub const Key_2: &'static str = "2";
-pub const Key_3: &'static str = "3";
-pub const Key_4: &'static str = "4";
-pub const Key_5: &'static str = "5";
-pub const Key_6: &'static str = "6";
-pub const Key_7: &'static str = "7";
-pub const Key_8: &'static str = "8";
-pub const Key_9: &'static str = "9";
-pub const Key_Shift: &'static str = "Shift";
-pub const Key_Tab: &'static str = "Tab";
-pub const Key_Escape: &'static str = "Escape";
-pub const Key_Backspace: &'static str = "Backspace";
-pub const Key_Delete: &'static str = "Delete";
-pub const Key_Enter: &'static str = "Enter";
-pub const Key_Control: &'static str = "Control";
-pub const Key_Alt: &'static str = "Alt";
-pub const Key_Insert: &'static str = "Insert";
-pub const Key_Home: &'static str = "Home";
-pub const Key_End: &'static str = "End";
-pub const Key_PageUp: &'static str = "PageUp";
-pub const Key_PageDown: &'static str = "PageDown";
-pub const Key_F1: &'static str = "F1";
-pub const Key_F2: &'static str = "F2";
-pub const Key_F3: &'static str = "F3";
-pub const Key_F4: &'static str = "F4";
-pub const Key_F5: &'static str = "F5";
-pub const Key_F6: &'static str = "F6";
-pub const Key_F7: &'static str = "F7";
-pub const Key_F8: &'static str = "F8";
-pub const Key_F9: &'static str = "F9";
-pub const Key_F10: &'static str = "F10";
-pub const Key_F11: &'static str = "F11";
-pub const Key_F12: &'static str = "F12";
-pub const Key_ArrowUp: &'static str = "ArrowUp";
-pub const Key_ArrowLeft: &'static str = "ArrowLeft";
-pub const Key_ArrowRight: &'static str = "ArrowRight";
-pub const Key_ArrowDown: &'static str = "ArrowDown";
-pub const Key_Space: &'static str = " ";
-pub const Key_Comma: &'static str = ",";
-pub const Key_Minus: &'static str = "-";
-pub const Key_Underscore: &'static str = "_";
-pub const Key_Equals: &'static str = "=";
-pub const Key_Plus: &'static str = "+";
 pub const Mouse_LeftBtn: i16 = 0;
 pub const Mouse_MiddleBtn: i16 = 1;
 pub const Mouse_RightBtn: i16 = 2;
 pub const Mouse_Btn4: i16 = 3;
 pub const Mouse_Btn5: i16 = 4;
+pub const Gamepad_A: usize = 0;
+pub const Gamepad_B: usize = 1;
+pub const Gamepad_X: usize = 2;
+pub const Gamepad_Y: usize = 3;
+pub const Gamepad_LeftBumper: usize = 4;
+pub const Gamepad_RightBumper: usize = 5;
+pub const Gamepad_LeftTrigger: usize = 6;
+pub const Gamepad_RightTrigger: usize = 7;
+pub const Gamepad_Select: usize = 8;
+pub const Gamepad_Start: usize = 9;
+pub const Gamepad_LeftStick: usize = 10;
+pub const Gamepad_RightStick: usize = 11;
+pub const Gamepad_DPadUp: usize = 12;
+pub const Gamepad_DPadDown: usize = 13;
+pub const Gamepad_DPadLeft: usize = 14;
+pub const Gamepad_DPadRight: usize = 15;
+pub const Gamepad_Home: usize = 16;
+pub const GamepadAxis_LeftX: usize = 0;
+pub const GamepadAxis_LeftY: usize = 1;
+pub const GamepadAxis_RightX: usize = 2;
+pub const GamepadAxis_RightY: usize = 3;
@@ -0,0 +1,168 @@
+macro_rules! define_keys
+{
+    ($($key:ident),* $(,)?) =>
+    {
+        /// Physical key on the keyboard, named after its `KeyboardEvent.code`
+        ///
+        /// Codes name the key's position on a US QWERTY keyboard, no matter the layout or modifiers,
+        /// so `KeyW` is always the key above `KeyS` and is still `KeyW` while shift is held
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum Key
+        {
+            $($key),*
+        }
+
+        impl Key
+        {
+            /// Every key, in declaration order
+            #[allow(dead_code)]
+            pub const ALL: &'static [Key] = &[$(Key::$key),*];
+
+            /// `KeyboardEvent.code` of the key
+            pub fn code(self) -> &'static str
+            {
+                match self
+                {
+                    $(Key::$key => stringify!($key)),*
+                }
+            }
+
+            /// Get the key with the `KeyboardEvent.code` `code`
+            pub fn from_code(code: &str) -> Option<Key>
+            {
+                match code
+                {
+                    $(stringify!($key) => Some(Key::$key),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+define_keys!
+{
+    KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ, KeyK, KeyL, KeyM,
+    KeyN, KeyO, KeyP, KeyQ, KeyR, KeyS, KeyT, KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ,
+    Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    ShiftLeft, ShiftRight, ControlLeft, ControlRight, AltLeft, AltRight, MetaLeft, MetaRight, CapsLock,
+    Escape, Tab, Backspace, Enter, Space, Insert, Delete, Home, End, PageUp, PageDown,
+    ArrowUp, ArrowDown, ArrowLeft, ArrowRight,
+    Backquote, Minus, Equal, BracketLeft, BracketRight, Backslash, Semicolon, Quote, Comma, Period, Slash,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide, NumpadDecimal, NumpadEnter,
+}
+
+impl Key
+{
+    /// Get the key that types `key`, a `KeyboardEvent.key`, on a US QWERTY keyboard
+    ///
+    /// This is a fallback for events without a `code`, such as those from some virtual keyboards.
+    /// Keys that appear more than once, such as shift, map to the left one
+    pub fn from_key(key: &str) -> Option<Key>
+    {
+        let mut chars = key.chars();
+        if let (Some(c), None) = (chars.next(), chars.next())
+        {
+            if c.is_ascii_alphabetic()
+            {
+                return Key::from_code(&format!("Key{}", c.to_ascii_uppercase()));
+            }
+            if c.is_ascii_digit()
+            {
+                return Key::from_code(&format!("Digit{}", c));
+            }
+        }
+
+        match key
+        {
+            " " => Some(Key::Space),
+            "Shift" => Some(Key::ShiftLeft),
+            "Control" => Some(Key::ControlLeft),
+            "Alt" => Some(Key::AltLeft),
+            "Meta" => Some(Key::MetaLeft),
+            "!" => Some(Key::Digit1),
+            "@" => Some(Key::Digit2),
+            "#" => Some(Key::Digit3),
+            "$" => Some(Key::Digit4),
+            "%" => Some(Key::Digit5),
+            "^" => Some(Key::Digit6),
+            "&" => Some(Key::Digit7),
+            "*" => Some(Key::Digit8),
+            "(" => Some(Key::Digit9),
+            ")" => Some(Key::Digit0),
+            "`" | "~" => Some(Key::Backquote),
+            "-" | "_" => Some(Key::Minus),
+            "=" | "+" => Some(Key::Equal),
+            "[" | "{" => Some(Key::BracketLeft),
+            "]" | "}" => Some(Key::BracketRight),
+            "\\" | "|" => Some(Key::Backslash),
+            ";" | ":" => Some(Key::Semicolon),
+            "'" | "\"" => Some(Key::Quote),
+            "," | "<" => Some(Key::Comma),
+            "." | ">" => Some(Key::Period),
+            "/" | "?" => Some(Key::Slash),
+            // Named keys other than the ones above share their name with their code
+            _ if key.chars().count() > 1 => Key::from_code(key),
+            _ => None,
+        }
+    }
+
+    /// Get the physical key of `event`, falling back to its `key` if it doesn't have a `code`
+    pub fn from_event(event: &web_sys::KeyboardEvent) -> Option<Key>
+    {
+        Key::from_code(&event.code()).or_else(|| Key::from_key(&event.key()))
+    }
+}
+
+impl std::fmt::Display for Key
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl std::str::FromStr for Key
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        Key::from_code(s).ok_or_else(|| format!("unknown key code \"{}\"", s))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::input::key::Key;
+
+    #[test]
+    fn test_from_code()
+    {
+        for key in Key::ALL
+        {
+            assert_eq!(Some(*key), Key::from_code(key.code()));
+            assert_eq!(Ok(*key), key.to_string().parse());
+        }
+        assert_eq!(None, Key::from_code("w"));
+        assert_eq!(None, Key::from_code(""));
+    }
+
+    #[test]
+    fn test_from_key()
+    {
+        // Shift changes the key but not the code
+        assert_eq!(Some(Key::KeyW), Key::from_key("w"));
+        assert_eq!(Some(Key::KeyW), Key::from_key("W"));
+        assert_eq!(Some(Key::Digit1), Key::from_key("!"));
+        assert_eq!(Some(Key::Equal), Key::from_key("+"));
+        assert_eq!(Some(Key::Space), Key::from_key(" "));
+        assert_eq!(Some(Key::ControlLeft), Key::from_key("Control"));
+        assert_eq!(Some(Key::ArrowUp), Key::from_key("ArrowUp"));
+        assert_eq!(None, Key::from_key("é"));
+        assert_eq!(None, Key::from_key("Dead"));
+    }
+}
@@ -1,3 +1,7 @@
 pub mod input_consts;
+pub mod key;
 pub mod listener;
-pub mod states;
\ No newline at end of file
+pub mod states;
+pub mod gamepad;
+pub mod recorder;
+pub mod pointer_lock;
\ No newline at end of file
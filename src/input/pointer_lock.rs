@@ -0,0 +1,215 @@
+//! Pointer lock and fullscreen management for the canvas
+
+use std::
+{
+    rc::Rc,
+    cell::{Cell, RefCell},
+};
+use wasm_bindgen::JsValue;
+use web_sys::
+{
+    Document,
+    HtmlCanvasElement,
+};
+use crate::input::
+{
+    key::Key,
+    listener::EventListener,
+};
+
+/// How mouse movement should control the camera
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CameraMode
+{
+    /// Pointer is free, dragging orbits the camera around a point
+    Orbit,
+    /// Pointer is locked to the canvas, mouse movement looks around
+    FreeLook,
+}
+
+/// Whether `element` is `target`
+fn is_element(element: Option<web_sys::Element>, target: &JsValue) -> bool
+{
+    element.is_some_and(|element| JsValue::from(element) == *target)
+}
+
+/// Tracks and controls pointer lock and fullscreen for a canvas
+///
+/// Pointer lock is entered by double clicking the canvas and fullscreen is toggled with the
+/// `f` key, or both can be entered through the JS API. Escape exits both.
+/// Browsers only allow entering either mode from within a user input event handler,
+/// so the request functions won't work when called from the render loop
+pub struct PointerLockController
+{
+    document: Document,
+    canvas: HtmlCanvasElement,
+    locked: Rc<Cell<bool>>,
+    fullscreen: Rc<Cell<bool>>,
+    // JS callback for when either mode changes, called with `(locked, fullscreen)`
+    on_change: Rc<RefCell<Option<js_sys::Function>>>,
+    listeners: Vec<EventListener>,
+}
+
+impl PointerLockController
+{
+    pub fn new(document: &Document, canvas: &HtmlCanvasElement) -> Result<PointerLockController, JsValue>
+    {
+        let canvas_value = JsValue::from(canvas.clone());
+        let mut controller = PointerLockController
+        {
+            document: document.clone(),
+            canvas: canvas.clone(),
+            locked: Rc::new(Cell::new(is_element(document.pointer_lock_element(), &canvas_value))),
+            fullscreen: Rc::new(Cell::new(is_element(document.fullscreen_element(), &canvas_value))),
+            on_change: Rc::new(RefCell::new(None)),
+            listeners: Vec::with_capacity(6),
+        };
+
+        let notify =
+            {
+                clone!(controller.locked, controller.fullscreen, controller.on_change);
+                move ||
+                    {
+                        if let Some(on_change) = on_change.borrow().as_ref()
+                        {
+                            if let Err(err) = on_change.call2(&JsValue::null(), &locked.get().into(), &fullscreen.get().into())
+                            {
+                                crate::log_s(format!("Error executing view mode change callback: {:?}", err));
+                            }
+                        }
+                    }
+            };
+
+        // pointerlockchange listener
+        let ev = EventListener::new(document, "pointerlockchange",
+                                    {
+                                        clone!(controller.document, controller.locked);
+                                        clone!(canvas_value, notify);
+                                        move |_event: web_sys::Event|
+                                            {
+                                                locked.set(is_element(document.pointer_lock_element(), &canvas_value));
+                                                notify();
+                                            }
+                                    })?;
+        controller.listeners.push(ev);
+
+        // fullscreenchange listener
+        let ev = EventListener::new(document, "fullscreenchange",
+                                    {
+                                        clone!(controller.document, controller.fullscreen);
+                                        clone!(canvas_value, notify);
+                                        move |_event: web_sys::Event|
+                                            {
+                                                fullscreen.set(is_element(document.fullscreen_element(), &canvas_value));
+                                                notify();
+                                            }
+                                    })?;
+        controller.listeners.push(ev);
+
+        // pointerlockerror listener
+        let ev = EventListener::new(document, "pointerlockerror",
+                                    move |_event: web_sys::Event| { crate::log("Error entering pointer lock"); })?;
+        controller.listeners.push(ev);
+
+        // fullscreenerror listener
+        let ev = EventListener::new(document, "fullscreenerror",
+                                    move |_event: web_sys::Event| { crate::log("Error entering fullscreen"); })?;
+        controller.listeners.push(ev);
+
+        // dblclick listener, enters pointer lock
+        let ev = EventListener::new(canvas, "dblclick",
+                                    {
+                                        clone!(controller.canvas);
+                                        move |_event: web_sys::MouseEvent| { canvas.request_pointer_lock(); }
+                                    })?;
+        controller.listeners.push(ev);
+
+        // keydown listener, toggles fullscreen and exits both modes
+        let ev = EventListener::new(canvas, "keydown",
+                                    {
+                                        clone!(controller.document, controller.canvas, controller.fullscreen);
+                                        move |event: web_sys::KeyboardEvent|
+                                            {
+                                                // Browsers normally handle escape themselves while either mode is active,
+                                                // but not all of them pass the key on consistently
+                                                let key = Key::from_event(&event);
+                                                if key == Some(Key::Escape)
+                                                {
+                                                    document.exit_pointer_lock();
+                                                    if fullscreen.get()
+                                                    {
+                                                        document.exit_fullscreen();
+                                                    }
+                                                }
+                                                else if key == Some(Key::KeyF) && !event.repeat()
+                                                {
+                                                    if fullscreen.get()
+                                                    {
+                                                        document.exit_fullscreen();
+                                                    }
+                                                    else if let Err(err) = canvas.request_fullscreen()
+                                                    {
+                                                        crate::log_s(format!("Error requesting fullscreen: {:?}", err));
+                                                    }
+                                                }
+                                            }
+                                    })?;
+        controller.listeners.push(ev);
+
+        Ok(controller)
+    }
+
+    /// Lock the pointer to the canvas
+    /// Must be called from within a user input event handler
+    pub fn request_pointer_lock(&self)
+    {
+        self.canvas.request_pointer_lock();
+    }
+
+    /// Release the pointer if it is locked to the canvas
+    pub fn exit_pointer_lock(&self)
+    {
+        if self.locked.get()
+        {
+            self.document.exit_pointer_lock();
+        }
+    }
+
+    /// Make the canvas fullscreen
+    /// Must be called from within a user input event handler
+    pub fn request_fullscreen(&self) -> Result<(), JsValue>
+    {
+        self.canvas.request_fullscreen()
+    }
+
+    /// Leave fullscreen if the canvas is fullscreen
+    pub fn exit_fullscreen(&self)
+    {
+        if self.fullscreen.get()
+        {
+            self.document.exit_fullscreen();
+        }
+    }
+
+    pub fn is_pointer_locked(&self) -> bool
+    {
+        self.locked.get()
+    }
+
+    pub fn is_fullscreen(&self) -> bool
+    {
+        self.fullscreen.get()
+    }
+
+    /// Set a JS function to be called with `(locked, fullscreen)` whenever either changes
+    pub fn set_on_change(&self, callback: Option<js_sys::Function>)
+    {
+        *self.on_change.borrow_mut() = callback;
+    }
+
+    /// How the camera should currently be controlled by the mouse
+    pub fn camera_mode(&self) -> CameraMode
+    {
+        if self.locked.get() { CameraMode::FreeLook } else { CameraMode::Orbit }
+    }
+}
@@ -0,0 +1,451 @@
+//! Recording and replaying of the input seen by `InputStateListener`
+
+use std::
+{
+    fmt,
+    str::FromStr,
+    time::Duration,
+};
+use crate::input::
+{
+    key::Key,
+    states::Modifiers,
+};
+
+/// A single input event, as seen by `InputStateListener`
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent
+{
+    KeyDown { key: Key, repeat: bool, modifiers: Modifiers },
+    KeyUp { key: Key, modifiers: Modifiers },
+    MouseDown { button: i16 },
+    MouseUp { button: i16 },
+    /// `pos` is the mouse position within the target element and `movement` is the
+    /// distance moved since the last mousemove event
+    MouseMove { pos: (i32, i32), movement: (i32, i32) },
+    Wheel { delta: (f64, f64) },
+}
+
+/// An input event along with when it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedEvent
+{
+    /// Frame that the event was received during, counting from the start of the recording
+    pub frame: u64,
+    /// Render loop time, in milliseconds, from the start of the recording
+    pub time: f64,
+    pub event: InputEvent,
+}
+
+/// A recorded input session that can be replayed with `InputStateListener::replay`
+///
+/// Events are replayed by frame rather than by time so that a replay sees the exact same
+/// sequence of input snapshots as the recording did, no matter the frame rate of the replay
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputRecording
+{
+    pub events: Vec<RecordedEvent>,
+    /// Total number of frames that were recorded
+    pub frames: u64,
+}
+
+/// Current mode of an `InputRecorder`
+enum RecorderMode
+{
+    Idle,
+    Recording
+    {
+        recording: InputRecording,
+        start_time: Option<Duration>,
+    },
+    Replaying
+    {
+        recording: InputRecording,
+        next_event: usize,
+    },
+}
+
+/// Records input events per frame, or plays back a previous recording
+pub struct InputRecorder
+{
+    mode: RecorderMode,
+    // Frame counter since recording/replaying started
+    frame: u64,
+    // Current frame time relative to the start of the recording
+    time: f64,
+}
+
+impl Default for InputRecorder
+{
+    fn default() -> Self
+    {
+        InputRecorder::new()
+    }
+}
+
+impl InputRecorder
+{
+    pub fn new() -> InputRecorder
+    {
+        InputRecorder
+        {
+            mode: RecorderMode::Idle,
+            frame: 0,
+            time: 0.0,
+        }
+    }
+
+    /// Start recording, discarding any in-progress recording or replay
+    /// `initial_events` are recorded on the first frame, i.e. for keys that are already held
+    pub fn start_recording(&mut self, initial_events: Vec<InputEvent>)
+    {
+        let events = initial_events.into_iter()
+            .map(|event| RecordedEvent { frame: 0, time: 0.0, event })
+            .collect();
+        self.mode = RecorderMode::Recording
+        {
+            recording: InputRecording { events, frames: 0 },
+            start_time: None,
+        };
+        self.frame = 0;
+        self.time = 0.0;
+    }
+
+    /// Stop recording and get the recorded input
+    /// Returns `None` if the recorder wasn't recording
+    pub fn stop_recording(&mut self) -> Option<InputRecording>
+    {
+        match std::mem::replace(&mut self.mode, RecorderMode::Idle)
+        {
+            RecorderMode::Recording { mut recording, .. } =>
+                {
+                    recording.frames = self.frame;
+                    Some(recording)
+                },
+            mode =>
+                {
+                    self.mode = mode;
+                    None
+                }
+        }
+    }
+
+    /// Start replaying `recording`, discarding any in-progress recording or replay
+    pub fn start_replay(&mut self, recording: InputRecording)
+    {
+        self.mode = RecorderMode::Replaying { recording, next_event: 0 };
+        self.frame = 0;
+        self.time = 0.0;
+    }
+
+    /// Stop any in-progress replay
+    pub fn stop_replay(&mut self)
+    {
+        if self.is_replaying()
+        {
+            self.mode = RecorderMode::Idle;
+        }
+    }
+
+    pub fn is_recording(&self) -> bool
+    {
+        matches!(self.mode, RecorderMode::Recording { .. })
+    }
+
+    pub fn is_replaying(&self) -> bool
+    {
+        matches!(self.mode, RecorderMode::Replaying { .. })
+    }
+
+    /// The current frame of the recording or replay, if either is in progress
+    pub fn frame(&self) -> Option<u64>
+    {
+        match self.mode
+        {
+            RecorderMode::Idle => None,
+            _ => Some(self.frame),
+        }
+    }
+
+    /// Record `event` into the current frame if recording
+    pub fn record(&mut self, event: &InputEvent)
+    {
+        if let RecorderMode::Recording { recording, .. } = &mut self.mode
+        {
+            recording.events.push(RecordedEvent { frame: self.frame, time: self.time, event: event.clone() });
+        }
+    }
+
+    /// Finish the current frame, moving on to the next one
+    ///
+    /// `time` is the current render loop time
+    /// When replaying, returns the events that were recorded during the frame that was just finished,
+    /// which must be applied before the frame's input snapshot is taken
+    pub fn end_frame(&mut self, time: Duration) -> Vec<InputEvent>
+    {
+        let mut replayed = vec![];
+        let mut finished = false;
+
+        match &mut self.mode
+        {
+            RecorderMode::Idle => return replayed,
+            RecorderMode::Recording { start_time, .. } =>
+                {
+                    let start_time = *start_time.get_or_insert(time);
+                    self.time = time.checked_sub(start_time).unwrap_or_default().as_secs_f64() * 1000.0;
+                },
+            RecorderMode::Replaying { recording, next_event } =>
+                {
+                    while let Some(recorded) = recording.events.get(*next_event)
+                    {
+                        if recorded.frame > self.frame
+                        {
+                            break;
+                        }
+                        replayed.push(recorded.event.clone());
+                        *next_event += 1;
+                    }
+                    finished = self.frame + 1 >= recording.frames && *next_event >= recording.events.len();
+                },
+        }
+
+        if finished
+        {
+            self.mode = RecorderMode::Idle;
+        }
+        else
+        {
+            self.frame += 1;
+        }
+        replayed
+    }
+}
+
+impl fmt::Display for InputEvent
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            InputEvent::KeyDown { key, repeat, modifiers } => write!(f, "keydown\t{}\t{}\t{}", key, repeat, modifiers),
+            InputEvent::KeyUp { key, modifiers } => write!(f, "keyup\t{}\t{}", key, modifiers),
+            InputEvent::MouseDown { button } => write!(f, "mousedown\t{}", button),
+            InputEvent::MouseUp { button } => write!(f, "mouseup\t{}", button),
+            InputEvent::MouseMove { pos, movement } => write!(f, "mousemove\t{}\t{}\t{}\t{}", pos.0, pos.1, movement.0, movement.1),
+            InputEvent::Wheel { delta } => write!(f, "wheel\t{}\t{}", delta.0, delta.1),
+        }
+    }
+}
+
+/// Serializes the recording as text, one tab separated event per line,
+/// so that recordings can be saved and shipped alongside the website
+impl fmt::Display for InputRecording
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        writeln!(f, "frames\t{}", self.frames)?;
+        for recorded in &self.events
+        {
+            writeln!(f, "{}\t{}\t{}", recorded.frame, recorded.time, recorded.event)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for InputRecording
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        fn field<'a, T: FromStr>(fields: &mut impl Iterator<Item = &'a str>, name: &str, line: usize) -> Result<T, String>
+        {
+            fields.next()
+                .ok_or_else(|| format!("line {}: missing {}", line, name))?
+                .parse()
+                .map_err(|_| format!("line {}: invalid {}", line, name))
+        }
+
+        let mut recording = InputRecording::default();
+        let mut has_header = false;
+
+        for (i, line) in s.lines().enumerate()
+        {
+            let line_num = i + 1;
+            if line.trim().is_empty()
+            {
+                continue;
+            }
+            let mut fields = line.split('\t');
+
+            if !has_header
+            {
+                if fields.next() != Some("frames")
+                {
+                    return Err(format!("line {}: expected \"frames\" header", line_num));
+                }
+                recording.frames = field(&mut fields, "frame count", line_num)?;
+                has_header = true;
+                continue;
+            }
+
+            let frame: u64 = field(&mut fields, "frame", line_num)?;
+            let time: f64 = field(&mut fields, "time", line_num)?;
+            let kind: String = field(&mut fields, "event type", line_num)?;
+            let event = match kind.as_str()
+            {
+                "keydown" => InputEvent::KeyDown
+                {
+                    key: field(&mut fields, "key", line_num)?,
+                    repeat: field(&mut fields, "repeat", line_num)?,
+                    modifiers: field(&mut fields, "modifiers", line_num)?,
+                },
+                "keyup" => InputEvent::KeyUp
+                {
+                    key: field(&mut fields, "key", line_num)?,
+                    modifiers: field(&mut fields, "modifiers", line_num)?,
+                },
+                "mousedown" => InputEvent::MouseDown { button: field(&mut fields, "button", line_num)? },
+                "mouseup" => InputEvent::MouseUp { button: field(&mut fields, "button", line_num)? },
+                "mousemove" => InputEvent::MouseMove
+                {
+                    pos: (field(&mut fields, "x", line_num)?, field(&mut fields, "y", line_num)?),
+                    movement: (field(&mut fields, "movement x", line_num)?, field(&mut fields, "movement y", line_num)?),
+                },
+                "wheel" => InputEvent::Wheel
+                {
+                    delta: (field(&mut fields, "delta x", line_num)?, field(&mut fields, "delta y", line_num)?),
+                },
+                _ => return Err(format!("line {}: unknown event type \"{}\"", line_num, kind)),
+            };
+
+            if recording.events.last().is_some_and(|last| last.frame > frame)
+            {
+                return Err(format!("line {}: events are out of order", line_num));
+            }
+            recording.events.push(RecordedEvent { frame, time, event });
+        }
+
+        if !has_header
+        {
+            return Err("missing \"frames\" header".to_string());
+        }
+        Ok(recording)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::input::
+    {
+        key::Key,
+        recorder::*,
+        states::Modifiers,
+    };
+
+    fn key_down(key: Key) -> InputEvent
+    {
+        InputEvent::KeyDown { key, repeat: false, modifiers: Modifiers::default() }
+    }
+
+    fn key_up(key: Key) -> InputEvent
+    {
+        InputEvent::KeyUp { key, modifiers: Modifiers::default() }
+    }
+
+    fn ms(ms: u64) -> Duration
+    {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_record()
+    {
+        let mut recorder = InputRecorder::new();
+        recorder.record(&key_down(Key::KeyA));
+        assert_eq!(None, recorder.frame());
+
+        recorder.start_recording(vec![key_down(Key::ShiftLeft)]);
+        recorder.end_frame(ms(1000));
+        recorder.record(&key_down(Key::KeyW));
+        recorder.end_frame(ms(1016));
+        recorder.end_frame(ms(1032));
+        recorder.record(&key_up(Key::KeyW));
+        recorder.end_frame(ms(1048));
+
+        let recording = recorder.stop_recording().expect("recording");
+        assert!(!recorder.is_recording());
+        assert_eq!(4, recording.frames);
+        assert_eq!(
+            vec![
+                RecordedEvent { frame: 0, time: 0.0, event: key_down(Key::ShiftLeft) },
+                RecordedEvent { frame: 1, time: 0.0, event: key_down(Key::KeyW) },
+                RecordedEvent { frame: 3, time: 32.0, event: key_up(Key::KeyW) },
+            ],
+            recording.events
+        );
+    }
+
+    #[test]
+    fn test_replay()
+    {
+        let recording = InputRecording
+        {
+            events: vec![
+                RecordedEvent { frame: 0, time: 0.0, event: key_down(Key::KeyA) },
+                RecordedEvent { frame: 0, time: 0.0, event: key_down(Key::KeyB) },
+                RecordedEvent { frame: 2, time: 33.3, event: key_down(Key::KeyC) },
+            ],
+            frames: 4,
+        };
+
+        let mut recorder = InputRecorder::new();
+        recorder.start_replay(recording);
+        assert_eq!(vec![key_down(Key::KeyA), key_down(Key::KeyB)], recorder.end_frame(ms(0)));
+        assert!(recorder.end_frame(ms(5)).is_empty());
+        assert_eq!(vec![key_down(Key::KeyC)], recorder.end_frame(ms(10)));
+        assert!(recorder.is_replaying());
+        assert!(recorder.end_frame(ms(15)).is_empty());
+        assert!(!recorder.is_replaying());
+    }
+
+    #[test]
+    fn test_serialization()
+    {
+        let recording = InputRecording
+        {
+            events: vec![
+                RecordedEvent { frame: 0, time: 0.0, event: key_down(Key::Space) },
+                RecordedEvent { frame: 0, time: 0.0, event: InputEvent::KeyDown
+                {
+                    key: Key::KeyW,
+                    repeat: true,
+                    modifiers: Modifiers { shift: true, ctrl: false, alt: true, meta: false },
+                } },
+                RecordedEvent { frame: 1, time: 16.5, event: key_up(Key::Space) },
+                RecordedEvent { frame: 1, time: 16.5, event: InputEvent::MouseDown { button: 0 } },
+                RecordedEvent { frame: 2, time: 33.0, event: InputEvent::MouseMove { pos: (10, 20), movement: (-3, 4) } },
+                RecordedEvent { frame: 2, time: 33.0, event: InputEvent::MouseUp { button: 0 } },
+                RecordedEvent { frame: 3, time: 50.25, event: InputEvent::Wheel { delta: (0.0, -100.0) } },
+            ],
+            frames: 5,
+        };
+
+        let text = recording.to_string();
+        assert_eq!(Ok(recording), text.parse::<InputRecording>());
+    }
+
+    #[test]
+    fn test_parse_errors()
+    {
+        assert!("".parse::<InputRecording>().is_err());
+        assert!("0\t0\tkeyup\tKeyA\tnone".parse::<InputRecording>().is_err());
+        // Keys are stored by code rather than by the character they type
+        assert!("frames\t2\n0\t0\tkeyup\ta\tnone".parse::<InputRecording>().is_err());
+        assert!("frames\t2\n0\t0\tkeyup\tKeyA\tshift+super".parse::<InputRecording>().is_err());
+        assert!("frames\t2\n0\t0\tjump".parse::<InputRecording>().is_err());
+        assert!("frames\t2\n0\t0\tmousedown\tleft".parse::<InputRecording>().is_err());
+        assert!("frames\t2\n1\t0\tkeyup\tKeyA\tnone\n0\t0\tkeyup\tKeyA\tnone".parse::<InputRecording>().is_err());
+    }
+}
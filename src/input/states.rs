@@ -1,39 +1,291 @@
 use std::
 {
+    fmt,
+    str::FromStr,
     hash::BuildHasherDefault,
     collections::HashMap,
     rc::Rc,
     cell::{Cell, RefCell},
+    time::Duration,
 };
 use twox_hash::XxHash32;
 use wasm_bindgen::JsValue;
-use crate::input::listener::EventListener;
+use crate::input::
+{
+    key::Key,
+    listener::EventListener,
+    recorder::{InputEvent, InputRecording, InputRecorder, RecordedEvent},
+};
 
-#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Ord, Eq, Hash)]
+#[derive(Debug, Copy, Clone, Default, PartialOrd, PartialEq, Ord, Eq, Hash)]
 pub enum InputState
 {
     /// Key/Button is not pressed
+    #[default]
     Up,
     /// Key/Button is pressed
     Down,
-    /// Key/Button is being held down
-    Repeating
+    /// Key/Button is being held down and the browser sent a key repeat since the last update
+    Repeating,
+    /// Key/Button went down since the last update
+    Pressed,
+    /// Key/Button went up since the last update
+    Released,
+}
+
+impl InputState
+{
+    /// Whether the key/button is currently held, including the frame it was pressed on
+    pub fn is_down(self) -> bool
+    {
+        matches!(self, InputState::Down | InputState::Repeating | InputState::Pressed)
+    }
+
+    /// Whether the key/button is currently not held, including the frame it was released on
+    #[allow(dead_code)]
+    pub fn is_up(self) -> bool
+    {
+        !self.is_down()
+    }
+}
+
+/// Modifier keys held during a key event, as reported by the event rather than tracked from
+/// keydowns, so that they stay correct when a modifier is released while the page is unfocused
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers
+{
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+impl Modifiers
+{
+    pub fn from_event(event: &web_sys::KeyboardEvent) -> Modifiers
+    {
+        Modifiers { shift: event.shift_key(), ctrl: event.ctrl_key(), alt: event.alt_key(), meta: event.meta_key() }
+    }
+
+    /// Whether any modifier is held
+    #[allow(dead_code)]
+    pub fn any(self) -> bool
+    {
+        self.shift || self.ctrl || self.alt || self.meta
+    }
+}
+
+/// Held modifiers separated by '+', or "none"
+impl fmt::Display for Modifiers
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let names: Vec<&str> = [(self.shift, "shift"), (self.ctrl, "ctrl"), (self.alt, "alt"), (self.meta, "meta")].iter()
+            .filter(|(held, _)| *held)
+            .map(|(_, name)| *name)
+            .collect();
+        if names.is_empty() { write!(f, "none") } else { write!(f, "{}", names.join("+")) }
+    }
+}
+
+impl FromStr for Modifiers
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        let mut modifiers = Modifiers::default();
+        if s == "none"
+        {
+            return Ok(modifiers);
+        }
+        for name in s.split('+')
+        {
+            match name
+            {
+                "shift" => modifiers.shift = true,
+                "ctrl" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "meta" => modifiers.meta = true,
+                _ => return Err(format!("unknown modifier \"{}\"", name)),
+            }
+        }
+        Ok(modifiers)
+    }
+}
+
+/// Compute the state of a key/button for a new frame
+///
+/// `prev` is the state from the previous frame
+/// `down` is whether the key/button is currently held
+/// `pressed` is whether the key/button went down at any point since the previous frame
+/// `repeated` is whether a key repeat was received since the previous frame
+pub fn next_input_state(prev: InputState, down: bool, pressed: bool, repeated: bool) -> InputState
+{
+    if !prev.is_down()
+    {
+        // A press and release within a single frame still reports `Pressed` for
+        // one frame so that it isn't lost. It is reported as `Released` next frame
+        if down || pressed { InputState::Pressed } else { InputState::Up }
+    }
+    else if !down
+    {
+        InputState::Released
+    }
+    else if repeated
+    {
+        InputState::Repeating
+    }
+    else
+    {
+        InputState::Down
+    }
+}
+
+/// Key/Button input received since the last frame update
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+struct RawState
+{
+    down: bool,
+    pressed: bool,
+    repeated: bool,
+}
+
+impl RawState
+{
+    fn press(&mut self, repeat: bool)
+    {
+        // Browsers only send repeats for a key that is already down,
+        // but a key that was down when the page got focus never sent its first keydown
+        if repeat && self.down
+        {
+            self.repeated = true;
+        }
+        else
+        {
+            self.pressed = true;
+        }
+        self.down = true;
+    }
+
+    fn release(&mut self)
+    {
+        self.down = false;
+    }
+
+    /// Advance `prev` to the next frame's state and clear the per-frame flags
+    fn advance(&mut self, prev: InputState) -> InputState
+    {
+        let state = next_input_state(prev, self.down, self.pressed, self.repeated);
+        self.pressed = false;
+        self.repeated = false;
+        state
+    }
+}
+
+type KeyMap<V> = HashMap<Key, V, BuildHasherDefault<XxHash32>>;
+
+/// Input received from the event listeners since the last update
+#[derive(Default)]
+struct PendingInput
+{
+    keys: KeyMap<RawState>,
+    modifiers: Modifiers,
+    mouse_buttons: [RawState; 5],
+    mouse_pos: (i32, i32),
+    mouse_delta: (i32, i32),
+    wheel_delta: (f64, f64),
+}
+
+impl PendingInput
+{
+    fn apply(&mut self, event: &InputEvent)
+    {
+        match event
+        {
+            InputEvent::KeyDown { key, repeat, modifiers } =>
+                {
+                    self.keys.entry(*key).or_default().press(*repeat);
+                    self.modifiers = *modifiers;
+                },
+            InputEvent::KeyUp { key, modifiers } =>
+                {
+                    self.keys.entry(*key).or_default().release();
+                    self.modifiers = *modifiers;
+                },
+            InputEvent::MouseDown { button } =>
+                {
+                    if let Some(raw) = self.mouse_buttons.get_mut(*button as usize)
+                    {
+                        raw.press(false);
+                    }
+                },
+            InputEvent::MouseUp { button } =>
+                {
+                    if let Some(raw) = self.mouse_buttons.get_mut(*button as usize)
+                    {
+                        raw.release();
+                    }
+                },
+            InputEvent::MouseMove { pos, movement } =>
+                {
+                    self.mouse_pos = *pos;
+                    self.mouse_delta.0 += movement.0;
+                    self.mouse_delta.1 += movement.1;
+                },
+            InputEvent::Wheel { delta } =>
+                {
+                    self.wheel_delta.0 += delta.0;
+                    self.wheel_delta.1 += delta.1;
+                },
+        }
+    }
+}
+
+/// Pass a live event on to `pending`, recording it if necessary
+/// Live events are dropped while a recording is being replayed
+fn receive(pending: &RefCell<PendingInput>, recorder: &RefCell<InputRecorder>, event: InputEvent)
+{
+    let mut recorder = recorder.borrow_mut();
+    if !recorder.is_replaying()
+    {
+        recorder.record(&event);
+        pending.borrow_mut().apply(&event);
+    }
+}
+
+/// Input state as of the last update
+#[derive(Default)]
+struct FrameInput
+{
+    keys: KeyMap<InputState>,
+    modifiers: Modifiers,
+    mouse_buttons: [InputState; 5],
+    curr_mouse_pos: (i32, i32),
+    last_mouse_pos: (i32, i32),
+    mouse_delta: (i32, i32),
+    wheel_delta: (f64, f64),
+    recorder_frame: Option<u64>,
 }
 
 /// Stores current states from user key and mouse input
 /// This should NOT be used to replace an on<*> event listener,
 /// this is just for being able to query the current input state
 /// of a keyboard/mouse
+///
+/// Events are collected as they come in, but the queryable state only changes when
+/// `update()` is called. `update()` should be called exactly once per frame/tick so that
+/// the `Pressed` and `Released` states, as well as the mouse and wheel deltas, are seen
+/// for exactly one frame
 pub struct InputStateListener
 {
     target: web_sys::EventTarget,
     listeners: Vec<EventListener>,
 
-    keys: Rc<RefCell<HashMap<String, InputState, BuildHasherDefault<XxHash32>>>>,
-
-    mouse_buttons: Rc<RefCell<[InputState; 5]>>,
-    curr_mouse_pos: Rc<Cell<(i32, i32)>>,
-    last_mouse_pos: Rc<Cell<(i32, i32)>>
+    pending: Rc<RefCell<PendingInput>>,
+    frame: RefCell<FrameInput>,
+    has_updated: Cell<bool>,
+    recorder: Rc<RefCell<InputRecorder>>,
 }
 
 impl InputStateListener
@@ -43,28 +295,25 @@ impl InputStateListener
         let mut manager = InputStateListener
         {
             target: target.clone(),
-            listeners: Vec::with_capacity(5),
+            listeners: Vec::with_capacity(6),
 
-            keys: Default::default(),
-
-            mouse_buttons: Rc::new(RefCell::new([InputState::Up; 5])),
-            curr_mouse_pos: Rc::new(Cell::new((0, 0))),
-            last_mouse_pos: Rc::new(Cell::new((0, 0))),
+            pending: Default::default(),
+            frame: Default::default(),
+            has_updated: Cell::new(false),
+            recorder: Default::default(),
         };
 
         // keydown listener
         let ev = EventListener::new(&manager.target, "keydown",
                                     {
-                                        clone!(manager.keys);
+                                        clone!(manager.pending, manager.recorder);
                                         move |event: web_sys::KeyboardEvent|
                                             {
-                                                if event.repeat()
-                                                {
-                                                    keys.borrow_mut().insert(event.key(), InputState::Repeating);
-                                                }
-                                                else
+                                                // Keys that can't be identified, such as IME composition, are ignored
+                                                if let Some(key) = Key::from_event(&event)
                                                 {
-                                                    keys.borrow_mut().insert(event.key(), InputState::Down);
+                                                    let modifiers = Modifiers::from_event(&event);
+                                                    receive(&pending, &recorder, InputEvent::KeyDown { key, repeat: event.repeat(), modifiers });
                                                 }
                                             }
                                     }).expect("keydown event listener");
@@ -73,22 +322,24 @@ impl InputStateListener
         // keyup listener
         let ev = EventListener::new(&manager.target, "keyup",
                                     {
-                                        clone!(manager.keys);
-                                        move |event: web_sys::KeyboardEvent| { keys.borrow_mut().insert(event.key(), InputState::Up); }
+                                        clone!(manager.pending, manager.recorder);
+                                        move |event: web_sys::KeyboardEvent|
+                                            {
+                                                if let Some(key) = Key::from_event(&event)
+                                                {
+                                                    receive(&pending, &recorder, InputEvent::KeyUp { key, modifiers: Modifiers::from_event(&event) });
+                                                }
+                                            }
                                     }).expect("keyup event listener");
         manager.listeners.push(ev);
 
         // mouseup listener
         let ev = EventListener::new(&manager.target, "mouseup",
                                     {
-                                        clone!(manager.mouse_buttons);
+                                        clone!(manager.pending, manager.recorder);
                                         move |event: web_sys::MouseEvent|
                                             {
-                                                let button = event.button();
-                                                if button <= 4
-                                                {
-                                                    mouse_buttons.borrow_mut()[button as usize] = InputState::Up;
-                                                }
+                                                receive(&pending, &recorder, InputEvent::MouseUp { button: event.button() });
                                             }
                                     }).expect("mouseup event listener");
         manager.listeners.push(ev);
@@ -96,14 +347,10 @@ impl InputStateListener
         // mousedown listener
         let ev = EventListener::new(&manager.target, "mousedown",
                                     {
-                                        clone!(manager.mouse_buttons);
+                                        clone!(manager.pending, manager.recorder);
                                         move |event: web_sys::MouseEvent|
                                             {
-                                                let button = event.button();
-                                                if button <= 4
-                                                {
-                                                    mouse_buttons.borrow_mut()[button as usize] = InputState::Down;
-                                                }
+                                                receive(&pending, &recorder, InputEvent::MouseDown { button: event.button() });
                                             }
                                     }).expect("mousedown event listener");
         manager.listeners.push(ev);
@@ -111,50 +358,342 @@ impl InputStateListener
         // mousemove listener
         let ev = EventListener::new(&manager.target, "mousemove",
                                     {
-                                        clone!(manager.curr_mouse_pos, manager.last_mouse_pos);
+                                        clone!(manager.pending, manager.recorder);
                                         move |event: web_sys::MouseEvent|
                                             {
-                                                let last = curr_mouse_pos.replace((event.offset_x(), event.offset_y()));
-                                                last_mouse_pos.set(last);
+                                                receive(&pending, &recorder, InputEvent::MouseMove
+                                                {
+                                                    pos: (event.offset_x(), event.offset_y()),
+                                                    movement: (event.movement_x(), event.movement_y()),
+                                                });
                                             }
                                     }).expect("mousemove event listener");
         manager.listeners.push(ev);
 
+        // wheel listener
+        let ev = EventListener::new(&manager.target, "wheel",
+                                    {
+                                        clone!(manager.pending, manager.recorder);
+                                        move |event: web_sys::WheelEvent|
+                                            {
+                                                receive(&pending, &recorder, InputEvent::Wheel { delta: (event.delta_x(), event.delta_y()) });
+                                            }
+                                    }).expect("wheel event listener");
+        manager.listeners.push(ev);
+
         Ok(manager)
     }
 
-    /// Get the current state of `key`
+    /// Take a snapshot of all input received since the last call to `update()`
+    /// This should be called once at the start of every frame/tick
+    /// `time` is the current render loop time, used for timestamping recorded input
+    pub fn update(&self, time: Duration)
+    {
+        let mut pending = self.pending.borrow_mut();
+        let mut frame = self.frame.borrow_mut();
+        let frame = &mut *frame;
+
+        // Feed in this frame's events if a recording is being replayed
+        let mut recorder = self.recorder.borrow_mut();
+        frame.recorder_frame = recorder.frame();
+        for event in recorder.end_frame(time)
+        {
+            pending.apply(&event);
+        }
+
+        for (key, raw) in pending.keys.iter_mut()
+        {
+            let state = frame.keys.entry(*key).or_default();
+            *state = raw.advance(*state);
+        }
+        frame.modifiers = pending.modifiers;
+        for (raw, state) in pending.mouse_buttons.iter_mut().zip(frame.mouse_buttons.iter_mut())
+        {
+            *state = raw.advance(*state);
+        }
+
+        // The first update has no previous position to compare against
+        frame.last_mouse_pos = if self.has_updated.replace(true) { frame.curr_mouse_pos } else { pending.mouse_pos };
+        frame.curr_mouse_pos = pending.mouse_pos;
+        frame.mouse_delta = std::mem::take(&mut pending.mouse_delta);
+        frame.wheel_delta = std::mem::take(&mut pending.wheel_delta);
+    }
+
+    /// Clear all input state, as if no input has been received yet
+    fn reset(&self)
+    {
+        *self.pending.borrow_mut() = Default::default();
+        *self.frame.borrow_mut() = Default::default();
+        self.has_updated.set(false);
+    }
+
+    /// Start recording all received input, discarding any in-progress recording or replay
+    ///
+    /// All input states are reset, with any keys and mouse buttons that are currently held
+    /// being reported as `Pressed` on the next update in both the recording and its replay
+    #[allow(dead_code)]
+    pub fn start_recording(&self)
+    {
+        let initial_events =
+            {
+                let pending = self.pending.borrow();
+                let mut events: Vec<InputEvent> = pending.keys.iter()
+                    .filter(|(_, raw)| raw.down)
+                    .map(|(key, _)| InputEvent::KeyDown { key: *key, repeat: false, modifiers: pending.modifiers })
+                    .collect();
+                // Sort the keys so that recordings don't depend on the hashmap's iteration order
+                events.sort_by_key(|event| event.to_string());
+                events.extend(pending.mouse_buttons.iter().enumerate()
+                    .filter(|(_, raw)| raw.down)
+                    .map(|(button, _)| InputEvent::MouseDown { button: button as i16 }));
+                events.push(InputEvent::MouseMove { pos: pending.mouse_pos, movement: (0, 0) });
+                events
+            };
+
+        self.reset();
+        {
+            let mut pending = self.pending.borrow_mut();
+            for event in &initial_events
+            {
+                pending.apply(event);
+            }
+        }
+        self.recorder.borrow_mut().start_recording(initial_events);
+    }
+
+    /// Stop recording input
+    /// Returns `None` if input wasn't being recorded
+    ///
+    /// Any keys and mouse buttons that are still held are released at the end of the recording
+    /// so that a replay doesn't leave them stuck down
+    #[allow(dead_code)]
+    pub fn stop_recording(&self) -> Option<InputRecording>
+    {
+        let mut recording = self.recorder.borrow_mut().stop_recording()?;
+        let pending = self.pending.borrow();
+
+        let mut releases: Vec<InputEvent> = pending.keys.iter()
+            .filter(|(_, raw)| raw.down)
+            .map(|(key, _)| InputEvent::KeyUp { key: *key, modifiers: Modifiers::default() })
+            .collect();
+        releases.sort_by_key(|event| event.to_string());
+        releases.extend(pending.mouse_buttons.iter().enumerate()
+            .filter(|(_, raw)| raw.down)
+            .map(|(button, _)| InputEvent::MouseUp { button: button as i16 }));
+
+        let (frame, time) = (recording.frames, recording.events.last().map_or(0.0, |last| last.time));
+        recording.events.extend(releases.into_iter().map(|event| RecordedEvent { frame, time, event }));
+        Some(recording)
+    }
+
+    /// Replay a previous recording, discarding any in-progress recording or replay
+    /// Live input is ignored until the replay finishes or `stop_replay()` is called
+    #[allow(dead_code)]
+    pub fn replay(&self, recording: InputRecording)
+    {
+        self.reset();
+        self.recorder.borrow_mut().start_replay(recording);
+    }
+
+    /// Stop an in-progress replay, returning control to live input
+    #[allow(dead_code)]
+    pub fn stop_replay(&self)
+    {
+        let mut recorder = self.recorder.borrow_mut();
+        if recorder.is_replaying()
+        {
+            recorder.stop_replay();
+            drop(recorder);
+            self.reset();
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn is_recording(&self) -> bool
+    {
+        self.recorder.borrow().is_recording()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_replaying(&self) -> bool
+    {
+        self.recorder.borrow().is_replaying()
+    }
+
+    /// Frame of the in-progress recording or replay that the last `update()` snapshotted,
+    /// if either was in progress
+    /// `Some(0)` means that a recording or replay has just started
+    #[allow(dead_code)]
+    pub fn recorder_frame(&self) -> Option<u64>
+    {
+        self.frame.borrow().recorder_frame
+    }
+
+    /// Get the state of `key` as of the last `update()`
     #[allow(dead_code)]
-    pub fn key_state(&self, key: &str) -> InputState
+    pub fn key_state(&self, key: Key) -> InputState
     {
         // If `key` is not in the internal hashmap, it has not
         // been pressed yet since it would otherwise have been entered
         // into the internal hashmap via the "keydown" event listener,
-        // so it either doesn't exist or is "Up"
-        *self.keys.borrow().get(key).unwrap_or(&InputState::Up)
+        // so it is "Up"
+        *self.frame.borrow().keys.get(&key).unwrap_or(&InputState::Up)
     }
 
-    /// Get the current state of `button`
+    /// Modifiers held as of the last `update()`, according to the most recent key event
+    /// Either of a pair of modifier keys, such as left and right shift, counts as the modifier being held
+    #[allow(dead_code)]
+    pub fn modifiers(&self) -> Modifiers
+    {
+        self.frame.borrow().modifiers
+    }
+
+    /// Get the state of `button` as of the last `update()`
     /// Will panic if `button` is not a valid mouse button
     #[allow(dead_code)]
     pub fn mouse_btn_state(&self, button: usize) -> InputState
     {
-        self.mouse_buttons.borrow()[button]
+        self.frame.borrow().mouse_buttons[button]
     }
 
-    /// Current mouse position
+    /// Mouse position as of the last `update()`
     /// 0,0 is top left of `target` element
     #[allow(dead_code)]
     pub fn curr_mouse_pos(&self) -> (i32, i32)
     {
-        self.curr_mouse_pos.get()
+        self.frame.borrow().curr_mouse_pos
     }
 
-    /// Last mouse position
+    /// Mouse position as of the `update()` before the last one
     /// 0,0 is top left of `target` element
     #[allow(dead_code)]
     pub fn last_mouse_pos(&self) -> (i32, i32)
     {
-        self.last_mouse_pos.get()
+        self.frame.borrow().last_mouse_pos
+    }
+
+    /// Total mouse movement between the last two `update()`s
+    /// Unlike the mouse position, this keeps reporting movement while the pointer is locked
+    #[allow(dead_code)]
+    pub fn mouse_delta(&self) -> (i32, i32)
+    {
+        self.frame.borrow().mouse_delta
+    }
+
+    /// Total wheel scroll between the last two `update()`s as (x, y)
+    #[allow(dead_code)]
+    pub fn wheel_delta(&self) -> (f64, f64)
+    {
+        self.frame.borrow().wheel_delta
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::input::
+    {
+        key::Key,
+        recorder::InputEvent,
+        states::*,
+    };
+
+    /// Step `raw` through a frame, returning the new state
+    fn step(raw: &mut RawState, state: &mut InputState) -> InputState
+    {
+        *state = raw.advance(*state);
+        *state
+    }
+
+    #[test]
+    fn test_press_and_hold()
+    {
+        let mut raw = RawState::default();
+        let mut state = InputState::Up;
+
+        assert_eq!(InputState::Up, step(&mut raw, &mut state));
+
+        raw.press(false);
+        assert_eq!(InputState::Pressed, step(&mut raw, &mut state));
+        assert_eq!(InputState::Down, step(&mut raw, &mut state));
+
+        raw.press(true);
+        assert_eq!(InputState::Repeating, step(&mut raw, &mut state));
+        assert_eq!(InputState::Down, step(&mut raw, &mut state));
+
+        raw.release();
+        assert_eq!(InputState::Released, step(&mut raw, &mut state));
+        assert_eq!(InputState::Up, step(&mut raw, &mut state));
+    }
+
+    #[test]
+    fn test_tap_within_frame()
+    {
+        let mut raw = RawState::default();
+        let mut state = InputState::Up;
+
+        raw.press(false);
+        raw.release();
+        assert_eq!(InputState::Pressed, step(&mut raw, &mut state));
+        assert_eq!(InputState::Released, step(&mut raw, &mut state));
+        assert_eq!(InputState::Up, step(&mut raw, &mut state));
+    }
+
+    #[test]
+    fn test_release_and_press_within_frame()
+    {
+        let mut raw = RawState::default();
+        let mut state = InputState::Up;
+
+        raw.press(false);
+        assert_eq!(InputState::Pressed, step(&mut raw, &mut state));
+
+        // Still held at the end of the frame, so the key never appears to go up
+        raw.release();
+        raw.press(false);
+        assert_eq!(InputState::Down, step(&mut raw, &mut state));
+    }
+
+    #[test]
+    fn test_repeat_without_press()
+    {
+        // A key held while the page gains focus only sends repeats
+        let mut raw = RawState::default();
+        let mut state = InputState::Up;
+
+        raw.press(true);
+        assert_eq!(InputState::Pressed, step(&mut raw, &mut state));
+        raw.press(true);
+        assert_eq!(InputState::Repeating, step(&mut raw, &mut state));
+    }
+
+    #[test]
+    fn test_modifiers()
+    {
+        let mut pending = PendingInput::default();
+        let shift = Modifiers { shift: true, ..Default::default() };
+        pending.apply(&InputEvent::KeyDown { key: Key::ShiftLeft, repeat: false, modifiers: shift });
+        pending.apply(&InputEvent::KeyDown { key: Key::KeyW, repeat: false, modifiers: shift });
+        assert_eq!(shift, pending.modifiers);
+
+        // Shift doesn't change which key is held
+        pending.apply(&InputEvent::KeyUp { key: Key::ShiftLeft, modifiers: Modifiers::default() });
+        assert!(pending.keys[&Key::KeyW].down);
+        assert_eq!(Modifiers::default(), pending.modifiers);
+
+        let modifiers = Modifiers { ctrl: true, meta: true, ..Default::default() };
+        assert_eq!("ctrl+meta", modifiers.to_string());
+        assert_eq!(Ok(modifiers), "ctrl+meta".parse());
+        assert_eq!(Ok(Modifiers::default()), "none".parse());
+    }
+
+    #[test]
+    fn test_is_down()
+    {
+        assert!(InputState::Pressed.is_down());
+        assert!(InputState::Down.is_down());
+        assert!(InputState::Repeating.is_down());
+        assert!(InputState::Released.is_up());
+        assert!(InputState::Up.is_up());
     }
-}
\ No newline at end of file
+}
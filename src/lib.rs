@@ -16,6 +16,8 @@ use std::
 {
     rc::Rc,
     cell::RefCell,
+    collections::HashMap,
+    thread::LocalKey,
     time::Duration,
 };
 
@@ -52,30 +54,41 @@ use crate::
         },
         gl_object::
         {
-            traits::GlObject,
+            traits::{GlObject, MemoryUsage},
             buffer::Buffer,
             ArrayBuffer,
             ElementArrayBuffer,
-            vertex_array::{AttribPointer, VertexArray},
+            vertex_array::VertexArray,
             texture::{Texture2d, Texture2dParams},
-            manager::{GlObjectManager},
+            manager::{GlObjectManager, Handle},
         },
         camera::Camera,
+        resize::CanvasResizer,
     },
     input::
     {
         input_consts::*,
+        key::Key,
         listener::EventListener,
         states::{InputState, InputStateListener},
+        gamepad::GamepadStateListener,
+        recorder::InputRecording,
+        pointer_lock::{PointerLockController, CameraMode},
+    },
+    math::
+    {
+        transform::{Transformation},
+        animation::{AnimationClip, AnimationPlayer, Channel, Interpolation, Keyframes, Playback, Track},
     },
-    math::transform::{Transformation},
     resource::
     {
         loader::{ResourceLoader, OnloadCallbackArgs,},
         manager::ResourceManager,
     },
 };
-use cgmath::{vec3, Deg};
+#[cfg(feature = "debug")]
+use crate::resource::hot_reload::HotReloader;
+use cgmath::{vec3, Deg, Quaternion, Rotation3};
 
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
@@ -88,9 +101,189 @@ extern
     fn log(s: &str);
 }
 
+/// Log `s` to the console, or to stderr outside of the browser, i.e. in native tests
 fn log_s(s: String)
 {
+    #[cfg(target_arch = "wasm32")]
     log(s.as_str());
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("{}", s);
+}
+
+thread_local!
+{
+    /// Input listener of the running visualization, used by the input recording functions
+    static INPUT_LISTENER: RefCell<Option<Rc<InputStateListener>>> = const { RefCell::new(None) };
+    /// Pointer lock/fullscreen controller of the running visualization
+    static POINTER_LOCK: RefCell<Option<Rc<PointerLockController>>> = const { RefCell::new(None) };
+    /// Object manager of the running visualization, used by the GL object inspector
+    static GL_MANAGER: RefCell<Option<Rc<RefCell<GlObjectManager>>>> = const { RefCell::new(None) };
+}
+
+/// Run `func` with one of the running visualization's globals
+fn with_global<G, T>(global: &'static LocalKey<RefCell<Option<Rc<G>>>>, func: impl FnOnce(&G) -> Result<T, JsValue>) -> Result<T, JsValue>
+{
+    global.with(|global|
+        {
+            match global.borrow().as_ref()
+            {
+                Some(global) => func(global),
+                None => Err(JsValue::from_str("visualization has not been started")),
+            }
+        })
+}
+
+/// Run `func` with the running visualization's input listener
+fn with_input_listener<T>(func: impl FnOnce(&InputStateListener) -> Result<T, JsValue>) -> Result<T, JsValue>
+{
+    with_global(&INPUT_LISTENER, func)
+}
+
+/// Run `func` with the running visualization's pointer lock/fullscreen controller
+fn with_pointer_lock<T>(func: impl FnOnce(&PointerLockController) -> Result<T, JsValue>) -> Result<T, JsValue>
+{
+    with_global(&POINTER_LOCK, func)
+}
+
+/// Describe every GL object owned by the running visualization as JSON
+/// Each object lists its label, handle, GL id, size, whether it is bound, and state specific to its type,
+/// such as shader sources and uniform blocks
+#[wasm_bindgen]
+pub fn inspect_gl_objects() -> Result<String, JsValue>
+{
+    with_global(&GL_MANAGER, |manager|
+        {
+            let manager = manager.try_borrow().map_err(|_| JsValue::from_str("GL objects are being modified"))?;
+            serde_json::to_string_pretty(&manager.inspect()).map_err(|err| JsValue::from_str(&err.to_string()))
+        })
+}
+
+/// Log every GL object still owned by the running visualization, along with the memory it uses and its label
+#[wasm_bindgen]
+pub fn report_live_gl_objects() -> Result<(), JsValue>
+{
+    with_global(&GL_MANAGER, |manager|
+        {
+            let manager = manager.try_borrow().map_err(|_| JsValue::from_str("GL objects are being modified"))?;
+            manager.report_live_objects();
+            Ok(())
+        })
+}
+
+/// Lock the pointer to the canvas, switching the camera to free-look controls
+/// Must be called from within a user input event handler, i.e. a button's onclick
+#[wasm_bindgen]
+pub fn enter_pointer_lock() -> Result<(), JsValue>
+{
+    with_pointer_lock(|controller|
+        {
+            controller.request_pointer_lock();
+            Ok(())
+        })
+}
+
+/// Release the pointer, switching the camera back to orbit controls
+#[wasm_bindgen]
+pub fn exit_pointer_lock() -> Result<(), JsValue>
+{
+    with_pointer_lock(|controller|
+        {
+            controller.exit_pointer_lock();
+            Ok(())
+        })
+}
+
+/// Make the canvas fullscreen
+/// Must be called from within a user input event handler, i.e. a button's onclick
+#[wasm_bindgen]
+pub fn enter_fullscreen() -> Result<(), JsValue>
+{
+    with_pointer_lock(|controller| controller.request_fullscreen())
+}
+
+#[wasm_bindgen]
+pub fn exit_fullscreen() -> Result<(), JsValue>
+{
+    with_pointer_lock(|controller|
+        {
+            controller.exit_fullscreen();
+            Ok(())
+        })
+}
+
+#[wasm_bindgen]
+pub fn is_pointer_locked() -> Result<bool, JsValue>
+{
+    with_pointer_lock(|controller| Ok(controller.is_pointer_locked()))
+}
+
+#[wasm_bindgen]
+pub fn is_fullscreen() -> Result<bool, JsValue>
+{
+    with_pointer_lock(|controller| Ok(controller.is_fullscreen()))
+}
+
+/// Set a function to be called with `(locked, fullscreen)` whenever the pointer lock
+/// or fullscreen state changes. Pass `null` to remove the callback
+#[wasm_bindgen]
+pub fn set_view_mode_callback(callback: Option<js_sys::Function>) -> Result<(), JsValue>
+{
+    with_pointer_lock(|controller|
+        {
+            controller.set_on_change(callback);
+            Ok(())
+        })
+}
+
+/// Start recording user input
+/// The camera and robots are moved back to their starting positions so that the recording
+/// can be replayed from the same view. Gamepad input is ignored and the mouse uses the orbit
+/// controls while recording or replaying, since only keyboard and mouse input is recorded
+#[wasm_bindgen]
+pub fn start_input_recording() -> Result<(), JsValue>
+{
+    with_input_listener(|listener|
+        {
+            listener.start_recording();
+            Ok(())
+        })
+}
+
+/// Stop recording user input
+/// Returns the recording as text that can be given to `replay_input_recording`
+#[wasm_bindgen]
+pub fn stop_input_recording() -> Result<String, JsValue>
+{
+    with_input_listener(|listener|
+        {
+            listener.stop_recording()
+                .map(|recording| recording.to_string())
+                .ok_or_else(|| JsValue::from_str("input is not being recorded"))
+        })
+}
+
+/// Replay a recording from `stop_input_recording`, starting from the camera's starting position
+/// User input is ignored until the replay finishes or `stop_input_replay` is called
+#[wasm_bindgen]
+pub fn replay_input_recording(recording: &str) -> Result<(), JsValue>
+{
+    let recording = recording.parse::<InputRecording>().map_err(|err| JsValue::from_str(&err))?;
+    with_input_listener(|listener|
+        {
+            listener.replay(recording);
+            Ok(())
+        })
+}
+
+/// Stop an in-progress replay and give control back to the user
+#[wasm_bindgen]
+pub fn stop_input_replay() -> Result<(), JsValue>
+{
+    with_input_listener(|listener|
+        {
+            listener.stop_replay();
+            Ok(())
+        })
 }
 
 
@@ -166,6 +359,15 @@ pub fn init_visualization(canvas_id: &str, resource_dir: &str) -> Result<(), JsV
                 resource_manager.borrow_mut().insert_with_name("texture_vert.glsl".to_string(), bytes);
             });
     }
+    {
+        let resource = resource_dir.to_owned() + "shaders/vert_data.glsl";
+        let request_handle = resource_loader.add_request("GET", resource)?;
+        clone!(resource_manager);
+        resource_loader.set_request_onload(request_handle, move |OnloadCallbackArgs(_, bytes)|
+            {
+                resource_manager.borrow_mut().insert_with_name("vert_data.glsl".to_string(), bytes);
+            });
+    }
     {
         {
             let resource = resource_dir.to_owned() + "shaders/texture_frag.glsl";
@@ -190,7 +392,7 @@ pub fn init_visualization(canvas_id: &str, resource_dir: &str) -> Result<(), JsV
     Ok(())
 }
 
-fn start(canvas_id: String, _resource_dir: String, resource_manager: Rc<RefCell<ResourceManager>>) -> Result<(), JsValue>
+fn start(canvas_id: String, #[cfg_attr(not(feature = "debug"), allow(unused_variables))] resource_dir: String, resource_manager: Rc<RefCell<ResourceManager>>) -> Result<(), JsValue>
 {
 
     // Get HTML element references
@@ -201,131 +403,36 @@ fn start(canvas_id: String, _resource_dir: String, resource_manager: Rc<RefCell<
             let elem = document.get_element_by_id(&canvas_id).expect("canvas element exists");
             elem.dyn_into::<HtmlCanvasElement>()?
         };
-    let canvas_size: (u32, u32) = (canvas.width(), canvas.height());
+    let canvas_resizer = CanvasResizer::new(&window, &canvas)?;
+    canvas_resizer.update();
+    let canvas_size = canvas_resizer.shared_size();
 
     let context = new_context(&canvas)?;
     let context_config_func = move |context: &Context|
         {
-            context.viewport(0, 0, canvas_size.0 as i32, canvas_size.1 as i32);
-            context.pixel_storei(Context::UNPACK_ALIGNMENT, 1);
-            context.enable(Context::CULL_FACE);
-            context.enable(Context::DEPTH_TEST);
+            let canvas_size = canvas_size.get();
+            configure_context(context, canvas_size.0 as i32, canvas_size.1 as i32);
         };
     context_config_func(&context);
 
-    let robot_obj = resource_manager.borrow().get_by_name(&"robot.obj".to_string()).expect("robot obj resource").clone();
-    let robot_mesh = Mesh::from_reader(&*robot_obj).expect("robot mesh");
-    let room_obj = resource_manager.borrow().get_by_name(&"room.obj".to_string()).expect("room obj resource").clone();
-    let room_mesh = Mesh::from_reader(&*room_obj).expect("room mesh");
-
     // Setup object manager
     let manager = Rc::new(RefCell::new(GlObjectManager::new()));
+    GL_MANAGER.with(|global| *global.borrow_mut() = Some(manager.clone()));
     let mut manager_ref = manager.borrow_mut();
 
+    let scene = setup_scene(&context, &mut manager_ref, &resource_manager.borrow());
+    let texture_atlas_handle = scene.texture_atlas_handle;
+    // Textures that can be used by reloaded materials
+    #[cfg(feature = "debug")]
+    let textures = scene.textures;
+    let (robot_vao_handle, robot_arr_buff_handle, robot_elem_buff_handle) = (scene.robot.vert_arr_handle, scene.robot.arr_buff_handle, scene.robot.elem_buff_handle);
+    let (room_vao_handle, room_arr_buff_handle, room_elem_buff_handle) = (scene.room.vert_arr_handle, scene.room.arr_buff_handle, scene.room.elem_buff_handle);
 
-    // Texture atlas
-    let tex_atlas_pbm = resource_manager.borrow().get_by_name(&"tex_atlas.pbm".to_string()).expect("texture atlas").clone();
-
-    let texture_atlas_handle = manager_ref.insert_texture2d(
-        Texture2d::new(&context, Texture2dParams
-        {
-            target: Context::TEXTURE_2D,
-            internal_format: Context::RGB8,
-            format: Context::RGB,
-            size: (800, 400),
-            wrap_type: Context::REPEAT,
-            filter_type: Context::LINEAR,
-            data: tex_atlas_pbm
-        }).expect("texture")
-    );
-    {
-        Texture2d::bind(&manager_ref, texture_atlas_handle);
-        manager_ref.get_texture2d(texture_atlas_handle).expect("atlas texture2d").setup_texture().expect("texture2d setup");
-    }
-
-    let robot_vao_handle = manager_ref.insert_vertex_array(
-        VertexArray::new(&context).expect("robot vertex array")
-    );
-
-    {
-        let arr_buff_handle = manager_ref.insert_array_buffer(
-            ArrayBuffer::new(&context).expect("robot array buffer")
-        );
-
-        let elem_buff_handle = manager_ref.insert_element_array_buffer(
-            ElementArrayBuffer::new(&context).expect("robot element array buffer")
-        );
-        VertexArray::bind(&manager_ref, robot_vao_handle);
-        // Setup the vertex array buffer with the robot vertices
-        ArrayBuffer::bind(&manager_ref, arr_buff_handle);
-        {
-            let mut arr_buff = manager_ref.get_mut_array_buffer(arr_buff_handle).expect("robot array buffer");
-            arr_buff.buffer_data(&robot_mesh.vertices, Context::STATIC_DRAW);
-        }
-        // Setup the element array buffer with the robot indices
-        ElementArrayBuffer::bind(&manager_ref, elem_buff_handle);
-        {
-            let mut elem_arr_buff = manager_ref.get_mut_element_array_buffer(elem_buff_handle).expect("robot element array buffer");
-            elem_arr_buff.buffer_data(&robot_mesh.indices, Context::STATIC_DRAW);
-        }
-        // Register the vertex and element array buffers with the VAO
-        {
-            let mut vert_arr = manager_ref.get_mut_vertex_array(robot_vao_handle).expect("vertex array");
-
-            let attribs = vec![
-                AttribPointer::without_defaults(0, 3, Context::FLOAT, false, std::mem::size_of::<Vertex>() as i32, offset_of!(Vertex, position) as i32),
-                AttribPointer::without_defaults(1, 3, Context::FLOAT, false, std::mem::size_of::<Vertex>() as i32, offset_of!(Vertex, normal) as i32),
-                AttribPointer::without_defaults(2, 2, Context::FLOAT, false, std::mem::size_of::<Vertex>() as i32, offset_of!(Vertex, texcoord) as i32),
-            ];
-            vert_arr.register_array_buffer(arr_buff_handle, Some(attribs));
-            vert_arr.register_element_array_buffer(elem_buff_handle, None);
-        }
-        VertexArray::unbind(&manager_ref, robot_vao_handle);
-        ArrayBuffer::unbind(&manager_ref, arr_buff_handle);
-        ElementArrayBuffer::unbind(&manager_ref, elem_buff_handle);
-    }
-
-    let room_vao_handle = manager_ref.insert_vertex_array(
-        VertexArray::new(&context).expect("room vertex array")
-    );
-
-    {
-        let arr_buff_handle = manager_ref.insert_array_buffer(
-            ArrayBuffer::new(&context).expect("room array buffer")
-        );
-
-        let elem_buff_handle = manager_ref.insert_element_array_buffer(
-            ElementArrayBuffer::new(&context).expect("room element array buffer")
-        );
-        VertexArray::bind(&manager_ref, room_vao_handle);
-        // Setup the vertex array buffer with the room vertices
-        ArrayBuffer::bind(&manager_ref, arr_buff_handle);
-        {
-            let mut arr_buff = manager_ref.get_mut_array_buffer(arr_buff_handle).expect("room array buffer");
-            arr_buff.buffer_data(&room_mesh.vertices, Context::STATIC_DRAW);
-        }
-        // Setup the element array buffer with the room indices
-        ElementArrayBuffer::bind(&manager_ref, elem_buff_handle);
-        {
-            let mut elem_arr_buff = manager_ref.get_mut_element_array_buffer(elem_buff_handle).expect("room element array buffer");
-            elem_arr_buff.buffer_data(&room_mesh.indices, Context::STATIC_DRAW);
-        }
-        // Register the vertex and element array buffers with the VAO
-        {
-            let mut vert_arr = manager_ref.get_mut_vertex_array(room_vao_handle).expect("room vertex array");
-
-            let attribs = vec![
-                AttribPointer::without_defaults(0, 3, Context::FLOAT, false, std::mem::size_of::<Vertex>() as i32, offset_of!(Vertex, position) as i32),
-                AttribPointer::without_defaults(1, 3, Context::FLOAT, false, std::mem::size_of::<Vertex>() as i32, offset_of!(Vertex, normal) as i32),
-                AttribPointer::without_defaults(2, 2, Context::FLOAT, false, std::mem::size_of::<Vertex>() as i32, offset_of!(Vertex, texcoord) as i32),
-            ];
-            vert_arr.register_array_buffer(arr_buff_handle, Some(attribs));
-            vert_arr.register_element_array_buffer(elem_buff_handle, None);
-        }
-        VertexArray::unbind(&manager_ref, room_vao_handle);
-        ArrayBuffer::unbind(&manager_ref, arr_buff_handle);
-        ElementArrayBuffer::unbind(&manager_ref, elem_buff_handle);
-    }
+    // The meshes and textures are kept by the resource manager, so they don't need another copy
+    // The restore func supplies them again after a context loss
+    manager_ref.drop_shadow_copies();
+    #[cfg(feature = "debug")]
+    crate::log_s(format!("GPU memory after setup:\n{}", manager_ref.memory_summary()));
 
     // Release the borrow on the manager
     drop(manager_ref);
@@ -342,88 +449,96 @@ fn start(canvas_id: String, _resource_dir: String, resource_manager: Rc<RefCell<
 
     let delta_time: f32 = 0.01;
     let mut accumulator: f32 = 0.0;
+    let mut paused = false;
 
     let renderer = Renderer::new(&context.borrow(), &mut manager.borrow_mut(), &resource_manager.borrow()).expect("renderer");
-
-    let mut robot1_transform = Transformation::new();
-    robot1_transform.global.translate(vec3(3.0, 0.25, 2.5));
-    robot1_transform.local.rotate_angle_axis(Deg(90.0), vec3(0.0, 1.0, 0.0));
-    robot1_transform.local.translate(vec3(1.0, 0.0, 0.0));
-
-    let mut robot2_transform = Transformation::new();
-    robot2_transform.global.translate(vec3(-3.0, 0.25, -3.0));
-    robot2_transform.local.rotate_angle_axis(Deg(90.0), vec3(0.0, 1.0, 0.0));
-    robot2_transform.local.translate(vec3(1.0, 0.0, 0.0));
+    renderer.validate_vertex_layout::<Vertex>(&manager.borrow()).expect("vertex layout");
+    renderer.validate_vertex_array(&manager.borrow(), robot_vao_handle).expect("robot vertex array layout");
+    renderer.validate_vertex_array(&manager.borrow(), room_vao_handle).expect("room vertex array layout");
+
+    let (mut robot1_transform, mut robot2_transform) = robot_transforms();
+
+    // Spin the robots around their global positions at 40 degrees per second, starting from their 90 degree local orientation
+    // Keys are a third of a turn apart so that each segment takes the short way around
+    let spin_times = vec![0.0, 3.0, 6.0, 9.0];
+    let spin_values = [90.0, 210.0, 330.0, 450.0].iter().map(|angle| Quaternion::from_angle_y(Deg(*angle))).collect();
+    let spin_keyframes = Keyframes::new(Interpolation::Linear, spin_times, spin_values).expect("spin keyframes");
+    let spin_clip = Rc::new(AnimationClip::new("spin", vec![Channel { target: 0, track: Track::Rotation(spin_keyframes) }]));
+    let mut robot1_spin = AnimationPlayer::new(spin_clip.clone(), Playback::Loop);
+    let mut robot2_spin = AnimationPlayer::new(spin_clip, Playback::Loop);
+    robot2_spin.set_speed(-1.0);
 
     // Setup render information
-    let robot_renderable = RenderDto
-    {
-        tex_handle: texture_atlas_handle,
-        vert_arr_handle: robot_vao_handle,
-        num_indices: robot_mesh.indices.len() as i32,
-    };
+    #[cfg_attr(not(feature = "debug"), allow(unused_mut))]
+    let mut robot_renderable = scene.robot.renderable;
 
     let mut room_transform = Transformation::new();
-    let room_renderable = RenderDto
-    {
-        tex_handle: texture_atlas_handle,
-        vert_arr_handle: room_vao_handle,
-        num_indices: room_mesh.indices.len() as i32,
-    };
-
-    let perspective = cgmath::perspective(Deg(45.0f32), canvas_size.0 as f32 / canvas_size.1 as f32, 0.1f32, 50.0f32);
-    let camera = Rc::new(RefCell::new(
-        Camera::from_eye(
-            vec3(0.0, 0.0, 0.0),
-            vec3(0.0, -0.5, -1.0),
-            vec3(0.0, 1.0, 0.0)
-        )));
-    camera.borrow_mut().move_cam_locked(vec3(0.0, 5.0, 9.0));
-    {
-        clone!(camera);
-        let callback = move |event: web_sys::WheelEvent|
-            {
-                if event.delta_y() > 0.0
-                {
-                    camera.borrow_mut().move_cam_long_locked(0.1);
-                }
-                else if event.delta_y() < 0.0
-                {
-                    camera.borrow_mut().move_cam_long_locked(-0.1);
-                }
-            };
-        let ev = EventListener::new(&canvas, "wheel", callback).expect("zoom event listener");
-        ev.forget();
-    }
+    #[cfg_attr(not(feature = "debug"), allow(unused_mut))]
+    let mut room_renderable = scene.room.renderable;
 
-    {
-        clone!(camera);
-        let canvas_clone = canvas.clone();
-        let callback = move |event: web_sys::MouseEvent|
-            {
-                if event.buttons() == 1
+    let mut perspective = projection(canvas_resizer.aspect_ratio());
+    let camera = Rc::new(RefCell::new(start_camera()));
+    // Starting point for recorded/replayed camera tours
+    let initial_camera = *camera.borrow();
+    let orbit_center = vec3(0.0, 0.0, 0.0);
+
+    let pointer_lock = Rc::new(PointerLockController::new(&document, &canvas)?);
+    POINTER_LOCK.with(|controller| *controller.borrow_mut() = Some(pointer_lock.clone()));
+
+    let input_listener = Rc::new(InputStateListener::new(&canvas).expect("input state listener"));
+    INPUT_LISTENER.with(|listener| *listener.borrow_mut() = Some(input_listener.clone()));
+    let gamepad_listener = GamepadStateListener::new(&window).expect("gamepad state listener");
+
+    // Pick up changes to shaders and models without restarting the visualization
+    #[cfg(feature = "debug")]
+    let mut hot_reloader =
+        {
+            let mut hot_reloader = HotReloader::new(&resource_dir, resource_manager.clone(), Duration::from_secs(1));
+            hot_reloader.watch("shaders/texture_vert.glsl", "texture_vert.glsl");
+            hot_reloader.watch("shaders/texture_frag.glsl", "texture_frag.glsl");
+            hot_reloader.watch("shaders/vert_data.glsl", "vert_data.glsl");
+            hot_reloader.watch("models/robot.obj", "robot.obj");
+            hot_reloader.watch("models/room.obj", "room.obj");
+            hot_reloader
+        };
+
+    // Supply the data that was dropped with the shadow copies after a context loss
+    let restore_func =
+        {
+            clone!(resource_manager);
+
+            move |_context: &Context, manager: &GlObjectManager|
                 {
-                    canvas_clone.request_pointer_lock();
-                    borrow_mut!(camera);
-                    if event.movement_x() != 0
+                    let resource_manager = resource_manager.borrow();
+                    for (name, vert_arr_handle, arr_buff_handle, elem_buff_handle) in [
+                        ("robot.obj", robot_vao_handle, robot_arr_buff_handle, robot_elem_buff_handle),
+                        ("room.obj", room_vao_handle, room_arr_buff_handle, room_elem_buff_handle),
+                    ]
                     {
-                        //camera.move_cam_lat(event.movement_x() as f32 / 800.0);
-                        let delta = if event.movement_x() < 0 { -1.0 } else { 1.0 };
-                        camera.rotate_world_yaw(delta);
+                        if manager.get_array_buffer(arr_buff_handle).is_some_and(|buffer| buffer.needs_data())
+                        {
+                            // Hot reloading may have left an OBJ that doesn't parse, in which case the mesh is skipped
+                            // until a working OBJ is reloaded
+                            let restored = Mesh::from_resource(name, &resource_manager)
+                                .map_err(|err| err.to_string())
+                                .and_then(|mesh| buffer_mesh(manager, &mesh, vert_arr_handle, arr_buff_handle, elem_buff_handle));
+                            if let Err(err) = restored
+                            {
+                                crate::log_s(format!("Error restoring {}, skipping it: {}", name, err));
+                            }
+                        }
                     }
-                    if event.movement_y() != 0
+                    if manager.get_texture2d(texture_atlas_handle).is_some_and(|texture| texture.needs_data())
                     {
-                        //camera.move_cam_vert(event.movement_y() as f32 / 800.0);
-                        let delta = if event.movement_y() < 0 { -1.0 } else { 1.0 };
-                        camera.rotate_cam_pitch(delta);
+                        let tex_atlas_pbm = resource_manager.get_by_name(&"tex_atlas.pbm".to_string()).expect("texture atlas").clone();
+                        Texture2d::bind(manager, texture_atlas_handle);
+                        if let Err(err) = manager.get_mut_texture2d(texture_atlas_handle).expect("atlas texture2d").set_data(tex_atlas_pbm)
+                        {
+                            crate::log_s(format!("Error restoring the texture atlas: {}", err));
+                        }
                     }
                 }
-            };
-        let ev = EventListener::new(&canvas, "mousemove", callback).expect("mouse move event listener");
-        ev.forget();
-    }
-
-    let input_listener = Rc::new(InputStateListener::new(&canvas).expect("input state listener"));
+        };
 
     let render_func =
         {
@@ -446,19 +561,94 @@ fn start(canvas_id: String, _resource_dir: String, resource_manager: Rc<RefCell<
                             if time > 0.25 { 0.25 } else { time }
                         };
 
-                    accumulator += elapsed_time;
+                    #[cfg(feature = "debug")]
+                    {
+                        hot_reloader.poll(now_time);
+                        let changed = hot_reloader.take_changed();
+                        if changed.iter().any(|name| name.ends_with(".glsl"))
+                        {
+                            match renderer.reload_shaders(&manager.borrow(), &resource_manager.borrow())
+                            {
+                                Ok(dropped) =>
+                                    {
+                                        crate::log("Reloaded shaders");
+                                        for name in dropped
+                                        {
+                                            crate::log_s(format!("Warning: {} no longer exists in the reloaded shaders", name));
+                                        }
+                                    },
+                                Err(err) => crate::log_s(format!("Error reloading shaders, keeping the previous shaders: {}", err)),
+                            }
+                        }
+                        for name in changed.iter().filter(|name| name.ends_with(".obj"))
+                        {
+                            let (renderable, arr_buff_handle, elem_buff_handle) = match name.as_str()
+                            {
+                                "robot.obj" => (&mut robot_renderable, robot_arr_buff_handle, robot_elem_buff_handle),
+                                "room.obj" => (&mut room_renderable, room_arr_buff_handle, room_elem_buff_handle),
+                                _ => continue,
+                            };
+                            match reload_mesh(&manager.borrow(), &resource_manager.borrow(), name, renderable.vert_arr_handle, arr_buff_handle, elem_buff_handle,
+                                              &textures, Some(texture_atlas_handle))
+                            {
+                                Ok(reloaded) =>
+                                    {
+                                        *renderable = reloaded;
+                                        crate::log_s(format!("Reloaded {}", name));
+                                    },
+                                Err(err) => crate::log_s(format!("Error reloading {}, keeping the previous mesh: {}", name, err)),
+                            }
+                        }
+                    }
+
+                    // Snapshot the input received since the last frame
+                    input_listener.update(now_time);
+                    // Recordings and replays always start from the same view and animation state so that they line up
+                    if input_listener.recorder_frame() == Some(0)
+                    {
+                        *camera.borrow_mut() = initial_camera;
+                        for spin in [&mut robot1_spin, &mut robot2_spin]
+                        {
+                            spin.seek(0.0);
+                        }
+                        accumulator = 0.0;
+                        paused = false;
+                    }
+                    // Only the listener's input is recorded, so the gamepad and pointer lock are left out of
+                    // recordings and replays, which use the orbit controls
+                    let recorder_active = input_listener.recorder_frame().is_some();
+
+                    if input_listener.key_state(Key::KeyP) == InputState::Pressed
+                    {
+                        paused = !paused;
+                    }
+
+                    if !paused
+                    {
+                        accumulator += elapsed_time;
+                    }
 
                     // Perform any updates skipped due to missed frames
                     while accumulator >= delta_time
                     {
-                        robot1_transform.local.rotate_angle_axis(Deg(40.0 * delta_time), vec3(0.0, 1.0, 0.0));
-                        robot2_transform.local.rotate_angle_axis(Deg(-40.0 * delta_time), vec3(0.0, 1.0, 0.0));
+                        robot1_spin.advance(delta_time);
+                        robot1_spin.apply(std::slice::from_mut(&mut robot1_transform));
+                        robot2_spin.advance(delta_time);
+                        robot2_spin.apply(std::slice::from_mut(&mut robot2_transform));
 
                         accumulator -= delta_time;
                     }
 
                     {
                         borrow!(context);
+                        // Match the canvas to its displayed size. Anything that depends on the size
+                        // of the drawing buffer needs to be updated here
+                        if let Some((width, height)) = canvas_resizer.update()
+                        {
+                            context.viewport(0, 0, width as i32, height as i32);
+                            perspective = projection(canvas_resizer.aspect_ratio());
+                        }
+
                         // Reset the render area
                         context.clear_color(0.0, 0.0, 0.0, 1.0);
                         context.clear(Context::COLOR_BUFFER_BIT | Context::DEPTH_BUFFER_BIT);
@@ -490,49 +680,103 @@ fn start(canvas_id: String, _resource_dir: String, resource_manager: Rc<RefCell<
 
                     // Input state tests
                     borrow_mut!(camera);
-                    let key_state = input_listener.key_state(Key_w);
-                    if key_state == InputState::Down || key_state == InputState::Repeating
+                    if input_listener.key_state(Key::KeyW).is_down()
                     {
                         camera.move_cam_long_locked(-0.1);
                     }
-                    let key_state = input_listener.key_state(Key_s);
-                    if key_state == InputState::Down || key_state == InputState::Repeating
+                    if input_listener.key_state(Key::KeyS).is_down()
                     {
                         camera.move_cam_long_locked(0.1);
                     }
-                    let key_state = input_listener.key_state(Key_a);
-                    if key_state == InputState::Down || key_state == InputState::Repeating
+                    if input_listener.key_state(Key::KeyA).is_down()
                     {
                         camera.move_cam_lat(0.1);
                     }
-                    let key_state = input_listener.key_state(Key_d);
-                    if key_state == InputState::Down || key_state == InputState::Repeating
+                    if input_listener.key_state(Key::KeyD).is_down()
                     {
                         camera.move_cam_lat(-0.1);
                     }
-                    let key_state = input_listener.key_state(Key_Space);
-                    if key_state == InputState::Down || key_state == InputState::Repeating
+                    if input_listener.key_state(Key::Space).is_down()
                     {
                         camera.move_cam_vert_locked(0.1);
                     }
-                    let key_state = input_listener.key_state(Key_Control);
-                    if key_state == InputState::Down || key_state == InputState::Repeating
+                    if input_listener.modifiers().ctrl
                     {
                         camera.move_cam_vert_locked(-0.1);
                     }
+                    let (_, wheel_delta) = input_listener.wheel_delta();
+                    if wheel_delta > 0.0
+                    {
+                        camera.move_cam_long_locked(0.1);
+                    }
+                    else if wheel_delta < 0.0
+                    {
+                        camera.move_cam_long_locked(-0.1);
+                    }
+                    let (delta_x, delta_y) = input_listener.mouse_delta();
+                    let camera_mode = if recorder_active { CameraMode::Orbit } else { pointer_lock.camera_mode() };
+                    match camera_mode
+                    {
+                        // Look around from the camera's position
+                        CameraMode::FreeLook =>
+                            {
+                                if delta_x != 0
+                                {
+                                    camera.rotate_world_yaw(0.25 * delta_x as f32);
+                                }
+                                if delta_y != 0
+                                {
+                                    camera.rotate_cam_pitch(0.25 * delta_y as f32);
+                                }
+                            },
+                        // Drag to circle around the center of the room
+                        CameraMode::Orbit =>
+                            {
+                                if input_listener.mouse_btn_state(Mouse_LeftBtn as usize).is_down()
+                                {
+                                    if delta_x != 0
+                                    {
+                                        camera.orbit_yaw(0.25 * delta_x as f32, orbit_center);
+                                    }
+                                    if delta_y != 0
+                                    {
+                                        camera.orbit_pitch(0.25 * delta_y as f32, orbit_center);
+                                    }
+                                }
+                            },
+                    }
 
+                    // Gamepad camera controls
+                    gamepad_listener.poll();
+                    if !recorder_active
+                    {
+                        let (move_x, move_y) = gamepad_listener.stick(GamepadAxis_LeftX, GamepadAxis_LeftY);
+                        camera.move_cam_long_locked(0.1 * move_y);
+                        camera.move_cam_lat(-0.1 * move_x);
+                        let (look_x, look_y) = gamepad_listener.stick(GamepadAxis_RightX, GamepadAxis_RightY);
+                        if look_x != 0.0
+                        {
+                            camera.rotate_world_yaw(2.0 * look_x);
+                        }
+                        if look_y != 0.0
+                        {
+                            camera.rotate_cam_pitch(2.0 * look_y);
+                        }
+                        let up = gamepad_listener.button_value(Gamepad_RightTrigger) - gamepad_listener.button_value(Gamepad_LeftTrigger);
+                        camera.move_cam_vert_locked(0.1 * up);
+                    }
                 }
         };
 
     // Setup and start render loop
     let render_loop = Rc::new(RefCell::new(RenderLoop::init(&window, &canvas, &context, &manager, render_func, context_config_func).expect("render_loop")));
+    render_loop.borrow_mut().set_restore_func(restore_func);
     render_loop.borrow_mut().start().unwrap();
 
     {
         let callback = move |event: web_sys::KeyboardEvent|
             {
-                if event.key() == Key_ArrowUp || event.key() == Key_ArrowDown
-                    || event.key() == Key_ArrowLeft || event.key() == Key_ArrowRight
+                if matches!(Key::from_event(&event), Some(Key::ArrowUp | Key::ArrowDown | Key::ArrowLeft | Key::ArrowRight))
                 {
                     event.prevent_default();
                 }
@@ -554,6 +798,179 @@ fn start(canvas_id: String, _resource_dir: String, resource_manager: Rc<RefCell<
     Ok(())
 }
 
+/// GL objects of a mesh uploaded by `setup_scene`
+pub(crate) struct SceneMesh
+{
+    pub vert_arr_handle: Handle<VertexArray>,
+    pub arr_buff_handle: Handle<ArrayBuffer>,
+    pub elem_buff_handle: Handle<ElementArrayBuffer>,
+    pub renderable: RenderDto,
+}
+
+/// GL objects of the room and robots
+pub(crate) struct Scene
+{
+    pub texture_atlas_handle: Handle<Texture2d>,
+    /// Textures that can be used by materials
+    #[cfg_attr(not(feature = "debug"), allow(dead_code))]
+    pub textures: HashMap<String, Handle<Texture2d>>,
+    pub robot: SceneMesh,
+    pub room: SceneMesh,
+}
+
+/// Set the state of `context` that the scene is drawn with, for a `width` x `height` canvas
+pub(crate) fn configure_context(context: &Context, width: i32, height: i32)
+{
+    context.viewport(0, 0, width, height);
+    context.pixel_storei(Context::UNPACK_ALIGNMENT, 1);
+    context.enable(Context::CULL_FACE);
+    context.enable(Context::DEPTH_TEST);
+}
+
+/// Upload the texture atlas and the robot and room meshes in `resource_manager` to `manager`
+/// The golden image tests set up their scene with this too, so that they draw what `start` draws
+pub(crate) fn setup_scene(context: &Context, manager: &mut GlObjectManager, resource_manager: &ResourceManager) -> Scene
+{
+    // Texture atlas
+    let tex_atlas_pbm = resource_manager.get_by_name(&"tex_atlas.pbm".to_string()).expect("texture atlas").clone();
+
+    let texture_atlas_handle = manager.insert_labeled_texture2d("atlas texture",
+        Texture2d::new(context, Texture2dParams
+        {
+            target: Context::TEXTURE_2D,
+            internal_format: Context::RGB8,
+            format: Context::RGB,
+            size: (800, 400),
+            wrap_type: Context::REPEAT,
+            filter_type: Context::LINEAR,
+            data: tex_atlas_pbm
+        }).expect("texture")
+    );
+    {
+        Texture2d::bind(manager, texture_atlas_handle);
+        manager.get_texture2d(texture_atlas_handle).expect("atlas texture2d").setup_texture().expect("texture2d setup");
+    }
+    // Textures that can be used by materials
+    let textures = HashMap::from([("tex_atlas.pbm".to_string(), texture_atlas_handle)]);
+
+    let robot = setup_mesh(context, manager, resource_manager, "robot", &textures, texture_atlas_handle);
+    let room = setup_mesh(context, manager, resource_manager, "room", &textures, texture_atlas_handle);
+
+    Scene
+    {
+        texture_atlas_handle,
+        textures,
+        robot,
+        room,
+    }
+}
+
+/// Upload the mesh in resource `<name>.obj` to a new VAO and buffers labeled with `name`
+fn setup_mesh(context: &Context, manager: &mut GlObjectManager, resource_manager: &ResourceManager, name: &str,
+              textures: &HashMap<String, Handle<Texture2d>>, texture_atlas_handle: Handle<Texture2d>) -> SceneMesh
+{
+    let mesh = Mesh::from_resource(&format!("{}.obj", name), resource_manager).expect("mesh");
+
+    let vert_arr_handle = manager.insert_labeled_vertex_array(&format!("{} VAO", name),
+        VertexArray::new(context).expect("vertex array")
+    );
+    let arr_buff_handle = manager.insert_labeled_array_buffer(&format!("{} vertices", name),
+        ArrayBuffer::new(context).expect("array buffer")
+    );
+    let elem_buff_handle = manager.insert_labeled_element_array_buffer(&format!("{} indices", name),
+        ElementArrayBuffer::new(context).expect("element array buffer")
+    );
+    buffer_mesh(manager, &mesh, vert_arr_handle, arr_buff_handle, elem_buff_handle).expect("mesh buffers");
+
+    // Register the vertex and element array buffers with the VAO
+    VertexArray::bind(manager, vert_arr_handle);
+    ArrayBuffer::bind(manager, arr_buff_handle);
+    {
+        let mut vert_arr = manager.get_mut_vertex_array(vert_arr_handle).expect("vertex array");
+
+        vert_arr.register_vertex_buffer::<Vertex>(arr_buff_handle, None).expect("vertex layout");
+        vert_arr.register_element_array_buffer(elem_buff_handle, None);
+    }
+    VertexArray::unbind(manager, vert_arr_handle);
+    ArrayBuffer::unbind(manager, arr_buff_handle);
+
+    let renderable = RenderDto::from_mesh(vert_arr_handle, &mesh, textures, Some(texture_atlas_handle)).expect("render info");
+
+    SceneMesh
+    {
+        vert_arr_handle,
+        arr_buff_handle,
+        elem_buff_handle,
+        renderable,
+    }
+}
+
+/// Transformations of the two robots at their starting positions
+pub(crate) fn robot_transforms() -> (Transformation, Transformation)
+{
+    let mut robot1_transform = Transformation::new();
+    robot1_transform.global.translate(vec3(3.0, 0.25, 2.5));
+    robot1_transform.local.rotate_angle_axis(Deg(90.0), vec3(0.0, 1.0, 0.0));
+    robot1_transform.local.translate(vec3(1.0, 0.0, 0.0));
+
+    let mut robot2_transform = Transformation::new();
+    robot2_transform.global.translate(vec3(-3.0, 0.25, -3.0));
+    robot2_transform.local.rotate_angle_axis(Deg(90.0), vec3(0.0, 1.0, 0.0));
+    robot2_transform.local.translate(vec3(1.0, 0.0, 0.0));
+
+    (robot1_transform, robot2_transform)
+}
+
+/// The camera that the visualization begins with
+pub(crate) fn start_camera() -> Camera
+{
+    let mut camera = Camera::from_eye(
+        vec3(0.0, 0.0, 0.0),
+        vec3(0.0, -0.5, -1.0),
+        vec3(0.0, 1.0, 0.0)
+    );
+    camera.move_cam_locked(vec3(0.0, 5.0, 9.0));
+    camera
+}
+
+/// Perspective projection for a canvas with `aspect_ratio`
+pub(crate) fn projection(aspect_ratio: f32) -> cgmath::Matrix4<f32>
+{
+    cgmath::perspective(Deg(45.0f32), aspect_ratio, 0.1f32, 50.0f32)
+}
+
+/// Replace the contents of a mesh's buffers with the mesh in resource `name`
+/// Returns the render info for the new mesh
+#[cfg(feature = "debug")]
+#[allow(clippy::too_many_arguments)]
+fn reload_mesh(manager: &GlObjectManager, resource_manager: &ResourceManager, name: &str,
+               vert_arr_handle: Handle<VertexArray>, arr_buff_handle: Handle<ArrayBuffer>, elem_buff_handle: Handle<ElementArrayBuffer>,
+               textures: &HashMap<String, Handle<Texture2d>>, default_tex_handle: Option<Handle<Texture2d>>) -> Result<RenderDto, String>
+{
+    let mesh = Mesh::from_resource(name, resource_manager).map_err(|err| format!("{:?}", err))?;
+    let render_dto = RenderDto::from_mesh(vert_arr_handle, &mesh, textures, default_tex_handle).map_err(|err| err.to_string())?;
+    buffer_mesh(manager, &mesh, vert_arr_handle, arr_buff_handle, elem_buff_handle)?;
+
+    Ok(render_dto)
+}
+
+/// Set the contents of a mesh's buffers to `mesh`
+fn buffer_mesh(manager: &GlObjectManager, mesh: &Mesh,
+               vert_arr_handle: Handle<VertexArray>, arr_buff_handle: Handle<ArrayBuffer>, elem_buff_handle: Handle<ElementArrayBuffer>) -> Result<(), String>
+{
+    // The element array buffer binding is part of the VAO's state
+    VertexArray::bind(manager, vert_arr_handle);
+    ArrayBuffer::bind(manager, arr_buff_handle);
+    manager.get_mut_array_buffer(arr_buff_handle).ok_or("missing array buffer")?.buffer_data(&mesh.vertices, Context::STATIC_DRAW);
+    ElementArrayBuffer::bind(manager, elem_buff_handle);
+    manager.get_mut_element_array_buffer(elem_buff_handle).ok_or("missing element array buffer")?.buffer_data(&mesh.indices, Context::STATIC_DRAW);
+    VertexArray::unbind(manager, vert_arr_handle);
+    ArrayBuffer::unbind(manager, arr_buff_handle);
+    ElementArrayBuffer::unbind(manager, elem_buff_handle);
+
+    Ok(())
+}
+
 #[wasm_bindgen(start)]
 pub fn main_function() -> Result<(), JsValue>
 {
@@ -0,0 +1,513 @@
+//! Keyframe animation of transformations, i.e. for spinning wheels and rotating sensors
+//!
+//! An `AnimationClip` holds channels that each animate the translation, rotation or scale of
+//! one target. Clips are played back by an `AnimationPlayer`, which is advanced by the
+//! visualization clock and poses the local part of each target's `Transformation`
+#![allow(dead_code)]
+
+use std::rc::Rc;
+use cgmath::
+{
+    prelude::*,
+    Vector3,
+    Quaternion,
+};
+use crate::math::transform::Transformation;
+
+/// How values are found between keyframes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation
+{
+    /// Hold each keyframe's value until the next keyframe
+    Step,
+    /// Linear interpolation for vectors, and spherical linear interpolation for rotations
+    Linear,
+    /// Cubic Hermite spline, where every keyframe has an in-tangent and an out-tangent
+    CubicSpline,
+}
+
+/// Value that can be animated with keyframes
+pub trait Animatable: Copy
+{
+    fn interpolate(a: Self, b: Self, t: f32) -> Self;
+
+    /// Cubic Hermite spline from `v0` to `v1` with tangents `m0` and `m1`, where `delta` is
+    /// the time between the keyframes
+    fn hermite(v0: Self, m0: Self, v1: Self, m1: Self, t: f32, delta: f32) -> Self;
+}
+
+/// Weights of `v0`, `m0`, `v1` and `m1` at `t` in a cubic Hermite spline
+/// The tangent weights include `delta`, since tangents are per second
+fn hermite_weights(t: f32, delta: f32) -> [f32; 4]
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [2.0 * t3 - 3.0 * t2 + 1.0, (t3 - 2.0 * t2 + t) * delta, -2.0 * t3 + 3.0 * t2, (t3 - t2) * delta]
+}
+
+impl Animatable for Vector3<f32>
+{
+    fn interpolate(a: Self, b: Self, t: f32) -> Self
+    {
+        a + (b - a) * t
+    }
+
+    fn hermite(v0: Self, m0: Self, v1: Self, m1: Self, t: f32, delta: f32) -> Self
+    {
+        let [w0, w1, w2, w3] = hermite_weights(t, delta);
+        v0 * w0 + m0 * w1 + v1 * w2 + m1 * w3
+    }
+}
+
+impl Animatable for Quaternion<f32>
+{
+    /// Takes the shortest path between `a` and `b`
+    fn interpolate(a: Self, b: Self, t: f32) -> Self
+    {
+        let mut dot = a.dot(b);
+        let b = if dot < 0.0
+        {
+            dot = -dot;
+            -b
+        } else { b };
+
+        // Fall back to normalized linear interpolation when the rotations are nearly the same
+        if dot > 0.9995
+        {
+            return (a + (b - a) * t).normalize();
+        }
+        let theta = dot.acos();
+        let sin_theta = theta.sin();
+        (a * (((1.0 - t) * theta).sin() / sin_theta) + b * ((t * theta).sin() / sin_theta)).normalize()
+    }
+
+    fn hermite(v0: Self, m0: Self, v1: Self, m1: Self, t: f32, delta: f32) -> Self
+    {
+        let [w0, w1, w2, w3] = hermite_weights(t, delta);
+        (v0 * w0 + m0 * w1 + v1 * w2 + m1 * w3).normalize()
+    }
+}
+
+/// Values of a property at points in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes<T>
+{
+    interpolation: Interpolation,
+    // Time of each keyframe in seconds, in increasing order
+    times: Vec<f32>,
+    // With `CubicSpline`, each keyframe has three values: in-tangent, value, out-tangent
+    values: Vec<T>,
+}
+
+impl<T: Animatable> Keyframes<T>
+{
+    /// Create keyframes at `times` seconds
+    ///
+    /// `values` holds one value per keyframe, or three with `Interpolation::CubicSpline`
+    /// Fails if there are no keyframes, `times` isn't increasing, or there are the wrong number of values
+    pub fn new(interpolation: Interpolation, times: Vec<f32>, values: Vec<T>) -> Result<Keyframes<T>, String>
+    {
+        let values_per_key = if interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+        if times.is_empty()
+        {
+            return Err("keyframes must have at least one time".to_string());
+        }
+        if times.windows(2).any(|times| times[1] <= times[0])
+        {
+            return Err("keyframe times must be increasing".to_string());
+        }
+        if values.len() != times.len() * values_per_key
+        {
+            return Err(format!("{} keyframe times need {} values but there are {}", times.len(), times.len() * values_per_key, values.len()));
+        }
+        Ok(Keyframes { interpolation, times, values })
+    }
+
+    /// Time of the last keyframe
+    pub fn duration(&self) -> f32
+    {
+        *self.times.last().unwrap()
+    }
+
+    fn value(&self, key: usize) -> T
+    {
+        match self.interpolation
+        {
+            Interpolation::CubicSpline => self.values[3 * key + 1],
+            _ => self.values[key],
+        }
+    }
+
+    /// Value at `time` seconds
+    /// Times before the first keyframe or after the last keyframe hold their value
+    pub fn sample(&self, time: f32) -> T
+    {
+        // Index of the keyframe after `time`
+        let next = self.times.partition_point(|key_time| *key_time <= time);
+        if next == 0
+        {
+            return self.value(0);
+        }
+        if next == self.times.len()
+        {
+            return self.value(next - 1);
+        }
+
+        let key = next - 1;
+        let delta = self.times[next] - self.times[key];
+        let t = (time - self.times[key]) / delta;
+        match self.interpolation
+        {
+            Interpolation::Step => self.value(key),
+            Interpolation::Linear => T::interpolate(self.value(key), self.value(next), t),
+            Interpolation::CubicSpline =>
+                {
+                    let out_tangent = self.values[3 * key + 2];
+                    let in_tangent = self.values[3 * next];
+                    T::hermite(self.value(key), out_tangent, self.value(next), in_tangent, t, delta)
+                }
+        }
+    }
+}
+
+/// Animated property of a target
+#[derive(Debug, Clone, PartialEq)]
+pub enum Track
+{
+    Translation(Keyframes<Vector3<f32>>),
+    Rotation(Keyframes<Quaternion<f32>>),
+    Scale(Keyframes<Vector3<f32>>),
+}
+
+impl Track
+{
+    pub fn duration(&self) -> f32
+    {
+        match self
+        {
+            Track::Translation(keyframes) | Track::Scale(keyframes) => keyframes.duration(),
+            Track::Rotation(keyframes) => keyframes.duration(),
+        }
+    }
+
+    /// Order that tracks are applied in, so that the translation ends up as set
+    fn order(&self) -> u8
+    {
+        match self
+        {
+            Track::Scale(_) => 0,
+            Track::Rotation(_) => 1,
+            Track::Translation(_) => 2,
+        }
+    }
+}
+
+/// Track applied to one target of a clip
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel
+{
+    /// Index of the target within the transformations given to `AnimationClip::apply`
+    pub target: usize,
+    pub track: Track,
+}
+
+/// Set of channels that are played together
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip
+{
+    pub name: String,
+    channels: Vec<Channel>,
+    duration: f32,
+}
+
+impl AnimationClip
+{
+    pub fn new(name: &str, mut channels: Vec<Channel>) -> AnimationClip
+    {
+        channels.sort_by_key(|channel| (channel.target, channel.track.order()));
+        let duration = channels.iter().map(|channel| channel.track.duration()).fold(0.0, f32::max);
+        AnimationClip { name: name.to_string(), channels, duration }
+    }
+
+    pub fn channels(&self) -> &[Channel]
+    {
+        &self.channels
+    }
+
+    /// Time of the last keyframe of any channel
+    pub fn duration(&self) -> f32
+    {
+        self.duration
+    }
+
+    /// Pose the local transformations of `targets` as they are `time` seconds into the clip
+    ///
+    /// Tracks are applied with `SubTransformation`'s setters, so a rotation without a translation
+    /// track also rotates the target's current offset, the same as `rotate_quat`
+    /// Channels whose target isn't within `targets` are skipped
+    pub fn apply(&self, time: f32, targets: &mut [Transformation])
+    {
+        for channel in &self.channels
+        {
+            if let Some(target) = targets.get_mut(channel.target)
+            {
+                match &channel.track
+                {
+                    Track::Scale(keyframes) => target.local.set_scale(keyframes.sample(time)),
+                    Track::Rotation(keyframes) => target.local.set_orientation_quat(keyframes.sample(time)),
+                    Track::Translation(keyframes) => target.local.set_translation(keyframes.sample(time)),
+                }
+            }
+        }
+    }
+}
+
+/// What happens when playback reaches the end of a clip
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Playback
+{
+    /// Stop at the end
+    Once,
+    /// Start over from the beginning
+    Loop,
+    /// Play backwards to the beginning, then forwards again
+    PingPong,
+}
+
+/// Plays an animation clip
+///
+/// The speed can be changed at any time, so a wheel's spin can follow the speed of its robot
+/// Negative speeds play the clip backwards
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer
+{
+    clip: Rc<AnimationClip>,
+    playback: Playback,
+    speed: f32,
+    time: f32,
+    playing: bool,
+}
+
+impl AnimationPlayer
+{
+    /// Create a player at the start of `clip` that is already playing
+    pub fn new(clip: Rc<AnimationClip>, playback: Playback) -> AnimationPlayer
+    {
+        AnimationPlayer { clip, playback, speed: 1.0, time: 0.0, playing: true }
+    }
+
+    pub fn clip(&self) -> &AnimationClip
+    {
+        &self.clip
+    }
+
+    pub fn play(&mut self)
+    {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self)
+    {
+        self.playing = false;
+    }
+
+    /// Pause and go back to the start of the clip
+    pub fn stop(&mut self)
+    {
+        self.playing = false;
+        self.time = 0.0;
+    }
+
+    pub fn is_playing(&self) -> bool
+    {
+        self.playing
+    }
+
+    pub fn speed(&self) -> f32
+    {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32)
+    {
+        self.speed = speed;
+    }
+
+    /// Jump to `time` seconds into the clip
+    pub fn seek(&mut self, time: f32)
+    {
+        self.time = time;
+        self.wrap();
+    }
+
+    /// Advance playback by `elapsed` seconds of the visualization clock
+    pub fn advance(&mut self, elapsed: f32)
+    {
+        if self.playing
+        {
+            self.time += elapsed * self.speed;
+            self.wrap();
+        }
+    }
+
+    /// Bring the time back within the clip according to the playback mode
+    fn wrap(&mut self)
+    {
+        let duration = self.clip.duration();
+        match self.playback
+        {
+            Playback::Once =>
+                {
+                    // Stop at whichever end of the clip is being played towards
+                    if (self.speed > 0.0 && self.time >= duration) || (self.speed < 0.0 && self.time <= 0.0)
+                    {
+                        self.playing = false;
+                    }
+                    self.time = self.time.clamp(0.0, duration);
+                },
+            _ if duration <= 0.0 => self.time = 0.0,
+            Playback::Loop => self.time = self.time.rem_euclid(duration),
+            // One period is a forwards pass followed by a backwards pass
+            Playback::PingPong => self.time = self.time.rem_euclid(2.0 * duration),
+        }
+    }
+
+    /// Position within the clip, in seconds
+    pub fn clip_time(&self) -> f32
+    {
+        let duration = self.clip.duration();
+        match self.playback
+        {
+            Playback::PingPong if self.time > duration => 2.0 * duration - self.time,
+            _ => self.time,
+        }
+    }
+
+    /// Pose `targets` at the current position within the clip
+    pub fn apply(&self, targets: &mut [Transformation])
+    {
+        self.clip.apply(self.clip_time(), targets);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use cgmath::{Deg, Rotation3, vec3};
+    use crate::math::animation::*;
+
+    fn assert_vec_eq(expected: Vector3<f32>, actual: Vector3<f32>)
+    {
+        assert!(approx_eq!(f32, expected.x, actual.x, epsilon = 1e-5)
+                    && approx_eq!(f32, expected.y, actual.y, epsilon = 1e-5)
+                    && approx_eq!(f32, expected.z, actual.z, epsilon = 1e-5),
+                "expected {:?} but got {:?}", expected, actual);
+    }
+
+    fn spin_clip() -> Rc<AnimationClip>
+    {
+        let rotations = [0.0, 120.0, 240.0, 360.0].iter().map(|angle| Quaternion::from_angle_y(Deg(*angle))).collect();
+        Rc::new(AnimationClip::new("spin", vec![
+            Channel { target: 0, track: Track::Rotation(Keyframes::new(Interpolation::Linear, vec![0.0, 1.0, 2.0, 3.0], rotations).unwrap()) },
+        ]))
+    }
+
+    #[test]
+    fn test_keyframes_new()
+    {
+        assert!(Keyframes::<Vector3<f32>>::new(Interpolation::Linear, vec![], vec![]).is_err());
+        assert!(Keyframes::new(Interpolation::Linear, vec![1.0, 0.0], vec![vec3(0.0, 0.0, 0.0); 2]).is_err());
+        assert!(Keyframes::new(Interpolation::CubicSpline, vec![0.0, 1.0], vec![vec3(0.0, 0.0, 0.0); 2]).is_err());
+        assert!(Keyframes::new(Interpolation::CubicSpline, vec![0.0, 1.0], vec![vec3(0.0, 0.0, 0.0); 6]).is_ok());
+    }
+
+    #[test]
+    fn test_sample()
+    {
+        let values = vec![vec3(0.0, 0.0, 0.0), vec3(2.0, 0.0, 0.0), vec3(2.0, 4.0, 0.0)];
+        let linear = Keyframes::new(Interpolation::Linear, vec![1.0, 2.0, 4.0], values.clone()).unwrap();
+        assert_vec_eq(vec3(0.0, 0.0, 0.0), linear.sample(0.0));
+        assert_vec_eq(vec3(1.0, 0.0, 0.0), linear.sample(1.5));
+        assert_vec_eq(vec3(2.0, 1.0, 0.0), linear.sample(2.5));
+        assert_vec_eq(vec3(2.0, 4.0, 0.0), linear.sample(10.0));
+
+        let step = Keyframes::new(Interpolation::Step, vec![1.0, 2.0, 4.0], values).unwrap();
+        assert_vec_eq(vec3(0.0, 0.0, 0.0), step.sample(1.9));
+        assert_vec_eq(vec3(2.0, 0.0, 0.0), step.sample(2.0));
+
+        // Zero tangents ease in and out, so the midpoint is still halfway
+        let zero = vec3(0.0, 0.0, 0.0);
+        let cubic = Keyframes::new(Interpolation::CubicSpline, vec![0.0, 2.0],
+                                   vec![zero, zero, zero, zero, vec3(4.0, 0.0, 0.0), zero]).unwrap();
+        assert_vec_eq(vec3(2.0, 0.0, 0.0), cubic.sample(1.0));
+        assert!(cubic.sample(0.5).x < 1.0);
+        // Tangents are per second, so a constant slope matching the values is a straight line
+        let slope = vec3(2.0, 0.0, 0.0);
+        let cubic = Keyframes::new(Interpolation::CubicSpline, vec![0.0, 2.0],
+                                   vec![slope, zero, slope, slope, vec3(4.0, 0.0, 0.0), slope]).unwrap();
+        assert_vec_eq(vec3(1.0, 0.0, 0.0), cubic.sample(0.5));
+    }
+
+    #[test]
+    fn test_slerp()
+    {
+        let halfway = Quaternion::interpolate(Quaternion::from_angle_y(Deg(0.0)), Quaternion::from_angle_y(Deg(90.0)), 0.5);
+        assert_vec_eq(Quaternion::from_angle_y(Deg(45.0)) * vec3(1.0, 0.0, 0.0), halfway * vec3(1.0, 0.0, 0.0));
+
+        // Opposite signs are the same rotation, and shouldn't take the long way around
+        let halfway = Quaternion::interpolate(Quaternion::from_angle_y(Deg(0.0)), -Quaternion::from_angle_y(Deg(90.0)), 0.5);
+        assert_vec_eq(Quaternion::from_angle_y(Deg(45.0)) * vec3(1.0, 0.0, 0.0), halfway * vec3(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_apply()
+    {
+        let mut targets = vec![Transformation::new(), Transformation::new()];
+        targets[0].local.translate(vec3(1.0, 0.0, 0.0));
+        let clip = AnimationClip::new("wheel", vec![
+            Channel { target: 1, track: Track::Translation(Keyframes::new(Interpolation::Linear, vec![0.0, 2.0],
+                                                                          vec![vec3(0.0, 0.0, 0.0), vec3(0.0, 2.0, 0.0)]).unwrap()) },
+            // Past the end of the targets
+            Channel { target: 5, track: Track::Scale(Keyframes::new(Interpolation::Step, vec![0.0], vec![vec3(2.0, 2.0, 2.0)]).unwrap()) },
+        ]);
+        assert_eq!(2.0, clip.duration());
+        clip.apply(1.0, &mut targets);
+        assert_vec_eq(vec3(1.0, 0.0, 0.0), *targets[0].local.get_translation());
+        assert_vec_eq(vec3(0.0, 1.0, 0.0), *targets[1].local.get_translation());
+
+        // Rotating carries the current offset around, the same as `rotate_quat`
+        spin_clip().apply(0.75, &mut targets);
+        assert_vec_eq(vec3(0.0, 0.0, -1.0), *targets[0].local.get_translation());
+    }
+
+    #[test]
+    fn test_playback()
+    {
+        let mut player = AnimationPlayer::new(spin_clip(), Playback::Loop);
+        player.advance(2.5);
+        player.advance(1.0);
+        assert!(approx_eq!(f32, 0.5, player.clip_time(), epsilon = 1e-5));
+        // Backwards, wrapping around the start
+        player.set_speed(-2.0);
+        player.advance(0.5);
+        assert!(approx_eq!(f32, 2.5, player.clip_time(), epsilon = 1e-5));
+        player.pause();
+        player.advance(1.0);
+        assert!(approx_eq!(f32, 2.5, player.clip_time(), epsilon = 1e-5));
+
+        let mut player = AnimationPlayer::new(spin_clip(), Playback::PingPong);
+        player.advance(4.0);
+        assert!(approx_eq!(f32, 2.0, player.clip_time(), epsilon = 1e-5));
+        player.advance(3.0);
+        assert!(approx_eq!(f32, 1.0, player.clip_time(), epsilon = 1e-5));
+
+        let mut player = AnimationPlayer::new(spin_clip(), Playback::Once);
+        player.advance(2.0);
+        assert!(player.is_playing());
+        player.advance(2.0);
+        assert!(!player.is_playing());
+        assert_eq!(3.0, player.clip_time());
+        player.stop();
+        assert_eq!(0.0, player.clip_time());
+    }
+}
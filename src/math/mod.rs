@@ -1 +1,2 @@
-pub mod transform;
\ No newline at end of file
+pub mod transform;
+pub mod animation;
\ No newline at end of file
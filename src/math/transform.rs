@@ -103,7 +103,7 @@ impl SubTransformation
     pub fn set_orientation_quat(&mut self, orientation: Quaternion<f32>)
     {
         // Remove current orientation from the position
-        self.translation = (self.orientation.conjugate() * orientation).normalize() * self.translation;
+        self.translation = self.orientation.conjugate() * self.translation;
         // Set new orientation
         self.orientation = orientation;
         // Apply new orientation to position
@@ -381,12 +381,12 @@ mod tests
         assert_eq!(expected, *m);
 
         t.global.set_orientation_angle_axis(Deg(0.0), vec3(0.0, 0.0, 0.0));
-        let expected: [f32; 16] = [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.9881021, 1.0210148, 0.011897922, 1.0];
+        let expected: [f32; 16] = [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.9881022, 1.0210147, 0.011897802, 1.0];
         let m: &[f32; 16] = t.global.as_matrix().as_ref();
         assert_eq!(expected, *m);
 
         t.global.translate(vec3(-1.0, -1.0, 0.0));
-        assert_eq!(vec3(-0.011897922, 0.02101481, 0.011897922), *t.global.get_translation());
+        assert_eq!(vec3(-0.011897802, 0.02101469, 0.011897802), *t.global.get_translation());
 
         //let m: &[f32; 16] = t.global.as_matrix().as_ref();
         //TODO: These assertions are *technically* true, but fail because of
@@ -411,6 +411,19 @@ mod tests
         assert_eq!(expected, *m);
         assert_eq!(vec3(0.0, 0.0, 0.0), *t.global.get_translation());
     }
+    #[test]
+    fn set_orientation()
+    {
+        let mut t = Transformation::new();
+        t.local.translate(vec3(1.0, 0.0, 0.0));
+        t.local.rotate_angle_axis(Deg(45.0), vec3(0.0, 1.0, 0.0));
+
+        // Setting the orientation rotates the position the same as rotating to it would have
+        t.local.set_orientation_angle_axis(Deg(90.0), vec3(0.0, 1.0, 0.0));
+        let translation = *t.local.get_translation();
+        assert!(approx_eq!(f32, 0.0, translation.x, epsilon = 1e-6));
+        assert!(approx_eq!(f32, -1.0, translation.z, epsilon = 1e-6));
+    }
 /*    #[test]
     fn caching()
     {
@@ -0,0 +1,136 @@
+//! Development-only reloading of resources that have changed on the server
+
+use std::
+{
+    cell::{RefCell, Cell},
+    rc::Rc,
+    time::Duration,
+};
+use crate::resource::
+{
+    loader::{ResourceLoader, OnloadCallbackArgs, CallbackArgs},
+    manager::ResourceManager,
+};
+
+/// A resource that is checked for changes
+struct WatchedResource
+{
+    /// Path of the resource within the resource directory
+    path: String,
+    /// Name of the resource within the `ResourceManager`
+    name: String,
+}
+
+/// Hot Reloader
+///
+/// Periodically re-fetches watched resources and replaces the ones whose contents have changed
+/// within the `ResourceManager`. Whatever uses a changed resource is responsible for rebuilding
+/// itself from the new contents after `take_changed` reports it
+pub struct HotReloader
+{
+    resource_dir: String,
+    resource_manager: Rc<RefCell<ResourceManager>>,
+    watched: Vec<WatchedResource>,
+    // Names of resources that changed since `take_changed` was last called
+    changed: Rc<RefCell<Vec<String>>>,
+    // Is a round of requests still waiting on responses
+    in_flight: Rc<Cell<bool>>,
+    interval: Duration,
+    last_poll: Option<Duration>,
+}
+
+impl HotReloader
+{
+    /// Create a hot reloader that checks for changes every `interval`
+    /// `resource_dir` must end in a '/'
+    pub fn new(resource_dir: &str, resource_manager: Rc<RefCell<ResourceManager>>, interval: Duration) -> HotReloader
+    {
+        HotReloader
+        {
+            resource_dir: resource_dir.to_string(),
+            resource_manager,
+            watched: vec![],
+            changed: Rc::new(RefCell::new(vec![])),
+            in_flight: Rc::new(Cell::new(false)),
+            interval,
+            last_poll: None,
+        }
+    }
+
+    /// Check the resource at `path` within the resource directory for changes
+    /// `name` is the name that the resource is stored under in the `ResourceManager`
+    pub fn watch(&mut self, path: &str, name: &str)
+    {
+        self.watched.push(WatchedResource { path: path.to_string(), name: name.to_string() });
+    }
+
+    /// Re-fetch all watched resources if `interval` has passed since they were last fetched
+    /// and the previous fetch has finished
+    /// `now` is the current time, i.e. from `performance.now()`
+    pub fn poll(&mut self, now: Duration)
+    {
+        if self.watched.is_empty() || self.in_flight.get()
+        {
+            return;
+        }
+        if let Some(last_poll) = self.last_poll
+        {
+            if now < last_poll + self.interval
+            {
+                return;
+            }
+        }
+        self.last_poll = Some(now);
+
+        let mut resource_loader = ResourceLoader::new();
+        let mut requests = 0;
+        for watched in &self.watched
+        {
+            // Add a query to the url so that the browser doesn't give back a cached response
+            let url = format!("{}{}?hot_reload={}", self.resource_dir, watched.path, now.as_millis());
+            let request_handle = match resource_loader.add_request("GET", url)
+            {
+                Ok(request_handle) => request_handle,
+                Err(err) =>
+                    {
+                        crate::log_s(format!("Error creating hot reload request for {}: {:?}", watched.path, err));
+                        continue;
+                    }
+            };
+            let name = watched.name.clone();
+            let resource_manager = self.resource_manager.clone();
+            clone!(self.changed);
+            resource_loader.set_request_onload(request_handle, move |OnloadCallbackArgs(CallbackArgs(_, request), bytes)|
+                {
+                    // Missing files and server errors are ignored so that the last good version stays loaded
+                    if request.status().unwrap_or(0) != 200
+                    {
+                        return;
+                    }
+                    if resource_manager.borrow_mut().update_by_name(name.clone(), bytes)
+                    {
+                        changed.borrow_mut().push(name);
+                    }
+                });
+            requests += 1;
+        }
+        // The loader's `onloadend` is never called if it has no requests
+        if requests == 0
+        {
+            return;
+        }
+
+        self.in_flight.set(true);
+        {
+            clone!(self.in_flight);
+            resource_loader.set_onloadend(move || in_flight.set(false));
+        }
+        resource_loader.submit();
+    }
+
+    /// Get the names of the resources that changed since this was last called
+    pub fn take_changed(&self) -> Vec<String>
+    {
+        self.changed.replace(vec![])
+    }
+}
@@ -7,6 +7,7 @@ use twox_hash::XxHash32;
 use gen_vec::{Index, closed::ClosedGenVec};
 
 pub type ResourceHandle = Index;
+
 pub struct ResourceManager
 {
     resources: ClosedGenVec<Vec<u8>>,
@@ -44,6 +45,20 @@ impl ResourceManager
         handle
     }
 
+    /// Replace the resource associated with `name` if `resource` differs from it
+    ///
+    /// Returns true if the resource was replaced or didn't previously exist
+    #[allow(dead_code)]
+    pub fn update_by_name(&mut self, name: String, resource: Vec<u8>) -> bool
+    {
+        if self.get_by_name(&name) == Some(&resource)
+        {
+            return false;
+        }
+        self.insert_with_name(name, resource);
+        true
+    }
+
     #[allow(dead_code)]
     pub fn get(&self, handle: ResourceHandle) -> Option<&Vec<u8>>
     {
@@ -111,4 +126,20 @@ mod tests
         assert_eq!(manager.remove_by_name(&"name1".to_string()), Some(vec![0u8, 1u8]));
         assert_eq!(manager.remove_by_name(&"name2".to_string()), Some(vec![2u8, 3u8]));
     }
+
+    #[test]
+    fn test_update_by_name()
+    {
+        let mut manager = ResourceManager::new();
+        assert!(manager.update_by_name("name".to_string(), vec![0, 1]));
+        let handle = manager.get_named_handle(&"name".to_string());
+
+        // Same contents don't replace the resource
+        assert!(!manager.update_by_name("name".to_string(), vec![0, 1]));
+        assert_eq!(manager.get_named_handle(&"name".to_string()), handle);
+
+        assert!(manager.update_by_name("name".to_string(), vec![1, 0]));
+        assert_ne!(manager.get_named_handle(&"name".to_string()), handle);
+        assert_eq!(manager.get_by_name(&"name".to_string()), Some(&vec![1u8, 0u8]));
+    }
 }
\ No newline at end of file
@@ -1,2 +1,4 @@
 pub mod manager;
-pub mod loader;
\ No newline at end of file
+pub mod loader;
+#[cfg(feature = "debug")]
+pub mod hot_reload;
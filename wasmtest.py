@@ -1,4 +1,9 @@
 import os
+import sys
 
-os.system("wasm-pack test --firefox --chrome --safari")
-
+# `--headless` runs the tests without opening browser windows, with WebGL using
+# software rendering as configured in webdriver.json. Safari can't run headless
+if "--headless" in sys.argv:
+    os.system("wasm-pack test --headless --firefox --chrome")
+else:
+    os.system("wasm-pack test --firefox --chrome --safari")